@@ -0,0 +1,121 @@
+use std::collections::BTreeMap;
+
+use serde::Deserialize;
+use tower_lsp_server::ls_types::{Color, ColorInformation, Position, Range};
+
+use crate::color::{identifiers, parse_color, to_hex};
+
+/// Team-defined color names, e.g. `"brand-blue" = "#1a73e8"`.
+#[derive(Debug, Default, Clone, PartialEq, Deserialize)]
+#[serde(try_from = "BTreeMap<String, String>")]
+pub struct Aliases(BTreeMap<String, Color>);
+
+impl TryFrom<BTreeMap<String, String>> for Aliases {
+    type Error = String;
+
+    fn try_from(aliases: BTreeMap<String, String>) -> Result<Self, Self::Error> {
+        aliases
+            .into_iter()
+            .map(|(name, value)| {
+                if name.is_empty()
+                    || !name
+                        .chars()
+                        .all(|c| c.is_alphanumeric() || "-_".contains(c))
+                {
+                    return Err(format!("invalid alias name {name:?}"));
+                }
+                let value = value.trim();
+                match parse_color(value) {
+                    Some(color) => Ok((name, color)),
+                    None => Err(format!("invalid color {value:?} for alias {name:?}")),
+                }
+            })
+            .collect::<Result<_, _>>()
+            .map(Self)
+    }
+}
+
+impl Aliases {
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Color> {
+        self.0.get(name)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &Color)> {
+        self.0.iter().map(|(name, color)| (name.as_str(), color))
+    }
+
+    /// Returns the first alias, by name, whose color matches `color` at hex precision.
+    pub fn name_of(&self, color: &Color) -> Option<&str> {
+        let hex = to_hex(color);
+        self.iter()
+            .find(|(_, alias)| to_hex(alias) == hex)
+            .map(|(name, _)| name)
+    }
+
+    /// Finds aliases used as whole identifiers in a line.
+    pub fn find(&self, line: &str, line_idx: usize) -> Vec<ColorInformation> {
+        if self.is_empty() {
+            return Vec::new();
+        }
+
+        identifiers(line)
+            .into_iter()
+            .filter_map(|(start, end, word)| {
+                Some(ColorInformation {
+                    range: Range {
+                        start: Position {
+                            line: line_idx as u32,
+                            character: start,
+                        },
+                        end: Position {
+                            line: line_idx as u32,
+                            character: end,
+                        },
+                    },
+                    color: *self.get(word)?,
+                })
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use crate::alias::Aliases;
+    use crate::color::to_hex;
+
+    fn aliases() -> Aliases {
+        serde_json::from_value(json!({ "brand-blue": "#1a73e8", "ink": "#000000" })).unwrap()
+    }
+
+    #[test]
+    fn finds_whole_identifiers() {
+        let colors = aliases().find("color: brand-blue; border: brand-blue-dark ink", 2);
+
+        assert_eq!(colors.len(), 2);
+        assert_eq!(colors[0].range.start.line, 2);
+        assert_eq!(colors[0].range.start.character, 7);
+        assert_eq!(colors[0].range.end.character, 17);
+        assert_eq!(to_hex(&colors[0].color), "#1A73E8");
+        assert_eq!(colors[1].range.start.character, 43);
+    }
+
+    #[test]
+    fn reverse_lookup() {
+        let aliases = aliases();
+        let color = *aliases.get("ink").unwrap();
+        assert_eq!(aliases.name_of(&color), Some("ink"));
+    }
+
+    #[test]
+    fn rejects_invalid_colors() {
+        let result: Result<Aliases, _> = serde_json::from_value(json!({ "brand": "blue" }));
+        assert!(result.is_err());
+    }
+}
@@ -0,0 +1,356 @@
+//! Android resources: `#AARRGGBB` colors and `@color/name` or `R.color.name`
+//! references to colors defined in `res/values*/*.xml`.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use tower_lsp_server::ls_types::{Color, ColorInformation, Location, Position, Range, Uri};
+
+use crate::color::ColorMatch;
+use crate::document::Document;
+use crate::heuristics::{Syntax, line_range};
+use crate::workspace;
+
+/// Prefixes of references in resource XML and in Kotlin or Java code.
+const REFERENCE_PREFIXES: [&str; 2] = ["@color/", "R.color."];
+
+/// Decodes an 8-digit or 4-digit hex match as `#AARRGGBB` or `#ARGB`, per
+/// Android convention.
+pub fn argb(color_match: ColorMatch) -> ColorMatch {
+    let range = color_match.info.range;
+    if ![5, 9].contains(&(range.end.character - range.start.character)) {
+        return color_match;
+    }
+    // The hex parser reads the digits as RRGGBBAA.
+    let Color {
+        red,
+        green,
+        blue,
+        alpha,
+    } = color_match.info.color;
+    ColorMatch {
+        info: ColorInformation {
+            range,
+            color: Color {
+                red: green,
+                green: blue,
+                blue: alpha,
+                alpha: red,
+            },
+        },
+        ..color_match
+    }
+}
+
+/// A `<color name="...">` resource.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ColorResource {
+    pub name: String,
+    /// Location of the value of the `name` attribute.
+    pub location: Location,
+    pub color: Color,
+}
+
+/// Returns the color resources defined in a resource document, e.g.
+/// `<color name="primary">#1A73E8</color>`. Values that are references to
+/// other resources are skipped.
+pub fn definitions(uri: &Uri, document: &Document) -> Vec<ColorResource> {
+    let mut resources = Vec::new();
+    for (line_idx, line) in document.lines().enumerate() {
+        let Some(tag_idx) = line.find("<color ") else {
+            continue;
+        };
+        let Some(name_idx) = line[tag_idx..].find("name=\"").map(|idx| tag_idx + idx + 6) else {
+            continue;
+        };
+        let Some(name_len) = line[name_idx..].find('"') else {
+            continue;
+        };
+        let name_range = line_range(line, line_idx, name_idx..name_idx + name_len);
+        let Some(color) = document
+            .get_line_colors(line_idx..=line_idx)
+            .into_iter()
+            .find(|color_info| color_info.range.start > name_range.end)
+        else {
+            continue;
+        };
+        resources.push(ColorResource {
+            name: line[name_idx..name_idx + name_len].to_string(),
+            location: Location {
+                uri: uri.clone(),
+                range: name_range,
+            },
+            color: color.color,
+        });
+    }
+    resources
+}
+
+/// A reference to a color resource.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Reference<'a> {
+    /// Range of the whole reference, e.g. `@color/primary`.
+    pub range: Range,
+    /// Range of the resource name.
+    pub name_range: Range,
+    pub name: &'a str,
+}
+
+/// Returns `@color/name` and `R.color.name` references in a line.
+pub fn references(line: &str, line_idx: usize) -> Vec<Reference<'_>> {
+    let mut references: Vec<Reference> = REFERENCE_PREFIXES
+        .iter()
+        .flat_map(|prefix| line.match_indices(prefix))
+        .filter_map(|(start, prefix)| {
+            let name_start = start + prefix.len();
+            let name_len = line[name_start..]
+                .find(|c: char| !is_name_char(c))
+                .unwrap_or(line.len() - name_start);
+            (name_len > 0).then(|| {
+                let end = name_start + name_len;
+                Reference {
+                    range: line_range(line, line_idx, start..end),
+                    name_range: line_range(line, line_idx, name_start..end),
+                    name: &line[name_start..end],
+                }
+            })
+        })
+        .collect();
+    references.sort_by_key(|reference| reference.range.start);
+    references
+}
+
+/// Returns the reference at `position` in a document.
+pub fn reference_at(document: &Document, position: Position) -> Option<Reference<'_>> {
+    let line = document.line(position.line as usize)?;
+    references(line, position.line as usize)
+        .into_iter()
+        .find(|reference| reference.range.start <= position && position <= reference.range.end)
+}
+
+/// Whether `c` can be part of a resource name.
+pub fn is_name_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || c == '.'
+}
+
+/// Returns the resource files, `res/*/*.xml`, under `root`.
+pub fn workspace_resource_files(root: &Path) -> Vec<PathBuf> {
+    workspace::files(root, |path| {
+        path.extension().is_some_and(|extension| extension == "xml")
+            && path
+                .parent()
+                .and_then(Path::parent)
+                .is_some_and(|res_dir| res_dir.ends_with("res"))
+    })
+}
+
+/// Returns the value resource files, `values*/*.xml`, of the `res` directory
+/// containing the resource at `uri`.
+pub fn values_files(uri: &Uri) -> Vec<PathBuf> {
+    let Some(path) = uri.to_file_path() else {
+        return Vec::new();
+    };
+    let Some(res_dir) = path.ancestors().find(|dir| dir.ends_with("res")) else {
+        return Vec::new();
+    };
+    let Ok(entries) = std::fs::read_dir(res_dir) else {
+        return Vec::new();
+    };
+
+    let mut files: Vec<PathBuf> = entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|dir| {
+            dir.file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| name.starts_with("values"))
+        })
+        .filter_map(|dir| std::fs::read_dir(dir).ok())
+        .flat_map(|entries| entries.flatten().map(|entry| entry.path()))
+        .filter(|file| file.extension().is_some_and(|extension| extension == "xml"))
+        .collect();
+    files.sort();
+    files
+}
+
+/// Color resources and references to them, by file.
+#[derive(Debug, Default)]
+pub struct ResourceIndex {
+    definitions: HashMap<Uri, Vec<ColorResource>>,
+    /// Name ranges and names of references.
+    references: HashMap<Uri, Vec<(Range, String)>>,
+}
+
+impl ResourceIndex {
+    pub fn contains(&self, uri: &Uri) -> bool {
+        self.definitions.contains_key(uri) || self.references.contains_key(uri)
+    }
+
+    /// Indexes the references of a document and, for Android resources, its
+    /// definitions.
+    pub fn update(&mut self, uri: Uri, document: &Document) {
+        if document.syntax() == Syntax::Android {
+            self.definitions
+                .insert(uri.clone(), definitions(&uri, document));
+        }
+        let references: Vec<(Range, String)> = document
+            .lines()
+            .enumerate()
+            .flat_map(|(line_idx, line)| {
+                references(line, line_idx)
+                    .into_iter()
+                    .map(|reference| (reference.name_range, reference.name.to_string()))
+                    .collect::<Vec<_>>()
+            })
+            .collect();
+        if references.is_empty() {
+            self.references.remove(&uri);
+        } else {
+            self.references.insert(uri, references);
+        }
+    }
+
+    /// Indexes a resource file read from disk.
+    pub fn update_from_text(&mut self, uri: Uri, text: &str) {
+        let document = Document::with_options(text, Default::default(), Syntax::Android);
+        self.update(uri, &document);
+    }
+
+    /// Returns the resource named `name`. Definitions are looked up by file
+    /// URI, so the result doesn't depend on the order files were indexed in.
+    pub fn get(&self, name: &str) -> Option<&ColorResource> {
+        self.definitions
+            .iter()
+            .filter_map(|(uri, resources)| {
+                Some((
+                    uri,
+                    resources.iter().find(|resource| resource.name == name)?,
+                ))
+            })
+            .min_by(|(a, _), (b, _)| a.as_str().cmp(b.as_str()))
+            .map(|(_, resource)| resource)
+    }
+
+    /// Returns all indexed resources.
+    pub fn all(&self) -> impl Iterator<Item = &ColorResource> {
+        self.definitions.values().flatten()
+    }
+
+    /// Returns the resource whose name is at `position` in `uri`.
+    pub fn definition_at(&self, uri: &Uri, position: Position) -> Option<&ColorResource> {
+        self.definitions.get(uri)?.iter().find(|resource| {
+            let range = resource.location.range;
+            range.start <= position && position <= range.end
+        })
+    }
+
+    /// Resolves the references of a document.
+    pub fn resolve(&self, document: &Document) -> Vec<ColorInformation> {
+        document
+            .lines()
+            .enumerate()
+            .flat_map(|(line_idx, line)| {
+                references(line, line_idx)
+                    .into_iter()
+                    .filter_map(|reference| {
+                        Some(ColorInformation {
+                            range: reference.range,
+                            color: self.get(reference.name)?.color,
+                        })
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect()
+    }
+
+    /// Returns the ranges to edit when renaming the resource `name`: the
+    /// names of its definitions and of all indexed references.
+    pub fn rename_locations(&self, name: &str) -> HashMap<Uri, Vec<Range>> {
+        let mut locations: HashMap<Uri, Vec<Range>> = HashMap::new();
+        for (uri, resources) in &self.definitions {
+            for resource in resources.iter().filter(|resource| resource.name == name) {
+                locations
+                    .entry(uri.clone())
+                    .or_default()
+                    .push(resource.location.range);
+            }
+        }
+        for (uri, references) in &self.references {
+            for (range, _) in references.iter().filter(|(_, reference)| reference == name) {
+                locations.entry(uri.clone()).or_default().push(*range);
+            }
+        }
+        locations
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tower_lsp_server::ls_types::Uri;
+
+    use crate::android::{ResourceIndex, references};
+    use crate::color::to_hex;
+    use crate::document::Document;
+    use crate::heuristics::Syntax;
+
+    #[test]
+    fn decodes_argb() {
+        let document = Document::with_options(
+            "<color name=\"scrim\">#801A73E8</color>",
+            Default::default(),
+            Syntax::Android,
+        );
+        assert_eq!(to_hex(&document.get_colors()[0].color), "#1A73E880");
+
+        let document = Document::with_options(
+            "<color name=\"scrim\">#8000</color>",
+            Default::default(),
+            Syntax::Android,
+        );
+        assert_eq!(to_hex(&document.get_colors()[0].color), "#00000088");
+    }
+
+    #[test]
+    fn resolves_references() {
+        let uri: Uri = "file:///app/src/main/res/values/colors.xml"
+            .parse()
+            .unwrap();
+        let mut index = ResourceIndex::default();
+        index.update_from_text(
+            uri,
+            "<resources>\n  <color name=\"primary\">#1A73E8</color>\n</resources>\n",
+        );
+
+        let layout = Document::with_options(
+            "<View android:background=\"@color/primary\" android:tint=\"@color/missing\"/>",
+            Default::default(),
+            Syntax::Android,
+        );
+        let colors = index.resolve(&layout);
+        assert_eq!(colors.len(), 1);
+        assert_eq!(colors[0].range.start.character, 26);
+        assert_eq!(colors[0].range.end.character, 40);
+        assert_eq!(to_hex(&colors[0].color), "#1A73E8");
+
+        let definition = index.get("primary").unwrap();
+        assert_eq!(definition.location.range.start.line, 1);
+        assert_eq!(definition.location.range.start.character, 15);
+        assert_eq!(references("@android:color/white", 0), []);
+        assert_eq!(references("tint(R.color.primary)", 0)[0].name, "primary");
+    }
+
+    #[test]
+    fn rename_locations() {
+        let values: Uri = "file:///lib/src/main/res/values/colors.xml"
+            .parse()
+            .unwrap();
+        let layout: Uri = "file:///app/src/main/res/layout/main.xml".parse().unwrap();
+        let mut index = ResourceIndex::default();
+        index.update_from_text(values.clone(), "<color name=\"brand\">#1A73E8</color>");
+        index.update_from_text(layout.clone(), "<View android:tint=\"@color/brand\"/>");
+
+        let locations = index.rename_locations("brand");
+        assert_eq!(locations[&values][0].start.character, 13);
+        assert_eq!(locations[&layout][0].start.character, 27);
+    }
+}
@@ -0,0 +1,225 @@
+use tower_lsp_server::ls_types::{Color, ColorInformation};
+
+use crate::heuristics::line_range;
+
+/// xterm defaults for the 16 base colors.
+const BASE_COLORS: [u32; 16] = [
+    0x000000, 0xCD0000, 0x00CD00, 0xCDCD00, 0x0000EE, 0xCD00CD, 0x00CDCD, 0xE5E5E5, 0x7F7F7F,
+    0xFF0000, 0x00FF00, 0xFFFF00, 0x5C5CFF, 0xFF00FF, 0x00FFFF, 0xFFFFFF,
+];
+
+/// Channel levels of the 6x6x6 color cube.
+const CUBE_LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+/// Names of the 8 normal colors, by index.
+const NAMES: [&str; 8] = [
+    "black", "red", "green", "yellow", "blue", "magenta", "cyan", "white",
+];
+
+/// Returns the color of an index in the xterm 256-color palette.
+pub fn ansi256(index: u8) -> Color {
+    let channel = |value: u8| value as f32 / 255.0;
+    let (red, green, blue) = match index {
+        0..16 => {
+            let rgb = BASE_COLORS[index as usize];
+            ((rgb >> 16) as u8, (rgb >> 8) as u8, rgb as u8)
+        }
+        16..232 => {
+            let i = index - 16;
+            (
+                CUBE_LEVELS[(i / 36) as usize],
+                CUBE_LEVELS[(i / 6 % 6) as usize],
+                CUBE_LEVELS[(i % 6) as usize],
+            )
+        }
+        232.. => {
+            let gray = 8 + (index - 232) * 10;
+            (gray, gray, gray)
+        }
+    };
+    Color {
+        red: channel(red),
+        green: channel(green),
+        blue: channel(blue),
+        alpha: 1.0,
+    }
+}
+
+/// Returns the palette index of a terminal color name such as `red`,
+/// `bright-red` or `light-red`. `purple` is accepted for magenta, and
+/// `gray` for bright black.
+pub fn index_of(name: &str) -> Option<u8> {
+    let name = name.to_ascii_lowercase();
+    let (bright, base) = match name
+        .strip_prefix("bright-")
+        .or_else(|| name.strip_prefix("bright_"))
+        .or_else(|| name.strip_prefix("light-"))
+    {
+        Some(base) => (true, base),
+        None => (false, name.as_str()),
+    };
+    let index = match base {
+        "purple" => 5,
+        "gray" | "grey" if bright => return Some(7),
+        "gray" | "grey" => return Some(8),
+        base => NAMES.iter().position(|&name| name == base)? as u8,
+    };
+    Some(if bright { index + 8 } else { index })
+}
+
+/// Control sequence introducers as written in source strings, followed by a
+/// raw escape character.
+const INTRODUCERS: [&str; 10] = [
+    "\\x1b[", "\\x1B[", "\\033[", "\\e[", "\\u001b[", "\\u001B[", "\\u{1b}[", "\\u{1B}[", "\\27[",
+    "\x1b[",
+];
+
+/// Finds the colors set by SGR escape sequences such as `\x1b[38;2;255;100;0m`
+/// or `\033[48;5;208m`: truecolor, 256-color and the 16 base colors, for the
+/// foreground, background and underline. A sequence setting one color is
+/// covered whole, otherwise each color covers its own parameters.
+pub fn escapes(line: &str, line_idx: usize) -> Vec<ColorInformation> {
+    let mut colors = Vec::new();
+    let mut idx = 0;
+    while let Some(offset) = line[idx..].find(['\\', '\x1b']) {
+        let start = idx + offset;
+        let Some(introducer) = INTRODUCERS
+            .iter()
+            .find(|introducer| line[start..].starts_with(**introducer))
+        else {
+            idx = start + 1;
+            continue;
+        };
+        let params_start = start + introducer.len();
+        let params_len = line[params_start..]
+            .find(|c: char| !c.is_ascii_digit() && c != ';')
+            .unwrap_or(line.len() - params_start);
+        let params_end = params_start + params_len;
+        idx = params_end;
+        if !line[params_end..].starts_with('m') {
+            continue;
+        }
+        let found = sgr_colors(&line[params_start..params_end]);
+        if let [(_, color)] = found[..] {
+            colors.push(ColorInformation {
+                range: line_range(line, line_idx, start..params_end + 1),
+                color,
+            });
+        } else {
+            colors.extend(found.into_iter().map(|(bytes, color)| ColorInformation {
+                range: line_range(
+                    line,
+                    line_idx,
+                    params_start + bytes.start..params_start + bytes.end,
+                ),
+                color,
+            }));
+        }
+    }
+    colors
+}
+
+/// Returns the colors set by the `;`-separated parameters of an SGR
+/// sequence, with the byte range of the parameters setting each.
+fn sgr_colors(params: &str) -> Vec<(std::ops::Range<usize>, Color)> {
+    let mut spans = Vec::new();
+    let mut offset = 0;
+    for param in params.split(';') {
+        spans.push((
+            offset..offset + param.len(),
+            param.parse::<u16>().unwrap_or(0),
+        ));
+        offset += param.len() + 1;
+    }
+    let channel = |value: u16| u8::try_from(value).ok().map(|value| value as f32 / 255.0);
+    let mut colors = Vec::new();
+    let mut i = 0;
+    while i < spans.len() {
+        let value = |n: usize| spans.get(i + n).map(|(_, value)| *value);
+        let (len, color) = match value(0).unwrap_or(0) {
+            code @ (30..=37 | 40..=47) => (1, Some(ansi256((code % 10) as u8))),
+            code @ (90..=97 | 100..=107) => (1, Some(ansi256((code % 10) as u8 + 8))),
+            38 | 48 | 58 => match value(1) {
+                Some(5) => (
+                    3,
+                    value(2)
+                        .and_then(|index| u8::try_from(index).ok())
+                        .map(ansi256),
+                ),
+                Some(2) => (
+                    5,
+                    (|| {
+                        Some(Color {
+                            red: channel(value(2)?)?,
+                            green: channel(value(3)?)?,
+                            blue: channel(value(4)?)?,
+                            alpha: 1.0,
+                        })
+                    })(),
+                ),
+                _ => (1, None),
+            },
+            _ => (1, None),
+        };
+        if let Some(color) = color {
+            colors.push((spans[i].0.start..spans[i + len - 1].0.end, color));
+        }
+        i += len;
+    }
+    colors
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ansi::{ansi256, escapes, index_of};
+    use crate::color::to_hex;
+
+    #[test]
+    fn palette_ranges() {
+        assert_eq!(to_hex(&ansi256(1)), "#CD0000");
+        assert_eq!(to_hex(&ansi256(208)), "#FF8700");
+        assert_eq!(to_hex(&ansi256(244)), "#808080");
+    }
+
+    #[test]
+    fn names() {
+        assert_eq!(index_of("red"), Some(1));
+        assert_eq!(index_of("bright-purple"), Some(13));
+        assert_eq!(index_of("light-gray"), Some(7));
+        assert_eq!(index_of("gray"), Some(8));
+        assert_eq!(index_of("orange"), None);
+    }
+
+    #[test]
+    fn escape_sequences() {
+        let found = |line: &str| {
+            escapes(line, 0)
+                .iter()
+                .map(|info| {
+                    (
+                        info.range.start.character,
+                        info.range.end.character,
+                        to_hex(&info.color),
+                    )
+                })
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            found(r#"print("\x1b[38;2;255;100;0mhot\x1b[0m")"#),
+            [(7, 27, "#FF6400".to_string())]
+        );
+        assert_eq!(
+            found(r"echo -e '\033[1;48;5;208m'"),
+            [(9, 25, "#FF8700".to_string())]
+        );
+        assert_eq!(found("\u{1b}[31m"), [(0, 5, "#CD0000".to_string())]);
+        assert_eq!(
+            found(r"\e[38;5;196;48;2;0;0;0m"),
+            [
+                (3, 11, "#FF0000".to_string()),
+                (12, 22, "#000000".to_string())
+            ]
+        );
+        assert!(found(r"\x1b[38;2;300;0;0m \x1b[38;5m \x1b[2J \e[0m").is_empty());
+    }
+}
@@ -0,0 +1,97 @@
+//! Coalescing of bursts of document changes, such as multi-cursor edits or a
+//! paste, so the document-wide work after a change, reindexing its colors and
+//! publishing its diagnostics, runs once per burst rather than once per change.
+
+use std::collections::{HashMap, HashSet};
+use std::sync::{Mutex, PoisonError};
+
+use tower_lsp_server::ls_types::Uri;
+
+/// Changes queued per document, and the documents left to settle.
+#[derive(Debug, Default)]
+pub struct Bursts {
+    /// Changes received but not applied yet, per document.
+    queued: HashMap<Uri, usize>,
+    /// Documents whose settling was left to a later change of their burst.
+    unsettled: HashSet<Uri>,
+}
+
+impl Bursts {
+    /// Counts a change of `uri` as queued until the returned guard is dropped.
+    pub fn queue<'a>(bursts: &'a Mutex<Self>, uri: &Uri) -> QueuedChange<'a> {
+        let mut locked = bursts.lock().unwrap_or_else(PoisonError::into_inner);
+        *locked.queued.entry(uri.clone()).or_default() += 1;
+        QueuedChange {
+            bursts,
+            uri: uri.clone(),
+        }
+    }
+
+    /// Whether `uri` is left to settle, clearing it, so reading its colors
+    /// can settle it without waiting for the rest of its burst.
+    pub fn take_unsettled(&mut self, uri: &Uri) -> bool {
+        self.unsettled.remove(uri)
+    }
+}
+
+/// A change counted as queued in `Bursts`.
+pub struct QueuedChange<'a> {
+    bursts: &'a Mutex<Bursts>,
+    uri: Uri,
+}
+
+impl QueuedChange<'_> {
+    /// Whether later changes of the document are queued behind this applied
+    /// one, in which case the document is left for them to settle.
+    pub fn defer(&self) -> bool {
+        let mut bursts = self.bursts.lock().unwrap_or_else(PoisonError::into_inner);
+        let later = bursts.queued.get(&self.uri).is_some_and(|&count| count > 1);
+        if later {
+            bursts.unsettled.insert(self.uri.clone());
+        } else {
+            bursts.unsettled.remove(&self.uri);
+        }
+        later
+    }
+}
+
+impl Drop for QueuedChange<'_> {
+    fn drop(&mut self) {
+        let mut bursts = self.bursts.lock().unwrap_or_else(PoisonError::into_inner);
+        if let Some(count) = bursts.queued.get_mut(&self.uri) {
+            *count -= 1;
+            if *count == 0 {
+                bursts.queued.remove(&self.uri);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use tower_lsp_server::ls_types::Uri;
+
+    use crate::burst::Bursts;
+
+    #[test]
+    fn defers_to_later_changes() {
+        let bursts = Mutex::new(Bursts::default());
+        let uri: Uri = "file:///a.css".parse().unwrap();
+
+        let first = Bursts::queue(&bursts, &uri);
+        let second = Bursts::queue(&bursts, &uri);
+        assert!(first.defer());
+        drop(first);
+        assert!(!second.defer());
+        drop(second);
+        assert!(!bursts.lock().unwrap().take_unsettled(&uri));
+
+        let first = Bursts::queue(&bursts, &uri);
+        let _second = Bursts::queue(&bursts, &uri);
+        assert!(first.defer());
+        assert!(bursts.lock().unwrap().take_unsettled(&uri));
+        assert!(!bursts.lock().unwrap().take_unsettled(&uri));
+    }
+}
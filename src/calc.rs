@@ -0,0 +1,112 @@
+//! Constant folding of CSS `calc()` expressions in color function arguments,
+//! e.g. `calc(255 * 0.5)` or `calc(50% + 10)`.
+
+/// Evaluates the body of a `calc()` expression made of numbers, `+`, `-`,
+/// `*`, `/` and parentheses. Percentages are resolved against `basis`, so
+/// `50%` of a channel in [0, 255] is 127.5 and may be mixed with numbers.
+/// Degrees are plain numbers, and other units aren't constant.
+pub fn evaluate(expr: &str, basis: f32) -> Option<f32> {
+    let mut parser = Parser {
+        rest: expr.trim(),
+        basis,
+    };
+    let value = parser.sum()?;
+    (parser.rest.trim().is_empty() && value.is_finite()).then_some(value)
+}
+
+/// Returns the body of `calc(...)`, if `value` is a call.
+pub fn body(value: &str) -> Option<&str> {
+    value.trim().strip_prefix("calc(")?.strip_suffix(')')
+}
+
+struct Parser<'a> {
+    rest: &'a str,
+    basis: f32,
+}
+
+impl Parser<'_> {
+    fn sum(&mut self) -> Option<f32> {
+        let mut value = self.product()?;
+        loop {
+            if self.eat('+') {
+                value += self.product()?;
+            } else if self.eat('-') {
+                value -= self.product()?;
+            } else {
+                return Some(value);
+            }
+        }
+    }
+
+    fn product(&mut self) -> Option<f32> {
+        let mut value = self.operand()?;
+        loop {
+            if self.eat('*') {
+                value *= self.operand()?;
+            } else if self.eat('/') {
+                value /= self.operand()?;
+            } else {
+                return Some(value);
+            }
+        }
+    }
+
+    fn operand(&mut self) -> Option<f32> {
+        self.rest = self.rest.trim_start();
+        if self.eat('-') {
+            return Some(-self.operand()?);
+        }
+        // Nested `calc()` is the same as parentheses.
+        if let Some(rest) = self.rest.strip_prefix("calc") {
+            self.rest = rest;
+        }
+        if self.eat('(') {
+            let value = self.sum()?;
+            return self.eat(')').then_some(value);
+        }
+
+        let length = self
+            .rest
+            .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+            .unwrap_or(self.rest.len());
+        let number: f32 = self.rest[..length].parse().ok()?;
+        self.rest = &self.rest[length..];
+        if self.eat('%') {
+            Some(number * self.basis / 100.0)
+        } else if let Some(rest) = self.rest.strip_prefix("deg") {
+            self.rest = rest;
+            Some(number)
+        } else if self.rest.starts_with(char::is_alphabetic) {
+            None
+        } else {
+            Some(number)
+        }
+    }
+
+    /// Consumes `c` after optional whitespace.
+    fn eat(&mut self, c: char) -> bool {
+        match self.rest.trim_start().strip_prefix(c) {
+            Some(rest) => {
+                self.rest = rest;
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::calc::evaluate;
+
+    #[test]
+    fn folds_constants() {
+        assert_eq!(evaluate("255 * 0.5", 255.0), Some(127.5));
+        assert_eq!(evaluate("50% + 10", 255.0), Some(137.5));
+        assert_eq!(evaluate("(100 - 20) / 2 * -1", 255.0), Some(-40.0));
+        assert_eq!(evaluate("calc(90deg * 2)", 1.0), Some(180.0));
+        assert_eq!(evaluate("10px + 5", 255.0), None);
+        assert_eq!(evaluate("1 / 0", 255.0), None);
+        assert_eq!(evaluate("var(--x) * 2", 255.0), None);
+    }
+}
@@ -0,0 +1,169 @@
+use tower_lsp_server::ls_types::{ClientCapabilities, ColorInformation, Range, SemanticToken};
+
+/// LSP surfaces used to present colors, negotiated from client capabilities.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ColorSurfaces {
+    pub document_color: bool,
+    /// Fallback when the client can't render `textDocument/documentColor`.
+    pub hover: bool,
+    /// Fallback when the client can't render `textDocument/documentColor`.
+    pub semantic_tokens: bool,
+}
+
+impl ColorSurfaces {
+    /// Prefers `textDocument/documentColor`, and falls back to hover and
+    /// semantic tokens only for clients that don't support it.
+    pub fn negotiate(capabilities: &ClientCapabilities) -> Self {
+        let Some(text_document) = &capabilities.text_document else {
+            return Self::default();
+        };
+        if text_document.color_provider.is_some() {
+            return Self {
+                document_color: true,
+                ..Default::default()
+            };
+        }
+
+        Self {
+            document_color: false,
+            hover: text_document.hover.is_some(),
+            semantic_tokens: text_document.semantic_tokens.is_some(),
+        }
+    }
+
+    /// Whether the client can't present colors at all.
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// Encodes color ranges as relative semantic tokens of type `token_type`.
+/// Ranges spanning several lines are split into a token per line, as
+/// tokens can't cross lines without `multilineTokenSupport`; `line_len`
+/// returns the UTF-16 length of a line.
+pub fn encode_semantic_tokens(
+    colors: &[ColorInformation],
+    line_len: impl Fn(u32) -> u32,
+    token_type: u32,
+) -> Vec<SemanticToken> {
+    let mut tokens = Vec::with_capacity(colors.len());
+    let (mut prev_line, mut prev_start) = (0, 0);
+    for color_info in colors {
+        let Range { start, end } = color_info.range;
+        for line in start.line..=end.line {
+            let from = if line == start.line {
+                start.character
+            } else {
+                0
+            };
+            let to = if line == end.line {
+                end.character
+            } else {
+                line_len(line)
+            };
+            if to <= from {
+                continue;
+            }
+            let delta_line = line - prev_line;
+            let delta_start = if delta_line == 0 {
+                from - prev_start
+            } else {
+                from
+            };
+            tokens.push(SemanticToken {
+                delta_line,
+                delta_start,
+                length: to - from,
+                token_type,
+                token_modifiers_bitset: 0,
+            });
+            (prev_line, prev_start) = (line, from);
+        }
+    }
+    tokens
+}
+
+#[cfg(test)]
+mod tests {
+    use tower_lsp_server::ls_types::{
+        ClientCapabilities, Color, ColorInformation, DocumentColorClientCapabilities,
+        HoverClientCapabilities, Position, Range, TextDocumentClientCapabilities,
+    };
+
+    use crate::capabilities::{ColorSurfaces, encode_semantic_tokens};
+    use crate::document::Document;
+
+    #[test]
+    fn prefers_document_color() {
+        let capabilities = ClientCapabilities {
+            text_document: Some(TextDocumentClientCapabilities {
+                color_provider: Some(DocumentColorClientCapabilities::default()),
+                hover: Some(HoverClientCapabilities::default()),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            ColorSurfaces::negotiate(&capabilities),
+            ColorSurfaces {
+                document_color: true,
+                hover: false,
+                semantic_tokens: false,
+            }
+        );
+    }
+
+    #[test]
+    fn falls_back_to_hover() {
+        let capabilities = ClientCapabilities {
+            text_document: Some(TextDocumentClientCapabilities {
+                hover: Some(HoverClientCapabilities::default()),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+
+        let surfaces = ColorSurfaces::negotiate(&capabilities);
+        assert!(surfaces.hover);
+        assert!(!surfaces.document_color);
+        assert!(!surfaces.is_empty());
+    }
+
+    #[test]
+    fn empty_capabilities() {
+        assert!(ColorSurfaces::negotiate(&ClientCapabilities::default()).is_empty());
+    }
+
+    #[test]
+    fn semantic_tokens_are_relative() {
+        let document = Document::from("#FF0000 #00FF00\n\n  #0000FF\n");
+        let tokens = encode_semantic_tokens(&document.get_colors(), |_| 0, 0);
+
+        let encoded: Vec<_> = tokens
+            .iter()
+            .map(|t| (t.delta_line, t.delta_start, t.length))
+            .collect();
+        assert_eq!(encoded, [(0, 0, 7), (0, 8, 7), (2, 2, 7)]);
+    }
+
+    #[test]
+    fn multiline_colors_are_split_per_line() {
+        let colors = [ColorInformation {
+            range: Range::new(Position::new(1, 4), Position::new(3, 2)),
+            color: Color {
+                red: 1.0,
+                green: 0.0,
+                blue: 0.0,
+                alpha: 1.0,
+            },
+        }];
+        let tokens = encode_semantic_tokens(&colors, |line| [0, 10, 6, 5][line as usize], 0);
+
+        let encoded: Vec<_> = tokens
+            .iter()
+            .map(|t| (t.delta_line, t.delta_start, t.length))
+            .collect();
+        assert_eq!(encoded, [(1, 4, 6), (1, 0, 6), (1, 0, 2)]);
+    }
+}
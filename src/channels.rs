@@ -0,0 +1,116 @@
+//! Ranges of the channels within functional colors, so editors can select or
+//! change a single channel, e.g. the `115` of `rgb(26 115 232)`.
+
+use serde::Serialize;
+use tower_lsp_server::ls_types::{Color, Position, Range};
+
+use crate::color::{ColorFormat, function_channels};
+use crate::document::Document;
+
+/// A functional color and the ranges of its channels.
+#[derive(Debug, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ColorChannels {
+    pub range: Range,
+    pub color: Color,
+    pub channels: Vec<ChannelRange>,
+}
+
+/// A channel of a functional color, such as `red`, `hue`, `chroma` or
+/// `alpha`, and its range.
+#[derive(Debug, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ChannelRange {
+    pub name: &'static str,
+    pub range: Range,
+}
+
+/// Returns the channels of the functional colors of a document, or of the
+/// one at `position` when given.
+pub fn find(document: &Document, position: Option<Position>) -> Vec<ColorChannels> {
+    document
+        .matches()
+        .filter(|color_match| color_match.format == ColorFormat::Function)
+        .filter(|color_match| {
+            let range = color_match.info.range;
+            position.is_none_or(|position| range.start <= position && position <= range.end)
+        })
+        .filter_map(|color_match| {
+            let range = color_match.info.range;
+            let text = document.range_text(&range)?;
+            let position = |byte_idx: usize| {
+                Position::new(
+                    range.start.line,
+                    range.start.character + text[..byte_idx].encode_utf16().count() as u32,
+                )
+            };
+            let channels = function_channels(text)?
+                .into_iter()
+                .map(|(name, bytes)| ChannelRange {
+                    name,
+                    range: Range::new(position(bytes.start), position(bytes.end)),
+                })
+                .collect();
+            Some(ColorChannels {
+                range,
+                color: color_match.info.color,
+                channels,
+            })
+        })
+        .collect()
+}
+
+/// Returns the name of the channel of a functional color at `position`.
+pub fn at(document: &Document, position: Position) -> Option<&'static str> {
+    find(document, Some(position))
+        .into_iter()
+        .flat_map(|color| color.channels)
+        .find(|channel| channel.range.start <= position && position <= channel.range.end)
+        .map(|channel| channel.name)
+}
+
+#[cfg(test)]
+mod tests {
+    use tower_lsp_server::ls_types::Position;
+
+    use crate::channels::{at, find};
+    use crate::document::Document;
+
+    #[test]
+    fn finds_channel_ranges() {
+        let document =
+            Document::from("a: rgb(26, 115, 232); b: oklch(70% 0.1 200 / 50%); c: #fff\n");
+        let colors = find(&document, None);
+        assert_eq!(colors.len(), 2);
+        let spans = |idx: usize| {
+            colors[idx]
+                .channels
+                .iter()
+                .map(|channel| {
+                    (
+                        channel.name,
+                        channel.range.start.character,
+                        channel.range.end.character,
+                    )
+                })
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            spans(0),
+            [("red", 7, 9), ("green", 11, 14), ("blue", 16, 19)]
+        );
+        assert_eq!(
+            spans(1),
+            [
+                ("lightness", 31, 34),
+                ("chroma", 35, 38),
+                ("hue", 39, 42),
+                ("alpha", 45, 48),
+            ]
+        );
+
+        assert_eq!(find(&document, Some(Position::new(0, 58))), []);
+        assert_eq!(at(&document, Position::new(0, 12)), Some("green"));
+        assert_eq!(at(&document, Position::new(0, 10)), None);
+    }
+}
@@ -0,0 +1,349 @@
+//! Command-line arguments of the `chroma-ls` binary.
+
+use std::path::PathBuf;
+
+use thiserror::Error;
+use tracing::level_filters::LevelFilter;
+
+use crate::presentation::Notation;
+
+pub const USAGE: &str = "\
+usage: chroma-ls [--stdio | --listen <addr:port> | --socket <path>]
+                 [--log-file <path>] [--log-level <level>]
+       chroma-ls scan [--expect-none] [--json] <path>...
+       chroma-ls convert --to <hex|rgb|hsl|oklch> [--stdout] <path>...
+
+Options:
+  --stdio               talk over stdin and stdout (default)
+  --listen <addr:port>  accept clients over TCP
+  --socket <path>       accept clients over a Unix socket, or a named pipe on Windows
+  --log-file <path>     append logs to a file instead of stderr
+  --log-level <level>   off, error, warn (default), info, debug or trace
+  -h, --help            print this help
+  -V, --version         print the version";
+
+pub const SCAN_USAGE: &str = "usage: chroma-ls scan [--expect-none] [--json] <path>...";
+
+pub const CONVERT_USAGE: &str =
+    "usage: chroma-ls convert --to <hex|rgb|hsl|oklch> [--stdout] <path>...";
+
+/// Transport the server is reached over.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum Transport {
+    #[default]
+    Stdio,
+    Tcp(String),
+    Socket(PathBuf),
+}
+
+/// How the server runs.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct ServeOptions {
+    pub transport: Transport,
+    pub log_file: Option<PathBuf>,
+    pub log_level: Option<LevelFilter>,
+}
+
+/// What the binary was asked to do.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Command {
+    Serve(ServeOptions),
+    Scan {
+        expect_none: bool,
+        json: bool,
+        paths: Vec<PathBuf>,
+    },
+    Convert {
+        notation: Notation,
+        stdout: bool,
+        paths: Vec<PathBuf>,
+    },
+    /// Prints the usage of the binary or of a subcommand.
+    Help(&'static str),
+    Version,
+}
+
+/// Invalid arguments, printed with the usage.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum CliError {
+    #[error("unknown flag {0}")]
+    UnknownFlag(String),
+    #[error("unexpected argument {0}")]
+    UnexpectedArgument(String),
+    #[error("{0} expects a value")]
+    MissingValue(&'static str),
+    #[error("{0} doesn't take a value")]
+    UnexpectedValue(&'static str),
+    #[error("invalid log level {0}, expected off, error, warn, info, debug or trace")]
+    InvalidLogLevel(String),
+    #[error("invalid notation {0}, expected hex, rgb, hsl or oklch")]
+    InvalidNotation(String),
+    #[error("only one of --stdio, --listen and --socket can be given")]
+    ConflictingTransports,
+    #[error("convert expects --to <notation>")]
+    NoNotation,
+    #[error("{0} expects at least one path")]
+    NoPaths(&'static str),
+}
+
+impl CliError {
+    /// Usage printed after the error.
+    pub fn usage(&self) -> &'static str {
+        match self {
+            CliError::NoPaths("scan") => SCAN_USAGE,
+            CliError::InvalidNotation(_) | CliError::NoNotation | CliError::NoPaths(_) => {
+                CONVERT_USAGE
+            }
+            _ => USAGE,
+        }
+    }
+}
+
+/// Parses the arguments after the program name. Flags take their value as
+/// the next argument or after `=`, as in `--listen=127.0.0.1:9257`.
+pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Command, CliError> {
+    let mut args = args.into_iter().peekable();
+    if args.next_if(|arg| arg == "scan").is_some() {
+        return parse_scan(args);
+    }
+    if args.next_if(|arg| arg == "convert").is_some() {
+        return parse_convert(args);
+    }
+    let mut transport = None;
+    let mut options = ServeOptions::default();
+    while let Some(arg) = args.next() {
+        let (flag, value) = match arg.split_once('=') {
+            Some((flag, value)) if flag.starts_with("--") => (flag, Some(value.to_string())),
+            _ => (arg.as_str(), None),
+        };
+        let mut value_of = |name: &'static str| {
+            value
+                .clone()
+                .or_else(|| args.next())
+                .ok_or(CliError::MissingValue(name))
+        };
+        match flag {
+            "-h" | "--help" => return Ok(Command::Help(USAGE)),
+            "-V" | "--version" => return Ok(Command::Version),
+            "--stdio" if value.is_some() => return Err(CliError::UnexpectedValue("--stdio")),
+            "--stdio" => set_transport(&mut transport, Transport::Stdio)?,
+            "--listen" => set_transport(&mut transport, Transport::Tcp(value_of("--listen")?))?,
+            "--socket" => {
+                let path = value_of("--socket")?;
+                set_transport(&mut transport, Transport::Socket(path.into()))?;
+            }
+            // Passed by VS Code language clients along with the transport;
+            // the server exits with the client's connection anyway.
+            "--log-file" => options.log_file = Some(value_of("--log-file")?.into()),
+            "--log-level" => {
+                let level = value_of("--log-level")?;
+                options.log_level = Some(
+                    level
+                        .parse()
+                        .map_err(|_| CliError::InvalidLogLevel(level))?,
+                );
+            }
+            "--clientProcessId" => {
+                value_of("--clientProcessId")?;
+            }
+            flag if flag.starts_with('-') => return Err(CliError::UnknownFlag(flag.to_string())),
+            _ => return Err(CliError::UnexpectedArgument(arg)),
+        }
+    }
+    options.transport = transport.unwrap_or_default();
+    Ok(Command::Serve(options))
+}
+
+fn set_transport(transport: &mut Option<Transport>, value: Transport) -> Result<(), CliError> {
+    match transport.replace(value) {
+        Some(_) => Err(CliError::ConflictingTransports),
+        None => Ok(()),
+    }
+}
+
+fn parse_scan(args: impl Iterator<Item = String>) -> Result<Command, CliError> {
+    let mut expect_none = false;
+    let mut json = false;
+    let mut paths = Vec::new();
+    for arg in args {
+        match arg.as_str() {
+            "-h" | "--help" => return Ok(Command::Help(SCAN_USAGE)),
+            "--expect-none" => expect_none = true,
+            "--json" => json = true,
+            flag if flag.starts_with('-') => return Err(CliError::UnknownFlag(arg)),
+            _ => paths.push(PathBuf::from(arg)),
+        }
+    }
+    if paths.is_empty() {
+        return Err(CliError::NoPaths("scan"));
+    }
+    Ok(Command::Scan {
+        expect_none,
+        json,
+        paths,
+    })
+}
+
+fn parse_convert(mut args: impl Iterator<Item = String>) -> Result<Command, CliError> {
+    let mut notation = None;
+    let mut stdout = false;
+    let mut paths = Vec::new();
+    while let Some(arg) = args.next() {
+        let (flag, value) = match arg.split_once('=') {
+            Some((flag, value)) if flag.starts_with("--") => (flag, Some(value.to_string())),
+            _ => (arg.as_str(), None),
+        };
+        match flag {
+            "-h" | "--help" => return Ok(Command::Help(CONVERT_USAGE)),
+            "--to" => {
+                let name = value
+                    .or_else(|| args.next())
+                    .ok_or(CliError::MissingValue("--to"))?;
+                notation = Some(Notation::parse(&name).ok_or(CliError::InvalidNotation(name))?);
+            }
+            "--stdout" if value.is_some() => return Err(CliError::UnexpectedValue("--stdout")),
+            "--stdout" => stdout = true,
+            flag if flag.starts_with('-') => return Err(CliError::UnknownFlag(flag.to_string())),
+            _ => paths.push(PathBuf::from(&arg)),
+        }
+    }
+    let notation = notation.ok_or(CliError::NoNotation)?;
+    if paths.is_empty() {
+        return Err(CliError::NoPaths("convert"));
+    }
+    Ok(Command::Convert {
+        notation,
+        stdout,
+        paths,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use tracing::level_filters::LevelFilter;
+
+    use crate::cli::{CONVERT_USAGE, CliError, Command, ServeOptions, Transport, USAGE, parse};
+    use crate::presentation::Notation;
+
+    fn parse_args(args: &[&str]) -> Result<Command, CliError> {
+        parse(args.iter().map(|arg| arg.to_string()))
+    }
+
+    fn serve(transport: Transport) -> Result<Command, CliError> {
+        Ok(Command::Serve(ServeOptions {
+            transport,
+            ..Default::default()
+        }))
+    }
+
+    #[test]
+    fn parses_transports() {
+        assert_eq!(parse_args(&[]), serve(Transport::Stdio));
+        assert_eq!(parse_args(&["--stdio"]), serve(Transport::Stdio));
+        assert_eq!(
+            parse_args(&["--listen", "127.0.0.1:9257"]),
+            serve(Transport::Tcp("127.0.0.1:9257".to_string()))
+        );
+        assert_eq!(
+            parse_args(&["--socket=/tmp/chroma.sock", "--clientProcessId=42"]),
+            serve(Transport::Socket("/tmp/chroma.sock".into()))
+        );
+        assert_eq!(parse_args(&["--stdio", "--version"]), Ok(Command::Version));
+        assert_eq!(parse_args(&["-h"]), Ok(Command::Help(USAGE)));
+    }
+
+    #[test]
+    fn parses_logging() {
+        assert_eq!(
+            parse_args(&["--log-level", "debug", "--log-file=/tmp/chroma.log"]),
+            Ok(Command::Serve(ServeOptions {
+                transport: Transport::Stdio,
+                log_file: Some("/tmp/chroma.log".into()),
+                log_level: Some(LevelFilter::DEBUG),
+            }))
+        );
+        assert_eq!(
+            parse_args(&["--log-level=loud"]),
+            Err(CliError::InvalidLogLevel("loud".to_string()))
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_arguments() {
+        assert_eq!(
+            parse_args(&["--verbose"]),
+            Err(CliError::UnknownFlag("--verbose".to_string()))
+        );
+        assert_eq!(
+            parse_args(&["serve"]),
+            Err(CliError::UnexpectedArgument("serve".to_string()))
+        );
+        assert_eq!(
+            parse_args(&["--listen"]),
+            Err(CliError::MissingValue("--listen"))
+        );
+        assert_eq!(
+            parse_args(&["--stdio", "--socket", "a.sock"]),
+            Err(CliError::ConflictingTransports)
+        );
+        assert_eq!(
+            parse_args(&["--stdio=yes"]),
+            Err(CliError::UnexpectedValue("--stdio"))
+        );
+    }
+
+    #[test]
+    fn parses_scan() {
+        assert_eq!(
+            parse_args(&["scan", "--json", "src", "README.md"]),
+            Ok(Command::Scan {
+                expect_none: false,
+                json: true,
+                paths: vec![PathBuf::from("src"), PathBuf::from("README.md")],
+            })
+        );
+        assert_eq!(
+            parse_args(&["scan", "--json"]),
+            Err(CliError::NoPaths("scan"))
+        );
+        assert_eq!(
+            parse_args(&["scan", "-x", "src"]),
+            Err(CliError::UnknownFlag("-x".to_string()))
+        );
+    }
+
+    #[test]
+    fn parses_convert() {
+        assert_eq!(
+            parse_args(&["convert", "--to", "oklch", "src"]),
+            Ok(Command::Convert {
+                notation: Notation::Oklch,
+                stdout: false,
+                paths: vec![PathBuf::from("src")],
+            })
+        );
+        assert_eq!(
+            parse_args(&["convert", "a.css", "--stdout", "--to=hsl"]),
+            Ok(Command::Convert {
+                notation: Notation::Hsl,
+                stdout: true,
+                paths: vec![PathBuf::from("a.css")],
+            })
+        );
+        assert_eq!(
+            parse_args(&["convert", "--to", "lab", "a.css"]),
+            Err(CliError::InvalidNotation("lab".to_string()))
+        );
+        assert_eq!(parse_args(&["convert", "a.css"]), Err(CliError::NoNotation));
+        assert_eq!(
+            parse_args(&["convert", "--to", "hex"]),
+            Err(CliError::NoPaths("convert"))
+        );
+        assert_eq!(
+            parse_args(&["convert", "--help"]),
+            Ok(Command::Help(CONVERT_USAGE))
+        );
+    }
+}
@@ -1,7 +1,227 @@
+use std::collections::BTreeSet;
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
 use tower_lsp_server::ls_types::{Color, ColorInformation, Position, Range};
 
-/// Parses all hex color codes in a line and returns them as `ColorInformation`.
+use crate::alias::Aliases;
+use crate::android;
+use crate::ansi;
+use crate::calc;
+use crate::colorspace::{from_hsl, from_hwb, from_lab, from_lch, from_oklab, from_oklch};
+use crate::conformance;
+use crate::desktop;
+use crate::heuristics::{KeyPatterns, Syntax, find_key_values, line_range};
+use crate::integer::{self, ChannelOrder};
+use crate::named;
+use crate::profile;
+use crate::tailwind;
+use crate::theme_object;
+
+/// Syntax a color was written in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ColorFormat {
+    /// `#RRGGBB` or `#RRGGBBAA`.
+    Hex,
+    /// A name from the configured aliases.
+    Alias,
+    /// A bare hex or named value of a color key in YAML or TOML, or an RGB
+    /// triple in a theme file.
+    Heuristic,
+    /// A terminal palette index or color name, e.g. `208` or `bright-red`, or
+    /// an escape sequence setting one, e.g. `\x1b[38;5;208m`.
+    Ansi,
+    /// A CSS color function, e.g. `rgb(255, 0, 0)`.
+    Function,
+    /// A Tailwind CSS utility class of the default palette, e.g.
+    /// `bg-red-500/80`.
+    Tailwind,
+}
+
+impl ColorFormat {
+    pub const ALL: [Self; 6] = [
+        Self::Hex,
+        Self::Alias,
+        Self::Heuristic,
+        Self::Ansi,
+        Self::Function,
+        Self::Tailwind,
+    ];
+}
+
+/// Options that control which colors are parsed.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct ParseOptions {
+    pub aliases: Aliases,
+    /// Keys whose bare values are colors in key/value documents.
+    pub color_keys: KeyPatterns,
+    /// Whether CSS named colors are decorated wherever they appear as whole
+    /// words.
+    pub named_colors: bool,
+    /// Formats whose colors are kept, or all formats when `None`.
+    pub formats: Option<BTreeSet<ColorFormat>>,
+    /// Channel order of `0x` integer literals read as colors, if any.
+    pub integer_colors: Option<ChannelOrder>,
+    /// Whether color functions must conform to CSS Color 4 to be decorated.
+    pub strict: bool,
+    /// Colors of Tailwind classes, from the default palette and the
+    /// workspace's Tailwind configs.
+    pub tailwind: Arc<tailwind::Theme>,
+}
+
+impl ParseOptions {
+    /// Whether colors written in `format` are kept.
+    pub fn enables(&self, format: ColorFormat) -> bool {
+        self.formats
+            .as_ref()
+            .is_none_or(|formats| formats.contains(&format))
+    }
+}
+
+/// A color found in a line, along with the format it was written in.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ColorMatch {
+    pub info: ColorInformation,
+    pub format: ColorFormat,
+}
+
+/// Parses all colors in a line, ordered by their position.
+pub fn parse_line(
+    line: &str,
+    line_idx: usize,
+    options: &ParseOptions,
+    syntax: Syntax,
+) -> Vec<ColorMatch> {
+    parse_line_timed(line, line_idx, options, syntax, &mut Untimed)
+}
+
+/// Measures the time spent in each parser of `parse_line_timed`.
+pub(crate) trait Stopwatch {
+    /// Runs `parse`, charging its time to `parser`.
+    fn time<T>(&mut self, parser: &'static str, parse: impl FnOnce() -> T) -> T;
+}
+
+/// Stopwatch of regular parsing, which measures nothing.
+struct Untimed;
+
+impl Stopwatch for Untimed {
+    #[inline(always)]
+    fn time<T>(&mut self, _: &'static str, parse: impl FnOnce() -> T) -> T {
+        parse()
+    }
+}
+
+/// Parses all colors in a line like `parse_line`, timing each parser with
+/// `stopwatch`.
+pub(crate) fn parse_line_timed(
+    line: &str,
+    line_idx: usize,
+    options: &ParseOptions,
+    syntax: Syntax,
+    stopwatch: &mut impl Stopwatch,
+) -> Vec<ColorMatch> {
+    let with_format = |format| move |info| ColorMatch { info, format };
+    let mut colors: Vec<ColorMatch> = stopwatch.time("hex", || {
+        parse_hex_colors(line, line_idx)
+            .into_iter()
+            .map(with_format(ColorFormat::Hex))
+            .collect()
+    });
+    colors.extend(stopwatch.time("function", || {
+        parse_function_colors(line, line_idx, options.strict)
+            .into_iter()
+            .map(with_format(ColorFormat::Function))
+    }));
+
+    let mut other: Vec<ColorMatch> = stopwatch.time("alias", || {
+        options
+            .aliases
+            .find(line, line_idx)
+            .into_iter()
+            .map(with_format(ColorFormat::Alias))
+            .collect()
+    });
+    other.extend(stopwatch.time("ansi", || {
+        ansi::escapes(line, line_idx)
+            .into_iter()
+            .map(with_format(ColorFormat::Ansi))
+    }));
+    other.extend(stopwatch.time("tailwind", || {
+        tailwind::find(line, line_idx, &options.tailwind)
+            .into_iter()
+            .map(with_format(ColorFormat::Tailwind))
+    }));
+    stopwatch.time("syntax", || match syntax {
+        Syntax::KeyValue => {
+            let values = find_key_values(line, line_idx, &options.color_keys);
+            other.extend(values.into_iter().map(with_format(ColorFormat::Heuristic)));
+        }
+        Syntax::Helix => other.extend(profile::helix(line, line_idx)),
+        Syntax::Starship => other.extend(profile::starship(line, line_idx)),
+        Syntax::Zellij => other.extend(profile::zellij(line, line_idx)),
+        Syntax::Alacritty => other.extend(profile::alacritty(line, line_idx)),
+        Syntax::Ghostty => other.extend(profile::ghostty(line, line_idx)),
+        Syntax::TmTheme => other.extend(profile::tm_theme(line, line_idx)),
+        Syntax::SublimeColorScheme => {
+            other.extend(profile::sublime_color_scheme(line, line_idx));
+        }
+        Syntax::Android => colors = colors.drain(..).map(android::argb).collect(),
+        Syntax::PackedHex => other.extend(profile::packed_hex(line, line_idx)),
+        Syntax::Gtk => other.extend(desktop::gtk(line, line_idx)),
+        Syntax::Qss => other.extend(desktop::qss(line, line_idx)),
+        Syntax::Unity => other.extend(profile::unity(line, line_idx)),
+        Syntax::Unreal => other.extend(profile::unreal(line, line_idx)),
+        Syntax::ColorConstructor => other.extend(theme_object::argb_literals(line, line_idx)),
+        Syntax::Json(schema) => {
+            colors = schema.parse_line(line, line_idx, std::mem::take(&mut colors));
+        }
+        Syntax::Plain | Syntax::WezTerm | Syntax::ColorSet | Syntax::Scss => {}
+    });
+    if let Some(order) = options.integer_colors {
+        other.extend(stopwatch.time("integer", || {
+            integer::find(line, line_idx, order)
+                .into_iter()
+                .map(with_format(ColorFormat::Hex))
+        }));
+    }
+    // GTK and Qt stylesheets only decorate named colors in declaration values.
+    if options.named_colors && !matches!(syntax, Syntax::Gtk | Syntax::Qss) {
+        other.extend(stopwatch.time("named", || {
+            named::find(line, line_idx)
+                .into_iter()
+                .map(with_format(ColorFormat::Heuristic))
+        }));
+    }
+    stopwatch.time("merge", || {
+        for color_match in other {
+            // Keep the first match when a value is both an alias and a color key value.
+            let start = color_match.info.range.start;
+            if colors.iter().all(|other| other.info.range.start != start) {
+                colors.push(color_match);
+            }
+        }
+        colors.retain(|color_match| options.enables(color_match.format));
+        colors.sort_by_key(|color_match| color_match.info.range.start);
+    });
+    colors
+}
+
+/// Parses all colors in a line with the default options and returns them as
+/// `ColorInformation`.
 pub fn parse_line_colors(line: &str, line_idx: usize) -> Vec<ColorInformation> {
+    parse_line(line, line_idx, &ParseOptions::default(), Syntax::Plain)
+        .into_iter()
+        .map(|color_match| color_match.info)
+        .collect()
+}
+
+fn is_hex_digit(unit: u16) -> bool {
+    u8::try_from(unit).is_ok_and(|byte| byte.is_ascii_hexdigit())
+}
+
+/// Parses all hex color codes in a line.
+fn parse_hex_colors(line: &str, line_idx: usize) -> Vec<ColorInformation> {
     let mut colors: Vec<ColorInformation> = Vec::new();
 
     let chars: Vec<u16> = line.encode_utf16().collect();
@@ -33,22 +253,47 @@ pub fn parse_line_colors(line: &str, line_idx: usize) -> Vec<ColorInformation> {
             length += 1;
             i += 1;
         }
-        if length < 6 {
+        // Longer runs are hashes, like the commit in `repo.git#0a1b2c3d4e…`,
+        // not colors.
+        if length == 8 && chars.get(i).is_some_and(|&unit| is_hex_digit(unit)) {
+            while chars.get(i).is_some_and(|&unit| is_hex_digit(unit)) {
+                i += 1;
+            }
             continue;
         }
-
         // Fallback to length 6 if 7 digits was parsed
         if length == 7 {
             length = 6;
             i -= 1;
         }
 
+        let written_length = length;
+        if length == 3 || length == 4 {
+            // Shorthand only when the run ends here, so `#12345` or `#abcz`
+            // isn't read as `#1234` or `#abc`.
+            let run_continues = chars.get(i).is_some_and(|&unit| {
+                char::from_u32(unit as u32)
+                    .is_some_and(|c| c.is_alphanumeric() || c == '-' || c == '_')
+            });
+            if run_continues {
+                continue;
+            }
+            // Expand each digit, so `#FFFA` reads as `#FFFFFFAA`.
+            for idx in (0..length as usize).rev() {
+                digits[idx * 2] = digits[idx];
+                digits[idx * 2 + 1] = digits[idx];
+            }
+            length *= 2;
+        } else if length < 6 {
+            continue;
+        }
+
         let color = color_from_digits(digits, length);
         colors.push(ColorInformation {
             range: Range {
                 start: Position {
                     line: line_idx as u32,
-                    character: i as u32 - (1 + length),
+                    character: i as u32 - (1 + written_length),
                 },
                 end: Position {
                     line: line_idx as u32,
@@ -61,6 +306,359 @@ pub fn parse_line_colors(line: &str, line_idx: usize) -> Vec<ColorInformation> {
     colors
 }
 
+/// Parses CSS `rgb()`, `rgba()`, `hsl()`, `hsla()`, `hwb()`, `lab()`, `lch()`,
+/// `oklab()` and `oklch()` colors in a line, e.g. `rgb(255, 0, 0)`,
+/// `rgba(255, 0, 0, 50%)`, `hsl(210 50% 40% / 0.8)` or `oklch(70% 0.1 200)`.
+/// In strict mode, calls that don't conform to CSS Color 4 are skipped.
+fn parse_function_colors(line: &str, line_idx: usize, strict: bool) -> Vec<ColorInformation> {
+    let mut colors = Vec::new();
+    for (name, call, args) in function_calls(line) {
+        if strict && conformance::check(&line[call.clone()]).is_err() {
+            continue;
+        }
+        if let Some(color) = function_color(name, &line[args]) {
+            colors.push(ColorInformation {
+                range: line_range(line, line_idx, call),
+                color,
+            });
+        }
+    }
+    colors.sort_by_key(|color| color.range.start);
+    colors
+}
+
+/// Most lines a color function call may span. An edit only has to reparse
+/// the calls opening this many lines before it.
+pub const MAX_CALL_LINES: usize = 8;
+
+/// Parses the CSS color function calls that open on the first of `lines` and
+/// close on a later one, as formatters write them, e.g. `rgba(` followed by
+/// one channel per line. Ranges are relative to the first line.
+pub fn parse_spanning_function_colors(lines: &[&str], strict: bool) -> Vec<ColorInformation> {
+    let Some(first) = lines.first() else {
+        return Vec::new();
+    };
+    // Only a parenthesis left open can close on a later line.
+    if first.matches('(').count() <= first.matches(')').count() {
+        return Vec::new();
+    }
+    let text = lines[..lines.len().min(MAX_CALL_LINES)].join("\n");
+    let mut colors = Vec::new();
+    for (name, call, args) in function_calls(&text) {
+        if call.start >= first.len() || call.end <= first.len() {
+            continue;
+        }
+        if strict && conformance::check(&text[call.clone()]).is_err() {
+            continue;
+        }
+        let Some(color) = function_color(name, &text[args]) else {
+            continue;
+        };
+        let before_end = &text[..call.end];
+        let end_line_start = before_end.rfind('\n').map_or(0, |idx| idx + 1);
+        colors.push(ColorInformation {
+            range: Range {
+                start: Position {
+                    line: 0,
+                    character: first[..call.start].encode_utf16().count() as u32,
+                },
+                end: Position {
+                    line: before_end.matches('\n').count() as u32,
+                    character: before_end[end_line_start..].encode_utf16().count() as u32,
+                },
+            },
+            color,
+        });
+    }
+    colors.sort_by_key(|color| color.range.start);
+    colors
+}
+
+/// Evaluates the arguments of a call of the CSS color function `name`, as
+/// returned by `function_calls`.
+fn function_color(name: &str, args: &str) -> Option<Color> {
+    match name {
+        "rgb" => rgb_arguments(args),
+        "hsl" => hsl_arguments(args),
+        "hwb" => hwb_arguments(args),
+        "lab" => lab_arguments(args),
+        "lch" => lch_arguments(args),
+        "oklab" => oklab_arguments(args),
+        _ => oklch_arguments(args),
+    }
+}
+
+/// Finds calls of CSS color functions in a line, returning the function
+/// name, with `rgba` and `hsla` read as `rgb` and `hsl`, and the byte ranges
+/// of the call and of its arguments.
+pub(crate) fn function_calls(
+    line: &str,
+) -> Vec<(&'static str, std::ops::Range<usize>, std::ops::Range<usize>)> {
+    let mut calls = Vec::new();
+    for name in ["rgb", "hsl", "hwb", "lab", "lch", "oklab", "oklch"] {
+        for (start, _) in line.match_indices(name) {
+            let inside_name = line[..start]
+                .chars()
+                .next_back()
+                .is_some_and(|c| c.is_alphanumeric() || c == '-' || c == '_');
+            if inside_name {
+                continue;
+            }
+            let rest = &line[start + name.len()..];
+            let args_start = if rest.starts_with("a(") && ["rgb", "hsl"].contains(&name) {
+                start + name.len() + 2
+            } else if rest.starts_with('(') {
+                start + name.len() + 1
+            } else {
+                continue;
+            };
+            let Some(args_len) = desktop::closing_paren(&line[args_start - 1..]) else {
+                continue;
+            };
+            let args_end = args_start + args_len - 1;
+            calls.push((name, start..args_end + 1, args_start..args_end));
+        }
+    }
+    calls
+}
+
+/// Returns the channels of a CSS color function spelled `text`, such as
+/// `rgb(26 115 232 / 50%)`, by name along with their byte ranges in `text`.
+pub fn function_channels(text: &str) -> Option<Vec<(&'static str, std::ops::Range<usize>)>> {
+    let open = text.find('(')?;
+    let names = match &text[..open] {
+        "rgb" | "rgba" => ["red", "green", "blue"],
+        "hsl" | "hsla" => ["hue", "saturation", "lightness"],
+        "hwb" => ["hue", "whiteness", "blackness"],
+        "lab" | "oklab" => ["lightness", "a", "b"],
+        "lch" | "oklch" => ["lightness", "chroma", "hue"],
+        _ => return None,
+    };
+    let (components, alpha) = function_arguments(text[open + 1..].strip_suffix(')')?)?;
+    // Components are slices of `text`.
+    let span = |part: &str| {
+        let start = part.as_ptr() as usize - text.as_ptr() as usize;
+        start..start + part.len()
+    };
+    let mut channels: Vec<_> = names
+        .into_iter()
+        .zip(components)
+        .map(|(name, component)| (name, span(component)))
+        .collect();
+    channels.extend(alpha.map(|alpha| ("alpha", span(alpha))));
+    Some(channels)
+}
+
+/// Splits the arguments of a CSS color function into its three components
+/// and optional alpha, in the legacy comma-separated or the modern
+/// space-separated syntax. Separators inside `calc()` don't split.
+pub(crate) fn function_arguments(args: &str) -> Option<([&str; 3], Option<&str>)> {
+    let (components, alpha): (Vec<&str>, Option<&str>) =
+        if split_outside_parens(args, |c| c == ',').len() > 1 {
+            let mut parts: Vec<&str> = split_outside_parens(args, |c| c == ',')
+                .into_iter()
+                .map(str::trim)
+                .collect();
+            let alpha = (parts.len() == 4).then(|| parts.pop()).flatten();
+            (parts, alpha)
+        } else {
+            let (components, alpha) = match split_outside_parens(args, |c| c == '/').as_slice() {
+                [components] => (*components, None),
+                [components, alpha] => (*components, Some(alpha.trim())),
+                _ => return None,
+            };
+            let components = split_outside_parens(components, char::is_whitespace)
+                .into_iter()
+                .filter(|component| !component.is_empty())
+                .collect();
+            (components, alpha)
+        };
+    Some((components.try_into().ok()?, alpha))
+}
+
+/// Splits `s` on separators outside parentheses.
+pub(crate) fn split_outside_parens(s: &str, is_separator: impl Fn(char) -> bool) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let (mut depth, mut start) = (0, 0);
+    for (idx, c) in s.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            c if depth == 0 && is_separator(c) => {
+                parts.push(&s[start..idx]);
+                start = idx + c.len_utf8();
+            }
+            _ => {}
+        }
+    }
+    parts.push(&s[start..]);
+    parts
+}
+
+/// Parses the arguments of `rgb()`. Channels are numbers in [0, 255] or
+/// percentages, alpha is a number in [0, 1] or a percentage, and `none` is 0.
+fn rgb_arguments(args: &str) -> Option<Color> {
+    let ([red, green, blue], alpha) = function_arguments(args)?;
+    let channel = |value: &str| css_number(value, 255.0);
+    Some(Color {
+        red: channel(red)?,
+        green: channel(green)?,
+        blue: channel(blue)?,
+        alpha: alpha.map_or(Some(1.0), |alpha| css_number(alpha, 1.0))?,
+    })
+}
+
+/// Parses the arguments of `hsl()`. Hue is in degrees, or in `deg`, `grad`,
+/// `rad` or `turn` units, and saturation and lightness are percentages, or
+/// bare numbers in [0, 100] in the space-separated syntax.
+fn hsl_arguments(args: &str) -> Option<Color> {
+    let ([hue, saturation, lightness], alpha) = function_arguments(args)?;
+    Some(from_hsl(
+        css_hue(hue)?,
+        css_number(saturation, 100.0)?,
+        css_number(lightness, 100.0)?,
+        css_alpha(alpha)?,
+    ))
+}
+
+/// Parses the arguments of `hwb()`: a hue as in `hsl()`, then whiteness and
+/// blackness percentages.
+fn hwb_arguments(args: &str) -> Option<Color> {
+    let ([hue, whiteness, blackness], alpha) = function_arguments(args)?;
+    Some(from_hwb(
+        css_hue(hue)?,
+        css_number(whiteness, 100.0)?,
+        css_number(blackness, 100.0)?,
+        css_alpha(alpha)?,
+    ))
+}
+
+/// Parses the arguments of `lab()`: lightness in [0, 100], and `a` and `b`
+/// axes where 100% is 125.
+fn lab_arguments(args: &str) -> Option<Color> {
+    let ([lightness, a, b], alpha) = function_arguments(args)?;
+    Some(from_lab(
+        css_component(lightness, 100.0)?.clamp(0.0, 100.0),
+        css_component(a, 125.0)?,
+        css_component(b, 125.0)?,
+        css_alpha(alpha)?,
+    ))
+}
+
+/// Parses the arguments of `lch()`: lightness as in `lab()`, chroma where
+/// 100% is 150, and a hue as in `hsl()`.
+fn lch_arguments(args: &str) -> Option<Color> {
+    let ([lightness, chroma, hue], alpha) = function_arguments(args)?;
+    Some(from_lch(
+        css_component(lightness, 100.0)?.clamp(0.0, 100.0),
+        css_component(chroma, 150.0)?,
+        css_hue(hue)?,
+        css_alpha(alpha)?,
+    ))
+}
+
+/// Parses the arguments of `oklab()`: lightness in [0, 1], and `a` and `b`
+/// axes where 100% is 0.4.
+fn oklab_arguments(args: &str) -> Option<Color> {
+    let ([lightness, a, b], alpha) = function_arguments(args)?;
+    Some(from_oklab(
+        css_number(lightness, 1.0)?,
+        css_component(a, 0.4)?,
+        css_component(b, 0.4)?,
+        css_alpha(alpha)?,
+    ))
+}
+
+/// Parses the arguments of `oklch()`: lightness as in `oklab()`, chroma where
+/// 100% is 0.4, and a hue as in `hsl()`.
+fn oklch_arguments(args: &str) -> Option<Color> {
+    let ([lightness, chroma, hue], alpha) = function_arguments(args)?;
+    Some(from_oklch(
+        css_number(lightness, 1.0)?,
+        css_component(chroma, 0.4)?,
+        css_hue(hue)?,
+        css_alpha(alpha)?,
+    ))
+}
+
+/// Parses a hue in degrees, or in `deg`, `grad`, `rad` or `turn` units.
+fn css_hue(hue: &str) -> Option<f32> {
+    let hue = hue.trim();
+    let hue = if hue == "none" {
+        0.0
+    } else if let Some(expr) = calc::body(hue) {
+        calc::evaluate(expr, f32::NAN)?
+    } else if let Some(degrees) = hue.strip_suffix("deg") {
+        degrees.parse().ok()?
+    } else if let Some(gradians) = hue.strip_suffix("grad") {
+        gradians.parse::<f32>().ok()? * 0.9
+    } else if let Some(radians) = hue.strip_suffix("rad") {
+        radians.parse::<f32>().ok()?.to_degrees()
+    } else if let Some(turns) = hue.strip_suffix("turn") {
+        turns.parse::<f32>().ok()? * 360.0
+    } else {
+        hue.parse().ok()?
+    };
+    hue.is_finite().then_some(hue)
+}
+
+/// Parses an optional alpha, a number in [0, 1] or a percentage, defaulting
+/// to opaque.
+fn css_alpha(alpha: Option<&str>) -> Option<f32> {
+    alpha.map_or(Some(1.0), |alpha| css_number(alpha, 1.0))
+}
+
+/// Parses a CSS number, percentage or constant `calc()` expression, scaling
+/// numbers by `1 / max` and clamping the result to [0, 1]. Percentages inside
+/// `calc()` are relative to `max`, so they mix with numbers.
+fn css_number(value: &str, max: f32) -> Option<f32> {
+    Some((css_component(value, max)? / max).clamp(0.0, 1.0))
+}
+
+/// Parses a CSS number, percentage or constant `calc()` expression, without
+/// clamping. Percentages are relative to `basis`.
+fn css_component(value: &str, basis: f32) -> Option<f32> {
+    let value = value.trim();
+    let value = if value == "none" {
+        0.0
+    } else if let Some(expr) = calc::body(value) {
+        calc::evaluate(expr, basis)?
+    } else if let Some(percentage) = value.strip_suffix('%') {
+        percentage.parse::<f32>().ok()? * basis / 100.0
+    } else {
+        value.parse::<f32>().ok()?
+    };
+    value.is_finite().then_some(value)
+}
+
+/// Returns the identifiers in a line with their UTF-16 start and end columns.
+///
+/// Identifiers are runs of alphanumerics, `-` and `_`. Runs right after `#`
+/// are skipped so hex digits are never taken for a name.
+pub(crate) fn identifiers(line: &str) -> Vec<(u32, u32, &str)> {
+    let mut identifiers = Vec::new();
+    // Byte index and column where the current run started, and whether it follows `#`.
+    let mut run: Option<(usize, u32, bool)> = None;
+    let (mut column, mut prev) = (0, None);
+    for (byte_idx, c) in line
+        .char_indices()
+        .chain(std::iter::once((line.len(), ' ')))
+    {
+        if c.is_alphanumeric() || c == '-' || c == '_' {
+            if run.is_none() {
+                run = Some((byte_idx, column, prev == Some('#')));
+            }
+        } else if let Some((start_byte, start_column, after_hash)) = run.take()
+            && !after_hash
+        {
+            identifiers.push((start_column, column, &line[start_byte..byte_idx]));
+        }
+        prev = Some(c);
+        column += c.len_utf16() as u32;
+    }
+    identifiers
+}
+
 fn color_from_digits(digits: [u8; 8], length: u32) -> Color {
     let red = (digits[0] * 16 + digits[1]) as f32 / 255.0;
     let green = (digits[2] * 16 + digits[3]) as f32 / 255.0;
@@ -79,9 +677,38 @@ fn color_from_digits(digits: [u8; 8], length: u32) -> Color {
     }
 }
 
+/// Parses a value that is a single color as a whole, such as `#1a73e8` or
+/// `rgb(26 115 232)`, as in settings.
+pub fn parse_color(value: &str) -> Option<Color> {
+    match parse_line_colors(value, 0).as_slice() {
+        [color_info]
+            if color_info.range.start.character == 0
+                && color_info.range.end.character as usize == value.encode_utf16().count() =>
+        {
+            Some(color_info.color)
+        }
+        _ => None,
+    }
+}
+
+/// Formats a color as `#RRGGBB`, or `#RRGGBBAA` when it is not fully opaque.
+pub fn to_hex(color: &Color) -> String {
+    let channel = |value: f32| (value.clamp(0.0, 1.0) * 255.0).round() as u8;
+    let mut hex = format!(
+        "#{:02X}{:02X}{:02X}",
+        channel(color.red),
+        channel(color.green),
+        channel(color.blue)
+    );
+    if channel(color.alpha) != 255 {
+        hex.push_str(&format!("{:02X}", channel(color.alpha)));
+    }
+    hex
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::color::parse_line_colors;
+    use crate::color::{parse_line_colors, parse_spanning_function_colors, to_hex};
 
     #[test]
     fn parse_line_colors_line_idx() {
@@ -106,6 +733,137 @@ mod tests {
         assert_eq!(color_info.range.end.character, 7);
     }
 
+    #[test]
+    fn parse_line_colors_shorthand() {
+        let colors = parse_line_colors("#FFF #abcd #12345 #abcz #0f0-x #f00)", 0);
+        let hexes: Vec<_> = colors
+            .iter()
+            .map(|info| {
+                (
+                    info.range.start.character,
+                    info.range.end.character,
+                    to_hex(&info.color),
+                )
+            })
+            .collect();
+        assert_eq!(
+            hexes,
+            [
+                (0, 4, "#FFFFFF".to_string()),
+                (5, 10, "#AABBCCDD".to_string()),
+                (31, 35, "#FF0000".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_line_colors_rgb_functions() {
+        let colors = parse_line_colors(
+            "a: rgb(255, 0, 0); b: rgba(0, 0, 255, 0.5); c: rgb(100% 50% 0% / 25%); d: myrgb(1, 2, 3)",
+            0,
+        );
+        let hexes: Vec<_> = colors
+            .iter()
+            .map(|info| {
+                (
+                    info.range.start.character,
+                    info.range.end.character,
+                    to_hex(&info.color),
+                )
+            })
+            .collect();
+        assert_eq!(
+            hexes,
+            [
+                (3, 17, "#FF0000".to_string()),
+                (22, 42, "#0000FF80".to_string()),
+                (47, 69, "#FF800040".to_string()),
+            ]
+        );
+        assert!(parse_line_colors("rgba(var(--bs-rgb), 0.5) rgb(1, 2)", 0).is_empty());
+    }
+
+    #[test]
+    fn parse_line_colors_in_shadows() {
+        let line = "filter: drop-shadow(0 4px 8px rgba(26, 115, 232, 0.25)); text-shadow: 0 1px 2px rgb(0 0 0 / .4);";
+        let ranges: Vec<_> = parse_line_colors(line, 0)
+            .iter()
+            .map(|info| (info.range.start.character, info.range.end.character))
+            .collect();
+        // Only the colors, not the lengths or the `drop-shadow()` around them.
+        assert_eq!(ranges, [(30, 54), (80, 95)]);
+    }
+
+    #[test]
+    fn parse_line_colors_hsl_functions() {
+        let colors = parse_line_colors(
+            "hsl(210, 50%, 40%) hsla(0.5turn 100% 50% / 0.8) hsl(120deg 100 25)",
+            0,
+        );
+        let hexes: Vec<_> = colors
+            .iter()
+            .map(|info| (info.range.start.character, to_hex(&info.color)))
+            .collect();
+        assert_eq!(
+            hexes,
+            [
+                (0, "#336699".to_string()),
+                (19, "#00FFFFCC".to_string()),
+                (48, "#008000".to_string()),
+            ]
+        );
+        assert!(parse_line_colors("hsl(210, 50%)", 0).is_empty());
+    }
+
+    #[test]
+    fn parse_line_colors_calc() {
+        let colors = parse_line_colors(
+            "rgb(calc(255 * 0.5) 0 0) rgba(100%, calc(50% + 0.5), 0, calc(1 / 2)) hsl(calc(60deg * 2) 100% 25%)",
+            0,
+        );
+        let hexes: Vec<_> = colors
+            .iter()
+            .map(|info| {
+                (
+                    info.range.start.character,
+                    info.range.end.character,
+                    to_hex(&info.color),
+                )
+            })
+            .collect();
+        assert_eq!(
+            hexes,
+            [
+                (0, 24, "#800000".to_string()),
+                (25, 68, "#FF800080".to_string()),
+                (69, 98, "#008000".to_string()),
+            ]
+        );
+        assert!(parse_line_colors("rgb(calc(var(--r) * 1) 0 0)", 0).is_empty());
+    }
+
+    #[test]
+    fn parse_line_colors_css_color_4() {
+        let colors = parse_line_colors(
+            "hwb(120 20% 20%) lab(50 0 0) lch(54.29% 106.84 40.86) oklab(0.628 0.2249 0.1258) oklch(62.8% 0.2577 29.23deg / 50%) color: lab(none)",
+            0,
+        );
+        let hexes: Vec<_> = colors
+            .iter()
+            .map(|info| (info.range.start.character, to_hex(&info.color)))
+            .collect();
+        assert_eq!(
+            hexes,
+            [
+                (0, "#33CC33".to_string()),
+                (17, "#777777".to_string()),
+                (29, "#FF0000".to_string()),
+                (54, "#FF0000".to_string()),
+                (81, "#FF000080".to_string()),
+            ]
+        );
+    }
+
     #[test]
     fn parse_line_colors_rgb_lowercase() {
         let colors = parse_line_colors("#ff0000", 0);
@@ -160,6 +918,14 @@ mod tests {
         assert_eq!(colors, Vec::new());
     }
 
+    #[test]
+    fn parse_line_colors_hash_runs() {
+        let line = "x.tgz#82a2f94f468c2825868c411a23f0137e81f1bf07 #123456789 #12345678";
+        let colors = parse_line_colors(line, 0);
+        assert_eq!(colors.len(), 1);
+        assert_eq!(colors[0].range.start.character, 58);
+    }
+
     #[test]
     fn parse_line_colors_text_with_color() {
         let colors = parse_line_colors("Color: #ABCDEF;", 0);
@@ -189,4 +955,45 @@ mod tests {
         assert_eq!(color_info.range.start.character, 3);
         assert_eq!(color_info.range.end.character, 10);
     }
+
+    #[test]
+    fn parse_spanning_function_colors_across_lines() {
+        let lines = [
+            "  color: rgba(",
+            "    255,",
+            "    0,",
+            "    0,",
+            "    0.5",
+            "  ); fill: hsl(",
+            "0 100% 50%);",
+        ];
+        let colors = parse_spanning_function_colors(&lines, false);
+        assert_eq!(colors.len(), 1);
+        assert_eq!(to_hex(&colors[0].color), "#FF000080");
+        let range = colors[0].range;
+        assert_eq!((range.start.line, range.start.character), (0, 9));
+        assert_eq!((range.end.line, range.end.character), (5, 3));
+
+        // Calls closing on their own line are parsed by `parse_line`.
+        assert_eq!(
+            parse_spanning_function_colors(&["rgb(1 2 3) rgb(", "4 5 6)"], false)[0]
+                .range
+                .start
+                .character,
+            11
+        );
+        assert!(parse_spanning_function_colors(&["rgb(1 2 3)", "4 5 6)"], false).is_empty());
+        assert!(parse_spanning_function_colors(&["rgb(", "1,", "2,", "3,", "4"], false).is_empty());
+        assert_eq!(
+            parse_spanning_function_colors(&["rgb(", "  1 2 3", ")"], true).len(),
+            1
+        );
+    }
+
+    #[test]
+    fn to_hex_opaque_and_translucent() {
+        let colors = parse_line_colors("#1A2B3C #1A2B3C80", 0);
+        assert_eq!(to_hex(&colors[0].color), "#1A2B3C");
+        assert_eq!(to_hex(&colors[1].color), "#1A2B3C80");
+    }
 }
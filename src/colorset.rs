@@ -0,0 +1,198 @@
+//! Xcode asset catalog colors, `*.colorset/Contents.json`, whose components
+//! are spread over several lines as strings like `"0x7F"` or `"0.498"`.
+
+use tower_lsp_server::ls_types::{Color, ColorPresentation, Range, TextEdit};
+
+use crate::heuristics::line_range;
+use crate::schema::string_pairs;
+
+const CHANNELS: [&str; 4] = ["red", "green", "blue", "alpha"];
+
+/// The color of one idiom or appearance.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ColorSetEntry {
+    /// Range of the `components` key, where the color is decorated.
+    pub range: Range,
+    pub color: Color,
+    /// Channel names with the ranges and text of their values.
+    pub components: Vec<(&'static str, Range, String)>,
+}
+
+/// Returns the colors of a `Contents.json`, one per `components` object.
+pub fn entries(lines: &[&str]) -> Vec<ColorSetEntry> {
+    let mut entries = Vec::new();
+    let mut current: Option<ColorSetEntry> = None;
+    for (line_idx, line) in lines.iter().enumerate() {
+        let mut rest_start = 0;
+        if let Some(key_idx) = line.find("\"components\"") {
+            let key_start = key_idx + 1;
+            current = Some(ColorSetEntry {
+                range: line_range(line, line_idx, key_start..key_start + "components".len()),
+                color: Color {
+                    red: 0.0,
+                    green: 0.0,
+                    blue: 0.0,
+                    alpha: 1.0,
+                },
+                components: Vec::new(),
+            });
+            rest_start = key_idx;
+        }
+        let Some(entry) = &mut current else {
+            continue;
+        };
+
+        let rest = &line[rest_start..];
+        for (key, value_idx, value) in string_pairs(rest) {
+            let Some(channel) = CHANNELS.into_iter().find(|channel| *channel == key) else {
+                continue;
+            };
+            let value_start = rest_start + value_idx;
+            let range = line_range(line, line_idx, value_start..value_start + value.len());
+            entry.components.push((channel, range, value.to_string()));
+        }
+
+        if rest.contains('}') {
+            let entry = current.take().expect("entry is set above");
+            entries.extend(finish(entry));
+        }
+    }
+    entries
+}
+
+/// Computes the color of an entry, which needs at least the RGB channels.
+fn finish(mut entry: ColorSetEntry) -> Option<ColorSetEntry> {
+    for channel in ["red", "green", "blue"] {
+        entry
+            .components
+            .iter()
+            .find(|(name, _, _)| *name == channel)?;
+    }
+    for (channel, _, value) in &entry.components {
+        let value = component(channel, value)?;
+        match *channel {
+            "red" => entry.color.red = value,
+            "green" => entry.color.green = value,
+            "blue" => entry.color.blue = value,
+            _ => entry.color.alpha = value,
+        }
+    }
+    Some(entry)
+}
+
+/// Parses a component written as `0xRR`, as an 8-bit integer or as a float
+/// in `[0, 1]`. Alpha is always a float.
+fn component(channel: &str, value: &str) -> Option<f32> {
+    if let Some(digits) = value
+        .strip_prefix("0x")
+        .or_else(|| value.strip_prefix("0X"))
+    {
+        return Some(u8::from_str_radix(digits, 16).ok()? as f32 / 255.0);
+    }
+    if channel != "alpha" && !value.contains('.') {
+        return Some(value.parse::<u8>().ok()? as f32 / 255.0);
+    }
+    value
+        .parse::<f32>()
+        .ok()
+        .filter(|value| (0.0..=1.0).contains(value))
+}
+
+/// Formats a component like `existing`, keeping hex, integer or float notation.
+fn format_component(existing: &str, value: f32) -> String {
+    let byte = (value.clamp(0.0, 1.0) * 255.0).round() as u8;
+    if existing.starts_with("0x") || existing.starts_with("0X") {
+        format!("{}{byte:02X}", &existing[..2])
+    } else if existing.contains('.') {
+        format!("{value:.3}")
+    } else {
+        byte.to_string()
+    }
+}
+
+/// Returns a presentation that rewrites the components of the entry decorated
+/// at `range`, keeping the notation of each value.
+pub fn presentation(lines: &[&str], range: Range, color: &Color) -> Option<ColorPresentation> {
+    let entry = entries(lines)
+        .into_iter()
+        .find(|entry| entry.range == range)?;
+    let edits = entry
+        .components
+        .iter()
+        .map(|(channel, range, value)| {
+            let channel_value = match *channel {
+                "red" => color.red,
+                "green" => color.green,
+                "blue" => color.blue,
+                _ => color.alpha,
+            };
+            TextEdit {
+                range: *range,
+                new_text: format_component(value, channel_value),
+            }
+        })
+        .collect();
+    Some(ColorPresentation {
+        label: "components".to_string(),
+        text_edit: Some(TextEdit {
+            range,
+            new_text: "components".to_string(),
+        }),
+        additional_text_edits: Some(edits),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::color::to_hex;
+    use crate::colorset::{entries, presentation};
+
+    const CONTENTS: &str = r#"{
+  "colors" : [
+    {
+      "color" : {
+        "color-space" : "srgb",
+        "components" : {
+          "alpha" : "1.000",
+          "blue" : "0xE8",
+          "green" : "0x73",
+          "red" : "0x1A"
+        }
+      },
+      "idiom" : "universal"
+    },
+    {
+      "appearances" : [ { "appearance" : "luminosity", "value" : "dark" } ],
+      "color" : {
+        "components" : { "alpha" : "0.500", "blue" : "1.000", "green" : "0.498", "red" : "0" }
+      },
+      "idiom" : "universal"
+    }
+  ]
+}"#;
+
+    #[test]
+    fn one_color_per_appearance() {
+        let lines: Vec<&str> = CONTENTS.lines().collect();
+        let entries = entries(&lines);
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].range.start.line, 5);
+        assert_eq!(entries[0].range.start.character, 9);
+        assert_eq!(to_hex(&entries[0].color), "#1A73E8");
+        assert_eq!(to_hex(&entries[1].color), "#007FFF80");
+    }
+
+    #[test]
+    fn presentation_keeps_notation() {
+        let lines: Vec<&str> = CONTENTS.lines().collect();
+        let range = entries(&lines)[0].range;
+        let color = entries(&lines)[1].color;
+
+        let edits = presentation(&lines, range, &color)
+            .unwrap()
+            .additional_text_edits
+            .unwrap();
+        let values: Vec<&str> = edits.iter().map(|edit| edit.new_text.as_str()).collect();
+        assert_eq!(values, ["0.500", "0xFF", "0x7F", "0x00"]);
+    }
+}
@@ -0,0 +1,464 @@
+//! Conversions between sRGB colors and the spaces formats are written in:
+//! linear sRGB, CIE XYZ, Lab and LCH, OKLab and OKLCH, HSL, HWB and HSV.
+//!
+//! Hues are in degrees and other components in the ranges CSS uses without
+//! percentages. Conversions to sRGB clip colors outside of its gamut.
+
+use tower_lsp_server::ls_types::Color;
+
+const LINEAR_SRGB_TO_XYZ_D65: [[f32; 3]; 3] = [
+    [0.4123908, 0.35758433, 0.1804808],
+    [0.212639, 0.71516865, 0.07219232],
+    [0.019330818, 0.11919478, 0.95053214],
+];
+const XYZ_D65_TO_LINEAR_SRGB: [[f32; 3]; 3] = [
+    [3.24097, -1.5373832, -0.49861076],
+    [-0.96924365, 1.8759675, 0.04155506],
+    [0.05563008, -0.20397696, 1.0569715],
+];
+// Bradford adapted between the D65 white point of sRGB and the D50 of Lab.
+const LINEAR_SRGB_TO_XYZ_D50: [[f32; 3]; 3] = [
+    [0.43606573, 0.3851515, 0.14307842],
+    [0.22249317, 0.716887, 0.06061981],
+    [0.013923922, 0.097081326, 0.71409935],
+];
+const XYZ_D50_TO_LINEAR_SRGB: [[f32; 3]; 3] = [
+    [3.134136, -1.6173863, -0.49066195],
+    [-0.9787955, 1.9162546, 0.033442732],
+    [0.07195538, -0.22897683, 1.4053861],
+];
+const WHITE_D50: [f32; 3] = [0.3457 / 0.3585, 1.0, (1.0 - 0.3457 - 0.3585) / 0.3585];
+const LAB_EPSILON: f32 = 216.0 / 24389.0;
+const LAB_KAPPA: f32 = 24389.0 / 27.0;
+
+/// Chroma below which a color is a gray, whose hue is meaningless.
+pub const ACHROMATIC: f32 = 1e-4;
+
+/// Converts an sRGB-encoded channel in [0, 1] to linear light.
+pub fn srgb_to_linear(value: f32) -> f32 {
+    if value <= 0.04045 {
+        value / 12.92
+    } else {
+        ((value + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+/// Converts a linear-light channel in [0, 1] to sRGB encoding.
+pub fn linear_to_srgb(value: f32) -> f32 {
+    if value <= 0.0031308 {
+        value * 12.92
+    } else {
+        1.055 * value.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+/// Converts a color to linear-light sRGB channels, clipping it to [0, 1]
+/// first.
+pub fn to_linear_srgb(color: &Color) -> [f32; 3] {
+    [color.red, color.green, color.blue].map(|value| srgb_to_linear(value.clamp(0.0, 1.0)))
+}
+
+/// Encodes linear-light sRGB channels, clipped to [0, 1].
+pub fn from_linear_srgb([red, green, blue]: [f32; 3], alpha: f32) -> Color {
+    let encode = |value: f32| linear_to_srgb(value.clamp(0.0, 1.0));
+    Color {
+        red: encode(red),
+        green: encode(green),
+        blue: encode(blue),
+        alpha,
+    }
+}
+
+/// Converts a color to CIE XYZ relative to the D65 white point.
+pub fn to_xyz_d65(color: &Color) -> [f32; 3] {
+    multiply(LINEAR_SRGB_TO_XYZ_D65, to_linear_srgb(color))
+}
+
+/// Builds a color from CIE XYZ relative to the D65 white point.
+pub fn from_xyz_d65(xyz: [f32; 3], alpha: f32) -> Color {
+    from_linear_srgb(multiply(XYZ_D65_TO_LINEAR_SRGB, xyz), alpha)
+}
+
+/// Converts a color to CIE XYZ relative to the D50 white point, as Lab is.
+pub fn to_xyz_d50(color: &Color) -> [f32; 3] {
+    multiply(LINEAR_SRGB_TO_XYZ_D50, to_linear_srgb(color))
+}
+
+/// Builds a color from CIE XYZ relative to the D50 white point.
+pub fn from_xyz_d50(xyz: [f32; 3], alpha: f32) -> Color {
+    from_linear_srgb(multiply(XYZ_D50_TO_LINEAR_SRGB, xyz), alpha)
+}
+
+/// Converts a color to CIE Lab lightness in [0, 100] and `a` and `b` axes.
+pub fn to_lab(color: &Color) -> (f32, f32, f32) {
+    let [x, y, z] = to_xyz_d50(color);
+    let f = |value: f32, white: f32| {
+        let value = value / white;
+        if value > LAB_EPSILON {
+            value.cbrt()
+        } else {
+            (LAB_KAPPA * value + 16.0) / 116.0
+        }
+    };
+    let (fx, fy, fz) = (f(x, WHITE_D50[0]), f(y, WHITE_D50[1]), f(z, WHITE_D50[2]));
+    (116.0 * fy - 16.0, 500.0 * (fx - fy), 200.0 * (fy - fz))
+}
+
+/// Builds a color from CIE Lab coordinates, relative to the D50 white point.
+pub fn from_lab(lightness: f32, a: f32, b: f32, alpha: f32) -> Color {
+    let fy = (lightness + 16.0) / 116.0;
+    let fx = fy + a / 500.0;
+    let fz = fy - b / 200.0;
+    let inverse = |f: f32| {
+        if f.powi(3) > LAB_EPSILON {
+            f.powi(3)
+        } else {
+            (116.0 * f - 16.0) / LAB_KAPPA
+        }
+    };
+    let y = if lightness > LAB_KAPPA * LAB_EPSILON {
+        fy.powi(3)
+    } else {
+        lightness / LAB_KAPPA
+    };
+    let xyz = [
+        inverse(fx) * WHITE_D50[0],
+        y * WHITE_D50[1],
+        inverse(fz) * WHITE_D50[2],
+    ];
+    from_xyz_d50(xyz, alpha)
+}
+
+/// Converts a color to CIE LCH lightness, chroma and hue.
+pub fn to_lch(color: &Color) -> (f32, f32, f32) {
+    let (lightness, a, b) = to_lab(color);
+    let (chroma, hue) = to_polar(a, b);
+    (lightness, chroma, hue)
+}
+
+/// Builds a color from CIE LCH lightness, chroma and hue.
+pub fn from_lch(lightness: f32, chroma: f32, hue: f32, alpha: f32) -> Color {
+    let (a, b) = from_polar(chroma, hue);
+    from_lab(lightness, a, b, alpha)
+}
+
+/// Converts a color to OKLab lightness in [0, 1] and `a` and `b` axes.
+pub fn to_oklab(color: &Color) -> (f32, f32, f32) {
+    const LINEAR_SRGB_TO_LMS: [[f32; 3]; 3] = [
+        [0.41222147, 0.53633254, 0.051445995],
+        [0.2119035, 0.6806995, 0.10739696],
+        [0.08830246, 0.28171884, 0.6299787],
+    ];
+    const LMS_TO_OKLAB: [[f32; 3]; 3] = [
+        [0.21045426, 0.7936178, -0.004072047],
+        [1.9779985, -2.4285922, 0.4505937],
+        [0.025904037, 0.78277177, -0.80867577],
+    ];
+
+    let lms = multiply(LINEAR_SRGB_TO_LMS, to_linear_srgb(color)).map(f32::cbrt);
+    let [lightness, a, b] = multiply(LMS_TO_OKLAB, lms);
+    (lightness, a, b)
+}
+
+/// Builds a color from OKLab coordinates.
+pub fn from_oklab(lightness: f32, a: f32, b: f32, alpha: f32) -> Color {
+    const OKLAB_TO_LMS: [[f32; 3]; 3] = [
+        [1.0, 0.39633778, 0.21580376],
+        [1.0, -0.105561346, -0.06385417],
+        [1.0, -0.08948418, -1.2914855],
+    ];
+    const LMS_TO_LINEAR_SRGB: [[f32; 3]; 3] = [
+        [4.0767417, -3.3077116, 0.23096993],
+        [-1.268438, 2.6097574, -0.3413194],
+        [-0.0041960863, -0.7034186, 1.7076147],
+    ];
+
+    let lms = multiply(OKLAB_TO_LMS, [lightness, a, b]).map(|value| value.powi(3));
+    from_linear_srgb(multiply(LMS_TO_LINEAR_SRGB, lms), alpha)
+}
+
+/// Converts a color to OKLCH lightness, chroma and hue.
+pub fn to_oklch(color: &Color) -> (f32, f32, f32) {
+    let (lightness, a, b) = to_oklab(color);
+    let (chroma, hue) = to_polar(a, b);
+    (lightness, chroma, hue)
+}
+
+/// Builds a color from OKLCH lightness, chroma and hue.
+pub fn from_oklch(lightness: f32, chroma: f32, hue: f32, alpha: f32) -> Color {
+    let (a, b) = from_polar(chroma, hue);
+    from_oklab(lightness, a, b, alpha)
+}
+
+/// Converts `a` and `b` axes to chroma and hue in [0, 360). Grays, with
+/// chroma below [`ACHROMATIC`], get a hue of 0.
+pub fn to_polar(a: f32, b: f32) -> (f32, f32) {
+    let chroma = a.hypot(b);
+    let hue = if chroma < ACHROMATIC {
+        0.0
+    } else {
+        b.atan2(a).to_degrees().rem_euclid(360.0)
+    };
+    (chroma, hue)
+}
+
+/// Converts chroma and hue to `a` and `b` axes. Negative chroma is clamped
+/// to 0.
+pub fn from_polar(chroma: f32, hue: f32) -> (f32, f32) {
+    let chroma = chroma.max(0.0);
+    let (sin, cos) = hue.to_radians().sin_cos();
+    (chroma * cos, chroma * sin)
+}
+
+/// Converts a color to hue in degrees and saturation and lightness in [0, 1].
+pub fn to_hsl(color: &Color) -> (f32, f32, f32) {
+    let (r, g, b) = (color.red, color.green, color.blue);
+    let max = r.max(g).max(b);
+    let min = r.min(g).min(b);
+    let lightness = (max + min) / 2.0;
+    let delta = max - min;
+    if delta == 0.0 {
+        return (0.0, 0.0, lightness);
+    }
+
+    let saturation = delta / (1.0 - (2.0 * lightness - 1.0).abs());
+    (hue(color), saturation, lightness)
+}
+
+/// Builds a color from hue in degrees and saturation and lightness in [0, 1].
+pub fn from_hsl(hue: f32, saturation: f32, lightness: f32, alpha: f32) -> Color {
+    let hue = hue.rem_euclid(360.0);
+    let chroma = (1.0 - (2.0 * lightness - 1.0).abs()) * saturation;
+    let x = chroma * (1.0 - ((hue / 60.0).rem_euclid(2.0) - 1.0).abs());
+    let m = lightness - chroma / 2.0;
+    let (r, g, b) = match hue as u32 / 60 {
+        0 => (chroma, x, 0.0),
+        1 => (x, chroma, 0.0),
+        2 => (0.0, chroma, x),
+        3 => (0.0, x, chroma),
+        4 => (x, 0.0, chroma),
+        _ => (chroma, 0.0, x),
+    };
+
+    Color {
+        red: r + m,
+        green: g + m,
+        blue: b + m,
+        alpha,
+    }
+}
+
+/// Converts a color to hue in degrees and whiteness and blackness in [0, 1].
+pub fn to_hwb(color: &Color) -> (f32, f32, f32) {
+    let max = color.red.max(color.green).max(color.blue);
+    let min = color.red.min(color.green).min(color.blue);
+    (hue(color), min, 1.0 - max)
+}
+
+/// Builds a color from hue in degrees and whiteness and blackness in [0, 1].
+/// Whiteness and blackness adding up to more than 1 give a gray.
+pub fn from_hwb(hue: f32, whiteness: f32, blackness: f32, alpha: f32) -> Color {
+    if whiteness + blackness >= 1.0 {
+        let gray = whiteness / (whiteness + blackness);
+        return Color {
+            red: gray,
+            green: gray,
+            blue: gray,
+            alpha,
+        };
+    }
+    let pure = from_hsl(hue, 1.0, 0.5, alpha);
+    let scale = |channel: f32| channel * (1.0 - whiteness - blackness) + whiteness;
+    Color {
+        red: scale(pure.red),
+        green: scale(pure.green),
+        blue: scale(pure.blue),
+        alpha,
+    }
+}
+
+/// Converts a color to hue in degrees and saturation and value in [0, 1].
+pub fn to_hsv(color: &Color) -> (f32, f32, f32) {
+    let max = color.red.max(color.green).max(color.blue);
+    let min = color.red.min(color.green).min(color.blue);
+    let saturation = if max == 0.0 { 0.0 } else { (max - min) / max };
+    (hue(color), saturation, max)
+}
+
+/// Builds a color from hue in degrees and saturation and value in [0, 1].
+pub fn from_hsv(hue: f32, saturation: f32, value: f32, alpha: f32) -> Color {
+    let lightness = value * (1.0 - saturation / 2.0);
+    let saturation = if lightness == 0.0 || lightness == 1.0 {
+        0.0
+    } else {
+        (value - lightness) / lightness.min(1.0 - lightness)
+    };
+    from_hsl(hue, saturation, lightness, alpha)
+}
+
+/// Returns the hue shared by HSL, HWB and HSV, 0 for grays.
+fn hue(color: &Color) -> f32 {
+    let (r, g, b) = (color.red, color.green, color.blue);
+    let max = r.max(g).max(b);
+    let delta = max - r.min(g).min(b);
+    if delta == 0.0 {
+        0.0
+    } else if max == r {
+        60.0 * ((g - b) / delta).rem_euclid(6.0)
+    } else if max == g {
+        60.0 * ((b - r) / delta + 2.0)
+    } else {
+        60.0 * ((r - g) / delta + 4.0)
+    }
+}
+
+fn multiply(matrix: [[f32; 3]; 3], vector: [f32; 3]) -> [f32; 3] {
+    matrix.map(|row| row[0] * vector[0] + row[1] * vector[1] + row[2] * vector[2])
+}
+
+#[cfg(test)]
+mod tests {
+    use tower_lsp_server::ls_types::Color;
+
+    use crate::color::{parse_line_colors, to_hex};
+    use crate::colorspace::{
+        from_hsl, from_hsv, from_hwb, from_lab, from_lch, from_oklab, from_oklch, from_xyz_d50,
+        from_xyz_d65, linear_to_srgb, srgb_to_linear, to_hsl, to_hsv, to_hwb, to_lab, to_lch,
+        to_oklab, to_oklch, to_xyz_d50, to_xyz_d65,
+    };
+
+    fn rgb(red: u8, green: u8, blue: u8) -> Color {
+        Color {
+            red: red as f32 / 255.0,
+            green: green as f32 / 255.0,
+            blue: blue as f32 / 255.0,
+            alpha: 1.0,
+        }
+    }
+
+    #[track_caller]
+    fn assert_close(actual: (f32, f32, f32), expected: (f32, f32, f32), tolerance: f32) {
+        let close = [
+            (actual.0, expected.0),
+            (actual.1, expected.1),
+            (actual.2, expected.2),
+        ]
+        .iter()
+        .all(|(actual, expected)| (actual - expected).abs() <= tolerance);
+        assert!(close, "{actual:?} isn't within {tolerance} of {expected:?}");
+    }
+
+    fn triple([x, y, z]: [f32; 3]) -> (f32, f32, f32) {
+        (x, y, z)
+    }
+
+    // Reference values from the CSS Color 4 conversion code, in f64.
+    #[test]
+    fn matches_reference_vectors() {
+        let red = rgb(255, 0, 0);
+        assert_close(triple(to_xyz_d65(&red)), (0.41239, 0.21264, 0.01933), 1e-4);
+        assert_close(to_lab(&red), (54.29054, 80.80493, 69.89096), 1e-2);
+        assert_close(to_lch(&red), (54.29054, 106.83718, 40.85766), 1e-2);
+        assert_close(to_oklab(&red), (0.62796, 0.22486, 0.12585), 1e-4);
+        assert_close(to_oklch(&red), (0.62796, 0.25768, 29.23388), 1e-2);
+
+        let white = rgb(255, 255, 255);
+        assert_close(triple(to_xyz_d65(&white)), (0.95046, 1.0, 1.08906), 1e-4);
+        assert_close(to_lab(&white), (100.0, 0.0, 0.0), 1e-2);
+        assert_close(to_oklch(&white), (1.0, 0.0, 0.0), 1e-4);
+
+        let blue = rgb(0x33, 0x66, 0x99);
+        assert_close(to_lab(&blue), (41.52082, -4.57309, -33.49419), 1e-2);
+        assert_close(to_oklch(&blue), (0.49931, 0.09866, 250.43306), 1e-2);
+        assert_close(to_hsl(&blue), (210.0, 0.5, 0.4), 1e-4);
+        assert_close(to_hwb(&blue), (210.0, 0.2, 0.4), 1e-4);
+        assert_close(to_hsv(&blue), (210.0, 2.0 / 3.0, 0.6), 1e-4);
+    }
+
+    // Every space round-trips a grid of sRGB colors.
+    #[test]
+    fn round_trips_srgb_colors() {
+        let levels = || (0..=255).step_by(15);
+        for color in levels().flat_map(|red| {
+            levels().flat_map(move |green| levels().map(move |blue| rgb(red, green, blue)))
+        }) {
+            let channels = |color: Color| (color.red, color.green, color.blue);
+            let expected = channels(color);
+            let round_trips = [
+                ("linear", {
+                    let linear = [color.red, color.green, color.blue].map(srgb_to_linear);
+                    let [red, green, blue] = linear.map(linear_to_srgb);
+                    (red, green, blue)
+                }),
+                ("xyz-d65", channels(from_xyz_d65(to_xyz_d65(&color), 1.0))),
+                ("xyz-d50", channels(from_xyz_d50(to_xyz_d50(&color), 1.0))),
+                ("lab", {
+                    let (lightness, a, b) = to_lab(&color);
+                    channels(from_lab(lightness, a, b, 1.0))
+                }),
+                ("lch", {
+                    let (lightness, chroma, hue) = to_lch(&color);
+                    channels(from_lch(lightness, chroma, hue, 1.0))
+                }),
+                ("oklab", {
+                    let (lightness, a, b) = to_oklab(&color);
+                    channels(from_oklab(lightness, a, b, 1.0))
+                }),
+                ("oklch", {
+                    let (lightness, chroma, hue) = to_oklch(&color);
+                    channels(from_oklch(lightness, chroma, hue, 1.0))
+                }),
+                ("hsl", {
+                    let (hue, saturation, lightness) = to_hsl(&color);
+                    channels(from_hsl(hue, saturation, lightness, 1.0))
+                }),
+                ("hwb", {
+                    let (hue, whiteness, blackness) = to_hwb(&color);
+                    channels(from_hwb(hue, whiteness, blackness, 1.0))
+                }),
+                ("hsv", {
+                    let (hue, saturation, value) = to_hsv(&color);
+                    channels(from_hsv(hue, saturation, value, 1.0))
+                }),
+            ];
+            for (space, actual) in round_trips {
+                let close = [
+                    (actual.0, expected.0),
+                    (actual.1, expected.1),
+                    (actual.2, expected.2),
+                ]
+                .iter()
+                .all(|(actual, expected)| (actual - expected).abs() <= 1e-3);
+                assert!(close, "{expected:?} through {space} gave {actual:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn oklab_round_trips() {
+        let color = Color {
+            red: 0.2,
+            green: 0.6,
+            blue: 0.9,
+            alpha: 1.0,
+        };
+        let (lightness, a, b) = to_oklab(&color);
+        assert_eq!(to_hex(&from_oklab(lightness, a, b, 1.0)), "#3399E6");
+    }
+
+    #[test]
+    fn hsl_round_trip() {
+        let color = parse_line_colors("#336699", 0)[0].color;
+        let (h, s, l) = to_hsl(&color);
+        assert_eq!(h.round(), 210.0);
+        assert_eq!(to_hex(&from_hsl(h, s, l, 1.0)), "#336699");
+
+        let red = Color {
+            red: 1.0,
+            green: 0.0,
+            blue: 0.0,
+            alpha: 1.0,
+        };
+        assert_eq!(to_hsl(&red), (0.0, 1.0, 0.5));
+    }
+}
@@ -0,0 +1,206 @@
+//! Completion of colors as they're typed: colors already used in the
+//! document after `#` or `rgb(`, and CSS named colors for partial names.
+
+use std::collections::BTreeMap;
+
+use tower_lsp_server::ls_types::{
+    Color, CompletionItem, CompletionItemKind, CompletionTextEdit, Documentation, Position, Range,
+    TextEdit,
+};
+
+use crate::color::to_hex;
+use crate::document::{Document, utf16_to_byte_index};
+use crate::named;
+use crate::presentation::css_rgb;
+
+/// What is being typed before the cursor.
+#[derive(Debug, PartialEq)]
+enum Context<'a> {
+    /// A hex color, from its `#`.
+    Hex { start: usize },
+    /// The arguments of `rgb(` or `rgba(`, from the function name.
+    Rgb { start: usize, function: &'a str },
+    /// A partial word that may become a named color.
+    Word { start: usize },
+}
+
+/// Finds what is being typed at the end of `before`, the text of the line
+/// before the cursor. Starts are byte offsets into `before`.
+fn context(before: &str) -> Context<'_> {
+    let word_start = before
+        .rfind(|c: char| !c.is_ascii_alphanumeric() && c != '-')
+        .map_or(0, |idx| idx + 1);
+    if before[..word_start].ends_with('#') {
+        return Context::Hex {
+            start: word_start - 1,
+        };
+    }
+    if let Some(start) = before.rfind("rgb") {
+        let function = &before[start..];
+        let arguments = function
+            .strip_prefix("rgba(")
+            .or_else(|| function.strip_prefix("rgb("));
+        if arguments.is_some_and(|arguments| !arguments.contains(')')) {
+            return Context::Rgb { start, function };
+        }
+    }
+    Context::Word { start: word_start }
+}
+
+/// Returns the colors to complete at `position`: colors used elsewhere in the
+/// document after `#` or in `rgb()`, and otherwise CSS named colors where
+/// the document decorates them, once part of their name is typed.
+pub fn items(document: &Document, position: Position) -> Vec<CompletionItem> {
+    let Some(line) = document.line(position.line as usize) else {
+        return Vec::new();
+    };
+    let cursor = utf16_to_byte_index(line, position.character as usize);
+    let before = &line[..cursor];
+    let edit_range = |start: usize, end: Position| Range {
+        start: Position::new(position.line, before[..start].encode_utf16().count() as u32),
+        end,
+    };
+    match context(before) {
+        Context::Hex { start } => used_colors(document, position)
+            .map(|(hex, color)| {
+                color_item(
+                    hex.clone(),
+                    &color,
+                    "used in this document",
+                    hex,
+                    edit_range(start, position),
+                )
+            })
+            .collect(),
+        Context::Rgb { start, function } => {
+            // Replace the closing parenthesis inserted by the editor, if any.
+            let end = if line[cursor..].starts_with(')') {
+                Position::new(position.line, position.character + 1)
+            } else {
+                position
+            };
+            used_colors(document, position)
+                .map(|(_, color)| {
+                    let rgb = css_rgb(&color, function);
+                    color_item(
+                        rgb.clone(),
+                        &color,
+                        "used in this document",
+                        rgb,
+                        edit_range(start, end),
+                    )
+                })
+                .collect()
+        }
+        Context::Word { start } if start < cursor && document.options().named_colors => {
+            named::all()
+                .filter(|(name, _)| name.starts_with(&before[start..].to_ascii_lowercase()))
+                .map(|(name, color)| {
+                    let range = edit_range(start, position);
+                    color_item(
+                        name.to_string(),
+                        &color,
+                        "CSS named color",
+                        name.to_string(),
+                        range,
+                    )
+                })
+                .collect()
+        }
+        Context::Word { .. } => Vec::new(),
+    }
+}
+
+/// Returns the distinct colors of the document by hex, except one at
+/// `position`, which is likely the one being typed.
+fn used_colors(document: &Document, position: Position) -> impl Iterator<Item = (String, Color)> {
+    let colors: BTreeMap<String, Color> = document
+        .colors()
+        .iter()
+        .filter(|color_info| {
+            !(color_info.range.start <= position && position <= color_info.range.end)
+        })
+        .map(|color_info| (to_hex(&color_info.color), color_info.color))
+        .collect();
+    colors.into_iter()
+}
+
+fn color_item(
+    label: String,
+    color: &Color,
+    detail: &str,
+    new_text: String,
+    range: Range,
+) -> CompletionItem {
+    let hex = to_hex(color);
+    CompletionItem {
+        label,
+        kind: Some(CompletionItemKind::COLOR),
+        detail: Some(detail.to_string()),
+        // Clients render a swatch for color items documented with a hex.
+        documentation: Some(Documentation::String(hex)),
+        text_edit: Some(CompletionTextEdit::Edit(TextEdit { range, new_text })),
+        ..Default::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use tower_lsp_server::ls_types::{CompletionItem, CompletionTextEdit, Position};
+
+    use crate::color::ParseOptions;
+    use crate::completion::{Context, context, items};
+    use crate::document::Document;
+    use crate::heuristics::Syntax;
+
+    #[test]
+    fn detects_what_is_typed() {
+        assert_eq!(context("a { color: #1a"), Context::Hex { start: 11 });
+        assert_eq!(context("#"), Context::Hex { start: 0 });
+        assert_eq!(
+            context("a: rgba(12, "),
+            Context::Rgb {
+                start: 3,
+                function: "rgba(12, "
+            }
+        );
+        assert_eq!(context("a: rgb(1 2 3) re"), Context::Word { start: 14 });
+        assert_eq!(context(""), Context::Word { start: 0 });
+    }
+
+    #[test]
+    fn completes_used_and_named_colors() {
+        let text =
+            "a { color: #1a73e8; }\nb { color: #; background: rgb(); }\nc { color: rebecc }\n";
+        let options = Arc::new(ParseOptions {
+            named_colors: true,
+            ..Default::default()
+        });
+        let document = Document::with_options(text, options, Syntax::Plain);
+        let edit = |item: &CompletionItem| match &item.text_edit {
+            Some(CompletionTextEdit::Edit(edit)) => (edit.new_text.clone(), edit.range),
+            _ => unreachable!(),
+        };
+
+        let hex = items(&document, Position::new(1, 12));
+        assert_eq!(hex.len(), 1);
+        let (new_text, range) = edit(&hex[0]);
+        assert_eq!(new_text, "#1A73E8");
+        assert_eq!((range.start.character, range.end.character), (11, 12));
+
+        let rgb = items(&document, Position::new(1, 30));
+        let (new_text, range) = edit(&rgb[0]);
+        assert_eq!(new_text, "rgb(26 115 232)");
+        assert_eq!((range.start.character, range.end.character), (26, 31));
+
+        let named = items(&document, Position::new(2, 17));
+        let rebeccapurple = named
+            .iter()
+            .find(|item| item.label == "rebeccapurple")
+            .unwrap();
+        let (_, range) = edit(rebeccapurple);
+        assert_eq!((range.start.character, range.end.character), (11, 17));
+    }
+}
@@ -0,0 +1,84 @@
+//! Previews of translucent colors over the backgrounds they're shown on, as
+//! `rgba(0, 0, 0, .06)` alone says little about the resulting color.
+
+use serde::Deserialize;
+use tower_lsp_server::ls_types::Color;
+
+use crate::color::parse_color;
+
+/// Opaque backgrounds translucent colors are composited over, e.g.
+/// `["#ffffff", "#1e1e1e"]` for light and dark pages. White by default.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(try_from = "Vec<String>")]
+pub struct Backgrounds(Vec<Color>);
+
+impl Default for Backgrounds {
+    fn default() -> Self {
+        Self(vec![Color {
+            red: 1.0,
+            green: 1.0,
+            blue: 1.0,
+            alpha: 1.0,
+        }])
+    }
+}
+
+impl TryFrom<Vec<String>> for Backgrounds {
+    type Error = String;
+
+    fn try_from(backgrounds: Vec<String>) -> Result<Self, Self::Error> {
+        backgrounds
+            .iter()
+            .map(|value| match parse_color(value.trim()) {
+                Some(color) if color.alpha == 1.0 => Ok(color),
+                Some(_) => Err(format!("background {value:?} isn't opaque")),
+                None => Err(format!("invalid background color {value:?}")),
+            })
+            .collect::<Result<_, _>>()
+            .map(Self)
+    }
+}
+
+impl Backgrounds {
+    pub fn first(&self) -> Option<&Color> {
+        self.0.first()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Color> {
+        self.0.iter()
+    }
+}
+
+/// Returns `color` painted over an opaque `background`, blending in sRGB as
+/// browsers do.
+pub fn composite(color: &Color, background: &Color) -> Color {
+    let alpha = color.alpha.clamp(0.0, 1.0);
+    let blend = |channel: f32, below: f32| channel * alpha + below * (1.0 - alpha);
+    Color {
+        red: blend(color.red, background.red),
+        green: blend(color.green, background.green),
+        blue: blend(color.blue, background.blue),
+        alpha: 1.0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::color::{parse_line_colors, to_hex};
+    use crate::composite::{Backgrounds, composite};
+
+    #[test]
+    fn composites_over_backgrounds() {
+        let shadow = parse_line_colors("rgba(0, 0, 0, .06)", 0)[0].color;
+        let backgrounds =
+            Backgrounds::try_from(vec!["#ffffff".to_string(), "#1e1e1e".to_string()]).unwrap();
+        let composited: Vec<String> = backgrounds
+            .iter()
+            .map(|background| to_hex(&composite(&shadow, background)))
+            .collect();
+        assert_eq!(composited, ["#F0F0F0", "#1C1C1C"]);
+
+        assert!(Backgrounds::try_from(vec!["#ffffff80".to_string()]).is_err());
+        assert!(Backgrounds::try_from(vec!["white-ish".to_string()]).is_err());
+    }
+}
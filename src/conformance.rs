@@ -0,0 +1,307 @@
+//! Conformance of color functions to the CSS Color 4 grammar, for documents
+//! validated strictly: units, separators and component ranges that browsers
+//! would reject or clamp.
+
+use serde::Deserialize;
+use tower_lsp_server::ls_types::{Diagnostic, DiagnosticSeverity, NumberOrString};
+
+use crate::color::{function_arguments, function_calls, split_outside_parens};
+use crate::document::Document;
+use crate::heuristics::line_range;
+
+/// Code of nonconforming color diagnostics.
+pub const DIAGNOSTIC_CODE: &str = "nonconforming-color";
+
+/// How color functions are validated.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Validation {
+    /// Colors are read on a best-effort basis, clamping out-of-range values.
+    #[default]
+    Lenient,
+    /// Nonconforming colors get a diagnostic instead of a swatch.
+    Strict,
+}
+
+/// Type and range of a function component.
+#[derive(Debug, Clone, Copy)]
+enum Component {
+    /// A number in the range, or a percentage of it.
+    Bounded(f32, f32),
+    /// A number or percentage, at least zero.
+    NonNegative,
+    /// A number or percentage of any sign.
+    Unbounded,
+    /// A number of degrees or an angle.
+    Hue,
+}
+
+/// Checks a color function call spelled `text`, e.g. `rgb(255 0 0 / 50%)`,
+/// against the CSS Color 4 grammar, returning why it doesn't conform.
+pub fn check(text: &str) -> Result<(), String> {
+    let open = text.find('(').ok_or("Expected a color function")?;
+    let function = &text[..open];
+    let name = match function {
+        "rgba" => "rgb",
+        "hsla" => "hsl",
+        name => name,
+    };
+    let args = text[open + 1..]
+        .strip_suffix(')')
+        .ok_or("Expected a closing parenthesis")?;
+    let components: [(&str, Component); 3] = match name {
+        "rgb" => [
+            ("red", Component::Bounded(0.0, 255.0)),
+            ("green", Component::Bounded(0.0, 255.0)),
+            ("blue", Component::Bounded(0.0, 255.0)),
+        ],
+        "hsl" => [
+            ("hue", Component::Hue),
+            ("saturation", Component::Bounded(0.0, 100.0)),
+            ("lightness", Component::Bounded(0.0, 100.0)),
+        ],
+        "hwb" => [
+            ("hue", Component::Hue),
+            ("whiteness", Component::Bounded(0.0, 100.0)),
+            ("blackness", Component::Bounded(0.0, 100.0)),
+        ],
+        "lab" => [
+            ("lightness", Component::Bounded(0.0, 100.0)),
+            ("a", Component::Unbounded),
+            ("b", Component::Unbounded),
+        ],
+        "lch" => [
+            ("lightness", Component::Bounded(0.0, 100.0)),
+            ("chroma", Component::NonNegative),
+            ("hue", Component::Hue),
+        ],
+        "oklab" => [
+            ("lightness", Component::Bounded(0.0, 1.0)),
+            ("a", Component::Unbounded),
+            ("b", Component::Unbounded),
+        ],
+        "oklch" => [
+            ("lightness", Component::Bounded(0.0, 1.0)),
+            ("chroma", Component::NonNegative),
+            ("hue", Component::Hue),
+        ],
+        _ => return Err(format!("`{function}()` is not a CSS color function")),
+    };
+    let legacy = split_outside_parens(args, |c| c == ',').len() > 1;
+    if legacy && !matches!(name, "rgb" | "hsl") {
+        return Err(format!(
+            "`{function}()` separates components with spaces, not commas"
+        ));
+    }
+    let (values, alpha) = function_arguments(args)
+        .ok_or_else(|| format!("`{function}()` takes three components and an optional alpha"))?;
+    for ((label, component), value) in components.into_iter().zip(values) {
+        check_component(value, label, component, legacy)?;
+    }
+    if legacy {
+        let percentages = values.map(|value| value.ends_with('%'));
+        if name == "rgb" && percentages.contains(&true) && percentages.contains(&false) {
+            return Err(format!(
+                "Comma-separated `{function}()` takes either numbers or percentages, not both"
+            ));
+        }
+        if name == "hsl"
+            && let Some(value) = values[1..].iter().find(|value| !is_percentage(value))
+        {
+            return Err(format!(
+                "Comma-separated `{function}()` takes a percentage, found `{value}`"
+            ));
+        }
+    }
+    match alpha {
+        Some("") => Err("Expected an alpha after the separator".to_string()),
+        Some(alpha) => check_component(alpha, "alpha", Component::Bounded(0.0, 1.0), legacy),
+        None => Ok(()),
+    }
+}
+
+/// Checks a component `value`, called `label` in messages.
+fn check_component(
+    value: &str,
+    label: &str,
+    component: Component,
+    legacy: bool,
+) -> Result<(), String> {
+    if value.eq_ignore_ascii_case("none") {
+        return if legacy {
+            Err("`none` is only valid in space-separated colors".to_string())
+        } else {
+            Ok(())
+        };
+    }
+    // Math functions aren't type checked; those the parser can't evaluate
+    // get no swatch either way.
+    if value.ends_with(')') {
+        return Ok(());
+    }
+    let invalid = || format!("`{value}` is not a valid {label}");
+    let (number, percentage) = match value.strip_suffix('%') {
+        Some(number) => (number, true),
+        None if matches!(component, Component::Hue) => {
+            let lowercase = value.to_ascii_lowercase();
+            let unit = ["deg", "grad", "rad", "turn"]
+                .into_iter()
+                .find(|unit| lowercase.ends_with(unit))
+                .map_or(0, str::len);
+            (&value[..value.len() - unit], false)
+        }
+        None => (value, false),
+    };
+    if !is_css_number(number) || (percentage && matches!(component, Component::Hue)) {
+        return Err(invalid());
+    }
+    let number: f32 = number.parse().map_err(|_| invalid())?;
+    let (min, max) = match component {
+        Component::Bounded(min, _) if percentage => (min, 100.0),
+        Component::Bounded(min, max) => (min, max),
+        Component::NonNegative => (0.0, f32::INFINITY),
+        Component::Unbounded | Component::Hue => (f32::NEG_INFINITY, f32::INFINITY),
+    };
+    if number < min || number > max {
+        let expected = if max.is_infinite() {
+            format!("at least {min}")
+        } else if percentage {
+            format!("{min}% to {max}%")
+        } else {
+            format!("{min} to {max}")
+        };
+        return Err(format!(
+            "`{value}` is out of range for {label}, expected {expected}"
+        ));
+    }
+    Ok(())
+}
+
+/// Whether `value` is a percentage, including `calc()` and other math
+/// functions, whose type isn't checked.
+fn is_percentage(value: &str) -> bool {
+    value.ends_with('%') || value.ends_with(')')
+}
+
+/// Whether `value` is a CSS `<number>`: an optional sign, digits with an
+/// optional fraction or a fraction alone, and an optional exponent.
+fn is_css_number(value: &str) -> bool {
+    let value = value.strip_prefix(['+', '-']).unwrap_or(value);
+    let (mantissa, exponent) = match value.find(['e', 'E']) {
+        Some(idx) => (&value[..idx], Some(&value[idx + 1..])),
+        None => (value, None),
+    };
+    let digits = |s: &str| !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit());
+    let mantissa = match mantissa.split_once('.') {
+        Some((integer, fraction)) => (integer.is_empty() || digits(integer)) && digits(fraction),
+        None => digits(mantissa),
+    };
+    mantissa
+        && exponent
+            .is_none_or(|exponent| digits(exponent.strip_prefix(['+', '-']).unwrap_or(exponent)))
+}
+
+/// Whether the arguments of a color function depend on values only known at
+/// runtime: `var()`, `env()` or `attr()` references, or a relative color.
+pub fn unresolvable(args: &str) -> bool {
+    ["var(", "env(", "attr("]
+        .iter()
+        .any(|function| args.contains(function))
+        || args.trim_start().starts_with("from ")
+}
+
+/// Returns a warning on each color function of a document that doesn't
+/// conform to CSS Color 4. Calls with `var()`, `env()` or `attr()` arguments
+/// and relative colors can't be checked and are skipped.
+pub fn diagnostics(document: &Document) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    for (line_idx, line) in document.lines().enumerate() {
+        for (_, call, args) in function_calls(line) {
+            if unresolvable(&line[args]) {
+                continue;
+            }
+            if let Err(message) = check(&line[call.clone()]) {
+                diagnostics.push(Diagnostic {
+                    range: line_range(line, line_idx, call),
+                    severity: Some(DiagnosticSeverity::WARNING),
+                    code: Some(NumberOrString::String(DIAGNOSTIC_CODE.to_string())),
+                    source: Some("chroma-ls".to_string()),
+                    message,
+                    ..Default::default()
+                });
+            }
+        }
+    }
+    diagnostics
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::conformance::check;
+
+    #[test]
+    fn accepts_conforming_colors() {
+        for text in [
+            "rgb(255, 0, 0)",
+            "rgba(100%, 0%, 0%, .5)",
+            "rgb(255 0 0 / 50%)",
+            "rgb(none 128 0)",
+            "hsl(210deg, 50%, 40%)",
+            "hsla(0.5turn 50 40 / 0.8)",
+            "hwb(120 10% 20%)",
+            "lab(50% -40 60)",
+            "lch(70 45 1e2)",
+            "oklch(70% 0.1 200)",
+            "oklab(0.5 -0.1 +0.1 / calc(1 / 2))",
+        ] {
+            assert_eq!(check(text), Ok(()), "{text}");
+        }
+    }
+
+    #[test]
+    fn rejects_nonconforming_colors() {
+        for (text, message) in [
+            (
+                "rgb(255, 0%, 0)",
+                "Comma-separated `rgb()` takes either numbers or percentages, not both",
+            ),
+            (
+                "hsl(210, 50, 40%)",
+                "Comma-separated `hsl()` takes a percentage, found `50`",
+            ),
+            (
+                "hwb(120, 10%, 20%)",
+                "`hwb()` separates components with spaces, not commas",
+            ),
+            (
+                "rgb(none, 0, 0)",
+                "`none` is only valid in space-separated colors",
+            ),
+            ("rgb(10px 0 0)", "`10px` is not a valid red"),
+            ("rgb(1. 0 0)", "`1.` is not a valid red"),
+            ("hsl(50% 50% 50%)", "`50%` is not a valid hue"),
+            (
+                "rgb(300 0 0)",
+                "`300` is out of range for red, expected 0 to 255",
+            ),
+            (
+                "rgba(0 0 0 / 1.5)",
+                "`1.5` is out of range for alpha, expected 0 to 1",
+            ),
+            (
+                "oklch(120% 0.1 200)",
+                "`120%` is out of range for lightness, expected 0% to 100%",
+            ),
+            (
+                "lch(50 -10 200)",
+                "`-10` is out of range for chroma, expected at least 0",
+            ),
+            (
+                "rgb(0 0)",
+                "`rgb()` takes three components and an optional alpha",
+            ),
+        ] {
+            assert_eq!(check(text), Err(message.to_string()), "{text}");
+        }
+    }
+}
@@ -0,0 +1,546 @@
+use serde::Deserialize;
+use tower_lsp_server::ls_types::{
+    Color, ColorInformation, Diagnostic, DiagnosticSeverity, Position, Range,
+};
+
+use crate::color::to_hex;
+use crate::colorspace::srgb_to_linear;
+use crate::document::Document;
+use crate::heuristics::Syntax;
+use crate::schema::JsonSchema;
+
+/// Minimum contrast ratio for normal text under WCAG AA.
+pub const WCAG_AA: f32 = 4.5;
+/// Minimum contrast ratio for normal text under WCAG AAA.
+pub const WCAG_AAA: f32 = 7.0;
+
+/// WCAG conformance level contrast is checked against.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub enum ContrastLevel {
+    #[default]
+    #[serde(rename = "AA")]
+    Aa,
+    #[serde(rename = "AAA")]
+    Aaa,
+}
+
+impl ContrastLevel {
+    /// Minimum contrast ratio of normal text at the level.
+    pub fn min_ratio(self) -> f32 {
+        match self {
+            ContrastLevel::Aa => WCAG_AA,
+            ContrastLevel::Aaa => WCAG_AAA,
+        }
+    }
+}
+
+/// Terminal palette keys, paired with the default background.
+const PALETTE_KEYS: [&str; 8] = [
+    "black", "red", "green", "yellow", "blue", "magenta", "cyan", "white",
+];
+
+/// Returns the WCAG relative luminance of a color.
+pub fn relative_luminance(color: &Color) -> f32 {
+    0.2126 * srgb_to_linear(color.red)
+        + 0.7152 * srgb_to_linear(color.green)
+        + 0.0722 * srgb_to_linear(color.blue)
+}
+
+/// Returns the WCAG contrast ratio between two colors, in `[1, 21]`.
+pub fn contrast_ratio(a: &Color, b: &Color) -> f32 {
+    let (a, b) = (relative_luminance(a), relative_luminance(b));
+    (a.max(b) + 0.05) / (a.min(b) + 0.05)
+}
+
+/// Returns the APCA lightness contrast `Lc` of text on a background, from
+/// about 106 for black on white to about -108 for white on black. Positive
+/// values are dark text on a light background; values within 15 of zero are
+/// not readable.
+pub fn apca_contrast(text: &Color, background: &Color) -> f32 {
+    // APCA-W3 0.0.98G-4g constants.
+    let luminance = |color: &Color| {
+        let y = 0.2126729 * color.red.clamp(0.0, 1.0).powf(2.4)
+            + 0.7151522 * color.green.clamp(0.0, 1.0).powf(2.4)
+            + 0.0721750 * color.blue.clamp(0.0, 1.0).powf(2.4);
+        // Soft clamp of near black.
+        if y < 0.022 {
+            y + (0.022 - y).powf(1.414)
+        } else {
+            y
+        }
+    };
+    let (text, background) = (luminance(text), luminance(background));
+    if (background - text).abs() < 0.0005 {
+        return 0.0;
+    }
+    let contrast = if background > text {
+        let sapc = (background.powf(0.56) - text.powf(0.57)) * 1.14;
+        if sapc < 0.1 { 0.0 } else { sapc - 0.027 }
+    } else {
+        let sapc = (background.powf(0.65) - text.powf(0.62)) * 1.14;
+        if sapc > -0.1 { 0.0 } else { sapc + 0.027 }
+    };
+    contrast * 100.0
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Role {
+    Foreground,
+    Background,
+}
+
+/// A color assigned to a key in a theme file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ThemeEntry {
+    pub key: String,
+    pub color: Color,
+    pub range: Range,
+}
+
+/// A foreground/background pair whose contrast is too low.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ContrastIssue {
+    pub foreground: ThemeEntry,
+    pub background: ThemeEntry,
+    pub ratio: f32,
+}
+
+/// Returns the foreground/background pairs of a document whose contrast
+/// ratio is below `min_ratio`, from theme keys as well as CSS rules and Vim
+/// highlight groups, in document order.
+pub fn contrast_issues(document: &Document, min_ratio: f32) -> Vec<ContrastIssue> {
+    let mut issues = theme_contrast_issues(document, min_ratio);
+    issues.extend(declaration_contrast_issues(document, min_ratio));
+    issues.sort_by_key(|issue| issue.foreground.range.start);
+    issues
+}
+
+/// Pairs foreground keys with background keys in a theme file and returns
+/// the pairs whose contrast ratio is below `min_ratio`.
+///
+/// Keys are paired by their stem, e.g. `editor.foreground` with
+/// `editor.background` or `--fg-muted` with `--bg-muted`. Foregrounds without
+/// a matching stem, including terminal palette colors, are checked against
+/// the default background: `editor.background` in VS Code color themes and
+/// the unqualified background elsewhere.
+pub fn theme_contrast_issues(document: &Document, min_ratio: f32) -> Vec<ContrastIssue> {
+    let default_stem = match document.syntax() {
+        Syntax::Json(JsonSchema::VsCodeTheme) => "editor",
+        _ => "",
+    };
+
+    let mut foregrounds = Vec::new();
+    let mut backgrounds = Vec::new();
+    for color_info in document.get_colors() {
+        let Some(entry) = theme_entry(document, &color_info) else {
+            continue;
+        };
+        match classify(&entry.key) {
+            Some((Role::Foreground, stem)) => foregrounds.push((stem, entry)),
+            Some((Role::Background, stem)) => backgrounds.push((stem, entry)),
+            None => {}
+        }
+    }
+
+    let mut issues = Vec::new();
+    for (stem, foreground) in foregrounds {
+        let background = backgrounds
+            .iter()
+            .find(|(bg_stem, _)| *bg_stem == stem)
+            .or_else(|| {
+                backgrounds
+                    .iter()
+                    .find(|(bg_stem, _)| bg_stem == default_stem)
+            });
+        let Some((_, background)) = background else {
+            continue;
+        };
+
+        let ratio = contrast_ratio(&foreground.color, &background.color);
+        if ratio < min_ratio {
+            issues.push(ContrastIssue {
+                foreground,
+                background: background.clone(),
+                ratio,
+            });
+        }
+    }
+    issues
+}
+
+/// Where a foreground declaration looks for its background.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Scope {
+    /// The CSS rule opened by the `{` with this index.
+    Rule(usize),
+    /// A Vim highlight group, on this line.
+    Line(u32),
+}
+
+/// Pairs the `color` of each CSS rule with its `background-color` or
+/// `background`, and the `guifg` of each Vim highlight group with its
+/// `guibg`, or with the `guibg` of `Normal` when it has none. Returns the
+/// pairs whose contrast ratio is below `min_ratio`.
+fn declaration_contrast_issues(document: &Document, min_ratio: f32) -> Vec<ContrastIssue> {
+    let rules = Rules::new(document);
+    let mut foregrounds = Vec::new();
+    let mut backgrounds = Vec::new();
+    let mut normal_background = None;
+    for color_info in document.get_colors() {
+        let Some(entry) = theme_entry(document, &color_info) else {
+            continue;
+        };
+        let start = color_info.range.start;
+        match entry.key.to_ascii_lowercase().as_str() {
+            "color" => {
+                if let Some(rule) = rules.at(start) {
+                    foregrounds.push((Scope::Rule(rule), entry));
+                }
+            }
+            "background" | "background-color" => {
+                if let Some(rule) = rules.at(start) {
+                    backgrounds.push((Scope::Rule(rule), entry));
+                }
+            }
+            "guifg" => foregrounds.push((Scope::Line(start.line), entry)),
+            "guibg" => {
+                let line = document.line(start.line as usize).unwrap_or_default();
+                if highlight_group(line).is_some_and(|group| group == "Normal") {
+                    normal_background = Some(entry.clone());
+                }
+                backgrounds.push((Scope::Line(start.line), entry));
+            }
+            _ => {}
+        }
+    }
+
+    let mut issues = Vec::new();
+    for (scope, foreground) in foregrounds {
+        let background = backgrounds
+            .iter()
+            .find(|(bg_scope, _)| *bg_scope == scope)
+            .map(|(_, background)| background)
+            .or(match scope {
+                Scope::Line(_) => normal_background.as_ref(),
+                Scope::Rule(_) => None,
+            });
+        let Some(background) = background else {
+            continue;
+        };
+
+        let ratio = contrast_ratio(&foreground.color, &background.color);
+        if ratio < min_ratio {
+            issues.push(ContrastIssue {
+                foreground,
+                background: background.clone(),
+                ratio,
+            });
+        }
+    }
+    issues
+}
+
+/// Returns the group of a Vim `highlight` command, e.g. `Normal` in
+/// `hi Normal guifg=#C0C0C0`.
+fn highlight_group(line: &str) -> Option<&str> {
+    let mut words = line.split_whitespace();
+    let command = words.next()?;
+    if !"highlight".starts_with(command) || command.len() < 2 {
+        return None;
+    }
+    words.find(|word| *word != "default" && *word != "clear" && !word.ends_with('!'))
+}
+
+/// The innermost `{` block around each position of a document.
+struct Rules {
+    /// Positions after each brace, with the index of the block it leaves
+    /// open, in document order.
+    changes: Vec<(Position, Option<usize>)>,
+}
+
+impl Rules {
+    fn new(document: &Document) -> Self {
+        let mut changes = Vec::new();
+        let mut open = Vec::new();
+        let mut next = 0;
+        for (line_idx, line) in document.lines().enumerate() {
+            let mut character = 0;
+            for c in line.chars() {
+                character += c.len_utf16() as u32;
+                match c {
+                    '{' => {
+                        open.push(next);
+                        next += 1;
+                    }
+                    '}' => {
+                        open.pop();
+                    }
+                    _ => continue,
+                }
+                let position = Position::new(line_idx as u32, character);
+                changes.push((position, open.last().copied()));
+            }
+        }
+        Self { changes }
+    }
+
+    /// Returns the index of the block around `position`, if any.
+    fn at(&self, position: Position) -> Option<usize> {
+        let idx = self
+            .changes
+            .partition_point(|(start, _)| *start <= position);
+        idx.checked_sub(1).and_then(|idx| self.changes[idx].1)
+    }
+}
+
+/// Renders the issues as a Markdown summary.
+pub fn markdown_report(issues: &[ContrastIssue], min_ratio: f32) -> String {
+    if issues.is_empty() {
+        return format!("All foreground/background pairs meet {min_ratio}:1.\n");
+    }
+
+    let mut report = format!(
+        "{} foreground/background pairs are below {min_ratio}:1.\n\n\
+         | Foreground | Background | Ratio | Line |\n\
+         | --- | --- | --- | --- |\n",
+        issues.len()
+    );
+    for issue in issues {
+        report.push_str(&format!(
+            "| `{}` {} | `{}` {} | {:.2}:1 | {} |\n",
+            issue.foreground.key,
+            to_hex(&issue.foreground.color),
+            issue.background.key,
+            to_hex(&issue.background.color),
+            issue.ratio,
+            issue.foreground.range.start.line + 1,
+        ));
+    }
+    report
+}
+
+/// Returns a warning on each foreground of the issues.
+pub fn diagnostics(issues: &[ContrastIssue], min_ratio: f32) -> Vec<Diagnostic> {
+    issues
+        .iter()
+        .map(|issue| Diagnostic {
+            range: issue.foreground.range,
+            severity: Some(DiagnosticSeverity::WARNING),
+            source: Some("chroma-ls".to_string()),
+            message: format!(
+                "Contrast {:.2}:1 against `{}` ({}) is below {min_ratio}:1",
+                issue.ratio,
+                issue.background.key,
+                to_hex(&issue.background.color)
+            ),
+            ..Default::default()
+        })
+        .collect()
+}
+
+fn theme_entry(document: &Document, color_info: &ColorInformation) -> Option<ThemeEntry> {
+    let line = document.line(color_info.range.start.line as usize)?;
+    let key = key_before(line, color_info.range.start.character as usize)?;
+    Some(ThemeEntry {
+        key,
+        color: color_info.color,
+        range: color_info.range,
+    })
+}
+
+/// Extracts the key assigned to the value starting at UTF-16 column `column`,
+/// e.g. `editor.background` in `"editor.background": "#1e1e1e"`.
+pub(crate) fn key_before(line: &str, column: usize) -> Option<String> {
+    let mut utf16_len = 0;
+    let prefix: String = line
+        .chars()
+        .take_while(|c| {
+            utf16_len += c.len_utf16();
+            utf16_len <= column
+        })
+        .collect();
+
+    let prefix = prefix.trim_end_matches(|c: char| c.is_whitespace() || ":=\"',".contains(c));
+    let is_key_char = |c: char| c.is_alphanumeric() || "_-.".contains(c);
+    let start = prefix
+        .char_indices()
+        .rev()
+        .take_while(|&(_, c)| is_key_char(c))
+        .last()?
+        .0;
+    Some(prefix[start..].to_string())
+}
+
+/// Returns the role of a key and its stem, the key without the role segment.
+fn classify(key: &str) -> Option<(Role, String)> {
+    let segments: Vec<String> = key
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|segment| !segment.is_empty())
+        .map(str::to_lowercase)
+        .collect();
+
+    let mut role = None;
+    let mut stem = Vec::new();
+    for segment in &segments {
+        match segment.as_str() {
+            "background" | "bg" => role = Some(Role::Background),
+            "foreground" | "fg" | "text" => role = Some(Role::Foreground),
+            s if is_palette_key(s) => return Some((Role::Foreground, String::new())),
+            s => stem.push(s),
+        }
+    }
+    role.map(|role| (role, stem.join(".")))
+}
+
+fn is_palette_key(segment: &str) -> bool {
+    let segment = segment.strip_prefix("bright").unwrap_or(segment);
+    PALETTE_KEYS.contains(&segment)
+        || segment
+            .strip_prefix("color")
+            .is_some_and(|n| !n.is_empty() && n.chars().all(|c| c.is_ascii_digit()))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::contrast::{
+        WCAG_AA, WCAG_AAA, apca_contrast, contrast_issues, contrast_ratio, markdown_report,
+        theme_contrast_issues,
+    };
+    use crate::document::Document;
+    use crate::heuristics::Syntax;
+    use crate::schema::JsonSchema;
+
+    #[test]
+    fn contrast_ratio_extremes() {
+        let document = Document::from("#000000 #FFFFFF");
+        let colors = document.get_colors();
+        assert_eq!(
+            contrast_ratio(&colors[0].color, &colors[1].color).round(),
+            21.0
+        );
+        assert_eq!(contrast_ratio(&colors[0].color, &colors[0].color), 1.0);
+    }
+
+    #[test]
+    fn apca_contrast_polarity() {
+        let document = Document::from("#000000 #FFFFFF #888888");
+        let colors = document.get_colors();
+        let (black, white, gray) = (&colors[0].color, &colors[1].color, &colors[2].color);
+        let lc = |text, background| (apca_contrast(text, background) * 10.0).round() / 10.0;
+        assert_eq!(lc(black, white), 106.0);
+        assert_eq!(lc(white, black), -107.9);
+        assert_eq!(lc(gray, white), 63.1);
+        assert_eq!(lc(gray, gray), 0.0);
+    }
+
+    #[test]
+    fn pairs_by_stem() {
+        let document = Document::from(
+            r##"{
+  "editor.background": "#1E1E1E",
+  "editor.foreground": "#D4D4D4",
+  "panel.background": "#FFFFFF",
+  "panel.foreground": "#EEEEEE"
+}"##,
+        );
+
+        let issues = theme_contrast_issues(&document, WCAG_AA);
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].foreground.key, "panel.foreground");
+        assert_eq!(issues[0].background.key, "panel.background");
+        assert_eq!(issues[0].foreground.range.start.line, 4);
+    }
+
+    #[test]
+    fn palette_against_default_background() {
+        let document = Document::from(
+            "background = '#1D2021'\ncolor0 = '#282828'\nbright_white = '#FBF1C7'\n",
+        );
+
+        let issues = theme_contrast_issues(&document, WCAG_AA);
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].foreground.key, "color0");
+        assert_eq!(issues[0].background.key, "background");
+    }
+
+    #[test]
+    fn vscode_token_colors_against_editor_background() {
+        let text = r##"{
+  "colors": {
+    "editor.background": "#1E1E1E",
+    "editorLineNumber.foreground": "#858585",
+    "sideBar.background": "#252526"
+  },
+  "tokenColors": [
+    { "scope": "comment", "settings": { "foreground": "#3A3A3A" } }
+  ]
+}"##;
+        let document = Document::with_options(
+            text,
+            Default::default(),
+            Syntax::Json(JsonSchema::VsCodeTheme),
+        );
+
+        let issues = theme_contrast_issues(&document, WCAG_AA);
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].foreground.key, "foreground");
+        assert_eq!(issues[0].background.key, "editor.background");
+    }
+
+    #[test]
+    fn css_variables() {
+        let document = Document::from(":root {\n  --bg: #FFFFFF;\n  --fg: #777777;\n}\n");
+
+        let issues = theme_contrast_issues(&document, WCAG_AA);
+        assert_eq!(issues.len(), 1);
+        assert!(markdown_report(&issues, WCAG_AA).contains("| `--fg` #777777 | `--bg` #FFFFFF |"));
+    }
+
+    #[test]
+    fn css_rules() {
+        let document = Document::from(
+            ".a {\n  color: #767676;\n  background-color: #FFFFFF;\n}\n\
+             .b { color: #666666; background: #FFFFFF; }\n\
+             .c { color: #777777; }\n.d { background: #000000; }\n",
+        );
+
+        let issues = contrast_issues(&document, WCAG_AA);
+        assert!(issues.is_empty());
+
+        let issues = contrast_issues(&document, WCAG_AAA);
+        let lines: Vec<_> = issues
+            .iter()
+            .map(|issue| {
+                (
+                    issue.foreground.range.start.line,
+                    issue.background.key.as_str(),
+                )
+            })
+            .collect();
+        // The color of `.c` has no background of its own to pair with.
+        assert_eq!(lines, [(1, "background-color"), (4, "background")]);
+        assert_eq!((issues[0].ratio * 100.0).round(), 454.0);
+    }
+
+    #[test]
+    fn vim_highlight_groups() {
+        let document = Document::from(
+            "hi Normal guifg=#C0C0C0 guibg=#1C1C1C\n\
+             hi Comment guifg=#4E4E4E\n\
+             highlight Visual guifg=#FFFFFF guibg=#3A3A3A\n\
+             hi Search guifg=#303030 guibg=#444444\n",
+        );
+
+        let issues = contrast_issues(&document, WCAG_AA);
+        let pairs: Vec<_> = issues
+            .iter()
+            .map(|issue| {
+                (
+                    issue.foreground.range.start.line,
+                    issue.background.range.start.line,
+                )
+            })
+            .collect();
+        assert_eq!(pairs, [(1, 0), (3, 3)]);
+    }
+}
@@ -0,0 +1,140 @@
+//! Conversion of colors between CSS notations, for `chroma.convertColor`,
+//! `chroma.convertAllColors` and the `chroma-ls convert` command.
+
+use tower_lsp_server::ls_types::{Color, TextEdit};
+
+use crate::color::to_hex;
+use crate::document::{Document, utf16_to_byte_index};
+use crate::presentation::Notation;
+
+/// Returns the edits converting the colors of a document equal to `color`
+/// to `notation`. Colors written in another notation, such as named colors
+/// or Tailwind classes, are left as they are.
+pub fn edits(document: &Document, color: &Color, notation: Notation) -> Vec<TextEdit> {
+    let hex = to_hex(color);
+    convert(document, notation, |other| to_hex(other) == hex)
+}
+
+/// Returns the edits converting every color of a document to `notation`,
+/// leaving colors written in another notation as they are.
+pub fn all_edits(document: &Document, notation: Notation) -> Vec<TextEdit> {
+    convert(document, notation, |_| true)
+}
+
+/// Applies single-line edits in document order to the text of `document`,
+/// written with its line ending. Returns the text and how many edits were
+/// applied; edits spanning lines are skipped.
+pub fn apply(document: &Document, edits: &[TextEdit]) -> (String, usize) {
+    let mut edits = edits.iter().peekable();
+    let mut applied = String::new();
+    let mut count = 0;
+    for (line_idx, line) in document.lines().enumerate() {
+        if line_idx > 0 {
+            applied.push_str(document.line_ending().as_str());
+        }
+        let mut end = 0;
+        while let Some(edit) = edits.next_if(|edit| edit.range.start.line as usize == line_idx) {
+            if edit.range.end.line != edit.range.start.line {
+                continue;
+            }
+            let start = utf16_to_byte_index(line, edit.range.start.character as usize);
+            applied.push_str(&line[end..start]);
+            applied.push_str(&edit.new_text);
+            end = utf16_to_byte_index(line, edit.range.end.character as usize);
+            count += 1;
+        }
+        applied.push_str(&line[end..]);
+    }
+    (applied, count)
+}
+
+fn convert(
+    document: &Document,
+    notation: Notation,
+    matches: impl Fn(&Color) -> bool,
+) -> Vec<TextEdit> {
+    document
+        .get_colors()
+        .into_iter()
+        .filter(|color_info| matches(&color_info.color))
+        .filter_map(|color_info| {
+            let existing = document.range_text(&color_info.range)?;
+            (Notation::of(existing)? != notation).then(|| TextEdit {
+                range: color_info.range,
+                new_text: notation.format(&color_info.color, existing, document.syntax()),
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::convert::{all_edits, apply, edits};
+    use crate::document::Document;
+    use crate::presentation::Notation;
+
+    #[test]
+    fn converts_equal_colors() {
+        let document = Document::from(
+            "a { color: #FF0000; border: 1px solid rgb(255, 0, 0); fill: red; }\n\
+             b { color: #ff0000; background: #00FF00; }\n",
+        );
+        let red = document.get_colors()[0].color;
+
+        let converted: Vec<_> = edits(&document, &red, Notation::Hsl)
+            .into_iter()
+            .map(|edit| (edit.range.start.line, edit.new_text))
+            .collect();
+        assert_eq!(
+            converted,
+            [
+                (0, "hsl(0 100% 50%)".to_string()),
+                (0, "hsl(0, 100%, 50%)".to_string()),
+                (1, "hsl(0 100% 50%)".to_string()),
+            ]
+        );
+        assert_eq!(edits(&document, &red, Notation::Hex).len(), 1);
+    }
+
+    #[test]
+    fn converts_all_colors() {
+        let document =
+            Document::from("a { color: hsl(0, 100%, 50%); fill: #00ff00; stroke: blue; }\n");
+
+        let converted: Vec<_> = all_edits(&document, Notation::Rgb)
+            .into_iter()
+            .map(|edit| edit.new_text)
+            .collect();
+        assert_eq!(converted, ["rgb(255, 0, 0)", "rgb(0 255 0)"]);
+    }
+
+    #[test]
+    fn applies_edits() {
+        let text = "a { color: #FF0000; }\r\n/* ✓ */ b { fill: #0F0; stroke: #00F; }";
+        let document = Document::from(text);
+        let edits = all_edits(&document, Notation::Oklch);
+        assert_eq!(
+            apply(&document, &edits),
+            (
+                "a { color: oklch(62.8% 0.2577 29.23); }\r\n/* ✓ */ b { fill: \
+                 oklch(86.64% 0.2948 142.5); stroke: oklch(45.2% 0.3132 264.05); }"
+                    .to_string(),
+                3
+            )
+        );
+    }
+
+    #[test]
+    fn applies_edits_to_cr_only_text() {
+        let text = "a { color: #FF0000; }\rb { color: #00FF00; }\r";
+        let document = Document::from(text);
+        let edits = all_edits(&document, Notation::Rgb);
+        assert_eq!(
+            apply(&document, &edits),
+            (
+                "a { color: rgb(255 0 0); }\rb { color: rgb(0 255 0); }\r".to_string(),
+                2
+            )
+        );
+    }
+}
@@ -0,0 +1,78 @@
+//! Simulation of color vision deficiencies, after Machado, Oliveira and
+//! Fernandes (2009), at full severity.
+
+use tower_lsp_server::ls_types::Color;
+
+use crate::colorspace::{from_linear_srgb, to_linear_srgb};
+
+/// A color vision deficiency.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Deficiency {
+    Protanopia,
+    Deuteranopia,
+    Tritanopia,
+}
+
+impl Deficiency {
+    pub const ALL: [Deficiency; 3] = [
+        Deficiency::Protanopia,
+        Deficiency::Deuteranopia,
+        Deficiency::Tritanopia,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Deficiency::Protanopia => "protanopia",
+            Deficiency::Deuteranopia => "deuteranopia",
+            Deficiency::Tritanopia => "tritanopia",
+        }
+    }
+
+    /// Linear sRGB transform of the deficiency.
+    fn matrix(self) -> [[f32; 3]; 3] {
+        match self {
+            Deficiency::Protanopia => [
+                [0.152286, 1.052583, -0.204868],
+                [0.114503, 0.786281, 0.099216],
+                [-0.003882, -0.048116, 1.051998],
+            ],
+            Deficiency::Deuteranopia => [
+                [0.367322, 0.860646, -0.227968],
+                [0.280085, 0.672501, 0.047413],
+                [-0.011820, 0.042940, 0.968881],
+            ],
+            Deficiency::Tritanopia => [
+                [1.255528, -0.076749, -0.178779],
+                [-0.078411, 0.930809, 0.147602],
+                [0.004733, 0.691367, 0.303900],
+            ],
+        }
+    }
+
+    /// Returns the color as seen with the deficiency, keeping its alpha.
+    pub fn simulate(self, color: &Color) -> Color {
+        let rgb = to_linear_srgb(color);
+        let simulated = self
+            .matrix()
+            .map(|row| row[0] * rgb[0] + row[1] * rgb[1] + row[2] * rgb[2]);
+        from_linear_srgb(simulated, color.alpha)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::color::{parse_line_colors, to_hex};
+    use crate::cvd::Deficiency;
+
+    #[test]
+    fn simulates_deficiencies() {
+        let simulate = |hex: &str, deficiency: Deficiency| {
+            to_hex(&deficiency.simulate(&parse_line_colors(hex, 0)[0].color))
+        };
+        assert_eq!(simulate("#FF0000", Deficiency::Protanopia), "#6D5F00");
+        assert_eq!(simulate("#00FF00", Deficiency::Deuteranopia), "#EFD63A");
+        assert_eq!(simulate("#0000FF80", Deficiency::Tritanopia), "#006B9680");
+        // Grays are seen alike.
+        assert_eq!(simulate("#808080", Deficiency::Protanopia), "#808080");
+    }
+}
@@ -0,0 +1,81 @@
+use std::collections::HashSet;
+
+use serde::Serialize;
+use tower_lsp_server::ls_types::ColorInformation;
+
+/// Difference between two `textDocument/documentColor` results.
+#[derive(Debug, Default, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ColorsDelta {
+    pub added: Vec<ColorInformation>,
+    pub removed: Vec<ColorInformation>,
+}
+
+/// Hashable identity of a color occurrence: its range and exact channel bits.
+type ColorKey = ([u32; 4], [u32; 4]);
+
+fn key(color_info: &ColorInformation) -> ColorKey {
+    let range = color_info.range;
+    let color = color_info.color;
+    (
+        [
+            range.start.line,
+            range.start.character,
+            range.end.line,
+            range.end.character,
+        ],
+        [
+            color.red.to_bits(),
+            color.green.to_bits(),
+            color.blue.to_bits(),
+            color.alpha.to_bits(),
+        ],
+    )
+}
+
+/// Returns the entries added to and removed from `previous` to get `current`.
+pub fn diff(previous: &[ColorInformation], current: &[ColorInformation]) -> ColorsDelta {
+    let previous_keys: HashSet<ColorKey> = previous.iter().map(key).collect();
+    let current_keys: HashSet<ColorKey> = current.iter().map(key).collect();
+
+    ColorsDelta {
+        added: current
+            .iter()
+            .filter(|c| !previous_keys.contains(&key(c)))
+            .cloned()
+            .collect(),
+        removed: previous
+            .iter()
+            .filter(|c| !current_keys.contains(&key(c)))
+            .cloned()
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::delta::diff;
+    use crate::document::Document;
+
+    #[test]
+    fn diff_reports_changed_entries_only() {
+        let previous = Document::from("#FF0000\n#00FF00\n").get_colors();
+        let current = Document::from("#FF0000\n#0000FF\n#00FF00\n").get_colors();
+
+        let delta = diff(&previous, &current);
+        let lines = |colors: &[_]| {
+            colors
+                .iter()
+                .map(|c: &tower_lsp_server::ls_types::ColorInformation| c.range.start.line)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(lines(&delta.added), [1, 2]);
+        assert_eq!(lines(&delta.removed), [1]);
+    }
+
+    #[test]
+    fn diff_of_identical_results_is_empty() {
+        let colors = Document::from("#FF0000 #00FF00").get_colors();
+        assert_eq!(diff(&colors, &colors), Default::default());
+    }
+}
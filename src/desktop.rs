@@ -0,0 +1,262 @@
+//! Built-in parsers for GTK and Qt stylesheets.
+
+use tower_lsp_server::ls_types::{Color, ColorInformation};
+
+use crate::color::{ColorFormat, ColorMatch, parse_line_colors};
+use crate::colorspace::{from_hsl, to_hsl};
+use crate::heuristics::line_range;
+use crate::named;
+
+/// GTK color functions, which are decorated with the color they compute.
+const GTK_FUNCTIONS: [&str; 5] = ["shade", "mix", "alpha", "lighter", "darker"];
+
+/// Finds CSS named colors in declaration values and calls of GTK color
+/// functions, e.g. `color: white;` or `shade(#3584E4, 1.2)`.
+pub fn gtk(line: &str, line_idx: usize) -> Vec<ColorMatch> {
+    let calls = function_calls(line, &GTK_FUNCTIONS);
+    let mut colors: Vec<ColorMatch> = calls
+        .iter()
+        .filter_map(|call| {
+            Some(ColorMatch {
+                info: ColorInformation {
+                    range: line_range(line, line_idx, call.clone()),
+                    color: evaluate(&line[call.clone()])?,
+                },
+                format: ColorFormat::Heuristic,
+            })
+        })
+        .collect();
+
+    // Named colors inside function calls are covered by the call.
+    let in_call = |bytes: &std::ops::Range<usize>| {
+        calls
+            .iter()
+            .any(|call| call.start <= bytes.start && bytes.end <= call.end)
+    };
+    colors.extend(
+        named_values(line, line_idx)
+            .into_iter()
+            .filter(|(bytes, _)| !in_call(bytes))
+            .map(|(_, color_match)| color_match),
+    );
+    colors
+}
+
+/// Finds CSS named colors in declaration values of a Qt stylesheet, e.g.
+/// `QPushButton { color: white; }`.
+pub fn qss(line: &str, line_idx: usize) -> Vec<ColorMatch> {
+    named_values(line, line_idx)
+        .into_iter()
+        .map(|(_, color_match)| color_match)
+        .collect()
+}
+
+/// Returns named colors in the values of `property: value;` declarations,
+/// with their byte ranges. Selectors, including pseudo-classes like
+/// `:hover`, aren't values.
+fn named_values(line: &str, line_idx: usize) -> Vec<(std::ops::Range<usize>, ColorMatch)> {
+    let mut colors = Vec::new();
+    let mut declarations = line;
+    let mut offset = 0;
+    if let Some(block_start) = line.find('{') {
+        declarations = &line[block_start + 1..];
+        offset = block_start + 1;
+    } else if line.trim_end().ends_with('{') {
+        return colors;
+    }
+
+    for declaration in declarations.split(';') {
+        let start = offset;
+        offset += declaration.len() + 1;
+        let Some((_, value)) = declaration.split_once(':') else {
+            continue;
+        };
+        let value_start = start + declaration.len() - value.len();
+        for (word_idx, word) in words(value) {
+            let bytes = value_start + word_idx..value_start + word_idx + word.len();
+            if let Some(color) = named::lookup(word) {
+                let color_match = ColorMatch {
+                    info: ColorInformation {
+                        range: line_range(line, line_idx, bytes.clone()),
+                        color,
+                    },
+                    format: ColorFormat::Heuristic,
+                };
+                colors.push((bytes, color_match));
+            }
+        }
+    }
+    colors
+}
+
+/// Returns the byte ranges of outermost calls of the named functions in a
+/// line.
+pub(crate) fn function_calls(line: &str, names: &[&str]) -> Vec<std::ops::Range<usize>> {
+    let mut calls = Vec::new();
+    let mut search_from = 0;
+    while let Some((name_start, name)) = words(&line[search_from..])
+        .into_iter()
+        .map(|(idx, word)| (search_from + idx, word))
+        .find(|&(idx, word)| names.contains(&word) && line[idx + word.len()..].starts_with('('))
+    {
+        let args_start = name_start + name.len();
+        let Some(end) = closing_paren(&line[args_start..]).map(|idx| args_start + idx + 1) else {
+            break;
+        };
+        calls.push(name_start..end);
+        search_from = end;
+    }
+    calls
+}
+
+/// Evaluates a color expression: hex, a named color or a GTK color function.
+/// `@name` references aren't resolved.
+fn evaluate(expr: &str) -> Option<Color> {
+    let expr = expr.trim();
+    if let Some((name, args)) = expr.split_once('(') {
+        let args = split_args(args.strip_suffix(')')?);
+        let factor = |idx: usize| args.get(idx)?.trim().parse::<f32>().ok();
+        return match (name.trim(), args.len()) {
+            ("shade", 2) => Some(shade(&evaluate(args[0])?, factor(1)?)),
+            ("lighter", 1) => Some(shade(&evaluate(args[0])?, 1.3)),
+            ("darker", 1) => Some(shade(&evaluate(args[0])?, 0.7)),
+            ("alpha", 2) => {
+                let color = evaluate(args[0])?;
+                Some(Color {
+                    alpha: (color.alpha * factor(1)?).clamp(0.0, 1.0),
+                    ..color
+                })
+            }
+            ("mix", 3) => {
+                let (a, b, factor) = (evaluate(args[0])?, evaluate(args[1])?, factor(2)?);
+                let mix = |a: f32, b: f32| (a + (b - a) * factor).clamp(0.0, 1.0);
+                Some(Color {
+                    red: mix(a.red, b.red),
+                    green: mix(a.green, b.green),
+                    blue: mix(a.blue, b.blue),
+                    alpha: mix(a.alpha, b.alpha),
+                })
+            }
+            _ => None,
+        };
+    }
+
+    match parse_line_colors(expr, 0).as_slice() {
+        [color_info] if color_info.range.end.character as usize == expr.len() => {
+            Some(color_info.color)
+        }
+        _ => named::lookup(expr),
+    }
+}
+
+/// Scales lightness and saturation by `factor`, as GTK's `shade()` does.
+fn shade(color: &Color, factor: f32) -> Color {
+    let (hue, saturation, lightness) = to_hsl(color);
+    from_hsl(
+        hue,
+        (saturation * factor).clamp(0.0, 1.0),
+        (lightness * factor).clamp(0.0, 1.0),
+        color.alpha,
+    )
+}
+
+/// Splits function arguments on commas outside nested parentheses.
+pub(crate) fn split_args(args: &str) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let (mut depth, mut start) = (0, 0);
+    for (idx, c) in args.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            ',' if depth == 0 => {
+                parts.push(&args[start..idx]);
+                start = idx + 1;
+            }
+            _ => {}
+        }
+    }
+    parts.push(&args[start..]);
+    parts
+}
+
+/// Returns the byte index of the parenthesis closing the one `s` starts with.
+pub(crate) fn closing_paren(s: &str) -> Option<usize> {
+    let mut depth = 0;
+    for (idx, c) in s.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(idx);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Returns runs of alphanumerics, `-` and `_` that don't follow `#` or `@`,
+/// with their byte indices.
+fn words(s: &str) -> Vec<(usize, &str)> {
+    let is_word_char = |c: char| c.is_alphanumeric() || c == '-' || c == '_';
+    let mut words = Vec::new();
+    // Byte index where the current run started, and whether it is a reference.
+    let mut run: Option<(usize, bool)> = None;
+    let mut prev = ' ';
+    for (idx, c) in s.char_indices().chain(std::iter::once((s.len(), ' '))) {
+        if is_word_char(c) {
+            run.get_or_insert((idx, prev == '#' || prev == '@'));
+        } else if let Some((word_start, is_reference)) = run.take()
+            && !is_reference
+        {
+            words.push((word_start, &s[word_start..idx]));
+        }
+        prev = c;
+    }
+    words
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::color::{ColorFormat, ColorMatch, to_hex};
+    use crate::desktop::{gtk, qss};
+
+    fn summary(matches: Vec<ColorMatch>) -> Vec<(u32, u32, String, ColorFormat)> {
+        matches
+            .into_iter()
+            .map(|m| {
+                let range = m.info.range;
+                let hex = to_hex(&m.info.color);
+                (range.start.character, range.end.character, hex, m.format)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn gtk_functions() {
+        assert_eq!(
+            summary(gtk("  border-color: shade(#808080, 0.5);", 0)),
+            [(16, 35, "#404040".to_string(), ColorFormat::Heuristic)]
+        );
+        assert_eq!(
+            summary(gtk("  color: mix(black, white, 0.5);", 0)),
+            [(9, 31, "#808080".to_string(), ColorFormat::Heuristic)]
+        );
+        assert_eq!(summary(gtk("  color: alpha(shade(@fg, 0.5), 0.5);", 0)), []);
+    }
+
+    #[test]
+    fn named_values_skip_selectors() {
+        assert_eq!(
+            summary(gtk("button.red:hover { color: white; }", 0)),
+            [(26, 31, "#FFFFFF".to_string(), ColorFormat::Heuristic)]
+        );
+        assert_eq!(summary(qss("QPushButton:hover {", 0)), []);
+        assert_eq!(
+            summary(qss("    background-color: navy;", 0)),
+            [(22, 26, "#000080".to_string(), ColorFormat::Heuristic)]
+        );
+    }
+}
@@ -1,28 +1,86 @@
-use tower_lsp_server::ls_types::{ColorInformation, TextDocumentContentChangeEvent};
+use std::collections::BTreeMap;
+use std::ops::{Range as LineRange, RangeInclusive};
+use std::sync::{Arc, OnceLock};
 
-use crate::color::parse_line_colors;
+use tower_lsp_server::ls_types::{ColorInformation, Range, TextDocumentContentChangeEvent};
+
+use crate::color::{
+    ColorFormat, ColorMatch, MAX_CALL_LINES, ParseOptions, parse_line,
+    parse_spanning_function_colors,
+};
+use crate::colorset;
+use crate::heuristics::Syntax;
+use crate::rope::LineRope;
+use crate::stylesheet;
+
+/// Documents with more lines than this may have their lines parsed on
+/// several threads.
+const PARALLEL_LINE_COUNT: usize = 10_000;
 
 #[derive(Default)]
-pub struct Line {
-    text: String,
-    colors: Vec<ColorInformation>,
+pub struct Document {
+    /// Lines with their colors. Color ranges are stored relative to their
+    /// line, so edits don't have to renumber the colors of later lines.
+    lines: LineRope<Vec<ColorMatch>>,
+    /// All colors in document order, built on first request after a change.
+    colors: OnceLock<Arc<[ColorInformation]>>,
+    /// Whether the colors of `lines` were dropped to save memory.
+    evicted: bool,
+    /// Colors of each line of an evicted document, reparsed on first read.
+    reparsed: OnceLock<Vec<Vec<ColorMatch>>>,
+    /// Version reported by the client with the latest open or change.
+    version: i32,
+    options: Arc<ParseOptions>,
+    syntax: Syntax,
+    /// Language id reported by the client at open, if any.
+    language_id: String,
+    /// Line ending of the text the document was last set to.
+    line_ending: LineEnding,
+    /// Most lines a color extends past its first line since the last full
+    /// parse, so range lookups start early enough to find colors reaching
+    /// into the range.
+    max_span: usize,
 }
 
-impl std::fmt::Display for Line {
-    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        writeln!(f, "{}", self.text)
-    }
+/// Line ending written between lines when a document is displayed.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum LineEnding {
+    #[default]
+    Lf,
+    CrLf,
+    Cr,
 }
 
-#[derive(Default)]
-pub struct Document {
-    lines: Vec<Line>,
+impl LineEnding {
+    /// Returns the first line ending of `text`, or `\n` if it has none.
+    pub fn detect(text: &str) -> Self {
+        let Some(idx) = text.find(['\r', '\n']) else {
+            return Self::Lf;
+        };
+        match &text.as_bytes()[idx..] {
+            [b'\r', b'\n', ..] => Self::CrLf,
+            [b'\r', ..] => Self::Cr,
+            _ => Self::Lf,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Lf => "\n",
+            Self::CrLf => "\r\n",
+            Self::Cr => "\r",
+        }
+    }
 }
 
 impl std::fmt::Display for Document {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        for line in &self.lines {
-            write!(f, "{}", line)?;
+        // The last line follows the final line ending, if there is one.
+        for (idx, line, _) in self.lines.lines_from(0) {
+            if idx > 0 {
+                f.write_str(self.line_ending.as_str())?;
+            }
+            f.write_str(line)?;
         }
         Ok(())
     }
@@ -37,28 +95,247 @@ impl From<&str> for Document {
     }
 }
 
+/// Moves a color stored relative to its line to the line at `line_idx`.
+fn at_line(color_match: &ColorMatch, line_idx: usize) -> ColorMatch {
+    let mut color_match = color_match.clone();
+    let range = &mut color_match.info.range;
+    range.start.line += line_idx as u32;
+    range.end.line += line_idx as u32;
+    color_match
+}
+
 impl Document {
+    /// Creates a `Document` whose colors are parsed with the given options
+    /// and syntax.
+    pub fn with_options(s: &str, options: Arc<ParseOptions>, syntax: Syntax) -> Self {
+        let mut document = Self {
+            options,
+            syntax,
+            ..Default::default()
+        };
+        document.set_text(s);
+        document
+    }
+
     /// Replaces the internal text of the `Document` with the given string.
     pub fn set_text(&mut self, s: &str) {
-        let lines: Vec<&str> = s.lines().collect();
-        self.lines.clear();
-        self.lines.reserve(lines.len());
-        self.lines
-            .extend(lines.into_iter().enumerate().map(|(i, line)| Line {
-                text: line.to_string(),
-                colors: parse_line_colors(line, i),
-            }));
+        self.set_text_on(s, 1);
+    }
+
+    /// Replaces the text like [`Document::set_text`], parsing the lines on up
+    /// to `threads` threads. Colors spanning lines are then found on the
+    /// calling thread.
+    pub fn set_text_on(&mut self, s: &str, threads: usize) {
+        self.line_ending = LineEnding::detect(s);
+        let (options, syntax) = (&self.options, self.syntax);
+        self.lines = text_lines(s, threads, |line| parse_line(line, 0, options, syntax));
+        self.max_span = 0;
+        self.parse_spanning(0..self.lines.len());
+        self.parse_multiline();
+        self.colors = OnceLock::new();
+        self.evicted = false;
+        self.reparsed = OnceLock::new();
     }
 
     pub fn get_colors(&self) -> Vec<ColorInformation> {
-        // TODO: do smarter than collecting lines.
-        // TODO: process each line in parallel.
+        self.colors().to_vec()
+    }
+
+    /// Returns all colors, shared until the next change, so repeated
+    /// requests don't flatten the lines again.
+    pub fn colors(&self) -> Arc<[ColorInformation]> {
+        self.colors
+            .get_or_init(|| self.matches().map(|m| m.info).collect())
+            .clone()
+    }
+
+    /// Returns all colors along with their formats, in document order.
+    pub fn matches(&self) -> impl Iterator<Item = ColorMatch> + '_ {
+        self.line_matches(0..self.lines.len())
+    }
+
+    /// Returns the colors of the lines in `lines`, in document order.
+    fn line_matches(&self, lines: LineRange<usize>) -> impl Iterator<Item = ColorMatch> + '_ {
+        let reparsed = self
+            .evicted
+            .then(|| self.reparsed.get_or_init(|| self.reparse()));
         self.lines
-            .iter()
-            .flat_map(|line| line.colors.clone())
+            .lines_from(lines.start)
+            .take(lines.len())
+            .flat_map(move |(line_idx, _, colors)| {
+                let colors = reparsed.map_or(colors.as_slice(), |lines| {
+                    lines.get(line_idx).map_or(&[], Vec::as_slice)
+                });
+                colors.iter().map(move |m| at_line(m, line_idx))
+            })
+    }
+
+    /// Returns the colors of each line, parsed from scratch.
+    fn reparse(&self) -> Vec<Vec<ColorMatch>> {
+        let document = Self::with_options(&self.to_string(), self.options.clone(), self.syntax);
+        document
+            .lines
+            .lines_from(0)
+            .map(|(_, _, colors)| colors.clone())
+            .collect()
+    }
+
+    /// Drops the parsed colors to save memory. They're reparsed when read,
+    /// and kept again after the next change.
+    pub fn evict(&mut self) {
+        self.lines.for_each_mut(|_, _, colors| *colors = Vec::new());
+        self.colors = OnceLock::new();
+        self.reparsed = OnceLock::new();
+        self.evicted = true;
+    }
+
+    /// Whether the parsed colors were dropped by `evict` and not read since.
+    pub fn is_evicted(&self) -> bool {
+        self.evicted && self.reparsed.get().is_none()
+    }
+
+    /// Returns the approximate memory held by the document, in bytes.
+    pub fn approximate_size(&self) -> usize {
+        let vec_size = |len: usize| len * size_of::<ColorMatch>() + size_of::<Vec<ColorMatch>>();
+        let lines: usize = self
+            .lines
+            .lines_from(0)
+            .map(|(_, line, colors)| line.len() + size_of::<String>() + vec_size(colors.capacity()))
+            .sum();
+        let colors = self
+            .colors
+            .get()
+            .map_or(0, |colors| colors.len() * size_of::<ColorInformation>());
+        let reparsed = self.reparsed.get().map_or(0, |lines| {
+            lines.iter().map(|colors| vec_size(colors.capacity())).sum()
+        });
+        lines + colors + reparsed
+    }
+
+    /// Returns colors on the given lines, clamped to the document.
+    pub fn get_line_colors(&self, lines: RangeInclusive<usize>) -> Vec<ColorInformation> {
+        self.line_matches(*lines.start()..lines.end().saturating_add(1))
+            .map(|m| m.info)
+            .collect()
+    }
+
+    /// Returns colors whose ranges intersect the given range.
+    pub fn get_colors_in_range(&self, range: &Range) -> Vec<ColorInformation> {
+        self.get_matches_in_range(range)
+            .into_iter()
+            .map(|color_match| color_match.info)
             .collect()
     }
 
+    /// Returns colors whose ranges intersect the given range, along with
+    /// their formats.
+    pub fn get_matches_in_range(&self, range: &Range) -> Vec<ColorMatch> {
+        let start = (range.start.line as usize).saturating_sub(self.max_span);
+        self.line_matches(start..range.end.line as usize + 1)
+            .filter(|m| m.info.range.end >= range.start && m.info.range.start <= range.end)
+            .collect()
+    }
+
+    /// Returns the number of colors matched by each format, including
+    /// formats without matches.
+    pub fn format_counts(&self) -> BTreeMap<ColorFormat, usize> {
+        let mut counts: BTreeMap<ColorFormat, usize> = ColorFormat::ALL
+            .into_iter()
+            .map(|format| (format, 0))
+            .collect();
+        for color_match in self.matches() {
+            *counts.entry(color_match.format).or_default() += 1;
+        }
+        counts
+    }
+
+    /// Returns an empty document parsed the same way, to be filled outside
+    /// the lock guarding this one.
+    pub fn detached(&self) -> Self {
+        Self {
+            version: self.version,
+            options: self.options.clone(),
+            syntax: self.syntax,
+            language_id: self.language_id.clone(),
+            ..Default::default()
+        }
+    }
+
+    pub fn version(&self) -> i32 {
+        self.version
+    }
+
+    pub fn set_version(&mut self, version: i32) {
+        self.version = version;
+    }
+
+    pub fn syntax(&self) -> Syntax {
+        self.syntax
+    }
+
+    pub fn options(&self) -> &ParseOptions {
+        &self.options
+    }
+
+    pub fn language_id(&self) -> &str {
+        &self.language_id
+    }
+
+    pub fn line_ending(&self) -> LineEnding {
+        self.line_ending
+    }
+
+    pub fn set_language_id(&mut self, language_id: String) {
+        self.language_id = language_id;
+    }
+
+    /// Replaces the parse options and reparses every line.
+    pub fn set_options(&mut self, options: Arc<ParseOptions>) {
+        self.options = options;
+        self.parse_all();
+    }
+
+    /// Reparses every line, restoring the colors of an evicted document.
+    fn parse_all(&mut self) {
+        let (options, syntax) = (&self.options, self.syntax);
+        self.lines.for_each_mut(|_, line, colors| {
+            *colors = parse_line(line, 0, options, syntax);
+        });
+        self.max_span = 0;
+        self.parse_spanning(0..self.lines.len());
+        self.parse_multiline();
+        self.colors = OnceLock::new();
+        self.evicted = false;
+        self.reparsed = OnceLock::new();
+    }
+
+    /// Returns the number of lines, counting the empty line after a final
+    /// line ending.
+    pub fn line_count(&self) -> usize {
+        self.lines.len()
+    }
+
+    /// Returns the text of the line at `idx`, without its line ending.
+    pub fn line(&self, idx: usize) -> Option<&str> {
+        self.lines.line(idx)
+    }
+
+    /// Returns the text of each line, without its line ending.
+    pub fn lines(&self) -> impl Iterator<Item = &str> + Clone + '_ {
+        self.lines.lines_from(0).map(|(_, line, _)| line)
+    }
+
+    /// Returns the text covered by a single-line range.
+    pub fn range_text(&self, range: &Range) -> Option<&str> {
+        if range.start.line != range.end.line {
+            return None;
+        }
+        let line = self.line(range.start.line as usize)?;
+        let start = utf16_to_byte_index(line, range.start.character as usize);
+        let end = utf16_to_byte_index(line, range.end.character as usize);
+        line.get(start..end)
+    }
+
     pub fn edit(&mut self, change: &TextDocumentContentChangeEvent) {
         match &change.range {
             // Full content replace
@@ -67,81 +344,229 @@ impl Document {
             }
             // Partial change
             Some(range) => {
-                let start_line = range.start.line as usize;
-                let end_line = range.end.line as usize;
-
-                // Ensure enough lines exist
-                while self.lines.len() <= end_line {
-                    self.lines.push(Line::default());
+                // Lines outside the change keep their colors, so they have to
+                // be parsed first.
+                if self.evicted {
+                    self.parse_all();
                 }
+                splice_change(&mut self.lines, range, &change.text, |line| {
+                    parse_line(line, 0, &self.options, self.syntax)
+                });
+                // Calls opening shortly before the change may now close in it.
+                let start_line = range.start.line as usize;
+                let end_line = start_line + split_lines(&change.text).count();
+                self.parse_spanning(start_line.saturating_sub(MAX_CALL_LINES - 1)..end_line);
+                self.parse_multiline();
+                self.colors = OnceLock::new();
+            }
+        }
+    }
 
-                // TODO: refactor.
-                // TODO: explore idea of storing text in UTF-16.
-                let start_byte = utf16_to_byte_index(
-                    &self.lines[start_line].text,
-                    range.start.character as usize,
+    /// Reparses the color function calls spanning several lines, which
+    /// `parse_line` can't see, that open on `start_lines`.
+    fn parse_spanning(&mut self, start_lines: LineRange<usize>) {
+        if !self.options.enables(ColorFormat::Function) {
+            return;
+        }
+        let is_spanning = |m: &ColorMatch| m.info.range.end.line > m.info.range.start.line;
+        let mut found = Vec::new();
+        let mut window: Vec<&str> = Vec::with_capacity(MAX_CALL_LINES);
+        let mut lines = self.lines.lines_from(start_lines.start);
+        while let Some((line_idx, line, colors)) = lines.next() {
+            if line_idx >= start_lines.end {
+                break;
+            }
+            let spanning = if line.contains('(') {
+                window.clear();
+                window.push(line);
+                window.extend(
+                    lines
+                        .clone()
+                        .take(MAX_CALL_LINES - 1)
+                        .map(|(_, line, _)| line),
                 );
-                let end_byte =
-                    utf16_to_byte_index(&self.lines[end_line].text, range.end.character as usize);
-
-                let prefix = &self.lines[start_line].text
-                    [..start_byte.min(self.lines[start_line].text.len())];
-                let suffix =
-                    &self.lines[end_line].text[end_byte.min(self.lines[end_line].text.len())..];
-
-                let mut new_lines: Vec<Line> = change
-                    .text
-                    .lines()
-                    .map(|line| Line {
-                        text: line.to_string(),
-                        colors: Vec::new(),
-                    })
-                    .collect();
-
-                // .lines() ignores final line ending.
-                // TODO: handle \r.
-                if change.text.ends_with('\n') {
-                    new_lines.push(Line::default());
-                }
+                parse_spanning_function_colors(&window, self.options.strict)
+            } else {
+                Vec::new()
+            };
+            if !spanning.is_empty() || colors.iter().any(is_spanning) {
+                found.push((line_idx, spanning));
+            }
+        }
+        drop(lines);
+        for (line_idx, spanning) in found {
+            for info in &spanning {
+                self.max_span = self
+                    .max_span
+                    .max((info.range.end.line - info.range.start.line) as usize);
+            }
+            let Some(colors) = self.lines.data_mut(line_idx) else {
+                continue;
+            };
+            colors.retain(|m| !is_spanning(m));
+            colors.extend(spanning.into_iter().map(|info| ColorMatch {
+                info,
+                format: ColorFormat::Function,
+            }));
+            colors.sort_by_key(|m| m.info.range.start);
+        }
+    }
 
-                if new_lines.is_empty() {
-                    new_lines.push(Line {
-                        text: format!("{}{}", prefix, suffix),
-                        colors: Vec::new(),
-                    });
-                } else {
-                    new_lines[0].text.insert_str(0, prefix);
-                    let last_idx = new_lines.len() - 1;
-                    new_lines[last_idx].text.push_str(suffix);
-                }
+    /// Adds colors that depend on other lines, which `parse_line` can't see:
+    /// asset catalog colors spanning several lines, and stylesheet color
+    /// functions of variables. Those files are small enough to reparse whole.
+    fn parse_multiline(&mut self) {
+        // Both only add heuristic matches.
+        if !self.options.enables(ColorFormat::Heuristic) {
+            return;
+        }
+        match self.syntax {
+            Syntax::ColorSet => self.parse_color_sets(),
+            Syntax::Scss => self.parse_stylesheet_functions(),
+            _ => {}
+        }
+    }
 
-                // Reparse colors for each new line
-                for (i, line) in new_lines.iter_mut().enumerate() {
-                    line.colors = parse_line_colors(&line.text, start_line + i);
-                }
+    fn parse_color_sets(&mut self) {
+        let texts: Vec<&str> = self.lines.lines_from(0).map(|(_, line, _)| line).collect();
+        let entries = colorset::entries(&texts);
+        self.lines.for_each_mut(|_, _, colors| {
+            colors.retain(|color_match| color_match.format != ColorFormat::Heuristic);
+        });
+        for entry in entries {
+            let line_idx = entry.range.start.line;
+            self.max_span = self
+                .max_span
+                .max((entry.range.end.line - line_idx) as usize);
+            let mut range = entry.range;
+            range.start.line -= line_idx;
+            range.end.line -= line_idx;
+            if let Some(colors) = self.lines.data_mut(line_idx as usize) {
+                colors.push(ColorMatch {
+                    info: ColorInformation {
+                        range,
+                        color: entry.color,
+                    },
+                    format: ColorFormat::Heuristic,
+                });
+            }
+        }
+    }
 
-                let replaced_line_count = end_line - start_line + 1;
-                // Replace lines in the document.
-                // Adjust line numbers of all colors after the edited range.
-                let line_delta = new_lines.len() as isize - replaced_line_count as isize;
-                if line_delta != 0 {
-                    for line in &mut self.lines[start_line + replaced_line_count..] {
-                        for color in &mut line.colors {
-                            color.range.start.line =
-                                (color.range.start.line as isize + line_delta) as u32;
-                            color.range.end.line =
-                                (color.range.end.line as isize + line_delta) as u32;
-                        }
-                    }
+    fn parse_stylesheet_functions(&mut self) {
+        let variables = stylesheet::variables(self.lines.lines_from(0).map(|(_, line, _)| line));
+        self.lines.for_each_mut(|_, line, colors| {
+            colors.retain(|color_match| color_match.format != ColorFormat::Heuristic);
+            for color_match in stylesheet::functions(line, 0, &variables) {
+                let start = color_match.info.range.start;
+                if colors.iter().all(|other| other.info.range.start != start) {
+                    colors.push(color_match);
                 }
-
-                self.lines.splice(start_line..=end_line, new_lines);
             }
-        }
+            colors.sort_by_key(|color_match| color_match.info.range.start);
+        });
+    }
+}
+
+/// Splits `text` into lines, attaching `data` to each. The data of long
+/// texts is computed in chunks on up to `threads` threads.
+pub(crate) fn text_lines<T: Send>(
+    text: &str,
+    threads: usize,
+    data: impl Fn(&str) -> T + Sync,
+) -> LineRope<T> {
+    let lines: Vec<&str> = split_lines(text).collect();
+    if lines.len() <= PARALLEL_LINE_COUNT || threads <= 1 {
+        return LineRope::from_lines(lines.into_iter().map(|line| (line, data(line))));
+    }
+
+    let data = &data;
+    let chunk_len = lines.len().div_ceil(threads);
+    let line_data: Vec<T> = std::thread::scope(|scope| {
+        let chunks: Vec<_> = lines
+            .chunks(chunk_len)
+            .map(|chunk| {
+                scope.spawn(move || chunk.iter().map(|line| data(line)).collect::<Vec<_>>())
+            })
+            .collect();
+        chunks
+            .into_iter()
+            .flat_map(|chunk| {
+                chunk
+                    .join()
+                    .unwrap_or_else(|panic| std::panic::resume_unwind(panic))
+            })
+            .collect()
+    });
+    LineRope::from_lines(lines.into_iter().zip(line_data))
+}
+
+/// Splits `text` at `\n`, `\r\n` and `\r`, the line endings LSP allows.
+/// Unlike [`str::lines`], a final line ending starts an empty last line, so
+/// positions past it exist.
+pub(crate) fn split_lines(text: &str) -> impl Iterator<Item = &str> {
+    let mut rest = Some(text);
+    std::iter::from_fn(move || {
+        let text = rest?;
+        let Some(idx) = text.find(['\r', '\n']) else {
+            rest = None;
+            return Some(text);
+        };
+        let ending = if text[idx..].starts_with("\r\n") {
+            2
+        } else {
+            1
+        };
+        rest = Some(&text[idx + ending..]);
+        Some(&text[..idx])
+    })
+}
+
+/// Replaces the text of a range of `lines` with `text`, attaching `data` to
+/// each new line. Lines missing up to the end of the range are added first.
+pub(crate) fn splice_change<T: Default>(
+    lines: &mut LineRope<T>,
+    range: &Range,
+    text: &str,
+    data: impl Fn(&str) -> T,
+) {
+    let start_line = range.start.line as usize;
+    let end_line = range.end.line as usize;
+
+    // Ensure enough lines exist
+    if lines.len() <= end_line {
+        let missing = (lines.len()..=end_line)
+            .map(|_| (String::new(), T::default()))
+            .collect();
+        lines.splice(lines.len()..lines.len(), missing);
     }
+
+    // TODO: explore idea of storing text in UTF-16.
+    let first = lines.line(start_line).unwrap_or_default();
+    let last = lines.line(end_line).unwrap_or_default();
+    let start_byte = utf16_to_byte_index(first, range.start.character as usize);
+    let end_byte = utf16_to_byte_index(last, range.end.character as usize);
+    let prefix = &first[..start_byte.min(first.len())];
+    let suffix = &last[end_byte.min(last.len())..];
+
+    let mut new_lines: Vec<String> = split_lines(text).map(str::to_string).collect();
+    new_lines[0].insert_str(0, prefix);
+    let last_idx = new_lines.len() - 1;
+    new_lines[last_idx].push_str(suffix);
+
+    // Attach data, such as colors, to each new line
+    let new_lines = new_lines
+        .into_iter()
+        .map(|line| {
+            let line_data = data(&line);
+            (line, line_data)
+        })
+        .collect();
+
+    lines.splice(start_line..end_line + 1, new_lines);
 }
 
-fn utf16_to_byte_index(line: &str, utf16_idx: usize) -> usize {
+pub(crate) fn utf16_to_byte_index(line: &str, utf16_idx: usize) -> usize {
     let mut count = 0;
     for (byte_idx, _) in line.char_indices() {
         if count == utf16_idx {
@@ -159,11 +584,15 @@ fn utf16_to_byte_index(line: &str, utf16_idx: usize) -> usize {
 
 #[cfg(test)]
 mod tests {
+    use std::sync::Arc;
+
     use tower_lsp_server::ls_types::{
         ColorInformation, Position, Range, TextDocumentContentChangeEvent,
     };
 
-    use crate::document::Document;
+    use crate::color::to_hex;
+    use crate::document::{Document, LineEnding, PARALLEL_LINE_COUNT, split_lines};
+    use crate::heuristics::Syntax;
 
     #[test]
     fn unicode_edit_in_string() {
@@ -187,10 +616,10 @@ mod tests {
         assert_eq!(document.to_string(), "a•b\n");
     }
 
-    fn assert_colors_eq(
-        colors: Vec<ColorInformation>,
-        expected: &[(f32, f32, f32, f32, u32, u32, u32, u32)],
-    ) {
+    /// Expected color as (r, g, b, a, start line, start char, end line, end char).
+    type ExpectedColor = (f32, f32, f32, f32, u32, u32, u32, u32);
+
+    fn assert_colors_eq(colors: Vec<ColorInformation>, expected: &[ExpectedColor]) {
         assert_eq!(colors.len(), expected.len(), "unexpected number of colors");
         for (i, (c, &(r, g, b, a, start_line, start_char, end_line, end_char))) in
             colors.iter().zip(expected.iter()).enumerate()
@@ -227,7 +656,7 @@ mod tests {
             range_length: None,
             text: "#00FF00".to_string(),
         });
-        assert_eq!(document.to_string(), "#00FF00\n");
+        assert_eq!(document.to_string(), "#00FF00");
 
         assert_colors_eq(document.get_colors(), &[(0.0, 1.0, 0.0, 1.0, 0, 0, 0, 7)]);
     }
@@ -252,7 +681,7 @@ mod tests {
             range_length: None,
             text: "\n#00FF00".to_string(),
         });
-        assert_eq!(document.to_string(), "#FF0000\n#00FF00\n");
+        assert_eq!(document.to_string(), "#FF0000\n#00FF00");
 
         assert_colors_eq(
             document.get_colors(),
@@ -303,6 +732,49 @@ mod tests {
         );
     }
 
+    #[test]
+    fn crlf_edits() {
+        let mut document = Document::from("a { color: #FF0000; }\r\n#0000FF\r\n");
+        assert_eq!(document.line(0), Some("a { color: #FF0000; }"));
+
+        document.edit(&TextDocumentContentChangeEvent {
+            range: Some(Range {
+                start: Position {
+                    line: 0,
+                    character: 21,
+                },
+                end: Position {
+                    line: 1,
+                    character: 0,
+                },
+            }),
+            range_length: None,
+            text: "\r\n#00FF00\r\n".to_string(),
+        });
+        assert_eq!(
+            document.to_string(),
+            "a { color: #FF0000; }\r\n#00FF00\r\n#0000FF\r\n"
+        );
+
+        assert_colors_eq(
+            document.get_colors(),
+            &[
+                (1.0, 0.0, 0.0, 1.0, 0, 11, 0, 18),
+                (0.0, 1.0, 0.0, 1.0, 1, 0, 1, 7),
+                (0.0, 0.0, 1.0, 1.0, 2, 0, 2, 7),
+            ],
+        );
+    }
+
+    #[test]
+    fn splits_lines_at_any_ending() {
+        let lines: Vec<&str> = split_lines("a\r\nb\rc\n\nd\r\n").collect();
+        assert_eq!(lines, ["a", "b", "c", "", "d", ""]);
+        assert_eq!(split_lines("").collect::<Vec<_>>(), [""]);
+        assert_eq!(LineEnding::detect("a\rb\r\n"), LineEnding::Cr);
+        assert_eq!(LineEnding::detect("a"), LineEnding::Lf);
+    }
+
     #[test]
     fn delete_color_line() {
         let mut document = Document::from("#FF0000\n#00FF00\n#0000FF\n");
@@ -422,11 +894,115 @@ mod tests {
             range_length: None,
             text: "#FFFFFF".to_string(),
         });
-        assert_eq!(document.to_string(), "#FFFFFF\n");
+        assert_eq!(document.to_string(), "#FFFFFF");
 
         assert_colors_eq(document.get_colors(), &[(1.0, 1.0, 1.0, 1.0, 0, 0, 0, 7)]);
     }
 
+    #[test]
+    fn trailing_newline_starts_a_line() {
+        assert_eq!(Document::from("#FF0000").line_count(), 1);
+        let mut document = Document::from("#FF0000\n");
+        assert_eq!(document.line_count(), 2);
+        assert_eq!(document.line(1), Some(""));
+
+        document.edit(&TextDocumentContentChangeEvent {
+            range: Some(Range {
+                start: Position {
+                    line: 1,
+                    character: 0,
+                },
+                end: Position {
+                    line: 1,
+                    character: 0,
+                },
+            }),
+            range_length: None,
+            text: "#00FF00".to_string(),
+        });
+        assert_eq!(document.to_string(), "#FF0000\n#00FF00");
+
+        assert_colors_eq(
+            document.get_colors(),
+            &[
+                (1.0, 0.0, 0.0, 1.0, 0, 0, 0, 7),
+                (0.0, 1.0, 0.0, 1.0, 1, 0, 1, 7),
+            ],
+        );
+    }
+
+    #[test]
+    fn function_spanning_lines() {
+        let mut document =
+            Document::from("a {\n  color: rgba(\n    255,\n    0,\n    0\n  );\n}\n");
+        assert_colors_eq(document.get_colors(), &[(1.0, 0.0, 0.0, 1.0, 1, 9, 5, 3)]);
+
+        // Editing a channel reparses the call opening lines before it.
+        document.edit(&TextDocumentContentChangeEvent {
+            range: Some(Range {
+                start: Position {
+                    line: 4,
+                    character: 4,
+                },
+                end: Position {
+                    line: 4,
+                    character: 5,
+                },
+            }),
+            range_length: None,
+            text: "255".to_string(),
+        });
+        assert_colors_eq(document.get_colors(), &[(1.0, 0.0, 1.0, 1.0, 1, 9, 5, 3)]);
+
+        // Removing the closing parenthesis removes the color.
+        document.edit(&TextDocumentContentChangeEvent {
+            range: Some(Range {
+                start: Position {
+                    line: 5,
+                    character: 2,
+                },
+                end: Position {
+                    line: 5,
+                    character: 3,
+                },
+            }),
+            range_length: None,
+            text: String::new(),
+        });
+        assert_eq!(document.get_colors(), Vec::new());
+    }
+
+    #[test]
+    fn parses_long_documents_in_chunks() {
+        let document = Document::from("#FF0000 #00FF00\nnone\n".repeat(6_000).as_str());
+        assert_eq!(document.line_count(), 12_001);
+
+        let colors = document.get_colors();
+        assert_eq!(colors.len(), 12_000);
+        assert_colors_eq(
+            colors[colors.len() - 2..].to_vec(),
+            &[
+                (1.0, 0.0, 0.0, 1.0, 11_998, 0, 11_998, 7),
+                (0.0, 1.0, 0.0, 1.0, 11_998, 8, 11_998, 15),
+            ],
+        );
+    }
+
+    #[test]
+    fn reads_lines_of_large_documents_in_linear_time() {
+        let text = format!("{}#FF0000\n", "none\n".repeat(100_000));
+        let document = Document::from(text.as_str());
+
+        // Quadratic lookups take minutes here.
+        let start = std::time::Instant::now();
+        let colors: usize = (0..document.line_count())
+            .map(|line_idx| document.get_line_colors(line_idx..=line_idx).len())
+            .sum();
+        assert_eq!(colors, 1);
+        assert_eq!(document.lines().count(), document.line_count());
+        assert!(start.elapsed() < std::time::Duration::from_secs(5));
+    }
+
     #[test]
     fn multiple_incremental_edits() {
         let mut document = Document::from("#FF0000\n");
@@ -448,7 +1024,7 @@ mod tests {
             range_length: None,
             text: "\n#00FF00".to_string(),
         });
-        assert_eq!(document.to_string(), "#FF0000\n#00FF00\n");
+        assert_eq!(document.to_string(), "#FF0000\n#00FF00");
 
         assert_colors_eq(
             document.get_colors(),
@@ -473,7 +1049,7 @@ mod tests {
             range_length: None,
             text: "\n#0000FF".to_string(),
         });
-        assert_eq!(document.to_string(), "#FF0000\n#00FF00\n#0000FF\n");
+        assert_eq!(document.to_string(), "#FF0000\n#00FF00\n#0000FF");
 
         assert_colors_eq(
             document.get_colors(),
@@ -484,4 +1060,147 @@ mod tests {
             ],
         );
     }
+
+    #[test]
+    fn colors_in_range() {
+        let document = Document::from("#FF0000 #00FF00\n#0000FF\n");
+
+        let range = Range {
+            start: Position {
+                line: 0,
+                character: 9,
+            },
+            end: Position {
+                line: 1,
+                character: 0,
+            },
+        };
+        assert_colors_eq(
+            document.get_colors_in_range(&range),
+            &[
+                (0.0, 1.0, 0.0, 1.0, 0, 8, 0, 15),
+                (0.0, 0.0, 1.0, 1.0, 1, 0, 1, 7),
+            ],
+        );
+    }
+
+    #[test]
+    fn colors_in_range_reaching_from_earlier_lines() {
+        let document = Document::from("a {\n  color: rgba(\n    255,\n    0,\n    0\n  );\n}\n");
+
+        let range = Range {
+            start: Position {
+                line: 3,
+                character: 0,
+            },
+            end: Position {
+                line: 3,
+                character: 1,
+            },
+        };
+        assert_colors_eq(
+            document.get_colors_in_range(&range),
+            &[(1.0, 0.0, 0.0, 1.0, 1, 9, 5, 3)],
+        );
+    }
+
+    #[test]
+    fn line_colors_clamped() {
+        let document = Document::from("#FF0000\n#00FF00\n#0000FF\n");
+
+        assert_colors_eq(
+            document.get_line_colors(1..=10),
+            &[
+                (0.0, 1.0, 0.0, 1.0, 1, 0, 1, 7),
+                (0.0, 0.0, 1.0, 1.0, 2, 0, 2, 7),
+            ],
+        );
+        assert_eq!(document.get_line_colors(5..=10), Vec::new());
+    }
+
+    #[test]
+    fn edit_large_document() {
+        let text = "#FF0000\n".repeat(1000);
+        let mut document = Document::from(text.as_str());
+
+        // Replace lines 100 to 899 with a single color.
+        document.edit(&TextDocumentContentChangeEvent {
+            range: Some(Range {
+                start: Position {
+                    line: 100,
+                    character: 0,
+                },
+                end: Position {
+                    line: 900,
+                    character: 0,
+                },
+            }),
+            range_length: None,
+            text: "#00FF00\n".to_string(),
+        });
+        assert_eq!(document.line_count(), 202);
+        assert_colors_eq(
+            document.get_line_colors(99..=101),
+            &[
+                (1.0, 0.0, 0.0, 1.0, 99, 0, 99, 7),
+                (0.0, 1.0, 0.0, 1.0, 100, 0, 100, 7),
+                (1.0, 0.0, 0.0, 1.0, 101, 0, 101, 7),
+            ],
+        );
+        assert_eq!(document.get_colors().len(), 201);
+    }
+
+    #[test]
+    fn colors_shared_until_change() {
+        let mut document = Document::from("#FF0000\n");
+        let colors = document.colors();
+        assert!(Arc::ptr_eq(&colors, &document.colors()));
+
+        document.edit(&TextDocumentContentChangeEvent {
+            range: None,
+            range_length: None,
+            text: "#00FF00 #0000FF".to_string(),
+        });
+        assert!(!Arc::ptr_eq(&colors, &document.colors()));
+        assert_eq!(document.colors().len(), 2);
+    }
+
+    #[test]
+    fn evicted_colors_reparse_on_read() {
+        let text = "$red: #FF0000;\na { color: darken($red, 10%); }\n#00FF00\n";
+        let mut document = Document::with_options(text, Default::default(), Syntax::Scss);
+        let colors = document.get_colors();
+        let size = document.approximate_size();
+
+        document.evict();
+        assert!(document.is_evicted());
+        assert!(document.approximate_size() < size);
+        assert_eq!(document.get_colors(), colors);
+        assert!(!document.is_evicted());
+
+        document.evict();
+        document.edit(&TextDocumentContentChangeEvent {
+            range: Some(Range {
+                start: Position::new(2, 0),
+                end: Position::new(2, 7),
+            }),
+            range_length: None,
+            text: "#0000FF".to_string(),
+        });
+        let colors = document.get_colors();
+        assert_eq!(colors.len(), 3);
+        assert_eq!(colors[1].range.start, Position::new(1, 11));
+        assert_eq!(to_hex(&colors[2].color), "#0000FF");
+    }
+
+    #[test]
+    fn parallel_parse_matches_sequential() {
+        let text = "a { color: #FF0000; }\n\nb { color: rgb(0, 0,\n  255); }\n"
+            .repeat(PARALLEL_LINE_COUNT / 2);
+        let sequential = Document::from(text.as_str());
+        let mut parallel = Document::default();
+        parallel.set_text_on(&text, 4);
+        assert_eq!(parallel.get_colors(), sequential.get_colors());
+        assert_eq!(parallel.to_string(), text);
+    }
 }
@@ -0,0 +1,173 @@
+//! Colors defined under several names across the workspace, a common sign of
+//! design-system debt: the names can usually be consolidated into one.
+
+use std::collections::{BTreeMap, HashMap};
+
+use tower_lsp_server::ls_types::{Color, Diagnostic, DiagnosticSeverity, Location, Uri};
+
+use crate::color::to_hex;
+
+/// A named color definition: a stylesheet variable, an Android color
+/// resource or a theme object member.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Definition {
+    pub name: String,
+    pub location: Location,
+    pub color: Color,
+}
+
+/// A color defined under more than one name.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Duplicate {
+    pub hex: String,
+    /// Definitions ordered by file and position.
+    pub definitions: Vec<Definition>,
+}
+
+impl Duplicate {
+    /// Returns the distinct names of the definitions, in order.
+    pub fn names(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = Vec::new();
+        for definition in &self.definitions {
+            if !names.contains(&definition.name.as_str()) {
+                names.push(&definition.name);
+            }
+        }
+        names
+    }
+}
+
+/// Groups definitions by hex value and returns the groups with at least two
+/// distinct names, ordered by hex value. The same name defined in several
+/// files, like a resource and its night variant, isn't a duplicate by itself.
+pub fn find(definitions: impl IntoIterator<Item = Definition>) -> Vec<Duplicate> {
+    let mut groups: BTreeMap<String, Vec<Definition>> = BTreeMap::new();
+    for definition in definitions {
+        groups
+            .entry(to_hex(&definition.color))
+            .or_default()
+            .push(definition);
+    }
+    groups
+        .into_iter()
+        .map(|(hex, mut definitions)| {
+            definitions.sort_by(|a, b| {
+                (a.location.uri.as_str(), a.location.range.start)
+                    .cmp(&(b.location.uri.as_str(), b.location.range.start))
+            });
+            Duplicate { hex, definitions }
+        })
+        .filter(|duplicate| duplicate.names().len() > 1)
+        .collect()
+}
+
+/// Returns a hint on each duplicated definition naming the others, by file.
+pub fn diagnostics(duplicates: &[Duplicate]) -> HashMap<Uri, Vec<Diagnostic>> {
+    let mut diagnostics: HashMap<Uri, Vec<Diagnostic>> = HashMap::new();
+    for duplicate in duplicates {
+        let names = duplicate.names();
+        for definition in &duplicate.definitions {
+            let others: Vec<String> = names
+                .iter()
+                .filter(|&&name| name != definition.name)
+                .map(|name| format!("`{name}`"))
+                .collect();
+            diagnostics
+                .entry(definition.location.uri.clone())
+                .or_default()
+                .push(Diagnostic {
+                    range: definition.location.range,
+                    severity: Some(DiagnosticSeverity::HINT),
+                    source: Some("chroma-ls".to_string()),
+                    message: format!(
+                        "{} is also defined as {}; consider consolidating",
+                        duplicate.hex,
+                        others.join(", ")
+                    ),
+                    ..Default::default()
+                });
+        }
+    }
+    diagnostics
+}
+
+/// Renders the duplicates as a Markdown summary.
+pub fn markdown_report(duplicates: &[Duplicate]) -> String {
+    if duplicates.is_empty() {
+        return "No color is defined under more than one name.\n".to_string();
+    }
+
+    let count = match duplicates.len() {
+        1 => "1 color is".to_string(),
+        n => format!("{n} colors are"),
+    };
+    let mut report = format!(
+        "{count} defined under more than one name.\n\n\
+         | Color | Names | Files |\n\
+         | --- | --- | --- |\n"
+    );
+    for duplicate in duplicates {
+        let names: Vec<String> = duplicate
+            .names()
+            .iter()
+            .map(|name| format!("`{name}`"))
+            .collect();
+        let mut files: Vec<&str> = duplicate
+            .definitions
+            .iter()
+            .map(|definition| definition.location.uri.as_str())
+            .collect();
+        files.dedup();
+        report.push_str(&format!(
+            "| {} | {} | {} |\n",
+            duplicate.hex,
+            names.join(", "),
+            files.join(", ")
+        ));
+    }
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use tower_lsp_server::ls_types::{Location, Range};
+
+    use crate::color::parse_line_colors;
+    use crate::duplicates::{Definition, diagnostics, find, markdown_report};
+
+    fn definition(name: &str, uri: &str, hex: &str) -> Definition {
+        Definition {
+            name: name.to_string(),
+            location: Location {
+                uri: uri.parse().unwrap(),
+                range: Range::default(),
+            },
+            color: parse_line_colors(hex, 0)[0].color,
+        }
+    }
+
+    #[test]
+    fn groups_names_by_color() {
+        let duplicates = find([
+            definition("--brand", "file:///b.css", "#1a73e8"),
+            definition("primary", "file:///a.xml", "#1A73E8"),
+            definition("primary", "file:///night.xml", "#FFFFFF"),
+            definition("primary", "file:///day.xml", "#FFFFFF"),
+            definition("$accent", "file:///a.scss", "#FF5722"),
+        ]);
+        assert_eq!(duplicates.len(), 1);
+        assert_eq!(duplicates[0].hex, "#1A73E8");
+        assert_eq!(duplicates[0].names(), ["primary", "--brand"]);
+
+        let diagnostics = diagnostics(&duplicates);
+        let b = &diagnostics[&"file:///b.css".parse().unwrap()];
+        assert_eq!(
+            b[0].message,
+            "#1A73E8 is also defined as `primary`; consider consolidating"
+        );
+        assert!(
+            markdown_report(&duplicates)
+                .contains("| #1A73E8 | `primary`, `--brand` | file:///a.xml, file:///b.css |")
+        );
+    }
+}
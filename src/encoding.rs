@@ -0,0 +1,725 @@
+//! Position encodings negotiated with the client. The server counts columns
+//! in UTF-16 code units, as LSP does by default. For clients preferring UTF-8
+//! or UTF-32, columns are translated as messages enter and leave the server,
+//! against the lines of the open documents the server keeps. The ranges of
+//! changes are translated by the server as it applies them. Positions in
+//! other files, such as references found in the workspace index, are
+//! translated against their text on disk.
+
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::pin::Pin;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::task::{Context, Poll};
+
+use futures::{Stream, StreamExt};
+use serde::Deserialize;
+use serde_json::{Map, Value, json};
+use tokio::sync::RwLock;
+use tower_lsp_server::jsonrpc::{Id, Request, Response};
+use tower_lsp_server::ls_types::{
+    ClientCapabilities, PositionEncodingKind, SemanticToken, SemanticTokens,
+    TextDocumentContentChangeEvent, Uri,
+};
+use tower_lsp_server::{ClientSocket, Loopback};
+use tower_service::Service;
+
+use crate::document::{Document, split_lines};
+use crate::rope::LineRope;
+
+/// Most files other than open documents whose lines are kept.
+const MAX_FILES: usize = 32;
+
+/// Unit columns are counted in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PositionEncoding {
+    /// Bytes, as Helix and many Neovim setups count.
+    Utf8,
+    #[default]
+    Utf16,
+    /// Characters.
+    Utf32,
+}
+
+impl PositionEncoding {
+    /// Prefers UTF-8 when the client offers it, and otherwise the first
+    /// encoding it offers. Clients offering none get UTF-16.
+    pub fn negotiate(capabilities: &ClientCapabilities) -> Self {
+        let offered = capabilities
+            .general
+            .as_ref()
+            .and_then(|general| general.position_encodings.as_deref())
+            .unwrap_or_default();
+        if offered.contains(&PositionEncodingKind::UTF8) {
+            return PositionEncoding::Utf8;
+        }
+        offered
+            .iter()
+            .find_map(|kind| Self::parse(kind.as_str()))
+            .unwrap_or_default()
+    }
+
+    fn parse(name: &str) -> Option<Self> {
+        match name {
+            "utf-8" => Some(PositionEncoding::Utf8),
+            "utf-16" => Some(PositionEncoding::Utf16),
+            "utf-32" => Some(PositionEncoding::Utf32),
+            _ => None,
+        }
+    }
+
+    pub fn kind(self) -> PositionEncodingKind {
+        match self {
+            PositionEncoding::Utf8 => PositionEncodingKind::UTF8,
+            PositionEncoding::Utf16 => PositionEncodingKind::UTF16,
+            PositionEncoding::Utf32 => PositionEncodingKind::UTF32,
+        }
+    }
+
+    fn len(self, c: char) -> u32 {
+        match self {
+            PositionEncoding::Utf8 => c.len_utf8() as u32,
+            PositionEncoding::Utf16 => c.len_utf16() as u32,
+            PositionEncoding::Utf32 => 1,
+        }
+    }
+
+    /// Converts a column of `line` to the units of `to`. Columns inside a
+    /// character snap to its start, and columns past the end of the line stay
+    /// past it by as many units.
+    pub fn convert(self, line: &str, column: u32, to: Self) -> u32 {
+        if self == to {
+            return column;
+        }
+        let (mut from_column, mut to_column) = (0, 0);
+        for c in line.chars() {
+            let next = from_column + self.len(c);
+            if next > column {
+                return to_column;
+            }
+            from_column = next;
+            to_column += to.len(c);
+        }
+        to_column + (column - from_column)
+    }
+
+    /// Converts the range of `change` from these units to UTF-16, against
+    /// the text of `document` it applies to.
+    pub fn decode_change<'a>(
+        self,
+        document: &Document,
+        change: &'a TextDocumentContentChangeEvent,
+    ) -> Cow<'a, TextDocumentContentChangeEvent> {
+        let Some(mut range) = change.range.filter(|_| self != PositionEncoding::Utf16) else {
+            return Cow::Borrowed(change);
+        };
+        for position in [&mut range.start, &mut range.end] {
+            if let Some(line) = document.line(position.line as usize) {
+                position.character =
+                    self.convert(line, position.character, PositionEncoding::Utf16);
+            }
+        }
+        Cow::Owned(TextDocumentContentChangeEvent {
+            range: Some(range),
+            ..change.clone()
+        })
+    }
+}
+
+/// A service or client socket whose messages are translated between UTF-16
+/// and the position encoding negotiated with the client.
+pub struct Encoded<T> {
+    inner: T,
+    translator: Translator,
+}
+
+/// A service shared with the calls deferred until their positions are
+/// translated.
+pub type Shared<S> = Arc<Mutex<S>>;
+
+/// Open documents of the server, shared with the translator so positions in
+/// them are translated against the lines the server keeps.
+pub type Documents = Arc<RwLock<HashMap<Uri, Document>>>;
+
+/// Wraps a service and its client socket, sharing the negotiated encoding
+/// and the server's open documents.
+pub fn encoded<S>(
+    service: S,
+    socket: ClientSocket,
+    documents: Documents,
+) -> (Encoded<Shared<S>>, Encoded<ClientSocket>) {
+    let translator = Translator {
+        documents,
+        state: Arc::default(),
+    };
+    (
+        Encoded {
+            inner: Arc::new(Mutex::new(service)),
+            translator: translator.clone(),
+        },
+        Encoded {
+            inner: socket,
+            translator,
+        },
+    )
+}
+
+impl<S> Service<Request> for Encoded<Shared<S>>
+where
+    S: Service<Request, Response = Option<Response>> + Send + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = Option<Response>;
+    type Error = S::Error;
+    type Future =
+        Pin<Box<dyn Future<Output = Result<Option<Response>, S::Error>> + Send + 'static>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        lock(&self.inner).poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request) -> Self::Future {
+        let method = request.method().to_string();
+        let uri = request.params().and_then(document_uri);
+        let translator = self.translator.clone();
+        // Changes are passed on in the order received, and requests are
+        // translated once the changes received before them hold the
+        // documents lock or are applied.
+        let called = if translator.passes_through(&request) {
+            Ok(lock(&self.inner).call(request))
+        } else {
+            Err((Arc::clone(&self.inner), request))
+        };
+        Box::pin(async move {
+            let future = match called {
+                Ok(future) => future,
+                Err((inner, request)) => {
+                    let request = translator.decode(request).await;
+                    lock(&inner).call(request)
+                }
+            };
+            let Some(response) = future.await? else {
+                return Ok(None);
+            };
+            Ok(Some(
+                translator
+                    .encode_response(&method, uri.as_ref(), response)
+                    .await,
+            ))
+        })
+    }
+}
+
+impl Loopback for Encoded<ClientSocket> {
+    type RequestStream = Pin<Box<dyn Stream<Item = Request> + Send>>;
+    type ResponseSink = <ClientSocket as Loopback>::ResponseSink;
+
+    fn split(self) -> (Self::RequestStream, Self::ResponseSink) {
+        let (requests, responses) = Loopback::split(self.inner);
+        let translator = self.translator;
+        let requests = requests.then(move |request| {
+            let translator = translator.clone();
+            async move { translator.encode_request(request).await }
+        });
+        (Box::pin(requests), responses)
+    }
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+#[derive(Clone)]
+struct Translator {
+    documents: Documents,
+    state: Arc<Mutex<State>>,
+}
+
+#[derive(Default)]
+struct State {
+    encoding: PositionEncoding,
+    files: Files,
+}
+
+/// Lines of files positions were in other than open documents, or `None`
+/// for those that couldn't be read, kept until the client reports them
+/// changed, opens or closes them. Only the most recently used are kept.
+#[derive(Default)]
+struct Files {
+    lines: HashMap<Uri, Option<LineRope<()>>>,
+    /// From least to most recently used.
+    recency: VecDeque<Uri>,
+}
+
+impl Files {
+    /// Whether `uri` was read, marking it as the most recently used.
+    fn touch(&mut self, uri: &Uri) -> bool {
+        if !self.lines.contains_key(uri) {
+            return false;
+        }
+        self.recency.retain(|used| used != uri);
+        self.recency.push_back(uri.clone());
+        true
+    }
+
+    /// Keeps the lines of `uri`, dropping those of the least recently used
+    /// file past [`MAX_FILES`].
+    fn insert(&mut self, uri: Uri, lines: Option<LineRope<()>>) {
+        self.remove(&uri);
+        self.recency.push_back(uri.clone());
+        self.lines.insert(uri, lines);
+        if self.recency.len() > MAX_FILES
+            && let Some(oldest) = self.recency.pop_front()
+        {
+            self.lines.remove(&oldest);
+        }
+    }
+
+    fn remove(&mut self, uri: &Uri) {
+        if self.lines.remove(uri).is_some() {
+            self.recency.retain(|used| used != uri);
+        }
+    }
+
+    fn line(&self, uri: &Uri, idx: u32) -> Option<&str> {
+        self.lines.get(uri)?.as_ref()?.line(idx as usize)
+    }
+}
+
+impl Translator {
+    fn lock(&self) -> MutexGuard<'_, State> {
+        lock(&self.state)
+    }
+
+    /// Whether `request` goes to the server as it is, forgetting the files
+    /// it reports changed. Changes are translated by the server as it
+    /// applies them, against the text each applies to.
+    fn passes_through(&self, request: &Request) -> bool {
+        let mut state = self.lock();
+        if state.encoding == PositionEncoding::Utf16 {
+            return true;
+        }
+        let Some(params) = request.params() else {
+            return true;
+        };
+        match request.method() {
+            "workspace/didChangeWatchedFiles" => {
+                let changes = params.get("changes").and_then(Value::as_array);
+                for change in changes.into_iter().flatten() {
+                    if let Some(uri) = change["uri"].as_str().and_then(|uri| uri.parse().ok()) {
+                        state.files.remove(&uri);
+                    }
+                }
+                true
+            }
+            // The text on disk may differ from the document's.
+            "textDocument/didOpen" | "textDocument/didClose" => {
+                if let Some(uri) = document_uri(params) {
+                    state.files.remove(&uri);
+                }
+                true
+            }
+            "textDocument/didChange" => true,
+            _ => false,
+        }
+    }
+
+    /// Translates a request or notification from the client to UTF-16.
+    async fn decode(&self, request: Request) -> Request {
+        let (method, id, mut params) = request.into_parts();
+        if let Some(params) = &mut params {
+            let encoding = self.lock().encoding;
+            let uri = document_uri(params);
+            self.translate(params, uri.as_ref(), encoding, PositionEncoding::Utf16)
+                .await;
+        }
+        build_request(method, id, params)
+    }
+
+    /// Translates the result of a request from UTF-16, in the document the
+    /// request named, if any. The result of `initialize` sets the encoding.
+    async fn encode_response(
+        &self,
+        method: &str,
+        uri: Option<&Uri>,
+        response: Response,
+    ) -> Response {
+        let encoding = self.lock().encoding;
+        if encoding == PositionEncoding::Utf16 && method != "initialize" {
+            return response;
+        }
+        let (id, result) = response.into_parts();
+        let Ok(mut result) = result else {
+            return Response::from_parts(id, result);
+        };
+        if method == "initialize" {
+            self.lock().encoding = result
+                .pointer("/capabilities/positionEncoding")
+                .and_then(Value::as_str)
+                .and_then(PositionEncoding::parse)
+                .unwrap_or_default();
+        } else if method == "textDocument/semanticTokens/full" {
+            self.encode_tokens(uri, &mut result).await;
+        } else {
+            self.translate(&mut result, uri, PositionEncoding::Utf16, encoding)
+                .await;
+        }
+        Response::from_parts(id, Ok(result))
+    }
+
+    /// Translates a request or notification to the client from UTF-16.
+    async fn encode_request(&self, request: Request) -> Request {
+        let encoding = self.lock().encoding;
+        if encoding == PositionEncoding::Utf16 {
+            return request;
+        }
+        let (method, id, mut params) = request.into_parts();
+        if let Some(params) = &mut params {
+            self.translate(params, None, PositionEncoding::Utf16, encoding)
+                .await;
+        }
+        build_request(method, id, params)
+    }
+
+    /// Converts the columns of positions in `value` from `from` to `to`
+    /// units, against the open documents or the files on disk they are in.
+    async fn translate(
+        &self,
+        value: &mut Value,
+        uri: Option<&Uri>,
+        from: PositionEncoding,
+        to: PositionEncoding,
+    ) {
+        let mut uris = HashSet::new();
+        // Leaves the columns as they are.
+        translate(value, uri, &mut |uri, _, character| {
+            if !uris.contains(uri) {
+                uris.insert(uri.clone());
+            }
+            character
+        });
+        if uris.is_empty() {
+            return;
+        }
+        self.read_files(uris).await;
+
+        let documents = self.documents.read().await;
+        let state = self.lock();
+        translate(value, uri, &mut |uri, line, character| {
+            line_of(&documents, &state.files, uri, line)
+                .map_or(character, |text| from.convert(text, character, to))
+        });
+    }
+
+    /// Converts the columns of semantic tokens of `uri` from UTF-16.
+    async fn encode_tokens(&self, uri: Option<&Uri>, value: &mut Value) {
+        let (Some(uri), Ok(mut tokens)) = (uri, SemanticTokens::deserialize(&*value)) else {
+            return;
+        };
+        let documents = self.documents.read().await;
+        let state = self.lock();
+        encode_tokens(&mut tokens.data, |line, character| {
+            line_of(&documents, &state.files, uri, line).map_or(character, |text| {
+                PositionEncoding::Utf16.convert(text, character, state.encoding)
+            })
+        });
+        *value = json!(tokens);
+    }
+
+    /// Reads the files of `uris`, other than open documents and files read
+    /// before, on the blocking pool.
+    async fn read_files(&self, mut uris: HashSet<Uri>) {
+        {
+            let documents = self.documents.read().await;
+            let mut state = self.lock();
+            uris.retain(|uri| !documents.contains_key(uri) && !state.files.touch(uri));
+        }
+        if uris.is_empty() {
+            return;
+        }
+        let files = tokio::task::spawn_blocking(move || {
+            uris.into_iter()
+                .map(|uri| {
+                    let text = uri
+                        .to_file_path()
+                        .and_then(|path| std::fs::read_to_string(path).ok());
+                    (uri, text.as_deref().map(lines_of))
+                })
+                .collect::<Vec<_>>()
+        });
+        if let Ok(files) = files.await {
+            let mut state = self.lock();
+            for (uri, lines) in files {
+                state.files.insert(uri, lines);
+            }
+        }
+    }
+}
+
+/// Returns a line of an open document, or of a file read from disk.
+fn line_of<'a>(
+    documents: &'a HashMap<Uri, Document>,
+    files: &'a Files,
+    uri: &Uri,
+    idx: u32,
+) -> Option<&'a str> {
+    match documents.get(uri) {
+        Some(document) => document.line(idx as usize),
+        None => files.line(uri, idx),
+    }
+}
+
+/// Splits `text` into lines on the calling thread, as the file cache
+/// attaches no data worth spreading over threads.
+fn lines_of(text: &str) -> LineRope<()> {
+    LineRope::from_lines(split_lines(text).map(|line| (line, ())))
+}
+
+/// Converts the column of each position in `value` with `column`, given the
+/// document it is in: that of the closest enclosing object with a `uri` or
+/// `textDocument`, or `uri`. Only the fields LSP puts positions in are
+/// converted, and the `data` the server attaches for itself is left to
+/// round-trip as it is. Positions in no document are left as they are, such
+/// as those of code lenses and inlay hints sent back to be resolved.
+fn translate(value: &mut Value, uri: Option<&Uri>, column: &mut impl FnMut(&Uri, u32, u32) -> u32) {
+    match value {
+        Value::Array(values) => {
+            for value in values {
+                translate(value, uri, column);
+            }
+        }
+        Value::Object(object) => {
+            let own_uri = object_uri(object);
+            let uri = own_uri.as_ref().or(uri);
+            for (key, value) in object.iter_mut() {
+                match value {
+                    _ if key == "data" => {}
+                    _ if matches!(key.as_str(), "position" | "start" | "end") => {
+                        translate_position(value, uri, column);
+                    }
+                    Value::Array(positions) if key == "positions" => {
+                        for position in positions {
+                            translate_position(position, uri, column);
+                        }
+                    }
+                    // Workspace edits map each document to its edits.
+                    Value::Object(changes) if key == "changes" => {
+                        for (key, edits) in changes.iter_mut() {
+                            translate(edits, key.parse().ok().as_ref(), column);
+                        }
+                    }
+                    value => translate(value, uri, column),
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+fn translate_position(
+    position: &mut Value,
+    uri: Option<&Uri>,
+    column: &mut impl FnMut(&Uri, u32, u32) -> u32,
+) {
+    let (Some(uri), Some(line), Some(character)) = (
+        uri,
+        position.get("line").and_then(Value::as_u64),
+        position.get("character").and_then(Value::as_u64),
+    ) else {
+        return;
+    };
+    position["character"] = column(uri, line as u32, character as u32).into();
+}
+
+/// Converts the columns of relative semantic tokens with `column`, given
+/// absolute lines and columns.
+fn encode_tokens(tokens: &mut [SemanticToken], mut column: impl FnMut(u32, u32) -> u32) {
+    let (mut line, mut start, mut encoded_start) = (0, 0, 0);
+    for token in tokens {
+        if token.delta_line > 0 {
+            line += token.delta_line;
+            (start, encoded_start) = (0, 0);
+        }
+        start += token.delta_start;
+        let new_start = column(line, start);
+        let new_end = column(line, start + token.length);
+        token.delta_start = new_start - encoded_start;
+        token.length = new_end - new_start;
+        encoded_start = new_start;
+    }
+}
+
+fn object_uri(object: &Map<String, Value>) -> Option<Uri> {
+    object
+        .get("uri")
+        .or_else(|| object.get("textDocument")?.get("uri"))
+        .and_then(Value::as_str)?
+        .parse()
+        .ok()
+}
+
+fn document_uri(params: &Value) -> Option<Uri> {
+    params.as_object().and_then(object_uri)
+}
+
+fn build_request(method: Cow<'static, str>, id: Option<Id>, params: Option<Value>) -> Request {
+    let mut builder = Request::build(method);
+    if let Some(id) = id {
+        builder = builder.id(id);
+    }
+    if let Some(params) = params {
+        builder = builder.params(params);
+    }
+    builder.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use tower_lsp_server::ls_types::{
+        ClientCapabilities, GeneralClientCapabilities, Position, PositionEncodingKind, Range,
+        SemanticToken, TextDocumentContentChangeEvent, Uri,
+    };
+
+    use crate::document::Document;
+    use crate::encoding::{Files, MAX_FILES, PositionEncoding, encode_tokens, lines_of, translate};
+
+    #[test]
+    fn converts_columns() {
+        let line = "a: 'é😀' #FFF";
+        let utf16 = PositionEncoding::Utf16;
+        assert_eq!(utf16.convert(line, 8, PositionEncoding::Utf8), 11);
+        assert_eq!(utf16.convert(line, 8, PositionEncoding::Utf32), 7);
+        assert_eq!(PositionEncoding::Utf8.convert(line, 11, utf16), 8);
+        // Inside the emoji, and past the end of the line.
+        assert_eq!(PositionEncoding::Utf8.convert(line, 7, utf16), 5);
+        assert_eq!(PositionEncoding::Utf32.convert(line, 14, utf16), 15);
+    }
+
+    #[test]
+    fn prefers_utf8() {
+        let offering = |kinds: Vec<PositionEncodingKind>| ClientCapabilities {
+            general: Some(GeneralClientCapabilities {
+                position_encodings: Some(kinds),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let negotiate = |kinds| PositionEncoding::negotiate(&offering(kinds));
+        assert_eq!(
+            negotiate(vec![
+                PositionEncodingKind::UTF16,
+                PositionEncodingKind::UTF8
+            ]),
+            PositionEncoding::Utf8
+        );
+        assert_eq!(
+            negotiate(vec![
+                PositionEncodingKind::UTF32,
+                PositionEncodingKind::UTF16
+            ]),
+            PositionEncoding::Utf32
+        );
+        assert_eq!(
+            PositionEncoding::negotiate(&ClientCapabilities::default()),
+            PositionEncoding::Utf16
+        );
+    }
+
+    #[test]
+    fn translates_positions_by_document() {
+        let a: Uri = "file:///a.css".parse().unwrap();
+        let mut value = json!({
+            "range": { "start": { "line": 0, "character": 1 }, "end": { "line": 0, "character": 2 } },
+            "location": { "uri": "file:///b.css", "range": { "start": { "line": 1, "character": 3 } } },
+            "edit": { "changes": { "file:///c.css": [{ "newText": "", "range": { "start": { "line": 2, "character": 5 } } }] } },
+            "data": { "position": { "line": 3, "character": 7 } },
+            "color": { "line": 4, "character": 9 },
+        });
+        translate(&mut value, Some(&a), &mut |uri, line, character| {
+            let document = uri.as_str().as_bytes()[8] - b'a';
+            (document as u32 + 1) * 100 + line * 10 + character
+        });
+        assert_eq!(value["range"]["start"]["character"], 101);
+        assert_eq!(value["range"]["end"]["character"], 102);
+        assert_eq!(value["location"]["range"]["start"]["character"], 213);
+        assert_eq!(
+            value["edit"]["changes"]["file:///c.css"][0]["range"]["start"]["character"],
+            325
+        );
+        // Server data and fields other than positions are left as they are.
+        assert_eq!(value["data"]["position"]["character"], 7);
+        assert_eq!(value["color"]["character"], 9);
+
+        let mut value = json!({ "position": { "line": 0, "character": 4 } });
+        translate(&mut value, None, &mut |_, _, _| unreachable!());
+        assert_eq!(value["position"]["character"], 4);
+    }
+
+    #[test]
+    fn encodes_relative_tokens() {
+        let token = |delta_line, delta_start, length| SemanticToken {
+            delta_line,
+            delta_start,
+            length,
+            token_type: 0,
+            token_modifiers_bitset: 0,
+        };
+        let mut tokens = [token(0, 2, 4), token(0, 6, 4), token(1, 1, 4)];
+        // Columns of the first line double.
+        encode_tokens(&mut tokens, |line, character| {
+            if line == 0 { character * 2 } else { character }
+        });
+        assert_eq!(tokens, [token(0, 4, 8), token(0, 12, 8), token(1, 1, 4)]);
+    }
+
+    #[test]
+    fn decodes_changes_against_their_document() {
+        let document = Document::from("/* é */ #FF0000");
+        let change = TextDocumentContentChangeEvent {
+            range: Some(Range {
+                start: Position::new(0, 10),
+                end: Position::new(0, 16),
+            }),
+            range_length: None,
+            text: "00FF00".to_string(),
+        };
+        let decoded = PositionEncoding::Utf8.decode_change(&document, &change);
+        let range = decoded.range.unwrap();
+        assert_eq!(range.start, Position::new(0, 9));
+        assert_eq!(range.end, Position::new(0, 15));
+        assert_eq!(decoded.text, "00FF00");
+
+        let full = TextDocumentContentChangeEvent {
+            range: None,
+            range_length: None,
+            text: String::new(),
+        };
+        assert!(
+            PositionEncoding::Utf8
+                .decode_change(&document, &full)
+                .range
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn keeps_most_recently_used_files() {
+        let uri = |idx: usize| -> Uri { format!("file:///{idx}.css").parse().unwrap() };
+        let mut files = Files::default();
+        for idx in 0..MAX_FILES {
+            files.insert(uri(idx), Some(lines_of("a")));
+        }
+        assert!(files.touch(&uri(0)));
+        files.insert(uri(MAX_FILES), None);
+
+        assert!(files.touch(&uri(0)));
+        assert!(!files.touch(&uri(1)));
+        assert_eq!(files.lines.len(), MAX_FILES);
+        assert_eq!(files.line(&uri(2), 0), Some("a"));
+
+        files.remove(&uri(2));
+        assert_eq!(files.line(&uri(2), 0), None);
+        assert_eq!(files.recency.len(), MAX_FILES - 1);
+    }
+}
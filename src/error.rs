@@ -0,0 +1,112 @@
+use tower_lsp_server::jsonrpc::{self, ErrorCode};
+use tower_lsp_server::ls_types::{MessageType, Uri};
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// Failures surfaced by request handlers.
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("Document not found for {} URI", .0.as_str())]
+    DocumentNotFound(Uri),
+    #[error("{} kept changing while its colors were computed", .0.as_str())]
+    ContentModified(Uri),
+    #[error("{command} expects {expected} argument")]
+    InvalidArgument {
+        command: &'static str,
+        expected: &'static str,
+    },
+    #[error("Unknown command: {0}")]
+    UnknownCommand(String),
+    #[error("Background task failed: {0}")]
+    Task(String),
+    #[error("Invalid initializationOptions: {0}")]
+    InvalidSettings(#[from] serde_json::Error),
+    #[error("Invalid resource name: {0:?}")]
+    InvalidResourceName(String),
+    #[error("Invalid variable name: {0:?}")]
+    InvalidVariableName(String),
+    #[error("Invalid palette {path}: {reason}")]
+    InvalidPalette { path: String, reason: String },
+    #[error("Gradients have 2 to {max} stops, got {0}", max = crate::gradient::MAX_STOPS)]
+    InvalidStopCount(usize),
+    #[error("No colors to export in {}", .0.as_str())]
+    NoColors(Uri),
+    #[error("Failed to write state dump: {0}")]
+    DumpState(#[from] std::io::Error),
+}
+
+impl Error {
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            Error::DocumentNotFound(_) | Error::Task(_) | Error::DumpState(_) => {
+                ErrorCode::InternalError
+            }
+            Error::InvalidArgument { .. }
+            | Error::UnknownCommand(_)
+            | Error::InvalidSettings(_)
+            | Error::InvalidResourceName(_)
+            | Error::InvalidVariableName(_)
+            | Error::InvalidPalette { .. }
+            | Error::InvalidStopCount(_)
+            | Error::NoColors(_) => ErrorCode::InvalidParams,
+            Error::ContentModified(_) => ErrorCode::ContentModified,
+        }
+    }
+
+    /// Severity used when the error is logged to the client.
+    pub fn message_type(&self) -> MessageType {
+        match self.code() {
+            ErrorCode::InternalError => MessageType::ERROR,
+            // Expected while the user types.
+            ErrorCode::ContentModified => MessageType::LOG,
+            _ => MessageType::WARNING,
+        }
+    }
+}
+
+impl From<Error> for jsonrpc::Error {
+    fn from(error: Error) -> Self {
+        jsonrpc::Error {
+            code: error.code(),
+            message: error.to_string().into(),
+            data: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tower_lsp_server::jsonrpc::{self, ErrorCode};
+    use tower_lsp_server::ls_types::{MessageType, Uri};
+
+    use crate::error::Error;
+
+    #[test]
+    fn document_not_found_maps_to_internal_error() {
+        let uri: Uri = "file:///a.css".parse().unwrap();
+        let error = Error::DocumentNotFound(uri);
+        assert_eq!(error.message_type(), MessageType::ERROR);
+
+        let error: jsonrpc::Error = error.into();
+        assert_eq!(error.code, ErrorCode::InternalError);
+        assert_eq!(error.message, "Document not found for file:///a.css URI");
+    }
+
+    #[test]
+    fn content_modified_is_logged_quietly() {
+        let uri: Uri = "file:///a.css".parse().unwrap();
+        let error = Error::ContentModified(uri);
+        assert_eq!(error.code(), ErrorCode::ContentModified);
+        assert_eq!(error.message_type(), MessageType::LOG);
+    }
+
+    #[test]
+    fn invalid_argument_maps_to_invalid_params() {
+        let error = Error::InvalidArgument {
+            command: "chroma.test",
+            expected: "a color",
+        };
+        assert_eq!(error.to_string(), "chroma.test expects a color argument");
+        assert_eq!(error.code(), ErrorCode::InvalidParams);
+    }
+}
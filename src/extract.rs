@@ -0,0 +1,244 @@
+//! Extraction of a color repeated in a stylesheet into a variable, for the
+//! "Extract to custom property" code action.
+
+use tower_lsp_server::ls_types::{Position, Range, TextEdit};
+
+use crate::color::to_hex;
+use crate::document::Document;
+
+/// Kind of variable colors are extracted to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VariableKind {
+    /// A CSS custom property declared in `:root`.
+    CustomProperty,
+    /// A Sass variable declared at the top of an SCSS file.
+    Sass,
+}
+
+impl VariableKind {
+    /// Returns the kind of variable of a document with this language id or
+    /// extension, for CSS and SCSS.
+    pub fn detect(language_id: &str, extension: &str) -> Option<Self> {
+        let is = |name: &str| {
+            language_id.eq_ignore_ascii_case(name) || extension.eq_ignore_ascii_case(name)
+        };
+        if is("scss") {
+            Some(VariableKind::Sass)
+        } else if is("css") {
+            Some(VariableKind::CustomProperty)
+        } else {
+            None
+        }
+    }
+
+    fn prefix(self) -> &'static str {
+        match self {
+            VariableKind::CustomProperty => "--",
+            VariableKind::Sass => "$",
+        }
+    }
+
+    fn name(self, n: usize) -> String {
+        format!("{}color-{n}", self.prefix())
+    }
+
+    /// Returns the declaration of the variable `name`, given without its
+    /// `--` or `$` prefix, set to `value`. Both kinds can be declared inside
+    /// a rule.
+    pub fn declare(self, name: &str, value: &str) -> String {
+        format!("{}{name}: {value};", self.prefix())
+    }
+
+    fn reference(self, name: &str) -> String {
+        match self {
+            VariableKind::CustomProperty => format!("var({name})"),
+            VariableKind::Sass => name.to_string(),
+        }
+    }
+}
+
+/// Returns the title and edits of extracting the color at `range` into a new
+/// variable replacing every color of the document with the same value, or
+/// `None` if the color is used once.
+///
+/// Computed colors, such as `var()` references or Sass functions of
+/// variables, are left as they are.
+pub fn extract(
+    document: &Document,
+    range: &Range,
+    kind: VariableKind,
+) -> Option<(String, Vec<TextEdit>)> {
+    let literal = |range: &Range| {
+        document
+            .range_text(range)
+            .filter(|text| !text.starts_with("var(") && !text.contains(['$', '@']))
+    };
+    let target = document
+        .get_colors_in_range(range)
+        .into_iter()
+        .find(|color_info| literal(&color_info.range).is_some())?;
+    let value = literal(&target.range)?;
+    let hex = to_hex(&target.color);
+    let occurrences: Vec<Range> = document
+        .get_colors()
+        .into_iter()
+        .filter(|color_info| to_hex(&color_info.color) == hex)
+        .filter(|color_info| literal(&color_info.range).is_some())
+        .map(|color_info| color_info.range)
+        .collect();
+    if occurrences.len() < 2 {
+        return None;
+    }
+
+    let name = (1..)
+        .map(|n| kind.name(n))
+        .find(|name| !is_declared(document, name))?;
+    let mut edits = vec![declaration(document, kind, &name, value)];
+    let reference = kind.reference(&name);
+    edits.extend(occurrences.into_iter().map(|range| TextEdit {
+        range,
+        new_text: reference.clone(),
+    }));
+    let title = match kind {
+        VariableKind::CustomProperty => format!("Extract to custom property `{name}`"),
+        VariableKind::Sass => format!("Extract to variable `{name}`"),
+    };
+    Some((title, edits))
+}
+
+/// Returns the edit declaring the variable: at the start of `:root` for
+/// custom properties, adding the rule if there is none, or after the leading
+/// `@use`, `@forward`, `@import` and `@charset` rules.
+fn declaration(document: &Document, kind: VariableKind, name: &str, value: &str) -> TextEdit {
+    let lines = document.lines();
+    if kind == VariableKind::CustomProperty
+        && let Some((line_idx, line, brace)) = lines.clone().enumerate().find_map(|(idx, line)| {
+            let brace = line.find('{')?;
+            (line[..brace].trim() == ":root").then_some((idx, line, brace))
+        })
+    {
+        let indent: String = document
+            .line(line_idx + 1)
+            .filter(|next| !next.trim_start().starts_with('}'))
+            .map(|next| next.chars().take_while(|c| c.is_whitespace()).collect())
+            .filter(|indent: &String| !indent.is_empty())
+            .unwrap_or_else(|| {
+                let root_indent: String = line.chars().take_while(|c| c.is_whitespace()).collect();
+                format!("{root_indent}  ")
+            });
+        let position = Position::new(
+            line_idx as u32,
+            line[..=brace].encode_utf16().count() as u32,
+        );
+        return TextEdit {
+            range: Range::new(position, position),
+            new_text: format!("\n{indent}{name}: {value};"),
+        };
+    }
+
+    let after_imports = lines
+        .take_while(|line| {
+            let line = line.trim_start();
+            ["@use", "@forward", "@import", "@charset"]
+                .iter()
+                .any(|rule| line.starts_with(rule))
+        })
+        .count();
+    let declaration = match kind {
+        VariableKind::CustomProperty => format!(":root {{\n  {name}: {value};\n}}\n"),
+        VariableKind::Sass => format!("{name}: {value};\n"),
+    };
+    let position = Position::new(after_imports as u32, 0);
+    TextEdit {
+        range: Range::new(position, position),
+        new_text: if after_imports == 0 {
+            format!("{declaration}\n")
+        } else {
+            format!("\n{declaration}\n")
+        },
+    }
+}
+
+/// Whether a variable name is already used in the document, as a whole name.
+fn is_declared(document: &Document, name: &str) -> bool {
+    document.lines().any(|line| {
+        line.match_indices(name).any(|(start, _)| {
+            !line[start + name.len()..]
+                .starts_with(|c: char| c.is_alphanumeric() || c == '-' || c == '_')
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use tower_lsp_server::ls_types::{Position, Range};
+
+    use crate::document::Document;
+    use crate::extract::{VariableKind, extract};
+
+    fn apply(
+        text: &str,
+        line: u32,
+        character: u32,
+        kind: VariableKind,
+    ) -> Option<(String, String)> {
+        let document = Document::from(text);
+        let position = Position::new(line, character);
+        let (title, mut edits) = extract(&document, &Range::new(position, position), kind)?;
+        edits.sort_by_key(|edit| std::cmp::Reverse(edit.range.start));
+        let mut lines: Vec<String> = text.split('\n').map(str::to_string).collect();
+        for edit in edits {
+            let (start, end) = (edit.range.start, edit.range.end);
+            assert_eq!(start.line, end.line);
+            let line = &mut lines[start.line as usize];
+            line.replace_range(
+                start.character as usize..end.character as usize,
+                &edit.new_text,
+            );
+        }
+        Some((title, lines.join("\n")))
+    }
+
+    #[test]
+    fn extracts_into_root() {
+        let text =
+            ":root {\n    --bg: #fff;\n}\na { color: #1A73E8; }\nb { border-color: #1a73e8; }\n";
+        let (title, extracted) = apply(text, 3, 13, VariableKind::CustomProperty).unwrap();
+        assert_eq!(title, "Extract to custom property `--color-1`");
+        assert_eq!(
+            extracted,
+            ":root {\n    --color-1: #1A73E8;\n    --bg: #fff;\n}\n\
+             a { color: var(--color-1); }\nb { border-color: var(--color-1); }\n"
+        );
+    }
+
+    #[test]
+    fn adds_root_after_imports() {
+        let text = "@import \"base.css\";\na { color: #F00; }\nb { background: #ff0000; }\n";
+        let (_, extracted) = apply(text, 2, 20, VariableKind::CustomProperty).unwrap();
+        assert_eq!(
+            extracted,
+            "@import \"base.css\";\n\n:root {\n  --color-1: #ff0000;\n}\n\n\
+             a { color: var(--color-1); }\nb { background: var(--color-1); }\n"
+        );
+    }
+
+    #[test]
+    fn extracts_sass_variables() {
+        let text = "@use \"sass:math\";\n$color-1: #000;\na { color: #FFF; }\nb { fill: #fff; }\n";
+        let (title, extracted) = apply(text, 2, 12, VariableKind::Sass).unwrap();
+        assert_eq!(title, "Extract to variable `$color-2`");
+        assert_eq!(
+            extracted,
+            "@use \"sass:math\";\n\n$color-2: #FFF;\n\n$color-1: #000;\na { color: $color-2; }\nb { fill: $color-2; }\n"
+        );
+    }
+
+    #[test]
+    fn single_use() {
+        assert_eq!(
+            apply("a { color: #FFF; }\n", 0, 12, VariableKind::CustomProperty),
+            None
+        );
+    }
+}
@@ -0,0 +1,180 @@
+//! Interpolation between two colors, for previewing gradients and generating
+//! their stops.
+
+use serde::{Deserialize, Serialize};
+use tower_lsp_server::ls_types::Color;
+
+use crate::color::to_hex;
+use crate::colorspace::{ACHROMATIC, from_hsl, from_oklch, to_hsl, to_oklch};
+
+/// Most stops returned for one gradient.
+pub const MAX_STOPS: usize = 256;
+
+/// Color space colors are interpolated in.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Space {
+    #[default]
+    Srgb,
+    Oklch,
+    Hsl,
+}
+
+/// Way around the hue circle taken by polar spaces, as in CSS.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Hue {
+    #[default]
+    Shorter,
+    Longer,
+}
+
+impl Hue {
+    /// Returns the hue at `t` between `from` and `to`, in degrees.
+    fn interpolate(self, from: f32, to: f32, t: f32) -> f32 {
+        let mut delta = to - from;
+        match self {
+            Hue::Shorter if delta > 180.0 => delta -= 360.0,
+            Hue::Shorter if delta < -180.0 => delta += 360.0,
+            Hue::Longer if 0.0 < delta && delta < 180.0 => delta -= 360.0,
+            Hue::Longer if -180.0 < delta && delta <= 0.0 => delta += 360.0,
+            _ => {}
+        }
+        (from + delta * t).rem_euclid(360.0)
+    }
+}
+
+/// A color of a gradient at `offset`, from 0 to 1.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct GradientStop {
+    pub offset: f32,
+    pub color: Color,
+    pub hex: String,
+}
+
+/// Returns `stops` evenly spaced colors from `from` to `to`, both included.
+pub fn interpolate(
+    from: &Color,
+    to: &Color,
+    space: Space,
+    hue: Hue,
+    stops: usize,
+) -> Vec<GradientStop> {
+    (0..stops)
+        .map(|idx| {
+            let offset = idx as f32 / stops.saturating_sub(1).max(1) as f32;
+            let color = mix(from, to, space, hue, offset);
+            GradientStop {
+                offset,
+                color,
+                hex: to_hex(&color),
+            }
+        })
+        .collect()
+}
+
+/// Returns the color at `t` between `from` and `to`. Components other than
+/// hue are premultiplied by alpha, and the hue of a gray takes that of the
+/// other color, as in CSS.
+fn mix(from: &Color, to: &Color, space: Space, hue: Hue, t: f32) -> Color {
+    let lerp = |a: f32, b: f32| a + (b - a) * t;
+    let alpha = lerp(from.alpha, to.alpha);
+    let blend = |a: f32, b: f32| {
+        if alpha == 0.0 {
+            lerp(a, b)
+        } else {
+            lerp(a * from.alpha, b * to.alpha) / alpha
+        }
+    };
+    match space {
+        Space::Srgb => Color {
+            red: blend(from.red, to.red),
+            green: blend(from.green, to.green),
+            blue: blend(from.blue, to.blue),
+            alpha,
+        },
+        Space::Hsl => {
+            let (from_hue, from_saturation, from_lightness) = to_hsl(from);
+            let (to_hue, to_saturation, to_lightness) = to_hsl(to);
+            let (from_hue, to_hue) = hues(
+                (from_hue, from_saturation == 0.0),
+                (to_hue, to_saturation == 0.0),
+            );
+            from_hsl(
+                hue.interpolate(from_hue, to_hue, t),
+                blend(from_saturation, to_saturation),
+                blend(from_lightness, to_lightness),
+                alpha,
+            )
+        }
+        Space::Oklch => {
+            let (from_lightness, from_chroma, from_hue) = to_oklch(from);
+            let (to_lightness, to_chroma, to_hue) = to_oklch(to);
+            let (from_hue, to_hue) = hues(
+                (from_hue, from_chroma < ACHROMATIC),
+                (to_hue, to_chroma < ACHROMATIC),
+            );
+            from_oklch(
+                blend(from_lightness, to_lightness),
+                blend(from_chroma, to_chroma),
+                hue.interpolate(from_hue, to_hue, t),
+                alpha,
+            )
+        }
+    }
+}
+
+/// Returns the hues to interpolate between, given whether each color is a
+/// gray, whose hue is meaningless.
+fn hues((from_hue, from_gray): (f32, bool), (to_hue, to_gray): (f32, bool)) -> (f32, f32) {
+    match (from_gray, to_gray) {
+        (true, false) => (to_hue, to_hue),
+        (false, true) => (from_hue, from_hue),
+        _ => (from_hue, to_hue),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::color::parse_color;
+    use crate::gradient::{Hue, Space, interpolate};
+
+    fn hexes(from: &str, to: &str, space: Space, hue: Hue, stops: usize) -> Vec<String> {
+        let (from, to) = (parse_color(from).unwrap(), parse_color(to).unwrap());
+        interpolate(&from, &to, space, hue, stops)
+            .into_iter()
+            .map(|stop| stop.hex)
+            .collect()
+    }
+
+    #[test]
+    fn interpolates_in_each_space() {
+        assert_eq!(
+            hexes("#000000", "#FFFFFF", Space::Srgb, Hue::Shorter, 3),
+            ["#000000", "#808080", "#FFFFFF"]
+        );
+        assert_eq!(
+            hexes("#FF0000", "#0000FF", Space::Hsl, Hue::Shorter, 3),
+            ["#FF0000", "#FF00FF", "#0000FF"]
+        );
+        assert_eq!(
+            hexes("#FF0000", "#0000FF", Space::Hsl, Hue::Longer, 3),
+            ["#FF0000", "#00FF00", "#0000FF"]
+        );
+        assert_eq!(
+            hexes("#FFFFFF", "#FF0000", Space::Hsl, Hue::Shorter, 2),
+            ["#FFFFFF", "#FF0000"]
+        );
+        let oklch = hexes("#FF0000", "#0000FF", Space::Oklch, Hue::Shorter, 5);
+        assert_eq!(oklch.first().unwrap(), "#FF0000");
+        assert_eq!(oklch.last().unwrap(), "#0000FF");
+    }
+
+    #[test]
+    fn premultiplies_alpha() {
+        assert_eq!(
+            hexes("#FF000000", "#0000FF", Space::Srgb, Hue::Shorter, 3),
+            ["#FF000000", "#0000FF80", "#0000FF"]
+        );
+    }
+}
@@ -0,0 +1,79 @@
+use tower_lsp_server::ls_types::Color;
+
+use crate::colorspace::{from_hsl, to_hsl};
+
+/// A color harmony, described as hue rotations applied to a base color.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Harmony {
+    Analogous,
+    Triadic,
+    SplitComplementary,
+}
+
+impl Harmony {
+    pub const ALL: [Harmony; 3] = [
+        Harmony::Analogous,
+        Harmony::Triadic,
+        Harmony::SplitComplementary,
+    ];
+
+    /// Kebab-case name, used as the variable prefix for inserted sets.
+    pub fn name(self) -> &'static str {
+        match self {
+            Harmony::Analogous => "analogous",
+            Harmony::Triadic => "triadic",
+            Harmony::SplitComplementary => "split-complementary",
+        }
+    }
+
+    fn hue_offsets(self) -> [f32; 3] {
+        match self {
+            Harmony::Analogous => [-30.0, 0.0, 30.0],
+            Harmony::Triadic => [0.0, 120.0, 240.0],
+            Harmony::SplitComplementary => [0.0, 150.0, 210.0],
+        }
+    }
+
+    /// Returns the full set for `base`, including the base color itself.
+    pub fn colors(self, base: &Color) -> Vec<Color> {
+        let (hue, saturation, lightness) = to_hsl(base);
+        self.hue_offsets()
+            .iter()
+            .map(|offset| from_hsl(hue + offset, saturation, lightness, base.alpha))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::color::{parse_line_colors, to_hex};
+    use crate::harmony::Harmony;
+
+    fn hexes(harmony: Harmony, base: &str) -> Vec<String> {
+        let base = parse_line_colors(base, 0)[0].color;
+        harmony.colors(&base).iter().map(to_hex).collect()
+    }
+
+    #[test]
+    fn triadic_red() {
+        assert_eq!(
+            hexes(Harmony::Triadic, "#FF0000"),
+            ["#FF0000", "#00FF00", "#0000FF"]
+        );
+    }
+
+    #[test]
+    fn analogous_includes_base() {
+        let set = hexes(Harmony::Analogous, "#336699");
+        assert_eq!(set.len(), 3);
+        assert_eq!(set[1], "#336699");
+    }
+
+    #[test]
+    fn split_complementary_keeps_alpha() {
+        assert_eq!(
+            hexes(Harmony::SplitComplementary, "#FF000080"),
+            ["#FF000080", "#00FF8080", "#0080FF80"]
+        );
+    }
+}
@@ -0,0 +1,371 @@
+use serde::Deserialize;
+use tower_lsp_server::ls_types::{Color, ColorInformation, Position, Range, Uri};
+
+use crate::color::parse_line_colors;
+use crate::named;
+use crate::schema::JsonSchema;
+
+/// Language ids and file extensions of key/value configuration formats.
+const KEY_VALUE_LANGUAGES: [&str; 3] = ["yaml", "yml", "toml"];
+
+/// Language ids and file extensions of assembly and hardware description
+/// languages.
+const PACKED_HEX_LANGUAGES: [&str; 12] = [
+    "asm",
+    "nasm",
+    "s",
+    "inc",
+    "vhd",
+    "vhdl",
+    "v",
+    "verilog",
+    "sv",
+    "svh",
+    "systemverilog",
+    "arm",
+];
+
+/// Extensions of Unity serialized assets.
+const UNITY_EXTENSIONS: [&str; 3] = ["unity", "mat", "prefab"];
+
+/// Extensions of Unreal Engine configs and text exports.
+const UNREAL_EXTENSIONS: [&str; 3] = ["ini", "csv", "t3d"];
+
+/// How a document is structured, as far as color parsing is concerned.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Syntax {
+    #[default]
+    Plain,
+    /// YAML or TOML, where values of color keys may be bare hex or names.
+    KeyValue,
+    /// A Helix theme.
+    Helix,
+    /// `starship.toml`.
+    Starship,
+    /// A Zellij theme or config.
+    Zellij,
+    /// An Alacritty config, where colors may be written as `0xRRGGBB`.
+    Alacritty,
+    /// A WezTerm config.
+    WezTerm,
+    /// A Ghostty config or theme, where colors may be bare hex.
+    Ghostty,
+    /// A TextMate theme (`.tmTheme`), where `<string>` values may be named colors.
+    TmTheme,
+    /// A Sublime Text color scheme, where values may be named colors.
+    SublimeColorScheme,
+    /// An Android resource (`res/**/*.xml`), where 8-digit hex is `#AARRGGBB`.
+    Android,
+    /// Assembly or a hardware description language, where colors may be packed
+    /// hex literals like `0XFF8800`, `x"FF8800"` or `FF8800h`.
+    PackedHex,
+    /// A GTK stylesheet, with named colors and color functions like `shade()`.
+    Gtk,
+    /// A Qt stylesheet (`.qss`).
+    Qss,
+    /// An SCSS, Sass or LESS stylesheet, where color functions like
+    /// `darken($primary, 10%)` are evaluated with the document's variables.
+    Scss,
+    /// A Unity serialized asset (`.unity`, `.mat`, `.prefab`), where colors are
+    /// `{r: 0.1, g: 0.2, b: 0.3, a: 1}` maps.
+    Unity,
+    /// An Unreal Engine config or export (`.ini`, `.csv`, `.t3d`), where colors
+    /// are `(R=0.1,G=0.2,B=0.3,A=1.0)` struct literals.
+    Unreal,
+    /// Kotlin (Jetpack Compose) or Dart (Flutter), where `Color(0xAARRGGBB)`
+    /// literals are ARGB.
+    ColorConstructor,
+    /// An Xcode asset catalog color, `*.colorset/Contents.json`.
+    ColorSet,
+    /// JSON with a recognized `$schema`, where only color fields are parsed.
+    Json(JsonSchema),
+}
+
+impl Syntax {
+    /// Detects the syntax from the path of known theme files and the
+    /// `$schema` of JSON documents, then from the language id, falling back to
+    /// the file extension for clients that send a generic id.
+    pub fn detect(language_id: &str, uri: &Uri, text: &str) -> Self {
+        let path = uri.path().as_str().to_ascii_lowercase();
+        let file_name = path.rsplit('/').next().unwrap_or_default();
+        let extension = file_name
+            .rsplit_once('.')
+            .map_or("", |(_, extension)| extension);
+        if path.ends_with(".colorset/contents.json") {
+            Syntax::ColorSet
+        } else if extension == "webmanifest" {
+            Syntax::Json(JsonSchema::WebManifest)
+        } else if file_name.ends_with(".color-theme.json") {
+            Syntax::Json(JsonSchema::VsCodeTheme)
+        } else if let Some(schema) = ["json", "jsonc"]
+            .contains(&extension)
+            .then(|| JsonSchema::detect(text))
+            .flatten()
+        {
+            Syntax::Json(schema)
+        } else if extension == "tmtheme" {
+            Syntax::TmTheme
+        } else if extension == "sublime-color-scheme" {
+            Syntax::SublimeColorScheme
+        } else if (extension == "css" && file_name.starts_with("gtk"))
+            || path.contains("/gtk-3.0/")
+            || path.contains("/gtk-4.0/")
+        {
+            Syntax::Gtk
+        } else if extension == "qss" {
+            Syntax::Qss
+        } else if ["scss", "sass", "less"].contains(&language_id.to_ascii_lowercase().as_str())
+            || ["scss", "sass", "less"].contains(&extension)
+        {
+            Syntax::Scss
+        } else if extension == "xml" && path.contains("/res/") {
+            Syntax::Android
+        } else if UNITY_EXTENSIONS.contains(&extension) {
+            Syntax::Unity
+        } else if UNREAL_EXTENSIONS.contains(&extension) {
+            Syntax::Unreal
+        } else if ["kotlin", "dart"].contains(&language_id.to_ascii_lowercase().as_str())
+            || ["kt", "kts", "dart"].contains(&extension)
+        {
+            Syntax::ColorConstructor
+        } else if file_name == "starship.toml" {
+            Syntax::Starship
+        } else if extension == "toml" && path.contains("helix") && path.contains("/themes/") {
+            Syntax::Helix
+        } else if extension == "kdl" && path.contains("zellij") {
+            Syntax::Zellij
+        } else if file_name.starts_with("alacritty.") || path.contains("/alacritty/") {
+            Syntax::Alacritty
+        } else if file_name.ends_with("wezterm.lua") || path.contains("/wezterm/") {
+            Syntax::WezTerm
+        } else if path.contains("/ghostty/") {
+            Syntax::Ghostty
+        } else if KEY_VALUE_LANGUAGES.contains(&language_id.to_ascii_lowercase().as_str())
+            || KEY_VALUE_LANGUAGES.contains(&extension)
+        {
+            Syntax::KeyValue
+        } else if PACKED_HEX_LANGUAGES.contains(&language_id.to_ascii_lowercase().as_str())
+            || PACKED_HEX_LANGUAGES.contains(&extension)
+        {
+            Syntax::PackedHex
+        } else {
+            Syntax::Plain
+        }
+    }
+}
+
+/// Glob patterns, with `*` wildcards, for keys whose values are colors.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(transparent)]
+pub struct KeyPatterns(Vec<String>);
+
+impl Default for KeyPatterns {
+    fn default() -> Self {
+        Self(
+            ["*color*", "*colour*", "fg", "bg", "accent"]
+                .map(String::from)
+                .to_vec(),
+        )
+    }
+}
+
+impl KeyPatterns {
+    /// Whether the key, or the last segment of a dotted key, matches a
+    /// pattern. Matching ignores ASCII case.
+    pub fn matches(&self, key: &str) -> bool {
+        let key = key.to_ascii_lowercase();
+        let last_segment = key.rsplit('.').next().unwrap_or_default();
+        self.0.iter().any(|pattern| {
+            let pattern = pattern.to_ascii_lowercase();
+            glob_match(&pattern, &key) || glob_match(&pattern, last_segment)
+        })
+    }
+}
+
+fn glob_match(pattern: &str, text: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or_default();
+    let Some(mut rest) = text.strip_prefix(first) else {
+        return false;
+    };
+    let mut parts: Vec<&str> = parts.collect();
+    let Some(last) = parts.pop() else {
+        // No wildcard, so the pattern must match the whole text.
+        return rest.is_empty();
+    };
+    for part in parts {
+        let Some(idx) = rest.find(part) else {
+            return false;
+        };
+        rest = &rest[idx + part.len()..];
+    }
+    rest.ends_with(last)
+}
+
+/// Finds bare hex (`1a73e8`) and named (`red`) values assigned to keys that
+/// match `patterns`, e.g. `fg: 1a73e8` or `{ bg = "red" }`.
+pub fn find_key_values(
+    line: &str,
+    line_idx: usize,
+    patterns: &KeyPatterns,
+) -> Vec<ColorInformation> {
+    key_values(line)
+        .into_iter()
+        .filter(|(key, _, _)| patterns.matches(key))
+        .filter_map(|(_, value_start, value)| {
+            Some(ColorInformation {
+                range: line_range(line, line_idx, value_start..value_start + value.len()),
+                color: bare_color(value)?,
+            })
+        })
+        .collect()
+}
+
+/// Returns `key: value` and `key = value` pairs in a line, as the key, the
+/// byte index of the value and the value up to the first character that
+/// can't be part of a name. Quotes around keys and values are skipped.
+pub(crate) fn key_values(line: &str) -> Vec<(&str, usize, &str)> {
+    let is_key_char = |c: char| c.is_alphanumeric() || "_-.".contains(c);
+    let mut pairs = Vec::new();
+    for (separator_idx, _) in line.match_indices([':', '=']) {
+        let key = line[..separator_idx]
+            .trim_end()
+            .trim_end_matches(['"', '\'']);
+        let key_start = key
+            .char_indices()
+            .rev()
+            .take_while(|&(_, c)| is_key_char(c))
+            .last()
+            .map_or(key.len(), |(idx, _)| idx);
+
+        let rest = &line[separator_idx + 1..];
+        let value = rest.trim_start().trim_start_matches(['"', '\'']);
+        let value_start = line.len() - value.len();
+        let value = value
+            .split(|c: char| !(c.is_alphanumeric() || c == '-' || c == '_'))
+            .next()
+            .unwrap_or_default();
+        pairs.push((&key[key_start..], value_start, value));
+    }
+    pairs
+}
+
+/// Converts a byte range of a line into an LSP range.
+pub(crate) fn line_range(line: &str, line_idx: usize, bytes: std::ops::Range<usize>) -> Range {
+    let column = |byte_idx: usize| line[..byte_idx].encode_utf16().count() as u32;
+    Range {
+        start: Position {
+            line: line_idx as u32,
+            character: column(bytes.start),
+        },
+        end: Position {
+            line: line_idx as u32,
+            character: column(bytes.end),
+        },
+    }
+}
+
+/// Parses a hex color without `#`, or a CSS named color.
+fn bare_color(value: &str) -> Option<Color> {
+    if matches!(value.len(), 6 | 8) && value.chars().all(|c| c.is_ascii_hexdigit()) {
+        return parse_line_colors(&format!("#{value}"), 0)
+            .first()
+            .map(|color_info| color_info.color);
+    }
+    named::lookup(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use tower_lsp_server::ls_types::Uri;
+
+    use crate::color::to_hex;
+    use crate::heuristics::{KeyPatterns, Syntax, find_key_values};
+    use crate::schema::JsonSchema;
+
+    fn values(line: &str) -> Vec<(u32, u32, String)> {
+        find_key_values(line, 0, &KeyPatterns::default())
+            .iter()
+            .map(|c| {
+                let range = c.range;
+                (range.start.character, range.end.character, to_hex(&c.color))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn yaml_and_toml_values() {
+        assert_eq!(values("  fg: 1a73e8"), [(6, 12, "#1A73E8".to_string())]);
+        assert_eq!(
+            values("background_color = \"red\""),
+            [(20, 23, "#FF0000".to_string())]
+        );
+        assert_eq!(values("accent: '#1a73e8'"), []);
+        assert_eq!(values("name: red"), []);
+    }
+
+    #[test]
+    fn inline_tables() {
+        assert_eq!(
+            values("\"ui.text\" = { fg = \"white\", bg = \"282828\" }"),
+            [
+                (20, 25, "#FFFFFF".to_string()),
+                (34, 40, "#282828".to_string())
+            ]
+        );
+    }
+
+    #[test]
+    fn glob_patterns() {
+        let patterns = KeyPatterns::default();
+        assert!(patterns.matches("palette.Border_Color"));
+        assert!(patterns.matches("ui.bg"));
+        assert!(!patterns.matches("bg_image"));
+    }
+
+    #[test]
+    fn detects_syntax() {
+        let uri: Uri = "file:///app.toml".parse().unwrap();
+        assert_eq!(Syntax::detect("plaintext", &uri, ""), Syntax::KeyValue);
+        let uri: Uri = "file:///home/u/.config/starship.toml".parse().unwrap();
+        assert_eq!(Syntax::detect("toml", &uri, ""), Syntax::Starship);
+        let uri: Uri = "file:///home/u/.config/helix/themes/gruvbox.toml"
+            .parse()
+            .unwrap();
+        assert_eq!(Syntax::detect("toml", &uri, ""), Syntax::Helix);
+        let uri: Uri = "file:///home/u/.config/zellij/themes/dracula.kdl"
+            .parse()
+            .unwrap();
+        assert_eq!(Syntax::detect("kdl", &uri, ""), Syntax::Zellij);
+        let uri: Uri = "file:///home/u/.config/ghostty/config".parse().unwrap();
+        assert_eq!(Syntax::detect("plaintext", &uri, ""), Syntax::Ghostty);
+        let uri: Uri = "file:///themes/dark.color-theme.json".parse().unwrap();
+        assert_eq!(
+            Syntax::detect("json", &uri, "{}"),
+            Syntax::Json(JsonSchema::VsCodeTheme)
+        );
+        let uri: Uri = "file:///Packages/Monokai.tmTheme".parse().unwrap();
+        assert_eq!(Syntax::detect("xml", &uri, ""), Syntax::TmTheme);
+        let uri: Uri = "file:///home/u/.config/gtk-4.0/gtk.css".parse().unwrap();
+        assert_eq!(Syntax::detect("css", &uri, ""), Syntax::Gtk);
+        let uri: Uri = "file:///styles/_variables.scss".parse().unwrap();
+        assert_eq!(Syntax::detect("scss", &uri, ""), Syntax::Scss);
+        let uri: Uri = "file:///rtl/palette.vhd".parse().unwrap();
+        assert_eq!(Syntax::detect("plaintext", &uri, ""), Syntax::PackedHex);
+        let uri: Uri = "file:///app/src/main/res/layout/main.xml".parse().unwrap();
+        assert_eq!(Syntax::detect("xml", &uri, ""), Syntax::Android);
+        let uri: Uri = "file:///App/Assets.xcassets/Brand.colorset/Contents.json"
+            .parse()
+            .unwrap();
+        assert_eq!(Syntax::detect("json", &uri, "{}"), Syntax::ColorSet);
+        let uri: Uri = "file:///lib/theme.dart".parse().unwrap();
+        assert_eq!(Syntax::detect("dart", &uri, ""), Syntax::ColorConstructor);
+        let uri: Uri = "file:///Assets/Materials/Water.mat".parse().unwrap();
+        assert_eq!(Syntax::detect("yaml", &uri, ""), Syntax::Unity);
+        let uri: Uri = "file:///Game/Config/DefaultEngine.ini".parse().unwrap();
+        assert_eq!(Syntax::detect("ini", &uri, ""), Syntax::Unreal);
+        let uri: Uri = "file:///theme.css".parse().unwrap();
+        assert_eq!(Syntax::detect("css", &uri, ""), Syntax::Plain);
+        assert_eq!(Syntax::detect("yaml", &uri, ""), Syntax::KeyValue);
+    }
+}
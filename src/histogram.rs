@@ -0,0 +1,98 @@
+use std::collections::BTreeMap;
+
+use serde::Serialize;
+use tower_lsp_server::ls_types::Color;
+
+use crate::colorspace::to_hsl;
+
+/// Width of a hue bucket in degrees.
+pub const HUE_BUCKET_SIZE: u16 = 30;
+/// Number of lightness bands in `[0, 1]`.
+pub const LIGHTNESS_BUCKETS: u8 = 5;
+/// Colors with saturation below this are counted as achromatic.
+const ACHROMATIC_SATURATION: f32 = 0.1;
+
+/// Color frequencies bucketed by hue and lightness.
+#[derive(Debug, Default, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ColorHistogram {
+    pub total: usize,
+    /// Non-empty buckets, ordered by hue and then lightness.
+    pub buckets: Vec<HistogramBucket>,
+}
+
+#[derive(Debug, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct HistogramBucket {
+    /// Start of the hue range in degrees, or `None` for achromatic colors.
+    pub hue: Option<u16>,
+    /// Index of the lightness band, from darkest to lightest.
+    pub lightness: u8,
+    pub count: usize,
+}
+
+impl<'a> FromIterator<&'a Color> for ColorHistogram {
+    fn from_iter<I: IntoIterator<Item = &'a Color>>(colors: I) -> Self {
+        let mut counts: BTreeMap<(Option<u16>, u8), usize> = BTreeMap::new();
+        let mut total = 0;
+        for color in colors {
+            let (hue, saturation, lightness) = to_hsl(color);
+            let hue = (saturation >= ACHROMATIC_SATURATION)
+                .then(|| (hue as u16 % 360) / HUE_BUCKET_SIZE * HUE_BUCKET_SIZE);
+            let band = ((lightness * LIGHTNESS_BUCKETS as f32) as u8).min(LIGHTNESS_BUCKETS - 1);
+            *counts.entry((hue, band)).or_default() += 1;
+            total += 1;
+        }
+
+        Self {
+            total,
+            buckets: counts
+                .into_iter()
+                .map(|((hue, lightness), count)| HistogramBucket {
+                    hue,
+                    lightness,
+                    count,
+                })
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::color::parse_line_colors;
+    use crate::histogram::{ColorHistogram, HistogramBucket};
+
+    #[test]
+    fn buckets_by_hue_and_lightness() {
+        let colors = parse_line_colors("#FF0000 #EE0000 #000000 #FFFFFF #0000FF", 0);
+        let histogram: ColorHistogram = colors.iter().map(|c| &c.color).collect();
+
+        assert_eq!(histogram.total, 5);
+        assert_eq!(
+            histogram.buckets,
+            [
+                HistogramBucket {
+                    hue: None,
+                    lightness: 0,
+                    count: 1
+                },
+                HistogramBucket {
+                    hue: None,
+                    lightness: 4,
+                    count: 1
+                },
+                HistogramBucket {
+                    hue: Some(0),
+                    lightness: 2,
+                    count: 2
+                },
+                HistogramBucket {
+                    hue: Some(240),
+                    lightness: 2,
+                    count: 1
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,91 @@
+//! Details of a color shown on hover, for editors without a color picker.
+
+use tower_lsp_server::ls_types::Color;
+
+use crate::color::to_hex;
+use crate::composite::{Backgrounds, composite};
+use crate::contrast::apca_contrast;
+use crate::named;
+use crate::presentation::{css_hsl, css_oklch, css_rgb};
+
+/// Renders a color as Markdown: its value in hex, `rgb()`, `hsl()` and
+/// `oklch()`, its alpha and the nearest CSS named color, and for translucent
+/// colors the result over each of `backgrounds`. With `apca`, adds the APCA
+/// contrast of the color as text over each of `backgrounds`.
+pub fn color_details(color: &Color, backgrounds: &Backgrounds, apca: bool) -> String {
+    let hex = to_hex(color);
+    let alpha = (color.alpha.clamp(0.0, 1.0) * 1000.0).round() / 1000.0;
+    let nearest = match named::name_of(color) {
+        Some(name) => format!("`{name}`"),
+        None => {
+            let (name, named_color) = named::nearest(color);
+            format!("`{name}` (`{}`)", to_hex(&named_color))
+        }
+    };
+    let mut details = format!(
+        "**`{hex}`**\n\n\
+         | | |\n\
+         |---|---|\n\
+         | Hex | `{hex}` |\n\
+         | RGB | `{}` |\n\
+         | HSL | `{}` |\n\
+         | OKLCH | `{}` |\n\
+         | Alpha | `{alpha}` |\n\
+         | Nearest named | {nearest} |",
+        css_rgb(color, ""),
+        css_hsl(color, ""),
+        css_oklch(color),
+    );
+    if color.alpha < 1.0 {
+        for background in backgrounds.iter() {
+            details.push_str(&format!(
+                "\n| Over `{}` | `{}` |",
+                to_hex(background),
+                to_hex(&composite(color, background))
+            ));
+        }
+    }
+    if apca {
+        for background in backgrounds.iter() {
+            let contrast = apca_contrast(&composite(color, background), background);
+            details.push_str(&format!(
+                "\n| APCA on `{}` | `Lc {contrast:.1}` |",
+                to_hex(background)
+            ));
+        }
+    }
+    details
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::color::parse_line_colors;
+    use crate::composite::Backgrounds;
+    use crate::hover::color_details;
+
+    #[test]
+    fn shows_conversions_and_nearest_name() {
+        let color = parse_line_colors("#1A73E880", 0)[0].color;
+        assert_eq!(
+            color_details(&color, &Backgrounds::default(), false),
+            "**`#1A73E880`**\n\n\
+             | | |\n\
+             |---|---|\n\
+             | Hex | `#1A73E880` |\n\
+             | RGB | `rgb(26 115 232 / 0.502)` |\n\
+             | HSL | `hsl(214.1 81.7% 50.6% / 0.502)` |\n\
+             | OKLCH | `oklch(57.37% 0.1946 257.86 / 0.502)` |\n\
+             | Alpha | `0.502` |\n\
+             | Nearest named | `dodgerblue` (`#1E90FF`) |\n\
+             | Over `#FFFFFF` | `#8CB9F3` |"
+        );
+
+        let red = parse_line_colors("#FF0000", 0)[0].color;
+        let details = color_details(&red, &Backgrounds::default(), false);
+        assert!(details.ends_with("| Nearest named | `red` |"));
+
+        let gray = parse_line_colors("#888888", 0)[0].color;
+        let details = color_details(&gray, &Backgrounds::default(), true);
+        assert!(details.ends_with("| APCA on `#FFFFFF` | `Lc 63.1` |"));
+    }
+}
@@ -0,0 +1,120 @@
+//! Names of hex colors shown after them, for `textDocument/inlayHint`.
+
+use serde::{Deserialize, Serialize};
+use tower_lsp_server::ls_types::{
+    InlayHint, InlayHintLabel, InlayHintTooltip, MarkupContent, MarkupKind, Range,
+};
+
+use crate::color::{ColorFormat, parse_line_colors, to_hex};
+use crate::document::Document;
+use crate::named;
+
+/// Data of a color name hint, resolved into its tooltip.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct NameHintData {
+    hex: String,
+}
+
+/// Returns a hint after each hex color of `range` with the CSS named color
+/// it matches, or the closest one marked with `~`.
+pub fn hints(document: &Document, range: &Range) -> Vec<InlayHint> {
+    document
+        .get_matches_in_range(range)
+        .into_iter()
+        .filter(|color_match| color_match.format == ColorFormat::Hex)
+        .filter(|color_match| {
+            document
+                .range_text(&color_match.info.range)
+                .is_some_and(|text| text.starts_with('#'))
+        })
+        .map(|color_match| {
+            let color = color_match.info.color;
+            let label = match named::name_of(&color) {
+                Some(name) => format!("⟵ {name}"),
+                None => format!("⟵ ~{}", named::nearest(&color).0),
+            };
+            let data = NameHintData {
+                hex: to_hex(&color),
+            };
+            InlayHint {
+                position: color_match.info.range.end,
+                label: InlayHintLabel::String(label),
+                kind: None,
+                text_edits: None,
+                tooltip: None,
+                padding_left: Some(true),
+                padding_right: None,
+                data: serde_json::to_value(data).ok(),
+            }
+        })
+        .collect()
+}
+
+/// Adds the tooltip of a hint, telling exact names from the closest ones.
+pub fn resolve(mut hint: InlayHint) -> InlayHint {
+    let Some(data) = hint
+        .data
+        .take()
+        .and_then(|data| serde_json::from_value::<NameHintData>(data).ok())
+    else {
+        return hint;
+    };
+    let Some(color) = parse_line_colors(&data.hex, 0)
+        .into_iter()
+        .next()
+        .map(|color_info| color_info.color)
+    else {
+        return hint;
+    };
+    let value = match named::name_of(&color) {
+        Some(name) => format!("`{}` is the CSS named color `{name}`.", data.hex),
+        None => {
+            let (name, nearest) = named::nearest(&color);
+            format!(
+                "`{}` is closest to the CSS named color `{name}` (`{}`).",
+                data.hex,
+                to_hex(&nearest)
+            )
+        }
+    };
+    hint.tooltip = Some(InlayHintTooltip::MarkupContent(MarkupContent {
+        kind: MarkupKind::Markdown,
+        value,
+    }));
+    hint
+}
+
+#[cfg(test)]
+mod tests {
+    use tower_lsp_server::ls_types::{InlayHintLabel, InlayHintTooltip, Position, Range};
+
+    use crate::document::Document;
+    use crate::inlay_hint::{hints, resolve};
+
+    #[test]
+    fn names_hex_colors() {
+        let document = Document::from("a: #DC143C;\nb: #DC143D;\nc: rgb(0 0 0);\n");
+        let range = Range::new(Position::new(0, 0), Position::new(3, 0));
+        let hints = hints(&document, &range);
+        let labels: Vec<_> = hints
+            .iter()
+            .map(|hint| match &hint.label {
+                InlayHintLabel::String(label) => (hint.position.line, label.as_str()),
+                InlayHintLabel::LabelParts(_) => unreachable!(),
+            })
+            .collect();
+        assert_eq!(labels, [(0, "⟵ crimson"), (1, "⟵ ~crimson")]);
+        assert_eq!(hints[0].position, Position::new(0, 10));
+
+        let resolved = resolve(hints[1].clone());
+        let Some(InlayHintTooltip::MarkupContent(tooltip)) = resolved.tooltip else {
+            panic!("unresolved hint");
+        };
+        assert_eq!(
+            tooltip.value,
+            "`#DC143D` is closest to the CSS named color `crimson` (`#DC143C`)."
+        );
+        assert_eq!(resolved.data, None);
+    }
+}
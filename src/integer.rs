@@ -0,0 +1,197 @@
+//! `0x` integer color literals of game and embedded code, e.g. `0xFF00FF` or
+//! `0xFF112233`, whose channel order depends on the framework.
+
+use serde::Deserialize;
+use tower_lsp_server::ls_types::{Color, ColorInformation};
+
+use crate::heuristics::line_range;
+
+/// Order of the channels of an integer color, from the most significant byte.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ChannelOrder {
+    /// `0xRRGGBB`.
+    Rgb,
+    /// `0xAARRGGBB`, or `0xRRGGBB` when opaque.
+    Argb,
+    /// `0xRRGGBBAA`, or `0xRRGGBB` when opaque.
+    Rgba,
+    /// `0xBBGGRR`.
+    Bgr,
+}
+
+impl ChannelOrder {
+    /// Whether literals of `len` digits are colors in this order.
+    fn accepts(self, len: usize) -> bool {
+        match self {
+            ChannelOrder::Rgb | ChannelOrder::Bgr => len == 6,
+            ChannelOrder::Argb | ChannelOrder::Rgba => len == 6 || len == 8,
+        }
+    }
+
+    /// Returns the red, green, blue and alpha bytes of an accepted literal.
+    fn channels(self, bytes: &[u8]) -> [u8; 4] {
+        match (self, bytes) {
+            (ChannelOrder::Bgr, [blue, green, red]) => [*red, *green, *blue, 255],
+            (ChannelOrder::Argb, [alpha, red, green, blue])
+            | (ChannelOrder::Rgba, [red, green, blue, alpha]) => [*red, *green, *blue, *alpha],
+            (_, [red, green, blue, ..]) => [*red, *green, *blue, 255],
+            _ => [0, 0, 0, 255],
+        }
+    }
+}
+
+/// Finds `0x` literals of 6 or 8 hex digits read in `order`, ignoring
+/// integer suffixes such as `u`, `UL` or `_u32`. Ranges cover the prefix and
+/// digits.
+pub fn find(line: &str, line_idx: usize, order: ChannelOrder) -> Vec<ColorInformation> {
+    let is_word_char = |c: char| c.is_alphanumeric() || c == '_';
+    line.match_indices('0')
+        .filter_map(|(start, _)| {
+            if line[..start].chars().next_back().is_some_and(is_word_char) {
+                return None;
+            }
+            let digits = line[start + 1..]
+                .strip_prefix(['x', 'X'])?
+                .split(|c: char| !c.is_ascii_hexdigit())
+                .next()?;
+            let end = start + 2 + digits.len();
+            let suffix = line[end..]
+                .split(|c: char| !is_word_char(c))
+                .next()
+                .unwrap_or_default();
+            if !order.accepts(digits.len()) || !is_integer_suffix(suffix) {
+                return None;
+            }
+            let bytes: Vec<u8> = (0..digits.len())
+                .step_by(2)
+                .map(|idx| u8::from_str_radix(&digits[idx..idx + 2], 16))
+                .collect::<Result<_, _>>()
+                .ok()?;
+            let [red, green, blue, alpha] = order.channels(&bytes);
+            let channel = |value: u8| value as f32 / 255.0;
+            Some(ColorInformation {
+                range: line_range(line, line_idx, start..end),
+                color: Color {
+                    red: channel(red),
+                    green: channel(green),
+                    blue: channel(blue),
+                    alpha: channel(alpha),
+                },
+            })
+        })
+        .collect()
+}
+
+/// Whether `suffix` is empty or a C, C++, C# or Rust integer suffix.
+fn is_integer_suffix(suffix: &str) -> bool {
+    let suffix = suffix.strip_prefix('_').unwrap_or(suffix);
+    suffix.is_empty()
+        || ["u", "l", "ul", "lu", "ll", "ull", "llu"]
+            .contains(&suffix.to_ascii_lowercase().as_str())
+        || ["u32", "i32", "u64", "i64", "usize"].contains(&suffix)
+}
+
+/// Formats `color` as a literal in `order`, keeping the prefix, digit count
+/// and digit case of `existing`. Translucent colors get alpha digits when the
+/// order has them.
+pub fn format(color: &Color, order: ChannelOrder, existing: &str) -> String {
+    let byte = |value: f32| (value.clamp(0.0, 1.0) * 255.0).round() as u8;
+    let (red, green, blue, alpha) = (
+        byte(color.red),
+        byte(color.green),
+        byte(color.blue),
+        byte(color.alpha),
+    );
+    let digits = existing.get(2..).unwrap_or_default();
+    let with_alpha = digits.len() == 8 || alpha < 255;
+    let bytes = match order {
+        ChannelOrder::Bgr => vec![blue, green, red],
+        ChannelOrder::Argb if with_alpha => vec![alpha, red, green, blue],
+        ChannelOrder::Rgba if with_alpha => vec![red, green, blue, alpha],
+        _ => vec![red, green, blue],
+    };
+    let lowercase = digits.chars().any(|c| c.is_ascii_lowercase())
+        && !digits.chars().any(|c| c.is_ascii_uppercase());
+    let hex: String = bytes
+        .iter()
+        .map(|byte| {
+            if lowercase {
+                format!("{byte:02x}")
+            } else {
+                format!("{byte:02X}")
+            }
+        })
+        .collect();
+    let prefix = if existing.starts_with("0X") {
+        "0X"
+    } else {
+        "0x"
+    };
+    format!("{prefix}{hex}")
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::color::to_hex;
+    use crate::integer::{ChannelOrder, find, format};
+
+    fn found(line: &str, order: ChannelOrder) -> Vec<(u32, u32, String)> {
+        find(line, 0, order)
+            .iter()
+            .map(|info| {
+                (
+                    info.range.start.character,
+                    info.range.end.character,
+                    to_hex(&info.color),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn reads_channel_orders() {
+        let line = "tint(0xFF112233); fill(0xff00ff, 0x80FF0000u);";
+        assert_eq!(
+            found(line, ChannelOrder::Argb),
+            [
+                (5, 15, "#112233".to_string()),
+                (23, 31, "#FF00FF".to_string()),
+                (33, 43, "#FF000080".to_string()),
+            ]
+        );
+        assert_eq!(
+            found(line, ChannelOrder::Rgba),
+            [
+                (5, 15, "#FF112233".to_string()),
+                (23, 31, "#FF00FF".to_string()),
+                (33, 43, "#80FF0000".to_string()),
+            ]
+        );
+        assert_eq!(
+            found("RGB(0x0000FF_u32) 0x0000ff", ChannelOrder::Bgr),
+            [
+                (4, 12, "#FF0000".to_string()),
+                (18, 26, "#FF0000".to_string())
+            ]
+        );
+        assert!(
+            found(
+                "mask & 0xFF; 0xFFFF; x0xFF00FF; 0xFF00FFz",
+                ChannelOrder::Rgb
+            )
+            .is_empty()
+        );
+    }
+
+    #[test]
+    fn formats_in_channel_order() {
+        let color = find("0x80FF0000", 0, ChannelOrder::Argb)[0].color;
+        assert_eq!(
+            format(&color, ChannelOrder::Argb, "0x00000000"),
+            "0x80FF0000"
+        );
+        assert_eq!(format(&color, ChannelOrder::Rgba, "0Xffffff"), "0Xff000080");
+        assert_eq!(format(&color, ChannelOrder::Bgr, "0xFFFFFF"), "0x0000FF");
+    }
+}
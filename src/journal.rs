@@ -0,0 +1,218 @@
+//! Recent activity of the server, written out by `chroma.dumpState` so bug
+//! reports carry the settings, documents, timings and errors involved.
+
+use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+use serde::Serialize;
+use serde_json::Value;
+use tower_lsp_server::ls_types::Uri;
+
+use crate::output::Versioned;
+use crate::status::DocumentStatus;
+
+/// Most requests and errors kept, the oldest dropped first.
+pub const MAX_ENTRIES: usize = 50;
+
+/// A handled request and how long it took.
+#[derive(Debug, Clone, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct RequestTiming {
+    pub method: &'static str,
+    pub uri: Option<Uri>,
+    /// Duration in microseconds.
+    pub duration: u64,
+    /// Milliseconds since the Unix epoch when the request finished.
+    pub time: u64,
+}
+
+/// An error reported to the client.
+#[derive(Debug, Clone, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ErrorEntry {
+    pub message: String,
+    /// Milliseconds since the Unix epoch.
+    pub time: u64,
+}
+
+#[derive(Debug, Default)]
+struct Entries {
+    config: Value,
+    requests: VecDeque<RequestTiming>,
+    errors: VecDeque<ErrorEntry>,
+}
+
+/// The settings last sent by the client, and the latest requests and errors.
+#[derive(Debug, Default)]
+pub struct Journal {
+    entries: Mutex<Entries>,
+}
+
+impl Journal {
+    /// Keeps the settings as sent, including ones that failed to parse.
+    pub fn set_config(&self, config: Value) {
+        self.lock().config = config;
+    }
+
+    pub fn record_error(&self, message: String) {
+        push(
+            &mut self.lock().errors,
+            ErrorEntry {
+                message,
+                time: unix_millis(),
+            },
+        );
+    }
+
+    /// Starts timing a request, recorded once the returned guard is dropped.
+    pub fn time(&self, method: &'static str, uri: Option<&Uri>) -> Timing<'_> {
+        Timing {
+            journal: self,
+            method,
+            uri: uri.cloned(),
+            start: Instant::now(),
+        }
+    }
+
+    /// Returns a snapshot of the journal with the given open documents and
+    /// workspace folders.
+    pub fn dump(&self, documents: Vec<DocumentStatus>, workspace_folders: Vec<Uri>) -> StateDump {
+        let entries = self.lock();
+        StateDump {
+            server_version: env!("CARGO_PKG_VERSION"),
+            time: unix_millis(),
+            config: entries.config.clone(),
+            workspace_folders,
+            documents,
+            recent_requests: entries.requests.iter().cloned().collect(),
+            recent_errors: entries.errors.iter().cloned().collect(),
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Entries> {
+        self.entries
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+/// Times a request until dropped.
+pub struct Timing<'a> {
+    journal: &'a Journal,
+    method: &'static str,
+    uri: Option<Uri>,
+    start: Instant,
+}
+
+impl Drop for Timing<'_> {
+    fn drop(&mut self) {
+        let timing = RequestTiming {
+            method: self.method,
+            uri: self.uri.take(),
+            duration: u64::try_from(self.start.elapsed().as_micros()).unwrap_or(u64::MAX),
+            time: unix_millis(),
+        };
+        tracing::debug!(
+            method = timing.method,
+            uri = timing.uri.as_ref().map(|uri| uri.as_str()),
+            duration_us = timing.duration,
+            "handled request"
+        );
+        push(&mut self.journal.lock().requests, timing);
+    }
+}
+
+/// Snapshot written by `chroma.dumpState`. Document text is left out.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StateDump {
+    pub server_version: &'static str,
+    /// Milliseconds since the Unix epoch.
+    pub time: u64,
+    /// Settings as last sent by the client.
+    pub config: Value,
+    pub workspace_folders: Vec<Uri>,
+    pub documents: Vec<DocumentStatus>,
+    /// Latest requests, oldest first.
+    pub recent_requests: Vec<RequestTiming>,
+    /// Latest errors, oldest first.
+    pub recent_errors: Vec<ErrorEntry>,
+}
+
+impl StateDump {
+    /// Writes the dump as versioned JSON to a new file in `dir`, with the
+    /// home directory replaced by `~`, and returns its path.
+    pub fn write(&self, dir: &Path, home: Option<&Path>) -> std::io::Result<PathBuf> {
+        let json = serde_json::to_string_pretty(&Versioned::new(self))?;
+        let json = match home.and_then(Path::to_str) {
+            Some(home) if !home.is_empty() => sanitize(&json, home),
+            _ => json,
+        };
+        let path = dir.join(format!(
+            "chroma-ls-state-{}-{}.json",
+            std::process::id(),
+            self.time
+        ));
+        std::fs::write(&path, json)?;
+        Ok(path)
+    }
+}
+
+/// Replaces the paths under `home` in `text` with paths under `~`, as in
+/// `file:///home/me/app.css` or `/home/me/palettes`.
+fn sanitize(text: &str, home: &str) -> String {
+    let home = home.trim_end_matches('/');
+    text.replace(&format!("{home}/"), "~/")
+}
+
+fn push<T>(entries: &mut VecDeque<T>, entry: T) {
+    if entries.len() == MAX_ENTRIES {
+        entries.pop_front();
+    }
+    entries.push_back(entry);
+}
+
+fn unix_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_millis() as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use serde_json::json;
+    use tower_lsp_server::ls_types::Uri;
+
+    use crate::journal::{Journal, MAX_ENTRIES};
+
+    #[test]
+    fn keeps_latest_entries() {
+        let journal = Journal::default();
+        journal.set_config(json!({ "palettes": ["/home/me/palettes/brand.gpl"] }));
+        let uri: Uri = "file:///home/me/app.css".parse().unwrap();
+        for _ in 0..MAX_ENTRIES + 1 {
+            drop(journal.time("textDocument/documentColor", Some(&uri)));
+        }
+        journal.record_error("first".to_string());
+        journal.record_error("second".to_string());
+
+        let dump = journal.dump(Vec::new(), Vec::new());
+        assert_eq!(dump.recent_requests.len(), MAX_ENTRIES);
+        assert_eq!(dump.recent_requests[0].method, "textDocument/documentColor");
+        let errors: Vec<_> = dump.recent_errors.iter().map(|e| &e.message).collect();
+        assert_eq!(errors, ["first", "second"]);
+
+        let dir = std::env::temp_dir();
+        let path = dump.write(&dir, Some(Path::new("/home/me/"))).unwrap();
+        let written = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(path).unwrap();
+        assert!(written.contains("\"schemaVersion\": 1"));
+        assert!(written.contains("\"~/palettes/brand.gpl\""));
+        assert!(written.contains("\"file://~/app.css\""));
+        assert!(!written.contains("/home/me"));
+    }
+}
@@ -1,2 +1,66 @@
+pub mod alias;
+pub mod android;
+pub mod ansi;
+pub mod burst;
+pub mod calc;
+pub mod capabilities;
+pub mod channels;
+pub mod cli;
 pub mod color;
+pub mod colorset;
+pub mod colorspace;
+pub mod completion;
+pub mod composite;
+pub mod conformance;
+pub mod contrast;
+pub mod convert;
+pub mod cvd;
+pub mod delta;
+pub mod desktop;
 pub mod document;
+pub mod duplicates;
+pub mod encoding;
+pub mod error;
+pub mod extract;
+pub mod gradient;
+pub mod harmony;
+pub mod heuristics;
+pub mod histogram;
+pub mod hover;
+pub mod inlay_hint;
+pub mod integer;
+pub mod journal;
+pub mod linked_editing;
+pub mod links;
+pub mod logging;
+pub mod memory;
+pub mod missing;
+pub mod moniker;
+pub mod named;
+pub mod normalize;
+pub mod nudge;
+pub mod output;
+pub mod palette;
+pub mod presentation;
+pub mod profile;
+pub mod protocol;
+pub mod quantize;
+pub mod rope;
+pub mod scan;
+pub mod schema;
+pub mod server;
+pub mod settings;
+pub mod status;
+pub mod stylesheet;
+pub mod svg;
+pub mod symbols;
+pub mod tailwind;
+pub mod terminal_theme;
+pub mod theme_object;
+pub mod timing;
+pub mod tokens;
+pub mod transport;
+pub mod unknown;
+pub mod unused;
+pub mod variables;
+pub mod workspace;
@@ -0,0 +1,57 @@
+//! Linked editing of identical hex colors, for
+//! `textDocument/linkedEditingRange`.
+
+use tower_lsp_server::ls_types::{Position, Range};
+
+use crate::color::ColorFormat;
+use crate::document::Document;
+
+/// Text linked ranges may hold while being edited.
+pub const WORD_PATTERN: &str = "#[0-9A-Fa-f]{0,8}";
+
+/// Returns the ranges of the hex colors of a document spelled exactly like
+/// the one at `position`, or `None` if it has no other occurrence.
+pub fn ranges(document: &Document, position: Position) -> Option<Vec<Range>> {
+    let hexes: Vec<(Range, &str)> = document
+        .matches()
+        .filter(|color_match| color_match.format == ColorFormat::Hex)
+        .filter_map(|color_match| {
+            let range = color_match.info.range;
+            let text = document.range_text(&range)?;
+            text.starts_with('#').then_some((range, text))
+        })
+        .collect();
+    let (_, text) = hexes
+        .iter()
+        .find(|(range, _)| range.start <= position && position <= range.end)?;
+    let ranges: Vec<Range> = hexes
+        .iter()
+        .filter(|(_, other)| other == text)
+        .map(|(range, _)| *range)
+        .collect();
+    (ranges.len() > 1).then_some(ranges)
+}
+
+#[cfg(test)]
+mod tests {
+    use tower_lsp_server::ls_types::Position;
+
+    use crate::document::Document;
+    use crate::linked_editing::ranges;
+
+    #[test]
+    fn links_identical_hex_colors() {
+        let document = Document::from("a: #1A73E8;\nb: #1a73e8;\nc: #1A73E8;\nd: rgb(0 0 0);\n");
+        let at = |line, character| ranges(&document, Position { line, character });
+
+        let linked: Vec<_> = at(0, 5)
+            .unwrap()
+            .iter()
+            .map(|range| (range.start.line, range.start.character, range.end.character))
+            .collect();
+        assert_eq!(linked, [(0, 3, 10), (2, 3, 10)]);
+        // Spelled differently, so not linked.
+        assert_eq!(at(1, 5), None);
+        assert_eq!(at(3, 5), None);
+    }
+}
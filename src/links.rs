@@ -0,0 +1,194 @@
+//! Document links: stylesheet `@import`, `@use` and `@forward` paths, and
+//! link targets pointing at a position in a file.
+
+use std::path::{Component, Path, PathBuf};
+
+use tower_lsp_server::ls_types::{Location, Range, Uri};
+
+use crate::heuristics::line_range;
+
+/// Extensions of stylesheets that imports may leave out.
+const STYLESHEET_EXTENSIONS: [&str; 4] = ["scss", "sass", "less", "css"];
+
+/// At-rules whose quoted arguments are paths of other stylesheets.
+const IMPORT_RULES: [&str; 3] = ["@import", "@use", "@forward"];
+
+/// Returns a link target opening `location` at its start. The position is a
+/// 1-based `#L<line>,<column>` fragment, as understood by editors that open
+/// links themselves.
+pub fn target(location: &Location) -> Option<Uri> {
+    let start = location.range.start;
+    format!(
+        "{}#L{},{}",
+        location.uri.as_str(),
+        start.line + 1,
+        start.character + 1
+    )
+    .parse()
+    .ok()
+}
+
+/// Returns the quoted paths of an import rule in a line, e.g. `@import
+/// "colors", url("theme.css");` or `@use "palette" as p;`, with the ranges of
+/// the paths without quotes.
+pub fn imports(line: &str, line_idx: usize) -> Vec<(Range, &str)> {
+    let rest = line.trim_start();
+    let Some(rule) = IMPORT_RULES.iter().find(|rule| {
+        rest.strip_prefix(**rule)
+            .is_some_and(|rest| rest.starts_with(char::is_whitespace))
+    }) else {
+        return Vec::new();
+    };
+    let args_start = line.len() - rest.len() + rule.len();
+    let args_end = line[args_start..]
+        .find(';')
+        .map_or(line.len(), |idx| args_start + idx);
+
+    let mut imports = Vec::new();
+    let mut idx = args_start;
+    while let Some(offset) = line[idx..args_end].find(['"', '\'']) {
+        let quote_idx = idx + offset;
+        let quote = &line[quote_idx..quote_idx + 1];
+        let path_start = quote_idx + 1;
+        let Some(path_len) = line[path_start..args_end].find(quote) else {
+            break;
+        };
+        let path_end = path_start + path_len;
+        if path_len > 0 {
+            imports.push((
+                line_range(line, line_idx, path_start..path_end),
+                &line[path_start..path_end],
+            ));
+        }
+        idx = path_end + 1;
+    }
+    imports
+}
+
+/// Resolves an imported path against the directory of the importing
+/// document to the first of its candidates that exists.
+pub fn resolve_import(uri: &Uri, import: &str) -> Option<Uri> {
+    import_candidates(uri, import)
+        .into_iter()
+        .find(|candidate| candidate.is_file())
+        .and_then(Uri::from_file_path)
+}
+
+/// Returns the files an imported path may refer to, relative to the
+/// directory of the importing document. Paths without a stylesheet
+/// extension are tried with the document's extension, then the others, as
+/// Sass partials, e.g. `colors` as `_colors.scss`, and as index files of a
+/// directory.
+pub fn import_candidates(uri: &Uri, import: &str) -> Vec<PathBuf> {
+    if import.contains("://") || import.starts_with("sass:") || import.starts_with('~') {
+        return Vec::new();
+    }
+    let Some(document) = uri.to_file_path() else {
+        return Vec::new();
+    };
+    let Some(dir) = document.parent() else {
+        return Vec::new();
+    };
+    let mut path = PathBuf::new();
+    for component in dir.join(import).components() {
+        match component {
+            Component::ParentDir => {
+                path.pop();
+            }
+            Component::CurDir => {}
+            component => path.push(component),
+        }
+    }
+    let Some(file_name) = path.file_name().and_then(|name| name.to_str()) else {
+        return Vec::new();
+    };
+    let file_name = file_name.to_string();
+
+    let mut candidates = vec![path.clone()];
+    let has_extension = Path::new(import)
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .is_some_and(|extension| STYLESHEET_EXTENSIONS.contains(&extension));
+    if !has_extension {
+        let own = document
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .filter(|extension| STYLESHEET_EXTENSIONS.contains(extension));
+        for extension in own.into_iter().chain(STYLESHEET_EXTENSIONS) {
+            for candidate in [
+                path.with_file_name(format!("{file_name}.{extension}")),
+                path.with_file_name(format!("_{file_name}.{extension}")),
+            ] {
+                if !candidates.contains(&candidate) {
+                    candidates.push(candidate);
+                }
+            }
+        }
+        for index in ["_index.scss", "index.scss", "_index.sass", "index.sass"] {
+            candidates.push(path.join(index));
+        }
+    }
+    candidates.push(path.with_file_name(format!("_{file_name}")));
+    candidates
+}
+
+#[cfg(test)]
+mod tests {
+    use tower_lsp_server::ls_types::{Location, Position, Range};
+
+    use std::path::PathBuf;
+
+    use crate::links::{import_candidates, imports, target};
+
+    #[test]
+    fn finds_imports() {
+        let paths: Vec<_> = imports(r#"@import "colors", url('theme.css');"#, 0)
+            .into_iter()
+            .map(|(range, path)| (range.start.character, path))
+            .collect();
+        assert_eq!(paths, [(9, "colors"), (23, "theme.css")]);
+        assert_eq!(imports(r#"  @use "palette" as p;"#, 0)[0].1, "palette");
+        assert!(imports(r#"@importer "x";"#, 0).is_empty());
+    }
+
+    #[test]
+    fn import_candidate_files() {
+        let uri = "file:///app/styles/main.scss".parse().unwrap();
+        let candidates = import_candidates(&uri, "../base/colors");
+        assert_eq!(
+            candidates[..3],
+            [
+                PathBuf::from("/app/base/colors"),
+                PathBuf::from("/app/base/colors.scss"),
+                PathBuf::from("/app/base/_colors.scss"),
+            ]
+        );
+        assert!(candidates.contains(&PathBuf::from("/app/base/colors/_index.scss")));
+        assert_eq!(
+            import_candidates(&uri, "print.css"),
+            [
+                PathBuf::from("/app/styles/print.css"),
+                PathBuf::from("/app/styles/_print.css"),
+            ]
+        );
+        assert!(import_candidates(&uri, "sass:math").is_empty());
+    }
+
+    #[test]
+    fn targets_positions() {
+        let location = Location {
+            uri: "file:///design/colors.tokens.json".parse().unwrap(),
+            range: Range {
+                start: Position {
+                    line: 3,
+                    character: 4,
+                },
+                end: Position::default(),
+            },
+        };
+        assert_eq!(
+            target(&location).unwrap().as_str(),
+            "file:///design/colors.tokens.json#L4,5"
+        );
+    }
+}
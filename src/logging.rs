@@ -0,0 +1,92 @@
+//! Logging of requests, parse timings and errors with `tracing`, to stderr or
+//! a file, at the level given on the command line and raised by
+//! `$/setTrace`.
+
+use std::fs::OpenOptions;
+use std::path::Path;
+use std::sync::{Mutex, OnceLock};
+
+use tower_lsp_server::ls_types::TraceValue;
+use tracing::level_filters::LevelFilter;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{Registry, fmt, reload};
+
+/// Level logged without `--log-level`.
+pub const DEFAULT_LEVEL: LevelFilter = LevelFilter::WARN;
+
+struct Logger {
+    /// Level given on the command line, which `$/setTrace` never lowers.
+    level: LevelFilter,
+    handle: reload::Handle<LevelFilter, Registry>,
+}
+
+static LOGGER: OnceLock<Logger> = OnceLock::new();
+
+/// Installs the global logger, appending to `file` if given and writing to
+/// stderr otherwise, since stdout may carry the protocol.
+pub fn init(level: LevelFilter, file: Option<&Path>) -> std::io::Result<()> {
+    let (filter, handle) = reload::Layer::new(level);
+    let registry = tracing_subscriber::registry().with(filter);
+    match file {
+        Some(path) => {
+            let file = OpenOptions::new().create(true).append(true).open(path)?;
+            registry
+                .with(fmt::layer().with_writer(Mutex::new(file)))
+                .init();
+        }
+        None => registry
+            .with(fmt::layer().with_writer(std::io::stderr))
+            .init(),
+    }
+    let _ = LOGGER.set(Logger { level, handle });
+    Ok(())
+}
+
+/// Applies the trace value of `initialize` or `$/setTrace`. Does nothing
+/// before `init`, as in tests.
+pub fn set_trace(value: TraceValue) {
+    let Some(logger) = LOGGER.get() else {
+        return;
+    };
+    let _ = logger.handle.reload(traced_level(logger.level, value));
+}
+
+/// Returns the level logged with a trace value: `debug` for `messages` and
+/// `trace` for `verbose`, or `level` if more verbose.
+fn traced_level(level: LevelFilter, value: TraceValue) -> LevelFilter {
+    let traced = match value {
+        TraceValue::Off => LevelFilter::OFF,
+        TraceValue::Messages => LevelFilter::DEBUG,
+        TraceValue::Verbose => LevelFilter::TRACE,
+    };
+    level.max(traced)
+}
+
+#[cfg(test)]
+mod tests {
+    use tower_lsp_server::ls_types::TraceValue;
+    use tracing::level_filters::LevelFilter;
+
+    use crate::logging::traced_level;
+
+    #[test]
+    fn trace_raises_level() {
+        assert_eq!(
+            traced_level(LevelFilter::WARN, TraceValue::Off),
+            LevelFilter::WARN
+        );
+        assert_eq!(
+            traced_level(LevelFilter::WARN, TraceValue::Messages),
+            LevelFilter::DEBUG
+        );
+        assert_eq!(
+            traced_level(LevelFilter::DEBUG, TraceValue::Verbose),
+            LevelFilter::TRACE
+        );
+        assert_eq!(
+            traced_level(LevelFilter::TRACE, TraceValue::Messages),
+            LevelFilter::TRACE
+        );
+    }
+}
@@ -1,102 +1,140 @@
-use std::collections::HashMap;
+use std::path::PathBuf;
+use std::process::ExitCode;
 
-use tokio::sync::RwLock;
-use tower_lsp_server::jsonrpc::{Error, ErrorCode, Result};
-use tower_lsp_server::ls_types::{
-    ColorInformation, ColorProviderCapability, DidChangeTextDocumentParams,
-    DidCloseTextDocumentParams, DidOpenTextDocumentParams, DocumentColorParams, InitializeParams,
-    InitializeResult, ServerCapabilities, ServerInfo, TextDocumentSyncCapability,
-    TextDocumentSyncKind, TextDocumentSyncOptions, Uri,
-};
-use tower_lsp_server::{Client, LanguageServer, LspService, Server};
+use tokio::net::TcpListener;
 
-use chroma_ls::document::Document;
+use chroma_ls::cli::{self, Command, Transport};
+use chroma_ls::color::to_hex;
+use chroma_ls::logging;
+use chroma_ls::output::{FileColors, ScanResults, Versioned};
+use chroma_ls::presentation::Notation;
+use chroma_ls::scan;
+use chroma_ls::transport;
 
-struct Backend {
-    documents: RwLock<HashMap<Uri, Document>>,
-}
-
-impl Backend {
-    fn new(_client: Client) -> Self {
-        Self {
-            documents: RwLock::new(HashMap::new()),
+#[tokio::main]
+async fn main() -> ExitCode {
+    let options = match cli::parse(std::env::args().skip(1)) {
+        Ok(Command::Serve(options)) => options,
+        Ok(Command::Scan {
+            expect_none,
+            json,
+            paths,
+        }) => return scan(expect_none, json, &paths),
+        Ok(Command::Convert {
+            notation,
+            stdout,
+            paths,
+        }) => return convert(notation, stdout, &paths),
+        Ok(Command::Help(usage)) => {
+            println!("{usage}");
+            return ExitCode::SUCCESS;
         }
+        Ok(Command::Version) => {
+            println!("chroma-ls {}", env!("CARGO_PKG_VERSION"));
+            return ExitCode::SUCCESS;
+        }
+        Err(err) => {
+            eprintln!("chroma-ls: {err}\n{}", err.usage());
+            return ExitCode::from(2);
+        }
+    };
+    let level = options.log_level.unwrap_or(logging::DEFAULT_LEVEL);
+    if let Err(err) = logging::init(level, options.log_file.as_deref()) {
+        eprintln!("chroma-ls: can't open log file: {err}");
+        return ExitCode::FAILURE;
     }
-}
-
-impl LanguageServer for Backend {
-    async fn initialize(&self, _: InitializeParams) -> Result<InitializeResult> {
-        Ok(InitializeResult {
-            capabilities: ServerCapabilities {
-                text_document_sync: Some(TextDocumentSyncCapability::Options(
-                    TextDocumentSyncOptions {
-                        open_close: Some(true),
-                        change: Some(TextDocumentSyncKind::INCREMENTAL),
-                        ..Default::default()
-                    },
-                )),
-                color_provider: Some(ColorProviderCapability::Simple(true)),
-                // TODO: support UTF8 and UTF32 position encodings.
-                ..Default::default()
-            },
-            server_info: Some(ServerInfo {
-                name: "chroma-ls".to_string(),
-                version: Some(env!("CARGO_PKG_VERSION").to_string()),
-            }),
-        })
-    }
-
-    async fn shutdown(&self) -> Result<()> {
-        Ok(())
-    }
-
-    async fn did_open(&self, params: DidOpenTextDocumentParams) {
-        let uri = params.text_document.uri;
-        let content = params.text_document.text;
-        let mut documents = self.documents.write().await;
-
-        documents.insert(uri, Document::from(content.as_str()));
+    let result = match options.transport {
+        Transport::Stdio => {
+            transport::stdio().await;
+            Ok(())
+        }
+        Transport::Tcp(addr) => listen(&addr).await,
+        Transport::Socket(path) => transport::socket(&path).await,
+    };
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("chroma-ls: {err}");
+            ExitCode::FAILURE
+        }
     }
+}
 
-    async fn did_change(&self, params: DidChangeTextDocumentParams) {
-        let uri = params.text_document.uri;
-        let mut documents = self.documents.write().await;
+/// Serves clients connecting to `addr`, reporting the address bound, whose
+/// port is picked by the system when `0`.
+async fn listen(addr: &str) -> std::io::Result<()> {
+    let listener = TcpListener::bind(addr).await?;
+    eprintln!("chroma-ls listening on {}", listener.local_addr()?);
+    transport::tcp(listener).await
+}
 
-        // TODO: warn about error.
-        let document = documents
-            .get_mut(&uri)
-            .expect("document must exist on didChange");
-        for change in params.content_changes {
-            document.edit(&change);
+/// Prints the colors found in files as `path:line:column hex`. With
+/// `--expect-none`, finding any color is a failure, so files that must not
+/// contain colors can guard against false positives. With `--json`, prints a
+/// single versioned JSON object instead.
+fn scan(expect_none: bool, json: bool, paths: &[PathBuf]) -> ExitCode {
+    let mut found = 0;
+    let mut results = ScanResults::default();
+    for path in scan::files(paths) {
+        let colors = match scan::scan_file(&path) {
+            Ok(colors) => colors,
+            Err(err) => {
+                eprintln!("{}: {err}", path.display());
+                return ExitCode::from(2);
+            }
+        };
+        found += colors.len();
+        if json {
+            results.files.push(FileColors::new(&path, &colors));
+            continue;
+        }
+        for color_info in &colors {
+            let start = color_info.range.start;
+            println!(
+                "{}:{}:{} {}",
+                path.display(),
+                start.line + 1,
+                start.character + 1,
+                to_hex(&color_info.color)
+            );
         }
     }
-
-    async fn did_close(&self, params: DidCloseTextDocumentParams) {
-        let uri = params.text_document.uri;
-        let mut documents = self.documents.write().await;
-
-        documents.remove(&uri);
+    if json {
+        match serde_json::to_string_pretty(&Versioned::new(results)) {
+            Ok(output) => println!("{output}"),
+            Err(err) => {
+                eprintln!("{err}");
+                return ExitCode::from(2);
+            }
+        }
     }
-
-    async fn document_color(&self, params: DocumentColorParams) -> Result<Vec<ColorInformation>> {
-        let uri = params.text_document.uri;
-        let documents = self.documents.read().await;
-
-        let document = documents.get(&uri).ok_or_else(|| Error {
-            code: ErrorCode::InternalError,
-            message: format!("Document not found for {} URI", uri.as_str()).into(),
-            data: None,
-        })?;
-        let colors = document.get_colors();
-        Ok(colors)
+    if expect_none && found > 0 {
+        eprintln!("expected no colors, found {found}");
+        return ExitCode::FAILURE;
     }
+    ExitCode::SUCCESS
 }
 
-#[tokio::main]
-async fn main() {
-    let stdin = tokio::io::stdin();
-    let stdout = tokio::io::stdout();
-
-    let (service, socket) = LspService::new(Backend::new);
-    Server::new(stdin, stdout, socket).serve(service).await;
+/// Converts the colors of files to `notation`, rewriting each file with
+/// colors converted and printing how many. With `--stdout`, prints the
+/// converted text of each file instead, leaving the files as they are.
+fn convert(notation: Notation, stdout: bool, paths: &[PathBuf]) -> ExitCode {
+    for path in scan::files(paths) {
+        let result = scan::convert_file(&path, notation).and_then(|converted| {
+            match converted {
+                Some((text, _)) if stdout => print!("{text}"),
+                Some((text, count)) if count > 0 => {
+                    std::fs::write(&path, text)?;
+                    println!("{}: {count} converted", path.display());
+                }
+                _ => {}
+            }
+            Ok(())
+        });
+        if let Err(err) = result {
+            eprintln!("{}: {err}", path.display());
+            return ExitCode::from(2);
+        }
+    }
+    ExitCode::SUCCESS
 }
@@ -0,0 +1,86 @@
+//! Recency of open documents, so the parsed colors of the least recently used
+//! ones can be dropped when the document store grows past a memory budget.
+
+use std::collections::HashMap;
+
+use tower_lsp_server::ls_types::Uri;
+
+use crate::document::Document;
+
+/// Open documents, from least to most recently used.
+#[derive(Debug, Default)]
+pub struct Recency(Vec<Uri>);
+
+impl Recency {
+    /// Marks `uri` as the most recently used document.
+    pub fn touch(&mut self, uri: &Uri) {
+        self.remove(uri);
+        self.0.push(uri.clone());
+    }
+
+    pub fn remove(&mut self, uri: &Uri) {
+        self.0.retain(|used| used != uri);
+    }
+
+    /// Evicts the colors of the least recently used documents until all
+    /// documents fit in `budget` bytes, returning how many were evicted. The
+    /// most recently used document, likely the one being edited, is kept.
+    pub fn evict(&self, documents: &mut HashMap<Uri, Document>, budget: usize) -> usize {
+        let mut total: usize = documents.values().map(Document::approximate_size).sum();
+        let mut evicted = 0;
+        for uri in &self.0[..self.0.len().saturating_sub(1)] {
+            if total <= budget {
+                break;
+            }
+            let Some(document) = documents
+                .get_mut(uri)
+                .filter(|document| !document.is_evicted())
+            else {
+                continue;
+            };
+            let size = document.approximate_size();
+            document.evict();
+            total = total.saturating_sub(size) + document.approximate_size();
+            evicted += 1;
+        }
+        evicted
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use tower_lsp_server::ls_types::Uri;
+
+    use crate::document::Document;
+    use crate::memory::Recency;
+
+    #[test]
+    fn evicts_least_recently_used_first() {
+        let uris: Vec<Uri> = ["a", "b", "c"]
+            .map(|name| format!("file:///{name}.css").parse().unwrap())
+            .into();
+        let mut documents: HashMap<Uri, Document> = uris
+            .iter()
+            .map(|uri| {
+                (
+                    uri.clone(),
+                    Document::from("#FF0000 #00FF00\n".repeat(50).as_str()),
+                )
+            })
+            .collect();
+        let mut recency = Recency::default();
+        for uri in [&uris[1], &uris[0], &uris[2], &uris[1]] {
+            recency.touch(uri);
+        }
+        let size = documents[&uris[0]].approximate_size();
+
+        assert_eq!(recency.evict(&mut documents, 3 * size), 0);
+        assert_eq!(recency.evict(&mut documents, 3 * size - 1), 1);
+        assert!(documents[&uris[0]].is_evicted());
+        assert_eq!(recency.evict(&mut documents, 0), 1);
+        assert!(documents[&uris[2]].is_evicted());
+        assert!(!documents[&uris[1]].is_evicted());
+    }
+}
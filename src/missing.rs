@@ -0,0 +1,175 @@
+//! References that resolve to nothing in the workspace index: `var(--name)`
+//! uses of undeclared custom properties and `{group.token}` aliases of
+//! undefined design tokens, with did-you-mean suggestions.
+
+use tower_lsp_server::ls_types::{Diagnostic, DiagnosticSeverity, NumberOrString, Range, Uri};
+
+use crate::document::Document;
+use crate::tokens::{self, TokenIndex};
+use crate::variables::{self, VariableIndex};
+use crate::workspace::Scope;
+
+/// Code of missing reference diagnostics.
+pub const DIAGNOSTIC_CODE: &str = "missing-reference";
+/// How many names are suggested at most.
+const MAX_SUGGESTIONS: usize = 3;
+
+/// Returns the number of single-character insertions, deletions and
+/// substitutions that turn `a` into `b`.
+pub fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, a_char) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, &b_char) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(a_char != b_char);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+/// Returns the candidates closest to `name`, at most a third of its length
+/// away, ordered by distance and then by name.
+pub fn suggestions<'a>(name: &str, candidates: impl IntoIterator<Item = &'a str>) -> Vec<&'a str> {
+    let max_distance = (name.chars().count() / 3).max(1);
+    let mut suggestions: Vec<(usize, &str)> = candidates
+        .into_iter()
+        .map(|candidate| (edit_distance(name, candidate), candidate))
+        .filter(|&(distance, _)| distance <= max_distance)
+        .collect();
+    suggestions.sort();
+    suggestions.dedup();
+    suggestions
+        .into_iter()
+        .take(MAX_SUGGESTIONS)
+        .map(|(_, candidate)| candidate)
+        .collect()
+}
+
+/// Returns a warning on each reference of a document that isn't defined in
+/// the indexes. Token aliases are only checked in token files, against the
+/// tokens that apply in the document's `scope`.
+pub fn diagnostics(
+    uri: &Uri,
+    document: &Document,
+    variables: &VariableIndex,
+    tokens: &TokenIndex,
+    scope: Scope,
+) -> Vec<Diagnostic> {
+    let check_aliases = tokens::is_token_file(uri.path().as_str());
+    let mut diagnostics = Vec::new();
+    for (line_idx, line) in document.lines().enumerate() {
+        for (range, name) in variables::var_references(line, line_idx) {
+            if !variables
+                .custom_properties()
+                .any(|declared| declared == name)
+            {
+                let suggestions = suggestions(name, variables.custom_properties());
+                diagnostics.push(diagnostic(
+                    range,
+                    format!("`{name}` is not declared"),
+                    suggestions.iter().map(|name| format!("`{name}`")),
+                ));
+            }
+        }
+        if !check_aliases {
+            continue;
+        }
+        for (range, path) in tokens::aliases(line, line_idx) {
+            if !tokens.is_defined(path, scope) {
+                let suggestions = suggestions(path, tokens.all(scope));
+                diagnostics.push(diagnostic(
+                    range,
+                    format!("`{{{path}}}` doesn't resolve to a token"),
+                    suggestions.iter().map(|path| format!("`{{{path}}}`")),
+                ));
+            }
+        }
+    }
+    diagnostics
+}
+
+fn diagnostic(
+    range: Range,
+    message: String,
+    suggestions: impl Iterator<Item = String>,
+) -> Diagnostic {
+    let suggestions: Vec<String> = suggestions.collect();
+    let message = match suggestions.as_slice() {
+        [] => message,
+        [suggestion] => format!("{message}; did you mean {suggestion}?"),
+        [rest @ .., last] => format!("{message}; did you mean {} or {last}?", rest.join(", ")),
+    };
+    Diagnostic {
+        range,
+        severity: Some(DiagnosticSeverity::WARNING),
+        code: Some(NumberOrString::String(DIAGNOSTIC_CODE.to_string())),
+        source: Some("chroma-ls".to_string()),
+        message,
+        ..Default::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tower_lsp_server::ls_types::Uri;
+
+    use crate::document::Document;
+    use crate::missing::{diagnostics, edit_distance, suggestions};
+    use crate::tokens::TokenIndex;
+    use crate::variables::VariableIndex;
+    use crate::workspace::Scope;
+
+    #[test]
+    fn suggests_close_names() {
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+        assert_eq!(edit_distance("", "abc"), 3);
+        assert_eq!(
+            suggestions("--brnad", ["--gap", "--brand", "--bran", "--brand"]),
+            ["--bran", "--brand"]
+        );
+    }
+
+    #[test]
+    fn reports_missing_references() {
+        let mut variables = VariableIndex::default();
+        variables.update_from_text(
+            "file:///web/tokens.css".parse().unwrap(),
+            ":root { --brand: #1a73e8; --gap: 4px }\n",
+        );
+        let mut tokens = TokenIndex::default();
+        let uri: Uri = "file:///design/colors.tokens.json".parse().unwrap();
+        let text = r##"{ "color": { "primary": { "$value": "#1a73e8" }, "link": { "$value": "{color.primry}" } } }"##;
+        tokens.update_from_text(uri.clone(), text);
+
+        let document = Document::with_options(
+            "a { color: var(--brnad); margin: var(--gap); }\n",
+            Default::default(),
+            Default::default(),
+        );
+        let messages: Vec<String> = diagnostics(
+            &"file:///web/app.css".parse().unwrap(),
+            &document,
+            &variables,
+            &tokens,
+            Scope::default(),
+        )
+        .into_iter()
+        .map(|diagnostic| diagnostic.message)
+        .collect();
+        assert_eq!(
+            messages,
+            ["`--brnad` is not declared; did you mean `--brand`?"]
+        );
+
+        let document = Document::with_options(text, Default::default(), Default::default());
+        let diagnostics = diagnostics(&uri, &document, &variables, &tokens, Scope::default());
+        assert_eq!(
+            diagnostics[0].message,
+            "`{color.primry}` doesn't resolve to a token; did you mean `{color.primary}`?"
+        );
+        assert_eq!(diagnostics[0].range.start.character, 70);
+    }
+}
@@ -0,0 +1,81 @@
+//! Stable identifiers of color occurrences, so editor plugins can cache one
+//! swatch per distinct color instead of re-rendering identical decorations.
+
+use std::collections::BTreeMap;
+
+use serde::Serialize;
+use tower_lsp_server::ls_types::{Color, Range};
+
+use crate::color::{ColorFormat, ColorMatch, to_hex};
+
+/// Response of the `chroma/colorMonikers` request.
+#[derive(Debug, Default, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ColorMonikers {
+    /// Color occurrences in document order.
+    pub colors: Vec<ColorMoniker>,
+    /// Color of each identifier, listed once.
+    pub swatches: BTreeMap<String, Color>,
+}
+
+/// A color occurrence and the identifier of its swatch.
+#[derive(Debug, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ColorMoniker {
+    pub range: Range,
+    pub id: String,
+}
+
+/// Returns the identifier of a color written in `format`: a hash of its 8-bit
+/// hex value and format, the same across edits, documents and server runs.
+pub fn moniker(color: &Color, format: ColorFormat) -> String {
+    // FNV-1a, unlike `DefaultHasher`, is guaranteed not to change between
+    // Rust releases.
+    let mut hash: u64 = 0xcbf29ce484222325;
+    let format = serde_json::to_string(&format).unwrap_or_default();
+    for byte in to_hex(color).bytes().chain(format.bytes()) {
+        hash ^= u64::from(byte);
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    format!("{hash:016x}")
+}
+
+/// Assigns identifiers to the color matches of a document.
+pub fn monikers(matches: impl IntoIterator<Item = ColorMatch>) -> ColorMonikers {
+    let mut monikers = ColorMonikers::default();
+    for color_match in matches {
+        let id = moniker(&color_match.info.color, color_match.format);
+        monikers
+            .swatches
+            .entry(id.clone())
+            .or_insert(color_match.info.color);
+        monikers.colors.push(ColorMoniker {
+            range: color_match.info.range,
+            id,
+        });
+    }
+    monikers
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::document::Document;
+    use crate::moniker::monikers;
+
+    #[test]
+    fn identical_colors_share_ids() {
+        let document = Document::with_options(
+            "#ff0000 #FF0000\nrgb(255, 0, 0) #00ff00\n",
+            Default::default(),
+            Default::default(),
+        );
+        let monikers = monikers(document.matches());
+        let ids: Vec<&str> = monikers.colors.iter().map(|c| c.id.as_str()).collect();
+        assert_eq!(ids.len(), 4);
+        assert_eq!(ids[0], ids[1]);
+        assert_ne!(ids[0], ids[2]);
+        assert_ne!(ids[0], ids[3]);
+        assert_eq!(monikers.swatches.len(), 3);
+        assert_eq!(ids[0], "cf12d88557007e69");
+    }
+}
@@ -0,0 +1,94 @@
+use serde::Deserialize;
+use tower_lsp_server::ls_types::TextEdit;
+
+use crate::color::ColorFormat;
+use crate::document::Document;
+use crate::presentation::HexStyle;
+
+/// Canonical spelling of hex colors, applied on save.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum CanonicalFormat {
+    /// `#1A73E8`
+    UpperHex,
+    /// `#1a73e8`
+    LowerHex,
+}
+
+/// Returns edits rewriting hex colors that aren't already spelled in `format`.
+///
+/// Fully opaque 8-digit colors are shortened to 6 digits, and shorthand colors
+/// like `#fff` only change case. Other formats, such as named colors and
+/// aliases, and hex literals without `#`, like `0x1d2021`, are left as written.
+pub fn normalize_edits(document: &Document, format: CanonicalFormat) -> Vec<TextEdit> {
+    // Keeps the channel order of the document, e.g. `#AARRGGBB` on Android.
+    let style = HexStyle {
+        lowercase: format == CanonicalFormat::LowerHex,
+        ..HexStyle::for_syntax(document.syntax())
+    };
+    document
+        .matches()
+        .filter(|color_match| color_match.format == ColorFormat::Hex)
+        .filter_map(|color_match| {
+            let range = color_match.info.range;
+            let text = document.range_text(&range)?;
+            let new_text = match (text.len(), style.lowercase) {
+                (4 | 5, true) => text.to_ascii_lowercase(),
+                (4 | 5, false) => text.to_ascii_uppercase(),
+                _ => style.format(&color_match.info.color),
+            };
+            (text.starts_with('#') && text != new_text).then_some(TextEdit { range, new_text })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::document::Document;
+    use crate::heuristics::Syntax;
+    use crate::normalize::{CanonicalFormat, normalize_edits};
+
+    #[test]
+    fn rewrites_only_non_canonical_colors() {
+        let document = Document::from("a: #1a73e8;\nb: #1A73E8; c: #1A73E8FF; d: red;\n");
+
+        let edits: Vec<_> = normalize_edits(&document, CanonicalFormat::UpperHex)
+            .into_iter()
+            .map(|edit| {
+                (
+                    edit.range.start.line,
+                    edit.range.start.character,
+                    edit.new_text,
+                )
+            })
+            .collect();
+        assert_eq!(
+            edits,
+            [
+                (0, 3, "#1A73E8".to_string()),
+                (1, 15, "#1A73E8".to_string())
+            ]
+        );
+        assert_eq!(
+            normalize_edits(&document, CanonicalFormat::LowerHex).len(),
+            2
+        );
+
+        let shorthand = Document::from("a: #fFf; b: #FFFA;");
+        let edits: Vec<_> = normalize_edits(&shorthand, CanonicalFormat::LowerHex)
+            .into_iter()
+            .map(|edit| edit.new_text)
+            .collect();
+        assert_eq!(edits, ["#fff", "#fffa"]);
+    }
+
+    #[test]
+    fn keeps_packed_hex_literals() {
+        let document = Document::with_options(
+            "mov eax, 0x1a73e8\ndd 1A73E8h\n",
+            Default::default(),
+            Syntax::PackedHex,
+        );
+        assert_eq!(normalize_edits(&document, CanonicalFormat::UpperHex), []);
+    }
+}
@@ -0,0 +1,82 @@
+//! Fine-tuning of a single color channel, for the `chroma.nudgeColor` command.
+
+use serde::Deserialize;
+use tower_lsp_server::ls_types::Color;
+
+use crate::colorspace::{from_hsl, to_hsl};
+
+/// Channel changed by a nudge.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Channel {
+    Red,
+    Green,
+    Blue,
+    Hue,
+    Saturation,
+    Lightness,
+    Alpha,
+}
+
+impl Channel {
+    /// Returns the channel of a functional color named `name`, as in
+    /// `chroma/colorChannels`. Lightness and hue of Lab, LCH, OKLab and
+    /// OKLCH colors are nudged as in HSL.
+    pub fn named(name: &str) -> Option<Self> {
+        Some(match name {
+            "red" => Channel::Red,
+            "green" => Channel::Green,
+            "blue" => Channel::Blue,
+            "hue" => Channel::Hue,
+            "saturation" => Channel::Saturation,
+            "lightness" => Channel::Lightness,
+            "alpha" => Channel::Alpha,
+            _ => return None,
+        })
+    }
+
+    /// Returns `color` with the channel moved by `step`: 8-bit levels for
+    /// red, green and blue, degrees for hue, which wraps around, and
+    /// percentage points for saturation, lightness and alpha. Other channels
+    /// are left as is, and values are clamped to their range.
+    pub fn nudge(self, color: &Color, step: f32) -> Color {
+        let clamp = |value: f32| value.clamp(0.0, 1.0);
+        let mut color = *color;
+        match self {
+            Channel::Red => color.red = clamp(color.red + step / 255.0),
+            Channel::Green => color.green = clamp(color.green + step / 255.0),
+            Channel::Blue => color.blue = clamp(color.blue + step / 255.0),
+            Channel::Alpha => color.alpha = clamp(color.alpha + step / 100.0),
+            Channel::Hue | Channel::Saturation | Channel::Lightness => {
+                let (hue, saturation, lightness) = to_hsl(&color);
+                let (hue, saturation, lightness) = match self {
+                    Channel::Hue => (hue + step, saturation, lightness),
+                    Channel::Saturation => (hue, clamp(saturation + step / 100.0), lightness),
+                    _ => (hue, saturation, clamp(lightness + step / 100.0)),
+                };
+                color = from_hsl(hue, saturation, lightness, color.alpha);
+            }
+        }
+        color
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::color::{parse_line_colors, to_hex};
+    use crate::nudge::Channel;
+
+    #[test]
+    fn nudges_one_channel() {
+        let color = parse_line_colors("#1A73E8", 0)[0].color;
+        let nudged = |channel: Channel, step| to_hex(&channel.nudge(&color, step));
+
+        assert_eq!(nudged(Channel::Red, 1.0), "#1B73E8");
+        assert_eq!(nudged(Channel::Blue, 100.0), "#1A73FF");
+        assert_eq!(nudged(Channel::Green, -1.0), "#1A72E8");
+        assert_eq!(nudged(Channel::Alpha, -50.0), "#1A73E880");
+        assert_eq!(nudged(Channel::Hue, 360.0), "#1A73E8");
+        assert_eq!(nudged(Channel::Lightness, 100.0), "#FFFFFF");
+        assert_eq!(nudged(Channel::Saturation, -100.0), "#818181");
+    }
+}
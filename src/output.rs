@@ -0,0 +1,204 @@
+//! Versioned JSON output of color results, for `chroma-ls scan --json` and
+//! the custom requests and commands that return objects, so scripts can
+//! check which fields to expect as they grow.
+
+use std::collections::HashMap;
+use std::path::Path;
+
+use serde::Serialize;
+use tower_lsp_server::ls_types::{Color, ColorInformation, Location, Range, Uri};
+
+use crate::color::to_hex;
+
+/// Version of the output schema. Adding fields keeps it; renaming, removing
+/// or changing the meaning of a field bumps it.
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// A result along with the version of its schema, as a `schemaVersion` field
+/// next to its own.
+#[derive(Debug, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Versioned<T> {
+    pub schema_version: u32,
+    #[serde(flatten)]
+    pub result: T,
+}
+
+impl<T> Versioned<T> {
+    pub fn new(result: T) -> Self {
+        Self {
+            schema_version: SCHEMA_VERSION,
+            result,
+        }
+    }
+}
+
+/// Output of `chroma-ls scan --json`.
+#[derive(Debug, Default, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ScanResults {
+    pub files: Vec<FileColors>,
+}
+
+/// Colors found in a file.
+#[derive(Debug, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct FileColors {
+    pub path: String,
+    pub colors: Vec<ColorResult>,
+}
+
+/// A color with its hex value, e.g. `#1A73E8` or `#1A73E880`.
+#[derive(Debug, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ColorResult {
+    pub range: Range,
+    pub hex: String,
+    pub color: Color,
+}
+
+impl From<&ColorInformation> for ColorResult {
+    fn from(color_info: &ColorInformation) -> Self {
+        Self {
+            range: color_info.range,
+            hex: to_hex(&color_info.color),
+            color: color_info.color,
+        }
+    }
+}
+
+impl FileColors {
+    pub fn new(path: &Path, colors: &[ColorInformation]) -> Self {
+        Self {
+            path: path.display().to_string(),
+            colors: colors.iter().map(ColorResult::from).collect(),
+        }
+    }
+}
+
+/// Output of `chroma.extractPalette`: the distinct colors of documents, most
+/// used first.
+#[derive(Debug, Default, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ExtractedPalette {
+    pub colors: Vec<PaletteColor>,
+}
+
+/// A distinct color and where it is used.
+#[derive(Debug, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct PaletteColor {
+    pub hex: String,
+    pub color: Color,
+    pub count: usize,
+    /// Ordered by URI and position.
+    pub locations: Vec<Location>,
+}
+
+impl ExtractedPalette {
+    /// Groups the colors of documents by hex value.
+    pub fn new<'a>(documents: impl IntoIterator<Item = (&'a Uri, &'a [ColorInformation])>) -> Self {
+        let mut by_hex: HashMap<String, PaletteColor> = HashMap::new();
+        for (uri, colors) in documents {
+            for color_info in colors {
+                let hex = to_hex(&color_info.color);
+                let entry = by_hex.entry(hex.clone()).or_insert_with(|| PaletteColor {
+                    hex,
+                    color: color_info.color,
+                    count: 0,
+                    locations: Vec::new(),
+                });
+                entry.count += 1;
+                entry.locations.push(Location {
+                    uri: uri.clone(),
+                    range: color_info.range,
+                });
+            }
+        }
+        let mut colors: Vec<PaletteColor> = by_hex.into_values().collect();
+        for color in &mut colors {
+            color.locations.sort_by(|a, b| {
+                (a.uri.as_str(), a.range.start).cmp(&(b.uri.as_str(), b.range.start))
+            });
+        }
+        colors.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.hex.cmp(&b.hex)));
+        Self { colors }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use serde_json::json;
+    use tower_lsp_server::ls_types::{Color, ColorInformation, Position, Range, Uri};
+
+    use crate::document::Document;
+    use crate::output::{ExtractedPalette, FileColors, ScanResults, Versioned};
+
+    #[test]
+    fn serializes_with_schema_version() {
+        let color_info = ColorInformation {
+            range: Range {
+                start: Position {
+                    line: 2,
+                    character: 4,
+                },
+                end: Position {
+                    line: 2,
+                    character: 11,
+                },
+            },
+            color: Color {
+                red: 1.0,
+                green: 0.0,
+                blue: 0.0,
+                alpha: 0.5,
+            },
+        };
+        let results = Versioned::new(ScanResults {
+            files: vec![FileColors::new(Path::new("a.css"), &[color_info])],
+        });
+        assert_eq!(
+            serde_json::to_value(results).unwrap(),
+            json!({
+                "schemaVersion": 1,
+                "files": [{
+                    "path": "a.css",
+                    "colors": [{
+                        "range": {
+                            "start": { "line": 2, "character": 4 },
+                            "end": { "line": 2, "character": 11 },
+                        },
+                        "hex": "#FF000080",
+                        "color": { "red": 1.0, "green": 0.0, "blue": 0.0, "alpha": 0.5 },
+                    }],
+                }],
+            })
+        );
+    }
+
+    #[test]
+    fn groups_palette_colors() {
+        let (a, b): (Uri, Uri) = (
+            "file:///a.css".parse().unwrap(),
+            "file:///b.css".parse().unwrap(),
+        );
+        let a_colors = Document::from("x: #fff;\ny: #F00;\n").get_colors();
+        let b_colors = Document::from("z: rgb(255 0 0); w: red;\n").get_colors();
+
+        let palette = ExtractedPalette::new([(&b, &b_colors[..]), (&a, &a_colors[..])]);
+        let counts: Vec<_> = palette
+            .colors
+            .iter()
+            .map(|color| (color.hex.as_str(), color.count))
+            .collect();
+        assert_eq!(counts, [("#FF0000", 2), ("#FFFFFF", 1)]);
+        let red: Vec<_> = palette.colors[0]
+            .locations
+            .iter()
+            .map(|location| (location.uri.as_str(), location.range.start.line))
+            .collect();
+        assert_eq!(red, [("file:///a.css", 1), ("file:///b.css", 0)]);
+    }
+}
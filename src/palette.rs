@@ -0,0 +1,316 @@
+//! Palettes of painting and drawing tools, offered as snapping targets: GIMP
+//! and Inkscape `.gpl` files, Krita `.kpl` archives and the solid swatches of
+//! Inkscape SVG documents.
+
+use std::io::Read;
+use std::path::Path;
+
+use tower_lsp_server::ls_types::Color;
+
+use crate::color::{parse_line_colors, to_hex};
+use crate::error::{Error, Result};
+
+/// A named color of a palette.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PaletteEntry {
+    pub name: String,
+    pub color: Color,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Palette {
+    pub name: String,
+    pub entries: Vec<PaletteEntry>,
+}
+
+impl Palette {
+    /// Reads a palette file, choosing the format by extension. The file stem
+    /// names palettes that don't name themselves.
+    pub fn load(path: &Path) -> Result<Self> {
+        let invalid = |reason: String| Error::InvalidPalette {
+            path: path.display().to_string(),
+            reason,
+        };
+        let fallback_name = path
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .unwrap_or("palette");
+        let extension = path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .unwrap_or_default()
+            .to_ascii_lowercase();
+
+        let palette = match extension.as_str() {
+            "gpl" => {
+                let text = std::fs::read_to_string(path).map_err(|e| invalid(e.to_string()))?;
+                Self::from_gpl(&text, fallback_name)
+            }
+            "kpl" => {
+                let file = std::fs::File::open(path).map_err(|e| invalid(e.to_string()))?;
+                let mut archive = zip::ZipArchive::new(file).map_err(|e| invalid(e.to_string()))?;
+                let mut colorset = archive
+                    .by_name("colorset.xml")
+                    .map_err(|e| invalid(e.to_string()))?;
+                let mut xml = String::new();
+                colorset
+                    .read_to_string(&mut xml)
+                    .map_err(|e| invalid(e.to_string()))?;
+                Some(Self::from_krita_colorset(&xml, fallback_name))
+            }
+            "svg" => {
+                let text = std::fs::read_to_string(path).map_err(|e| invalid(e.to_string()))?;
+                Some(Self::from_inkscape_swatches(&text, fallback_name))
+            }
+            _ => return Err(invalid("expected a .gpl, .kpl or .svg file".to_string())),
+        };
+        match palette {
+            Some(palette) if !palette.entries.is_empty() => Ok(palette),
+            _ => Err(invalid("no colors found".to_string())),
+        }
+    }
+
+    /// Parses a GIMP palette, as also used by Inkscape: a `GIMP Palette`
+    /// header, optional `Name:` and `Columns:` lines, `#` comments and
+    /// `R G B name` entries.
+    pub fn from_gpl(text: &str, fallback_name: &str) -> Option<Self> {
+        let mut lines = text.lines();
+        if !lines.next()?.trim().eq_ignore_ascii_case("GIMP Palette") {
+            return None;
+        }
+
+        let mut name = fallback_name.to_string();
+        let mut entries = Vec::new();
+        for line in lines.map(str::trim) {
+            if let Some(header) = line.strip_prefix("Name:") {
+                name = header.trim().to_string();
+                continue;
+            }
+            if line.is_empty() || line.starts_with('#') || line.starts_with("Columns:") {
+                continue;
+            }
+            let mut fields = line.split_whitespace();
+            let channels: Option<Vec<u8>> = fields
+                .by_ref()
+                .take(3)
+                .map(|field| field.parse().ok())
+                .collect();
+            let Some([red, green, blue]) = channels.as_deref() else {
+                continue;
+            };
+            let color = Color {
+                red: *red as f32 / 255.0,
+                green: *green as f32 / 255.0,
+                blue: *blue as f32 / 255.0,
+                alpha: 1.0,
+            };
+            entries.push(PaletteEntry {
+                name: entry_name(fields.collect::<Vec<_>>().join(" "), &color),
+                color,
+            });
+        }
+        Some(Self { name, entries })
+    }
+
+    /// Parses the `colorset.xml` of a Krita palette. Entries in color spaces
+    /// other than RGB are skipped.
+    pub fn from_krita_colorset(xml: &str, fallback_name: &str) -> Self {
+        let name = xml
+            .find("<Colorset")
+            .and_then(|start| attribute(tag_at(xml, start), "name"))
+            .filter(|name| !name.is_empty())
+            .unwrap_or(fallback_name)
+            .to_string();
+
+        let mut entries = Vec::new();
+        for (start, _) in xml.match_indices("<ColorSetEntry") {
+            let end = xml[start..]
+                .find("</ColorSetEntry>")
+                .map_or(xml.len(), |end| start + end);
+            let body = &xml[start..end];
+            let Some(rgb) = ["<RGB ", "<sRGB "]
+                .iter()
+                .find_map(|open| body.find(open))
+                .map(|idx| tag_at(body, idx))
+            else {
+                continue;
+            };
+            let channel = |key| attribute(rgb, key).and_then(|value| value.parse::<f32>().ok());
+            let (Some(red), Some(green), Some(blue)) = (channel("r"), channel("g"), channel("b"))
+            else {
+                continue;
+            };
+            let color = Color {
+                red: red.clamp(0.0, 1.0),
+                green: green.clamp(0.0, 1.0),
+                blue: blue.clamp(0.0, 1.0),
+                alpha: 1.0,
+            };
+            let entry = attribute(tag_at(body, 0), "name").unwrap_or_default();
+            entries.push(PaletteEntry {
+                name: entry_name(entry.to_string(), &color),
+                color,
+            });
+        }
+        Self { name, entries }
+    }
+
+    /// Collects the solid swatches of an Inkscape document, gradients marked
+    /// `inkscape:swatch="solid"` with a single stop, named by their id.
+    pub fn from_inkscape_swatches(svg: &str, fallback_name: &str) -> Self {
+        let mut entries = Vec::new();
+        for (start, _) in svg.match_indices("<linearGradient") {
+            let tag = tag_at(svg, start);
+            if attribute(tag, "inkscape:swatch") != Some("solid") {
+                continue;
+            }
+            let Some(stop) = svg[start..]
+                .find("<stop")
+                .map(|idx| tag_at(svg, start + idx))
+            else {
+                continue;
+            };
+            let Some(mut color) = parse_line_colors(stop, 0).first().map(|info| info.color) else {
+                continue;
+            };
+            if let Some(opacity) = style_property(stop, "stop-opacity")
+                .or_else(|| attribute(stop, "stop-opacity"))
+                .and_then(|opacity| opacity.parse::<f32>().ok())
+            {
+                color.alpha = opacity.clamp(0.0, 1.0);
+            }
+            let id = attribute(tag, "id").unwrap_or_default();
+            entries.push(PaletteEntry {
+                name: entry_name(id.to_string(), &color),
+                color,
+            });
+        }
+        Self {
+            name: fallback_name.to_string(),
+            entries,
+        }
+    }
+
+    /// Returns the entry closest to `color` in RGB.
+    pub fn nearest(&self, color: &Color) -> Option<&PaletteEntry> {
+        let distance = |entry: &PaletteEntry| {
+            (entry.color.red - color.red).powi(2)
+                + (entry.color.green - color.green).powi(2)
+                + (entry.color.blue - color.blue).powi(2)
+        };
+        self.entries
+            .iter()
+            .min_by(|a, b| distance(a).total_cmp(&distance(b)))
+    }
+}
+
+/// Names unnamed entries by their hex value.
+fn entry_name(name: String, color: &Color) -> String {
+    if name.trim().is_empty() {
+        to_hex(color)
+    } else {
+        name.trim().to_string()
+    }
+}
+
+/// Returns the tag starting at `start`, up to its closing `>`.
+fn tag_at(text: &str, start: usize) -> &str {
+    let end = text[start..]
+        .find('>')
+        .map_or(text.len(), |end| start + end);
+    &text[start..end]
+}
+
+/// Returns the value of a double-quoted attribute of a tag.
+fn attribute<'a>(tag: &'a str, name: &str) -> Option<&'a str> {
+    let pattern = format!("{name}=\"");
+    let value_start = tag
+        .match_indices(&pattern)
+        .find(|(idx, _)| tag[..*idx].ends_with(char::is_whitespace))
+        .map(|(idx, _)| idx + pattern.len())?;
+    let value_len = tag[value_start..].find('"')?;
+    Some(&tag[value_start..value_start + value_len])
+}
+
+/// Returns the value of a property in the `style` attribute of a tag.
+fn style_property<'a>(tag: &'a str, name: &str) -> Option<&'a str> {
+    attribute(tag, "style")?
+        .split(';')
+        .filter_map(|declaration| declaration.split_once(':'))
+        .find(|(property, _)| property.trim() == name)
+        .map(|(_, value)| value.trim())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use crate::color::{parse_line_colors, to_hex};
+    use crate::palette::Palette;
+
+    fn hexes(palette: &Palette) -> Vec<(String, String)> {
+        palette
+            .entries
+            .iter()
+            .map(|entry| (entry.name.clone(), to_hex(&entry.color)))
+            .collect()
+    }
+
+    #[test]
+    fn gimp_palette() {
+        let text = "GIMP Palette\nName: Tango\nColumns: 3\n#\n252 233  79\tButter 1\n 46  52  54\n";
+        let palette = Palette::from_gpl(text, "tango").unwrap();
+        assert_eq!(palette.name, "Tango");
+        assert_eq!(
+            hexes(&palette),
+            [
+                ("Butter 1".to_string(), "#FCE94F".to_string()),
+                ("#2E3436".to_string(), "#2E3436".to_string()),
+            ]
+        );
+        assert_eq!(Palette::from_gpl("255 0 0 Red", "x"), None);
+
+        let orange = parse_line_colors("#FF8800", 0)[0].color;
+        assert_eq!(palette.nearest(&orange).unwrap().name, "Butter 1");
+    }
+
+    #[test]
+    fn krita_archive() {
+        let xml = r#"<Colorset version="2.0" name="Skin" columns="2">
+ <ColorSetEntry name="Light" id="0" bitdepth="U8">
+  <RGB r="1" g="0.8" b="0.6" space="sRGB-elle-V2-srgbtrc.icc"/>
+ </ColorSetEntry>
+ <ColorSetEntry name="Ink" id="1" bitdepth="U8">
+  <CMYK c="0" m="0" y="0" k="1" space="Generic CMYK"/>
+ </ColorSetEntry>
+</Colorset>"#;
+        let path = std::env::temp_dir().join(format!("chroma-ls-{}.kpl", std::process::id()));
+        let mut archive = zip::ZipWriter::new(std::fs::File::create(&path).unwrap());
+        archive
+            .start_file("colorset.xml", zip::write::SimpleFileOptions::default())
+            .unwrap();
+        archive.write_all(xml.as_bytes()).unwrap();
+        archive.finish().unwrap();
+
+        let palette = Palette::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(palette.name, "Skin");
+        assert_eq!(
+            hexes(&palette),
+            [("Light".to_string(), "#FFCC99".to_string())]
+        );
+    }
+
+    #[test]
+    fn inkscape_swatches() {
+        let svg = r##"<svg><defs>
+  <linearGradient id="brand" inkscape:swatch="solid"><stop style="stop-color:#1a73e8;stop-opacity:0.5" offset="0"/></linearGradient>
+  <linearGradient id="fade"><stop stop-color="#000000" offset="0"/><stop stop-color="#ffffff" offset="1"/></linearGradient>
+</defs></svg>"##;
+        let palette = Palette::from_inkscape_swatches(svg, "logo");
+        assert_eq!(
+            hexes(&palette),
+            [("brand".to_string(), "#1A73E880".to_string())]
+        );
+    }
+}
@@ -0,0 +1,376 @@
+use serde::{Deserialize, Serialize};
+use tower_lsp_server::ls_types::Color;
+
+use crate::color::to_hex;
+use crate::colorspace::{srgb_to_linear, to_hsl, to_oklch};
+use crate::heuristics::Syntax;
+
+/// How hex colors are spelled in a file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HexStyle {
+    pub prefix: &'static str,
+    pub suffix: &'static str,
+    pub lowercase: bool,
+    /// Whether translucent colors are written `#AARRGGBB`, as on Android.
+    pub alpha_first: bool,
+    /// Whether opaque colors keep their alpha digits, as `Color(0xFF1A73E8)`
+    /// requires.
+    pub keep_alpha: bool,
+}
+
+impl Default for HexStyle {
+    fn default() -> Self {
+        Self {
+            prefix: "#",
+            suffix: "",
+            lowercase: false,
+            alpha_first: false,
+            keep_alpha: false,
+        }
+    }
+}
+
+impl HexStyle {
+    /// Default style of a profile, used when the existing text doesn't tell.
+    /// Terminal configs conventionally use lowercase digits.
+    pub fn for_syntax(syntax: Syntax) -> Self {
+        Self {
+            prefix: "#",
+            suffix: "",
+            lowercase: matches!(
+                syntax,
+                Syntax::Alacritty | Syntax::WezTerm | Syntax::Ghostty
+            ),
+            alpha_first: syntax == Syntax::Android,
+            keep_alpha: false,
+        }
+    }
+
+    /// Infers the style from the text of an existing color, keeping its
+    /// prefix (`#`, `0x`, `x"` or none, where the profile allows bare hex),
+    /// its `h` suffix and the case of its digits.
+    pub fn infer(existing: &str, syntax: Syntax) -> Self {
+        let mut style = Self::for_syntax(syntax);
+        let digits = if let Some(digits) = existing.strip_prefix('#') {
+            digits
+        } else if let Some(digits) = existing
+            .strip_prefix("0x")
+            .or_else(|| existing.strip_prefix("0X"))
+        {
+            style.prefix = if existing.starts_with("0X") {
+                "0X"
+            } else {
+                "0x"
+            };
+            // `Color(0xAARRGGBB)` of Compose and Flutter.
+            if syntax == Syntax::ColorConstructor {
+                style.alpha_first = true;
+                style.keep_alpha = true;
+            }
+            digits
+        } else if syntax == Syntax::PackedHex
+            && let Some(digits) = existing
+                .strip_prefix("x\"")
+                .or_else(|| existing.strip_prefix("X\""))
+                .and_then(|quoted| quoted.strip_suffix('"'))
+        {
+            style.prefix = if existing.starts_with('X') {
+                "X\""
+            } else {
+                "x\""
+            };
+            style.suffix = "\"";
+            digits
+        } else if syntax == Syntax::PackedHex
+            && let Some(digits) = existing.strip_suffix(['h', 'H'])
+        {
+            // Keep the leading zero of assemblers that require a leading digit.
+            style.prefix = if digits.len() == 7 { "0" } else { "" };
+            style.suffix = if existing.ends_with('H') { "H" } else { "h" };
+            digits
+        } else if syntax == Syntax::Ghostty
+            && !existing.is_empty()
+            && existing.chars().all(|c| c.is_ascii_hexdigit())
+        {
+            style.prefix = "";
+            existing
+        } else {
+            return style;
+        };
+
+        let has_lower = digits.chars().any(|c| c.is_ascii_lowercase());
+        let has_upper = digits.chars().any(|c| c.is_ascii_uppercase());
+        if has_lower != has_upper {
+            style.lowercase = has_lower;
+        }
+        style
+    }
+
+    pub fn format(&self, color: &Color) -> String {
+        let mut hex = to_hex(color);
+        if self.keep_alpha && hex.len() == 7 {
+            hex.push_str("FF");
+        }
+        let digits = match &hex[1..] {
+            digits if self.alpha_first && digits.len() == 8 => {
+                format!("{}{}", &digits[6..], &digits[..6])
+            }
+            digits => digits.to_string(),
+        };
+        let digits = if self.lowercase {
+            digits.to_lowercase()
+        } else {
+            digits
+        };
+        format!("{}{digits}{}", self.prefix, self.suffix)
+    }
+}
+
+/// Formats a color as a CSS `rgb()` or `rgba()` call, keeping the function
+/// name and the comma-separated or space-separated syntax of `existing`.
+pub fn css_rgb(color: &Color, existing: &str) -> String {
+    let byte = |value: f32| (value.clamp(0.0, 1.0) * 255.0).round() as u8;
+    let (red, green, blue) = (byte(color.red), byte(color.green), byte(color.blue));
+    let alpha = (color.alpha.clamp(0.0, 1.0) * 1000.0).round() / 1000.0;
+    let name = if existing.starts_with("rgba") || (existing.contains(',') && alpha < 1.0) {
+        "rgba"
+    } else {
+        "rgb"
+    };
+    match (existing.contains(','), name == "rgba" || alpha < 1.0) {
+        (true, true) => format!("{name}({red}, {green}, {blue}, {alpha})"),
+        (true, false) => format!("{name}({red}, {green}, {blue})"),
+        (false, true) => format!("{name}({red} {green} {blue} / {alpha})"),
+        (false, false) => format!("{name}({red} {green} {blue})"),
+    }
+}
+
+/// Formats a color as a CSS `hsl()` or `hsla()` call, keeping the function
+/// name and the comma-separated or space-separated syntax of `existing`.
+pub fn css_hsl(color: &Color, existing: &str) -> String {
+    let (hue, saturation, lightness) = to_hsl(color);
+    let tenths = |value: f32| (value * 10.0).round() / 10.0;
+    let (hue, saturation, lightness) = (
+        tenths(hue),
+        tenths(saturation * 100.0),
+        tenths(lightness * 100.0),
+    );
+    let alpha = (color.alpha.clamp(0.0, 1.0) * 1000.0).round() / 1000.0;
+    let name = if existing.starts_with("hsla") || (existing.contains(',') && alpha < 1.0) {
+        "hsla"
+    } else {
+        "hsl"
+    };
+    match (existing.contains(','), name == "hsla" || alpha < 1.0) {
+        (true, true) => format!("{name}({hue}, {saturation}%, {lightness}%, {alpha})"),
+        (true, false) => format!("{name}({hue}, {saturation}%, {lightness}%)"),
+        (false, true) => format!("{name}({hue} {saturation}% {lightness}% / {alpha})"),
+        (false, false) => format!("{name}({hue} {saturation}% {lightness}%)"),
+    }
+}
+
+/// Formats a color as a CSS `oklch()` call, e.g. `oklch(62.8% 0.2577 29.23)`.
+pub fn css_oklch(color: &Color) -> String {
+    let (lightness, chroma, hue) = to_oklch(color);
+    let round = |value: f32, digits: i32| {
+        let scale = 10f32.powi(digits);
+        (value * scale).round() / scale
+    };
+    let (lightness, chroma, hue) = (round(lightness * 100.0, 2), round(chroma, 4), round(hue, 2));
+    let alpha = (color.alpha.clamp(0.0, 1.0) * 1000.0).round() / 1000.0;
+    if alpha < 1.0 {
+        format!("oklch({lightness}% {chroma} {hue} / {alpha})")
+    } else {
+        format!("oklch({lightness}% {chroma} {hue})")
+    }
+}
+
+/// Formats a color as a Unity serialized map, `{r: 1, g: 0.5, b: 0, a: 1}`.
+pub fn unity_map(color: &Color) -> String {
+    let component = |value: f32| (value * 1000.0).round() / 1000.0;
+    format!(
+        "{{r: {}, g: {}, b: {}, a: {}}}",
+        component(color.red),
+        component(color.green),
+        component(color.blue),
+        component(color.alpha)
+    )
+}
+
+/// Formats a color as an Unreal struct literal: linear `FLinearColor`
+/// components like `(R=0.214041,G=0.000000,B=1.000000,A=1.000000)`, or
+/// `FColor` bytes like `(R=127,G=0,B=255,A=255)`.
+pub fn unreal_struct(color: &Color, linear: bool) -> String {
+    let channels = [color.red, color.green, color.blue, color.alpha];
+    let [red, green, blue, alpha] = channels.map(|value| value.clamp(0.0, 1.0));
+    if linear {
+        format!(
+            "(R={:.6},G={:.6},B={:.6},A={alpha:.6})",
+            srgb_to_linear(red),
+            srgb_to_linear(green),
+            srgb_to_linear(blue)
+        )
+    } else {
+        let byte = |value: f32| (value * 255.0).round() as u8;
+        format!(
+            "(R={},G={},B={},A={})",
+            byte(red),
+            byte(green),
+            byte(blue),
+            byte(alpha)
+        )
+    }
+}
+
+/// CSS notation colors are converted to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Notation {
+    Hex,
+    Rgb,
+    Hsl,
+    Oklch,
+}
+
+impl Notation {
+    pub const ALL: [Notation; 4] = [Notation::Hex, Notation::Rgb, Notation::Hsl, Notation::Oklch];
+
+    /// Returns the notation of a name as in commands, e.g. `hsl`.
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "hex" => Some(Notation::Hex),
+            "rgb" => Some(Notation::Rgb),
+            "hsl" => Some(Notation::Hsl),
+            "oklch" => Some(Notation::Oklch),
+            _ => None,
+        }
+    }
+
+    /// Returns the notation of a color's text, if it is written in one.
+    pub fn of(existing: &str) -> Option<Self> {
+        if existing.starts_with('#') {
+            Some(Notation::Hex)
+        } else if existing.starts_with("rgb") {
+            Some(Notation::Rgb)
+        } else if existing.starts_with("hsl") {
+            Some(Notation::Hsl)
+        } else if existing.starts_with("oklch") {
+            Some(Notation::Oklch)
+        } else {
+            None
+        }
+    }
+
+    /// Name of the notation in titles, e.g. `rgb()`.
+    pub fn name(self) -> &'static str {
+        match self {
+            Notation::Hex => "hex",
+            Notation::Rgb => "rgb()",
+            Notation::Hsl => "hsl()",
+            Notation::Oklch => "oklch()",
+        }
+    }
+
+    /// Returns the notation after this one, cycling through hex, `rgb()`,
+    /// `hsl()` and `oklch()`.
+    pub fn next(self) -> Self {
+        match self {
+            Notation::Hex => Notation::Rgb,
+            Notation::Rgb => Notation::Hsl,
+            Notation::Hsl => Notation::Oklch,
+            Notation::Oklch => Notation::Hex,
+        }
+    }
+
+    /// Formats a color in the notation, keeping the digit case of an
+    /// `existing` hex color and the comma-separated syntax of an `existing`
+    /// function.
+    pub fn format(self, color: &Color, existing: &str, syntax: Syntax) -> String {
+        match self {
+            Notation::Hex => HexStyle::infer(existing, syntax).format(color),
+            Notation::Rgb => css_rgb(color, existing),
+            Notation::Hsl => css_hsl(color, existing),
+            Notation::Oklch => css_oklch(color),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::color::parse_line_colors;
+    use crate::heuristics::Syntax;
+    use crate::presentation::{
+        HexStyle, Notation, css_hsl, css_oklch, css_rgb, unity_map, unreal_struct,
+    };
+
+    #[test]
+    fn keeps_existing_spelling() {
+        let color = parse_line_colors("#1D2021", 0)[0].color;
+        let format = |existing, syntax| HexStyle::infer(existing, syntax).format(&color);
+
+        assert_eq!(format("0x282828", Syntax::Alacritty), "0x1d2021");
+        assert_eq!(format("#ABCDEF", Syntax::Alacritty), "#1D2021");
+        assert_eq!(format("282828", Syntax::Ghostty), "1d2021");
+        assert_eq!(format("x\"ABCDEF\"", Syntax::PackedHex), "x\"1D2021\"");
+        assert_eq!(format("0ff8800h", Syntax::PackedHex), "01d2021h");
+        assert_eq!(format("red", Syntax::WezTerm), "#1d2021");
+        assert_eq!(format("red", Syntax::Plain), "#1D2021");
+
+        let translucent = parse_line_colors("#1D202180", 0)[0].color;
+        let style = HexStyle::infer("#FF000000", Syntax::Android);
+        assert_eq!(style.format(&translucent), "#801D2021");
+        assert_eq!(format("0xFF282828", Syntax::ColorConstructor), "0xFF1D2021");
+        assert_eq!(
+            unity_map(&translucent),
+            "{r: 0.114, g: 0.125, b: 0.129, a: 0.502}"
+        );
+        assert_eq!(unreal_struct(&translucent, false), "(R=29,G=32,B=33,A=128)");
+        assert_eq!(
+            unreal_struct(&color, true),
+            "(R=0.012286,G=0.014444,B=0.015209,A=1.000000)"
+        );
+
+        assert_eq!(css_rgb(&color, "rgb(0, 0, 0)"), "rgb(29, 32, 33)");
+        assert_eq!(
+            css_rgb(&translucent, "rgb(0, 0, 0)"),
+            "rgba(29, 32, 33, 0.502)"
+        );
+        assert_eq!(css_rgb(&color, "rgba(0, 0, 0, 0.5)"), "rgba(29, 32, 33, 1)");
+        assert_eq!(css_rgb(&translucent, "rgb(0 0 0)"), "rgb(29 32 33 / 0.502)");
+        assert_eq!(css_rgb(&color, "rgb(0 0 0 / 50%)"), "rgb(29 32 33)");
+        assert_eq!(css_hsl(&color, "hsl(0, 0%, 0%)"), "hsl(195, 6.5%, 12.2%)");
+        assert_eq!(
+            css_hsl(&translucent, "hsl(0 0% 0%)"),
+            "hsl(195 6.5% 12.2% / 0.502)"
+        );
+
+        let red = parse_line_colors("#FF0000", 0)[0].color;
+        assert_eq!(css_oklch(&red), "oklch(62.8% 0.2577 29.23)");
+        assert_eq!(
+            css_oklch(&translucent),
+            "oklch(24.08% 0.0049 219.67 / 0.502)"
+        );
+        let gray = parse_line_colors("#808080", 0)[0].color;
+        assert_eq!(css_oklch(&gray), "oklch(59.99% 0 0)");
+    }
+
+    #[test]
+    fn converts_between_notations() {
+        let color = parse_line_colors("#1d2021", 0)[0].color;
+        let convert = |existing: &str| {
+            let notation = Notation::of(existing).unwrap().next();
+            notation.format(&color, existing, Syntax::Plain)
+        };
+
+        assert_eq!(convert("#1d2021"), "rgb(29 32 33)");
+        assert_eq!(convert("rgb(29, 32, 33)"), "hsl(195, 6.5%, 12.2%)");
+        assert_eq!(
+            convert("hsl(195 6.5% 12.2%)"),
+            "oklch(24.08% 0.0049 219.67)"
+        );
+        assert_eq!(convert("oklch(24.08% 0.0049 219.67)"), "#1D2021");
+        assert_eq!(Notation::of("red"), None);
+        assert_eq!(Notation::parse("oklch"), Some(Notation::Oklch));
+        assert_eq!(Notation::parse("lab"), None);
+    }
+}
@@ -0,0 +1,592 @@
+//! Built-in parsers for theme files of terminal tools, for hex literals of
+//! other languages and for Unity serialized assets.
+
+use tower_lsp_server::ls_types::{Color, ColorInformation};
+
+use crate::ansi::{ansi256, index_of};
+use crate::color::{ColorFormat, ColorMatch, parse_line_colors};
+use crate::colorspace::linear_to_srgb;
+use crate::heuristics::{key_values, line_range};
+use crate::named;
+
+/// Keys of Helix styles that take a color.
+const HELIX_KEYS: [&str; 3] = ["fg", "bg", "color"];
+
+/// Starship style modifiers, which aren't colors.
+const STARSHIP_MODIFIERS: [&str; 9] = [
+    "bold",
+    "italic",
+    "underline",
+    "dimmed",
+    "inverted",
+    "blink",
+    "hidden",
+    "strikethrough",
+    "none",
+];
+
+/// Keys of Zellij themes that take a color.
+const ZELLIJ_KEYS: [&str; 16] = [
+    "fg",
+    "bg",
+    "black",
+    "red",
+    "green",
+    "yellow",
+    "blue",
+    "magenta",
+    "cyan",
+    "white",
+    "orange",
+    "base",
+    "background",
+    "emphasis_0",
+    "emphasis_1",
+    "emphasis_2",
+];
+
+/// Finds terminal color names assigned to style keys of a Helix theme, e.g.
+/// `"ui.text" = { fg = "light-gray" }`.
+pub fn helix(line: &str, line_idx: usize) -> Vec<ColorMatch> {
+    key_values(line)
+        .into_iter()
+        .filter(|(key, _, _)| HELIX_KEYS.contains(key))
+        .filter_map(|(_, value_start, value)| {
+            ansi_match(
+                line,
+                line_idx,
+                value_start..value_start + value.len(),
+                value,
+            )
+        })
+        .collect()
+}
+
+/// Finds colors in Starship style strings, e.g. `style = "bold fg:208 bg:blue"`
+/// or the `(...)` groups of `format = "[$symbol](bold red)"`.
+pub fn starship(line: &str, line_idx: usize) -> Vec<ColorMatch> {
+    let Some((key, value)) = line.split_once('=') else {
+        return Vec::new();
+    };
+    let key = key.trim().trim_matches('"');
+    let value_start = line.len() - value.len();
+
+    let mut styles = Vec::new();
+    if key == "style" || key.ends_with("_style") {
+        styles.push((value_start, value));
+    } else if key == "format" || key.ends_with("_format") {
+        for (group_idx, _) in value.match_indices("](") {
+            let group = &value[group_idx + 2..];
+            let group = &group[..group.find(')').unwrap_or(group.len())];
+            styles.push((value_start + group_idx + 2, group));
+        }
+    }
+
+    let mut colors = Vec::new();
+    for (style_start, style) in styles {
+        for (token_idx, token) in tokens(style) {
+            let token_start = style_start + token_idx;
+            let (token_start, token) = match token
+                .strip_prefix("fg:")
+                .or_else(|| token.strip_prefix("bg:"))
+            {
+                Some(color) => (token_start + 3, color),
+                None => (token_start, token),
+            };
+            if STARSHIP_MODIFIERS.contains(&token) {
+                continue;
+            }
+            colors.extend(ansi_match(
+                line,
+                line_idx,
+                token_start..token_start + token.len(),
+                token,
+            ));
+        }
+    }
+    colors
+}
+
+/// Finds colors in a Zellij theme, written as RGB triples (`fg 248 248 242`)
+/// or palette indices (`red 160`).
+pub fn zellij(line: &str, line_idx: usize) -> Vec<ColorMatch> {
+    let mut tokens = tokens(line).into_iter();
+    let Some((_, key)) = tokens.next() else {
+        return Vec::new();
+    };
+    if !ZELLIJ_KEYS.contains(&key) {
+        return Vec::new();
+    }
+
+    let values: Vec<(usize, &str)> = tokens.collect();
+    match values.as_slice() {
+        [(start, value)] => ansi_match(line, line_idx, *start..start + value.len(), value)
+            .into_iter()
+            .collect(),
+        [(start, r), (_, g), (b_start, b)] => {
+            let end = b_start + b.len();
+            let (Ok(r), Ok(g), Ok(b)) = (r.parse::<u8>(), g.parse::<u8>(), b.parse::<u8>()) else {
+                return Vec::new();
+            };
+            let channel = |value: u8| value as f32 / 255.0;
+            vec![ColorMatch {
+                info: ColorInformation {
+                    range: line_range(line, line_idx, *start..end),
+                    color: Color {
+                        red: channel(r),
+                        green: channel(g),
+                        blue: channel(b),
+                        alpha: 1.0,
+                    },
+                },
+                format: ColorFormat::Heuristic,
+            }]
+        }
+        _ => Vec::new(),
+    }
+}
+
+/// Finds `0xRRGGBB` colors of an Alacritty config, e.g. `background = '0x1d2021'`.
+pub fn alacritty(line: &str, line_idx: usize) -> Vec<ColorMatch> {
+    let mut colors = Vec::new();
+    for (start, _) in line.match_indices("0x") {
+        let preceded_by_word = line[..start]
+            .chars()
+            .next_back()
+            .is_some_and(|c| c.is_alphanumeric());
+        let digits = &line[start + 2..];
+        let length = digits.chars().take_while(char::is_ascii_hexdigit).count();
+        if preceded_by_word || length != 6 {
+            continue;
+        }
+        colors.extend(bare_hex_match(
+            line,
+            line_idx,
+            start..start + 8,
+            &digits[..6],
+        ));
+    }
+    colors
+}
+
+/// Finds packed hex literals of assembly and hardware description languages:
+/// `0xFF8800` or `0XFF8800`, VHDL `x"FF8800"` and suffixed `FF8800h` (or
+/// `0FF8800h`, for assemblers that require a leading digit).
+pub fn packed_hex(line: &str, line_idx: usize) -> Vec<ColorMatch> {
+    let is_word_char = |c: char| c.is_alphanumeric() || c == '_';
+    let mut colors = Vec::new();
+    let mut idx = 0;
+    while idx < line.len() {
+        let rest = &line[idx..];
+        let Some(c) = rest.chars().next() else {
+            break;
+        };
+        let at_word_start = !line[..idx].chars().next_back().is_some_and(is_word_char);
+        if !at_word_start || !is_word_char(c) {
+            idx += c.len_utf8();
+            continue;
+        }
+
+        let word_len = rest.find(|c: char| !is_word_char(c)).unwrap_or(rest.len());
+        let word = &rest[..word_len];
+        let literal =
+            if let Some(digits) = word.strip_prefix("0x").or_else(|| word.strip_prefix("0X")) {
+                Some((word_len, digits))
+            } else if let Some(digits) = rest
+                .strip_prefix("x\"")
+                .or_else(|| rest.strip_prefix("X\""))
+                .and_then(|quoted| quoted.get(..7))
+                .and_then(|quoted| quoted.strip_suffix('"'))
+            {
+                Some((9, digits))
+            } else if let Some(digits) = word.strip_suffix(['h', 'H']) {
+                let digits = match digits.len() {
+                    7 => digits.strip_prefix('0').unwrap_or(digits),
+                    _ => digits,
+                };
+                Some((word_len, digits))
+            } else {
+                None
+            };
+
+        if let Some((len, digits)) = literal
+            && digits.len() == 6
+            && digits.chars().all(|c| c.is_ascii_hexdigit())
+        {
+            colors.extend(bare_hex_match(line, line_idx, idx..idx + len, digits));
+            idx += len;
+        } else {
+            idx += word_len;
+        }
+    }
+    colors
+}
+
+/// Finds bare hex values of a Ghostty config, e.g. `background = 1d2021`.
+pub fn ghostty(line: &str, line_idx: usize) -> Vec<ColorMatch> {
+    key_values(line)
+        .into_iter()
+        .filter(|(_, _, value)| value.len() == 6)
+        .filter_map(|(_, value_start, value)| {
+            bare_hex_match(line, line_idx, value_start..value_start + 6, value)
+        })
+        .collect()
+}
+
+/// Finds CSS named colors written as a whole `<string>` of a TextMate theme,
+/// e.g. `<string>white</string>`.
+pub fn tm_theme(line: &str, line_idx: usize) -> Vec<ColorMatch> {
+    let mut colors = Vec::new();
+    for (open_idx, open) in line.match_indices("<string>") {
+        let value_start = open_idx + open.len();
+        let Some(value_len) = line[value_start..].find("</string>") else {
+            continue;
+        };
+        let value = &line[value_start..value_start + value_len];
+        colors.extend(named_match(
+            line,
+            line_idx,
+            value_start..value_start + value_len,
+            value,
+        ));
+    }
+    colors
+}
+
+/// Finds CSS named colors assigned to keys of a Sublime Text color scheme,
+/// e.g. `"foreground": "white"`.
+pub fn sublime_color_scheme(line: &str, line_idx: usize) -> Vec<ColorMatch> {
+    key_values(line)
+        .into_iter()
+        .filter_map(|(_, value_start, value)| {
+            let value_end = value_start + value.len();
+            // Only whole string values, not `var(white)` and the like.
+            if !line[value_end..].starts_with('"') {
+                return None;
+            }
+            named_match(line, line_idx, value_start..value_end, value)
+        })
+        .collect()
+}
+
+/// Channel keys of Unity serialized colors, in order.
+const UNITY_CHANNELS: [&str; 4] = ["r", "g", "b", "a"];
+
+/// Finds Unity serialized colors, e.g. `m_Color: {r: 0.1, g: 0.2, b: 0.3, a: 1}`,
+/// decorating the whole map. HDR components above 1 are clamped.
+pub fn unity(line: &str, line_idx: usize) -> Vec<ColorMatch> {
+    let mut colors = Vec::new();
+    for (open_idx, _) in line.match_indices('{') {
+        let Some(map_len) = line[open_idx..].find('}') else {
+            continue;
+        };
+        let entries: Vec<&str> = line[open_idx + 1..open_idx + map_len].split(',').collect();
+        if entries.len() != UNITY_CHANNELS.len() {
+            continue;
+        }
+        let channels: Option<Vec<f32>> = entries
+            .iter()
+            .zip(UNITY_CHANNELS)
+            .map(|(entry, channel)| {
+                let (key, value) = entry.split_once(':')?;
+                let value = value.trim().parse::<f32>().ok()?;
+                (key.trim() == channel && value.is_finite()).then(|| value.clamp(0.0, 1.0))
+            })
+            .collect();
+        let Some([red, green, blue, alpha]) = channels.as_deref() else {
+            continue;
+        };
+        colors.push(ColorMatch {
+            info: ColorInformation {
+                range: line_range(line, line_idx, open_idx..open_idx + map_len + 1),
+                color: Color {
+                    red: *red,
+                    green: *green,
+                    blue: *blue,
+                    alpha: *alpha,
+                },
+            },
+            format: ColorFormat::Heuristic,
+        });
+    }
+    colors
+}
+
+/// Channel keys of Unreal colors, in order.
+const UNREAL_CHANNELS: [&str; 4] = ["R", "G", "B", "A"];
+
+/// Finds Unreal struct literals of colors, e.g. `(R=0.1,G=0.2,B=0.3,A=1.0)`,
+/// decorating the parenthesized literal. `FLinearColor` values, with any
+/// fractional or all components in [0, 1], are linear and converted to sRGB;
+/// `FColor` values are sRGB bytes.
+pub fn unreal(line: &str, line_idx: usize) -> Vec<ColorMatch> {
+    let mut colors = Vec::new();
+    for (open_idx, _) in line.match_indices('(') {
+        let Some(literal_len) = line[open_idx..].find(')') else {
+            continue;
+        };
+        let entries: Vec<&str> = line[open_idx + 1..open_idx + literal_len]
+            .split(',')
+            .collect();
+        if entries.len() != UNREAL_CHANNELS.len() {
+            continue;
+        }
+        let Some(values) = entries
+            .iter()
+            .zip(UNREAL_CHANNELS)
+            .map(|(entry, channel)| {
+                let (key, value) = entry.split_once('=')?;
+                (key.trim() == channel).then_some(value.trim())
+            })
+            .collect::<Option<Vec<&str>>>()
+        else {
+            continue;
+        };
+        let Some(channels) = values
+            .iter()
+            .map(|value| value.parse::<f32>().ok().filter(|value| value.is_finite()))
+            .collect::<Option<Vec<f32>>>()
+        else {
+            continue;
+        };
+
+        let is_linear = values.iter().any(|value| value.contains('.'))
+            || channels.iter().all(|&value| value <= 1.0);
+        let channel = |idx: usize| {
+            if is_linear {
+                let value = channels[idx].clamp(0.0, 1.0);
+                // Alpha is stored linearly in both.
+                if idx == 3 {
+                    value
+                } else {
+                    linear_to_srgb(value)
+                }
+            } else {
+                channels[idx].clamp(0.0, 255.0) / 255.0
+            }
+        };
+        colors.push(ColorMatch {
+            info: ColorInformation {
+                range: line_range(line, line_idx, open_idx..open_idx + literal_len + 1),
+                color: Color {
+                    red: channel(0),
+                    green: channel(1),
+                    blue: channel(2),
+                    alpha: channel(3),
+                },
+            },
+            format: ColorFormat::Heuristic,
+        });
+    }
+    colors
+}
+
+/// Parses six hex digits, reporting `bytes` as their range.
+fn bare_hex_match(
+    line: &str,
+    line_idx: usize,
+    bytes: std::ops::Range<usize>,
+    digits: &str,
+) -> Option<ColorMatch> {
+    let color = parse_line_colors(&format!("#{digits}"), 0).first()?.color;
+    Some(ColorMatch {
+        info: ColorInformation {
+            range: line_range(line, line_idx, bytes),
+            color,
+        },
+        format: ColorFormat::Hex,
+    })
+}
+
+/// Resolves a CSS named color.
+fn named_match(
+    line: &str,
+    line_idx: usize,
+    bytes: std::ops::Range<usize>,
+    value: &str,
+) -> Option<ColorMatch> {
+    Some(ColorMatch {
+        info: ColorInformation {
+            range: line_range(line, line_idx, bytes),
+            color: named::lookup(value)?,
+        },
+        format: ColorFormat::Heuristic,
+    })
+}
+
+/// Resolves a palette index or terminal color name.
+fn ansi_match(
+    line: &str,
+    line_idx: usize,
+    bytes: std::ops::Range<usize>,
+    value: &str,
+) -> Option<ColorMatch> {
+    let index = value.parse::<u8>().ok().or_else(|| index_of(value))?;
+    Some(ColorMatch {
+        info: ColorInformation {
+            range: line_range(line, line_idx, bytes),
+            color: ansi256(index),
+        },
+        format: ColorFormat::Ansi,
+    })
+}
+
+/// Splits on whitespace, quotes, parentheses, braces and semicolons,
+/// returning tokens with their byte indices.
+fn tokens(s: &str) -> Vec<(usize, &str)> {
+    let is_separator = |c: char| c.is_whitespace() || "\"'(){};".contains(c);
+    let mut tokens = Vec::new();
+    let mut start = None;
+    for (idx, c) in s.char_indices().chain(std::iter::once((s.len(), ' '))) {
+        match (start, is_separator(c)) {
+            (None, false) => start = Some(idx),
+            (Some(token_start), true) => {
+                tokens.push((token_start, &s[token_start..idx]));
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    tokens
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::color::{ColorFormat, ColorMatch, to_hex};
+    use crate::profile::{
+        alacritty, ghostty, helix, packed_hex, starship, sublime_color_scheme, tm_theme, unity,
+        unreal, zellij,
+    };
+
+    fn summary(matches: Vec<ColorMatch>) -> Vec<(u32, u32, String, ColorFormat)> {
+        matches
+            .into_iter()
+            .map(|m| {
+                let range = m.info.range;
+                let hex = to_hex(&m.info.color);
+                (range.start.character, range.end.character, hex, m.format)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn helix_terminal_names() {
+        assert_eq!(
+            summary(helix(r#""ui.text" = { fg = "light-gray", bg = "bg0" }"#, 0)),
+            [(20, 30, "#E5E5E5".to_string(), ColorFormat::Ansi)]
+        );
+    }
+
+    #[test]
+    fn starship_styles() {
+        assert_eq!(
+            summary(starship(r#"style = "bold fg:208 bg:blue""#, 0)),
+            [
+                (17, 20, "#FF8700".to_string(), ColorFormat::Ansi),
+                (24, 28, "#0000EE".to_string(), ColorFormat::Ansi),
+            ]
+        );
+        assert_eq!(
+            summary(starship(r#"format = "[$symbol 42]($style)[x](red)""#, 0)),
+            [(34, 37, "#CD0000".to_string(), ColorFormat::Ansi)]
+        );
+        assert_eq!(summary(starship(r#"symbol = "red 42""#, 0)), []);
+    }
+
+    #[test]
+    fn zellij_values() {
+        assert_eq!(
+            summary(zellij("        fg 248 248 242", 0)),
+            [(11, 22, "#F8F8F2".to_string(), ColorFormat::Heuristic)]
+        );
+        assert_eq!(
+            summary(zellij("red 160;", 0)),
+            [(4, 7, "#D70000".to_string(), ColorFormat::Ansi)]
+        );
+        assert_eq!(summary(zellij("theme \"dracula\"", 0)), []);
+    }
+
+    #[test]
+    fn alacritty_prefixed_hex() {
+        assert_eq!(
+            summary(alacritty(
+                "background = '0x1D2021' # 0x12345678 a0x123456",
+                0
+            )),
+            [(14, 22, "#1D2021".to_string(), ColorFormat::Hex)]
+        );
+    }
+
+    #[test]
+    fn packed_hex_literals() {
+        assert_eq!(
+            summary(packed_hex(
+                r#"  dd 0XFF8800, 0FF8800h ; x"1D2021" mask 0x12345678 label_ff8800h"#,
+                0
+            )),
+            [
+                (5, 13, "#FF8800".to_string(), ColorFormat::Hex),
+                (15, 23, "#FF8800".to_string(), ColorFormat::Hex),
+                (26, 35, "#1D2021".to_string(), ColorFormat::Hex),
+            ]
+        );
+    }
+
+    #[test]
+    fn ghostty_bare_hex() {
+        assert_eq!(
+            summary(ghostty("background = 1d2021", 0)),
+            [(13, 19, "#1D2021".to_string(), ColorFormat::Hex)]
+        );
+        assert_eq!(summary(ghostty("font-size = 123456x", 0)), []);
+    }
+
+    #[test]
+    fn tm_theme_named_strings() {
+        assert_eq!(
+            summary(tm_theme("<key>foreground</key><string>White</string>", 0)),
+            [(29, 34, "#FFFFFF".to_string(), ColorFormat::Heuristic)]
+        );
+        assert_eq!(summary(tm_theme("<string>comment</string>", 0)), []);
+    }
+
+    #[test]
+    fn sublime_named_values() {
+        assert_eq!(
+            summary(sublime_color_scheme(r#""foreground": "white","#, 0)),
+            [(15, 20, "#FFFFFF".to_string(), ColorFormat::Heuristic)]
+        );
+        assert_eq!(
+            summary(sublime_color_scheme(r#""background": "var(white)""#, 0)),
+            []
+        );
+    }
+
+    #[test]
+    fn unity_color_maps() {
+        assert_eq!(
+            summary(unity("    m_Color: {r: 1, g: 0.5, b: 0, a: 1}", 0)),
+            [(13, 39, "#FF8000".to_string(), ColorFormat::Heuristic)]
+        );
+        assert_eq!(
+            summary(unity("  - _EmissionColor: {r: 2.5, g: 0, b: 0, a: 1}", 0)),
+            [(20, 46, "#FF0000".to_string(), ColorFormat::Heuristic)]
+        );
+        assert_eq!(summary(unity("  m_LocalScale: {x: 1, y: 1, z: 1}", 0)), []);
+    }
+
+    #[test]
+    fn unreal_colors() {
+        assert_eq!(
+            summary(unreal(
+                "DefaultColor=(R=0.214041,G=0.0,B=1.0,A=1.0) Tint=(R=255,G=128,B=0,A=255)",
+                0
+            )),
+            [
+                (13, 43, "#7F00FF".to_string(), ColorFormat::Heuristic),
+                (49, 72, "#FF8000".to_string(), ColorFormat::Heuristic),
+            ]
+        );
+        assert_eq!(summary(unreal("Location=(X=1.0,Y=2.0,Z=3.0,W=1)", 0)), []);
+    }
+}
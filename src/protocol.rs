@@ -0,0 +1,171 @@
+//! Custom protocol extensions and their parameter types.
+
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+use tower_lsp_server::ls_types::{
+    Color, ColorInformation, Position, Range, TextDocumentIdentifier, Uri,
+};
+
+use crate::gradient::{Hue, Space};
+use crate::nudge::Channel;
+use crate::presentation::Notation;
+use crate::terminal_theme::ThemeFormat;
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ColorHistogramParams {
+    /// Document to analyze; all open documents when omitted.
+    pub text_document: Option<TextDocumentIdentifier>,
+}
+
+/// Params of the `chroma/status` request.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StatusParams {
+    /// Document to report on; all open documents when omitted.
+    pub text_document: Option<TextDocumentIdentifier>,
+}
+
+/// Params of the `chroma/profile` request.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProfileParams {
+    pub text_document: TextDocumentIdentifier,
+}
+
+/// Params of the `chroma.findUnknownColors` command.
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UnknownColorsParams {
+    /// Document to analyze; all open documents when omitted.
+    pub text_document: Option<TextDocumentIdentifier>,
+}
+
+/// Params of the `chroma.extractPalette` command.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct ExtractPaletteParams {
+    /// Also extracts the colors of the workspace files that aren't open.
+    pub workspace: bool,
+}
+
+/// Params of the `chroma/colorMonikers` request.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ColorMonikersParams {
+    pub text_document: TextDocumentIdentifier,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ColorsDeltaParams {
+    pub text_document: TextDocumentIdentifier,
+}
+
+/// Params of the `chroma/documentColors` request.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DocumentColorsParams {
+    pub uris: Vec<Uri>,
+}
+
+/// Result of the `chroma/documentColors` request.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DocumentColorsResult {
+    pub documents: Vec<DocumentColors>,
+}
+
+/// Colors of a document in the `chroma/documentColors` result.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DocumentColors {
+    pub uri: Uri,
+    pub colors: Arc<[ColorInformation]>,
+}
+
+/// Params of the `chroma/viewportColors` request.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ViewportColorsParams {
+    pub text_document: TextDocumentIdentifier,
+    /// Visible range; colors on all of its lines are returned.
+    pub range: Range,
+}
+
+/// Params of the `chroma/colorChannels` request.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ColorChannelsParams {
+    pub text_document: TextDocumentIdentifier,
+    /// Position within the color to return; all colors when omitted.
+    pub position: Option<Position>,
+}
+
+/// Argument of the `chroma.nudgeColor` command.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NudgeColorParams {
+    pub text_document: TextDocumentIdentifier,
+    /// Position within the color to change.
+    pub position: Position,
+    /// Channel to change; the one at `position` when omitted.
+    pub channel: Option<Channel>,
+    /// Amount to move the channel by, negative to decrease it.
+    pub step: f32,
+}
+
+/// Argument of the `chroma.exportTerminalTheme` command.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportTerminalThemeParams {
+    pub text_document: TextDocumentIdentifier,
+    pub format: ThemeFormat,
+    /// Name of the theme; the file name of the document when omitted.
+    pub name: Option<String>,
+}
+
+/// Argument of the `chroma.convertColor` command.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConvertColorParams {
+    pub text_document: TextDocumentIdentifier,
+    /// Position of a color; every color of the document equal to it is
+    /// converted.
+    pub position: Position,
+    pub notation: Notation,
+}
+
+/// Argument of the `chroma.convertAllColors` command.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConvertAllColorsParams {
+    pub text_document: TextDocumentIdentifier,
+    pub notation: Notation,
+}
+
+/// Data of the code lens counting the uses of a color, resolved lazily.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UsageLensData {
+    pub uri: Uri,
+    pub hex: String,
+}
+
+/// Params of the `chroma/gradient` request, and argument of the
+/// `chroma.generateGradient` command.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GradientParams {
+    pub from: Color,
+    pub to: Color,
+    /// Space to interpolate in; sRGB when omitted.
+    #[serde(default)]
+    pub space: Space,
+    /// Way around the hue circle in OKLCH and HSL; the shorter when omitted.
+    #[serde(default)]
+    pub hue: Hue,
+    /// Number of stops, both ends included.
+    pub stops: usize,
+}
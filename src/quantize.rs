@@ -0,0 +1,62 @@
+use tower_lsp_server::ls_types::Color;
+
+/// A reduced palette that colors can be snapped to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Snap {
+    /// The 216-color web-safe palette, with channels in steps of `0x33`.
+    WebSafe,
+    /// Channels reduced to the given number of bits, in `1..=8`.
+    Bits(u8),
+}
+
+impl Snap {
+    /// Human-readable name, used in code action titles.
+    pub fn name(self) -> String {
+        match self {
+            Snap::WebSafe => "web-safe palette".to_string(),
+            Snap::Bits(bits) => format!("{bits}-bit channels"),
+        }
+    }
+
+    fn levels(self) -> f32 {
+        match self {
+            Snap::WebSafe => 5.0,
+            Snap::Bits(bits) => ((1u32 << bits.clamp(1, 8)) - 1) as f32,
+        }
+    }
+
+    /// Returns the nearest color of the palette. Alpha is kept as is.
+    pub fn apply(self, color: &Color) -> Color {
+        let levels = self.levels();
+        let snap = |value: f32| (value.clamp(0.0, 1.0) * levels).round() / levels;
+        Color {
+            red: snap(color.red),
+            green: snap(color.green),
+            blue: snap(color.blue),
+            alpha: color.alpha,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::color::{parse_line_colors, to_hex};
+    use crate::quantize::Snap;
+
+    fn snapped(snap: Snap, color: &str) -> String {
+        to_hex(&snap.apply(&parse_line_colors(color, 0)[0].color))
+    }
+
+    #[test]
+    fn web_safe() {
+        assert_eq!(snapped(Snap::WebSafe, "#1A73E8"), "#3366FF");
+        assert_eq!(snapped(Snap::WebSafe, "#1A73E880"), "#3366FF80");
+    }
+
+    #[test]
+    fn bit_depths() {
+        assert_eq!(snapped(Snap::Bits(4), "#1A73E8"), "#2277EE");
+        assert_eq!(snapped(Snap::Bits(1), "#1A73E8"), "#0000FF");
+        assert_eq!(snapped(Snap::Bits(8), "#1A73E8"), "#1A73E8");
+    }
+}
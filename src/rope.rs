@@ -0,0 +1,256 @@
+//! A rope of lines: text stored in chunks of consecutive lines, one string
+//! per chunk, with data attached to each line. Looking up a line binary
+//! searches the chunks, and splicing lines only rebuilds the chunks it
+//! touches, so huge documents neither allocate per line nor shift every later
+//! line on edits.
+
+use std::ops::Range;
+
+/// Most lines in a chunk.
+const CHUNK_LINES: usize = 256;
+/// Chunks rebuilt by a splice are merged with the next chunk below this size.
+const MIN_CHUNK_LINES: usize = CHUNK_LINES / 4;
+
+/// Lines of text, without line endings, each with data of type `T`.
+pub struct LineRope<T> {
+    chunks: Vec<Chunk<T>>,
+    /// Index of the first line of each chunk.
+    starts: Vec<usize>,
+    len: usize,
+}
+
+struct Chunk<T> {
+    /// Lines concatenated without separators.
+    text: String,
+    /// End offset of each line in `text`.
+    ends: Vec<usize>,
+    data: Vec<T>,
+}
+
+impl<T> Chunk<T> {
+    fn len(&self) -> usize {
+        self.ends.len()
+    }
+
+    fn line(&self, idx: usize) -> &str {
+        let start = idx.checked_sub(1).map_or(0, |prev| self.ends[prev]);
+        &self.text[start..self.ends[idx]]
+    }
+
+    fn into_lines(self) -> impl Iterator<Item = (String, T)> {
+        let mut start = 0;
+        let text = self.text;
+        self.ends
+            .into_iter()
+            .map(move |end| {
+                let line = text[start..end].to_string();
+                start = end;
+                line
+            })
+            .zip(self.data)
+    }
+}
+
+impl<T> Default for LineRope<T> {
+    fn default() -> Self {
+        Self {
+            chunks: Vec::new(),
+            starts: Vec::new(),
+            len: 0,
+        }
+    }
+}
+
+impl<T> LineRope<T> {
+    pub fn from_lines<S: AsRef<str>>(lines: impl IntoIterator<Item = (S, T)>) -> Self {
+        let mut rope = Self {
+            chunks: chunked(lines),
+            ..Self::default()
+        };
+        rope.index_chunks();
+        rope
+    }
+
+    /// Recomputes the first line of each chunk and the line count.
+    fn index_chunks(&mut self) {
+        self.starts.clear();
+        self.len = 0;
+        for chunk in &self.chunks {
+            self.starts.push(self.len);
+            self.len += chunk.len();
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the chunk holding line `idx` and the line's index in it.
+    fn locate(&self, idx: usize) -> Option<(usize, usize)> {
+        if idx >= self.len {
+            return None;
+        }
+        let chunk_idx = self.starts.partition_point(|&start| start <= idx) - 1;
+        Some((chunk_idx, idx - self.starts[chunk_idx]))
+    }
+
+    pub fn line(&self, idx: usize) -> Option<&str> {
+        let (chunk_idx, idx) = self.locate(idx)?;
+        Some(self.chunks[chunk_idx].line(idx))
+    }
+
+    pub fn data_mut(&mut self, idx: usize) -> Option<&mut T> {
+        let (chunk_idx, idx) = self.locate(idx)?;
+        Some(&mut self.chunks[chunk_idx].data[idx])
+    }
+
+    /// Returns the lines from `start` on, with their indices and data.
+    pub fn lines_from(&self, start: usize) -> impl Iterator<Item = (usize, &str, &T)> + Clone {
+        let (first_chunk, skip) = self.locate(start).unwrap_or((self.chunks.len(), 0));
+        self.chunks[first_chunk..]
+            .iter()
+            .enumerate()
+            .flat_map(move |(chunk_offset, chunk)| {
+                let from = if chunk_offset == 0 { skip } else { 0 };
+                (from..chunk.len()).map(move |idx| (chunk.line(idx), &chunk.data[idx]))
+            })
+            .enumerate()
+            .map(move |(offset, (line, data))| (start + offset, line, data))
+    }
+
+    /// Calls `f` with the index, text and mutable data of every line.
+    pub fn for_each_mut(&mut self, mut f: impl FnMut(usize, &str, &mut T)) {
+        let mut line_idx = 0;
+        for chunk in &mut self.chunks {
+            let mut start = 0;
+            for (end, data) in chunk.ends.iter().zip(&mut chunk.data) {
+                f(line_idx, &chunk.text[start..*end], data);
+                start = *end;
+                line_idx += 1;
+            }
+        }
+    }
+
+    /// Replaces the lines in `range` with `lines`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `range` is out of bounds.
+    pub fn splice(&mut self, range: Range<usize>, lines: Vec<(String, T)>) {
+        assert!(
+            range.start <= range.end && range.end <= self.len,
+            "line range out of bounds"
+        );
+        if self.chunks.is_empty() {
+            *self = Self::from_lines(lines);
+            return;
+        }
+
+        // Chunks holding the first and last replaced lines, or the line
+        // before an insertion at the end.
+        let (first, start_offset) = self.locate(range.start).unwrap_or((
+            self.chunks.len() - 1,
+            self.chunks.last().map_or(0, Chunk::len),
+        ));
+        let (mut last, _) = self
+            .locate(range.end.saturating_sub(1).max(range.start))
+            .unwrap_or((first, 0));
+        let end_offset = range.end - self.starts[last];
+
+        let removed_len = range.end - range.start;
+        let added_len = lines.len();
+        let mut rebuilt_len = start_offset + added_len + self.chunks[last].len() - end_offset;
+        if last + 1 < self.chunks.len() && rebuilt_len < MIN_CHUNK_LINES {
+            last += 1;
+            rebuilt_len += self.chunks[last].len();
+        }
+
+        let mut removed = self.chunks.drain(first..=last);
+        let mut rebuilt: Vec<(String, T)> = Vec::with_capacity(rebuilt_len);
+        let first_chunk = removed.next().expect("at least one chunk is removed");
+        let mut old_lines = first_chunk
+            .into_lines()
+            .chain(removed.flat_map(Chunk::into_lines));
+        rebuilt.extend(old_lines.by_ref().take(start_offset));
+        rebuilt.extend(lines);
+        rebuilt.extend(old_lines.skip(removed_len));
+
+        let chunks = chunked(rebuilt);
+        self.chunks.splice(first..first, chunks);
+        self.index_chunks();
+    }
+}
+
+/// Splits lines into evenly sized chunks of at most `CHUNK_LINES` lines.
+fn chunked<S: AsRef<str>, T>(lines: impl IntoIterator<Item = (S, T)>) -> Vec<Chunk<T>> {
+    let lines: Vec<(S, T)> = lines.into_iter().collect();
+    if lines.is_empty() {
+        return Vec::new();
+    }
+    let chunk_count = lines.len().div_ceil(CHUNK_LINES);
+    let chunk_len = lines.len().div_ceil(chunk_count);
+
+    let mut chunks = Vec::with_capacity(chunk_count);
+    let mut lines = lines.into_iter().peekable();
+    while lines.peek().is_some() {
+        let mut chunk = Chunk {
+            text: String::new(),
+            ends: Vec::with_capacity(chunk_len),
+            data: Vec::with_capacity(chunk_len),
+        };
+        for (line, data) in lines.by_ref().take(chunk_len) {
+            chunk.text.push_str(line.as_ref());
+            chunk.ends.push(chunk.text.len());
+            chunk.data.push(data);
+        }
+        chunks.push(chunk);
+    }
+    chunks
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::rope::LineRope;
+
+    fn numbered(range: std::ops::Range<usize>) -> Vec<(String, usize)> {
+        range.map(|idx| (format!("line {idx}"), idx)).collect()
+    }
+
+    #[test]
+    fn splices_across_chunks() {
+        let mut rope = LineRope::from_lines(numbered(0..1000));
+        assert_eq!(rope.len(), 1000);
+        assert_eq!(rope.line(999), Some("line 999"));
+
+        rope.splice(200..700, numbered(5000..5002));
+        assert_eq!(rope.len(), 502);
+        assert_eq!(rope.line(199), Some("line 199"));
+        assert_eq!(rope.line(200), Some("line 5000"));
+        assert_eq!(rope.line(202), Some("line 700"));
+
+        rope.splice(502..502, numbered(9000..9001));
+        rope.splice(0..1, Vec::new());
+        let lines: Vec<(usize, String, usize)> = rope
+            .lines_from(499)
+            .map(|(idx, line, data)| (idx, line.to_string(), *data))
+            .collect();
+        assert_eq!(
+            lines,
+            [
+                (499, "line 998".to_string(), 998),
+                (500, "line 999".to_string(), 999),
+                (501, "line 9000".to_string(), 9000),
+            ]
+        );
+        assert_eq!(rope.lines_from(502).count(), 0);
+
+        rope.splice(0..502, Vec::new());
+        assert!(rope.is_empty());
+        rope.splice(0..0, numbered(0..1));
+        assert_eq!(rope.line(0), Some("line 0"));
+    }
+}
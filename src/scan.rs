@@ -0,0 +1,91 @@
+//! Color extraction from files on disk, for the `chroma-ls scan` and
+//! `chroma-ls convert` commands and the false-positive fixtures that keep
+//! heuristic formats in check.
+
+use std::io;
+use std::path::{Path, PathBuf};
+
+use tower_lsp_server::ls_types::{ColorInformation, Uri};
+
+use crate::color::ParseOptions;
+use crate::convert;
+use crate::document::Document;
+use crate::heuristics::Syntax;
+use crate::presentation::Notation;
+use crate::settings::Settings;
+use crate::workspace;
+
+/// Returns the colors of `text`, parsed as the server would parse a document
+/// at `path` opened with default settings, using the extension as its
+/// language id.
+pub fn colors(path: &Path, text: &str) -> Vec<ColorInformation> {
+    document(path, text).get_colors()
+}
+
+fn document(path: &Path, text: &str) -> Document {
+    let settings = Settings::default();
+    let extension = path
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .unwrap_or("");
+    let options = ParseOptions {
+        named_colors: settings.named_colors.contains(extension),
+        ..settings.parse_options()
+    };
+    let syntax = std::path::absolute(path)
+        .ok()
+        .and_then(Uri::from_file_path)
+        .map(|uri| Syntax::detect(extension, &uri, text))
+        .unwrap_or_default();
+    Document::with_options(text, options.into(), syntax)
+}
+
+/// Returns the files under each of `paths`, which may be files or
+/// directories, skipping the same directories as a workspace scan.
+pub fn files(paths: &[PathBuf]) -> Vec<PathBuf> {
+    paths
+        .iter()
+        .flat_map(|path| {
+            if path.is_dir() {
+                workspace::files(path, |_| true)
+            } else {
+                vec![path.clone()]
+            }
+        })
+        .collect()
+}
+
+/// Reads `path` and returns its colors. Files that aren't UTF-8, such as
+/// images and archives, have none.
+pub fn scan_file(path: &Path) -> io::Result<Vec<ColorInformation>> {
+    let bytes = std::fs::read(path)?;
+    Ok(String::from_utf8(bytes)
+        .map(|text| colors(path, &text))
+        .unwrap_or_default())
+}
+
+/// Reads `path` and returns its text with its colors converted to
+/// `notation`, along with the number of colors converted. Files that aren't
+/// UTF-8 have no text to convert.
+pub fn convert_file(path: &Path, notation: Notation) -> io::Result<Option<(String, usize)>> {
+    let Ok(text) = String::from_utf8(std::fs::read(path)?) else {
+        return Ok(None);
+    };
+    let document = document(path, &text);
+    let edits = convert::all_edits(&document, notation);
+    Ok(Some(convert::apply(&document, &edits)))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use crate::scan::colors;
+
+    #[test]
+    fn parses_by_extension() {
+        assert_eq!(colors(Path::new("a.css"), "a { color: red; }").len(), 1);
+        assert_eq!(colors(Path::new("a.txt"), "a { color: red; }").len(), 0);
+        assert_eq!(colors(Path::new("a.txt"), "#1a73e8").len(), 1);
+    }
+}
@@ -0,0 +1,261 @@
+//! Field-aware parsing of JSON files with a recognized `$schema`.
+
+use tower_lsp_server::ls_types::{ColorInformation, Diagnostic, DiagnosticSeverity};
+
+use crate::color::{ColorFormat, ColorMatch};
+use crate::document::Document;
+use crate::heuristics::line_range;
+use crate::named;
+
+/// Keys of VS Code color themes whose values aren't colors.
+const VSCODE_THEME_NON_COLOR_KEYS: [&str; 8] = [
+    "$schema",
+    "name",
+    "type",
+    "include",
+    "scope",
+    "fontStyle",
+    "semanticHighlighting",
+    "semanticClass",
+];
+
+/// Color fields of Windows Terminal settings and color schemes.
+const WINDOWS_TERMINAL_KEYS: [&str; 24] = [
+    "foreground",
+    "background",
+    "cursorColor",
+    "selectionBackground",
+    "tabColor",
+    "black",
+    "red",
+    "green",
+    "yellow",
+    "blue",
+    "purple",
+    "cyan",
+    "white",
+    "brightBlack",
+    "brightRed",
+    "brightGreen",
+    "brightYellow",
+    "brightBlue",
+    "brightPurple",
+    "brightCyan",
+    "brightWhite",
+    "unfocusedBackground",
+    "titlebar",
+    "frame",
+];
+
+/// Color fields of web app manifests.
+const WEB_MANIFEST_KEYS: [&str; 2] = ["theme_color", "background_color"];
+
+/// JSON schemas with known color fields.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JsonSchema {
+    VsCodeTheme,
+    WindowsTerminal,
+    WebManifest,
+}
+
+impl JsonSchema {
+    /// Recognizes the schema from the `$schema` field of a JSON document.
+    pub fn detect(text: &str) -> Option<Self> {
+        let (_, _, schema) = text
+            .lines()
+            .flat_map(string_pairs)
+            .find(|(key, _, _)| *key == "$schema")?;
+        if schema.contains("vscode://schemas/color-theme") {
+            Some(JsonSchema::VsCodeTheme)
+        } else if schema.contains("terminal-profiles-schema") {
+            Some(JsonSchema::WindowsTerminal)
+        } else if schema.contains("web-manifest") {
+            Some(JsonSchema::WebManifest)
+        } else {
+            None
+        }
+    }
+
+    pub fn is_color_field(self, key: &str) -> bool {
+        match self {
+            JsonSchema::VsCodeTheme => !VSCODE_THEME_NON_COLOR_KEYS.contains(&key),
+            JsonSchema::WindowsTerminal => WINDOWS_TERMINAL_KEYS.contains(&key),
+            JsonSchema::WebManifest => WEB_MANIFEST_KEYS.contains(&key),
+        }
+    }
+
+    /// Whether fields also accept CSS named colors.
+    fn allows_names(self) -> bool {
+        self == JsonSchema::WebManifest
+    }
+
+    /// Keeps the colors of `hex` that are the value of a color field, and
+    /// adds named values where the schema accepts them.
+    pub fn parse_line(self, line: &str, line_idx: usize, hex: Vec<ColorMatch>) -> Vec<ColorMatch> {
+        let pairs = string_pairs(line);
+        let mut colors: Vec<ColorMatch> = hex
+            .into_iter()
+            .filter(|color_match| {
+                pairs.iter().any(|&(key, start, value)| {
+                    let range = line_range(line, line_idx, start..start + value.len());
+                    self.is_color_field(key) && range == color_match.info.range
+                })
+            })
+            .collect();
+
+        if self.allows_names() {
+            for &(key, start, value) in &pairs {
+                if let Some(color) = named::lookup(value).filter(|_| self.is_color_field(key)) {
+                    colors.push(ColorMatch {
+                        info: ColorInformation {
+                            range: line_range(line, line_idx, start..start + value.len()),
+                            color,
+                        },
+                        format: ColorFormat::Heuristic,
+                    });
+                }
+            }
+        }
+        colors
+    }
+
+    /// Reports values of color fields that aren't valid colors and, in VS
+    /// Code color themes, 8-digit colors that look like `#AARRGGBB`.
+    pub fn diagnostics(self, document: &Document) -> Vec<Diagnostic> {
+        let colors = document.get_colors();
+        let mut diagnostics = Vec::new();
+        for (line_idx, line) in document.lines().enumerate() {
+            for (key, start, value) in string_pairs(line) {
+                if !self.is_color_field(key) || value.is_empty() {
+                    continue;
+                }
+                let range = line_range(line, line_idx, start..start + value.len());
+                if colors.iter().all(|color_info| color_info.range != range) {
+                    diagnostics.push(Diagnostic {
+                        range,
+                        severity: Some(DiagnosticSeverity::WARNING),
+                        source: Some("chroma-ls".to_string()),
+                        message: format!("`{key}` expects a color, found `{value}`"),
+                        ..Default::default()
+                    });
+                } else if self == JsonSchema::VsCodeTheme
+                    && let Some(reordered) = alpha_first(value)
+                {
+                    diagnostics.push(Diagnostic {
+                        range,
+                        severity: Some(DiagnosticSeverity::HINT),
+                        source: Some("chroma-ls".to_string()),
+                        message: format!(
+                            "VS Code reads `{value}` as #RRGGBBAA; if the alpha comes first, write `{reordered}`"
+                        ),
+                        ..Default::default()
+                    });
+                }
+            }
+        }
+        diagnostics
+    }
+}
+
+/// Returns `value` with its first byte moved to the end if it looks like an
+/// opaque `#AARRGGBB` color, i.e. it starts with `FF` and doesn't end with it.
+fn alpha_first(value: &str) -> Option<String> {
+    let digits = value.strip_prefix('#').filter(|digits| digits.len() == 8)?;
+    let (alpha, rgb) = digits.split_at(2);
+    let is_opaque = |byte: &str| byte.eq_ignore_ascii_case("ff");
+    (is_opaque(alpha) && !is_opaque(&rgb[4..])).then(|| format!("#{rgb}{alpha}"))
+}
+
+/// Returns `"key": "value"` pairs of string values in a line, as the key, the
+/// byte index of the value and the value. Escaped quotes aren't supported.
+pub(crate) fn string_pairs(line: &str) -> Vec<(&str, usize, &str)> {
+    // Byte ranges of the contents of each string on the line.
+    let quotes: Vec<usize> = line.match_indices('"').map(|(idx, _)| idx).collect();
+    let strings: Vec<(usize, usize)> = quotes
+        .chunks_exact(2)
+        .map(|pair| (pair[0] + 1, pair[1]))
+        .collect();
+
+    strings
+        .windows(2)
+        .filter(|pair| line[pair[0].1 + 1..pair[1].0 - 1].trim() == ":")
+        .map(|pair| {
+            let (key, value) = (pair[0], pair[1]);
+            (&line[key.0..key.1], value.0, &line[value.0..value.1])
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::color::to_hex;
+    use crate::document::Document;
+    use crate::heuristics::Syntax;
+    use crate::schema::JsonSchema;
+
+    #[test]
+    fn detects_schema() {
+        let text = "{\n  \"$schema\": \"vscode://schemas/color-theme\",\n}";
+        assert_eq!(JsonSchema::detect(text), Some(JsonSchema::VsCodeTheme));
+        assert_eq!(JsonSchema::detect("{ \"name\": \"x\" }"), None);
+    }
+
+    #[test]
+    fn parses_only_color_fields() {
+        let text = r##"{
+  "$schema": "https://json.schemastore.org/web-manifest-combined.json",
+  "name": "#1A73E8 app",
+  "theme_color": "#1A73E8",
+  "background_color": "white",
+  "description": "white"
+}"##;
+        let document = Document::with_options(
+            text,
+            Default::default(),
+            Syntax::Json(JsonSchema::WebManifest),
+        );
+
+        let colors: Vec<_> = document
+            .get_colors()
+            .iter()
+            .map(|c| (c.range.start.line, to_hex(&c.color)))
+            .collect();
+        assert_eq!(
+            colors,
+            [(3, "#1A73E8".to_string()), (4, "#FFFFFF".to_string())]
+        );
+    }
+
+    #[test]
+    fn alpha_first_hint() {
+        let text = "{\n  \"colors\": {\n    \"editor.background\": \"#FF1E1E1E\",\n    \"editor.foreground\": \"#D4D4D4FF\"\n  }\n}";
+        let document = Document::with_options(
+            text,
+            Default::default(),
+            Syntax::Json(JsonSchema::VsCodeTheme),
+        );
+
+        let diagnostics = JsonSchema::VsCodeTheme.diagnostics(&document);
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].range.start.line, 2);
+        assert!(diagnostics[0].message.ends_with("write `#1E1E1EFF`"));
+    }
+
+    #[test]
+    fn invalid_color_values() {
+        let text = "{\n  \"$schema\": \"https://aka.ms/terminal-profiles-schema\",\n  \"background\": \"#12\",\n  \"name\": \"x\"\n}";
+        let document = Document::with_options(
+            text,
+            Default::default(),
+            Syntax::Json(JsonSchema::WindowsTerminal),
+        );
+
+        let diagnostics = JsonSchema::WindowsTerminal.diagnostics(&document);
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].range.start.line, 2);
+        assert_eq!(
+            diagnostics[0].message,
+            "`background` expects a color, found `#12`"
+        );
+    }
+}
@@ -0,0 +1,3249 @@
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Arc, Mutex, OnceLock, PoisonError};
+use std::time::Instant;
+
+use serde::de::DeserializeOwned;
+use serde_json::{Map, Value, json};
+use tokio::sync::{RwLock, RwLockWriteGuard, Semaphore, watch};
+use tokio::task::JoinSet;
+use tower_lsp_server::jsonrpc::Result;
+use tower_lsp_server::ls_types::{
+    CodeAction, CodeActionKind, CodeActionOrCommand, CodeActionParams,
+    CodeActionProviderCapability, CodeActionResponse, CodeLens, CodeLensOptions, CodeLensParams,
+    Color, ColorInformation, ColorPresentation, ColorPresentationParams, ColorProviderCapability,
+    Command, CompletionItem, CompletionItemKind, CompletionOptions, CompletionParams,
+    CompletionResponse, Diagnostic, DiagnosticSeverity, DidChangeConfigurationParams,
+    DidChangeTextDocumentParams, DidChangeWatchedFilesParams,
+    DidChangeWatchedFilesRegistrationOptions, DidCloseTextDocumentParams,
+    DidOpenTextDocumentParams, DocumentColorParams, DocumentHighlight, DocumentHighlightKind,
+    DocumentHighlightParams, DocumentLink, DocumentLinkOptions, DocumentLinkParams,
+    DocumentSymbolParams, DocumentSymbolResponse, Documentation, ExecuteCommandOptions,
+    ExecuteCommandParams, FileSystemWatcher, GlobPattern, GotoDefinitionParams,
+    GotoDefinitionResponse, Hover, HoverContents, HoverParams, HoverProviderCapability,
+    InitializeParams, InitializeResult, InitializedParams, InlayHint, InlayHintOptions,
+    InlayHintParams, InlayHintServerCapabilities, LSPAny, LinkedEditingRangeParams,
+    LinkedEditingRangeServerCapabilities, LinkedEditingRanges, Location, MarkupContent, MarkupKind,
+    MessageType, NumberOrString, OneOf, Position, PrepareRenameResponse, ProgressToken, Range,
+    ReferenceParams, Registration, RenameOptions, RenameParams, SemanticTokenType, SemanticTokens,
+    SemanticTokensFullOptions, SemanticTokensLegend, SemanticTokensOptions, SemanticTokensParams,
+    SemanticTokensResult, ServerCapabilities, ServerInfo, SetTraceParams,
+    TextDocumentContentChangeEvent, TextDocumentPositionParams, TextDocumentSyncCapability,
+    TextDocumentSyncKind, TextDocumentSyncOptions, TextEdit, Uri, WillSaveTextDocumentParams,
+    WorkDoneProgressCancelParams, WorkspaceEdit, WorkspaceSymbolParams, WorkspaceSymbolResponse,
+};
+use tower_lsp_server::{Client, ClientSocket, LanguageServer, LspService};
+
+use crate::android::{self, ResourceIndex};
+use crate::burst::Bursts;
+use crate::capabilities::{ColorSurfaces, encode_semantic_tokens};
+use crate::channels::{self, ColorChannels};
+use crate::color::{ColorFormat, ParseOptions, to_hex};
+use crate::colorset;
+use crate::completion;
+use crate::composite::composite;
+use crate::conformance::{self, Validation};
+use crate::contrast::{self, contrast_issues, markdown_report};
+use crate::convert;
+use crate::cvd::Deficiency;
+use crate::delta::{ColorsDelta, diff};
+use crate::document::Document;
+use crate::duplicates::{self, Definition};
+use crate::encoding::{self, Encoded, PositionEncoding, Shared};
+use crate::error::{self, Error};
+use crate::extract::{self, VariableKind};
+use crate::gradient::{self, GradientStop};
+use crate::harmony::Harmony;
+use crate::heuristics::Syntax;
+use crate::histogram::ColorHistogram;
+use crate::hover;
+use crate::inlay_hint;
+use crate::integer;
+use crate::journal::Journal;
+use crate::linked_editing;
+use crate::links;
+use crate::logging;
+use crate::memory::Recency;
+use crate::missing;
+use crate::moniker::{self, ColorMonikers};
+use crate::named;
+use crate::normalize::normalize_edits;
+use crate::nudge::Channel;
+use crate::output::{ExtractedPalette, Versioned};
+use crate::palette::Palette;
+use crate::presentation::{HexStyle, Notation, css_hsl, css_rgb, unity_map, unreal_struct};
+use crate::protocol::{
+    ColorChannelsParams, ColorHistogramParams, ColorMonikersParams, ColorsDeltaParams,
+    ConvertAllColorsParams, ConvertColorParams, DocumentColors, DocumentColorsParams,
+    DocumentColorsResult, ExportTerminalThemeParams, ExtractPaletteParams, GradientParams,
+    NudgeColorParams, ProfileParams, StatusParams, UnknownColorsParams, UsageLensData,
+    ViewportColorsParams,
+};
+use crate::quantize::Snap;
+use crate::schema::JsonSchema;
+use crate::settings::Settings;
+use crate::status::{DocumentStatus, Status};
+use crate::svg;
+use crate::symbols::{self, MAX_WORKSPACE_SYMBOLS, SymbolQuery};
+use crate::tailwind;
+use crate::terminal_theme;
+use crate::theme_object::{self, ThemeIndex};
+use crate::timing::{self, DocumentProfile};
+use crate::tokens::{self, TokenIndex};
+use crate::unknown;
+use crate::unused;
+use crate::variables::{self, VariableIndex};
+use crate::workspace::{self, Scope};
+
+const SUGGEST_HARMONIES_COMMAND: &str = "chroma.suggestHarmonies";
+const CONTRAST_REPORT_COMMAND: &str = "chroma.contrastReport";
+const IMPORT_PALETTE_COMMAND: &str = "chroma.importPalette";
+const DUPLICATE_COLORS_COMMAND: &str = "chroma.findDuplicateColors";
+const UNUSED_COLORS_COMMAND: &str = "chroma.findUnusedColors";
+const UNKNOWN_COLORS_COMMAND: &str = "chroma.findUnknownColors";
+const REINDEX_WORKSPACE_COMMAND: &str = "chroma.reindexWorkspace";
+const NUDGE_COLOR_COMMAND: &str = "chroma.nudgeColor";
+const GENERATE_GRADIENT_COMMAND: &str = "chroma.generateGradient";
+const EXPORT_TERMINAL_THEME_COMMAND: &str = "chroma.exportTerminalTheme";
+const DUMP_STATE_COMMAND: &str = "chroma.dumpState";
+const CONVERT_COLOR_COMMAND: &str = "chroma.convertColor";
+const CONVERT_ALL_COLORS_COMMAND: &str = "chroma.convertAllColors";
+const EXTRACT_PALETTE_COMMAND: &str = "chroma.extractPalette";
+/// Kind of the source actions converting every color of a document.
+const CONVERT_ALL_COLORS_KIND: &str = "source.convertAllColors";
+/// How many times documentColor recomputes when edits land mid-computation,
+/// before failing with `ContentModified`.
+const MAX_STALE_RETRIES: usize = 3;
+/// Documents with more lines than this are processed on the blocking pool.
+const HEAVY_LINE_COUNT: usize = 5_000;
+
+/// Semantic token type used by the fallback for clients without documentColor.
+const COLOR_TOKEN_TYPE: &str = "color";
+
+pub struct Backend {
+    client: Client,
+    documents: Arc<RwLock<HashMap<Uri, Document>>>,
+    surfaces: OnceLock<ColorSurfaces>,
+    /// Encoding negotiated at initialization, which the ranges of changes
+    /// count columns in.
+    position_encoding: OnceLock<PositionEncoding>,
+    /// Settings from `initializationOptions`, replaced on
+    /// `workspace/didChangeConfiguration`, with the parse options built from them.
+    settings: Mutex<(Arc<Settings>, Arc<ParseOptions>)>,
+    /// Last result returned by `chroma/colorsDelta`, per document.
+    delta_baselines: RwLock<HashMap<Uri, Arc<[ColorInformation]>>>,
+    /// Bounds how many heavy jobs run on the blocking pool at once.
+    blocking_jobs: Semaphore,
+    /// Held by `didChange` while applying changes, so changes received while
+    /// a heavy full change is parsed outside the documents lock still apply
+    /// in order.
+    editing: tokio::sync::Mutex<()>,
+    /// Android color resources, from open documents and `res/values*` files.
+    resources: RwLock<ResourceIndex>,
+    /// Compose and Flutter theme colors, from open documents and workspace sources.
+    theme_colors: RwLock<ThemeIndex>,
+    /// Stylesheet color variables, from open documents and workspace stylesheets.
+    variables: RwLock<VariableIndex>,
+    /// Design tokens, from open documents and workspace token files.
+    tokens: RwLock<TokenIndex>,
+    /// Number of notifications updating the indexes, so requests reading them
+    /// can wait for the ones received earlier.
+    indexing: watch::Sender<usize>,
+    /// Documents whose heavy full change is parsed outside the documents
+    /// lock, so color requests can wait for the new text.
+    parsing: watch::Sender<HashSet<Uri>>,
+    /// Documents with published missing reference or conformance
+    /// diagnostics, to clear.
+    diagnosed_uris: RwLock<HashSet<Uri>>,
+    /// Palettes offered as snapping targets, from settings and imports, with
+    /// the URIs of their files.
+    palettes: RwLock<Vec<(Uri, Palette)>>,
+    workspace_roots: OnceLock<Vec<Uri>>,
+    /// Open documents the server doesn't activate on, per the settings. They
+    /// are tracked as empty documents, so requests find no colors.
+    inactive_documents: RwLock<HashSet<Uri>>,
+    /// Order in which documents were last opened, changed or decorated, to
+    /// evict the least recently used past `memoryBudget`.
+    recency: Mutex<Recency>,
+    /// Changes of each document being applied, so a burst of them settles
+    /// the document once.
+    bursts: Mutex<Bursts>,
+    /// Whether the client accepts server-initiated progress.
+    work_done_progress: OnceLock<bool>,
+    /// Whether the client watches files for the server once asked to.
+    watch_files: OnceLock<bool>,
+    /// Progress tokens of running reindexes, and whether the client asked
+    /// to cancel each.
+    reindexing: RwLock<HashMap<ProgressToken, bool>>,
+    /// Numbers server-created progress tokens.
+    next_progress_token: AtomicU32,
+    /// Raw settings and recent requests and errors, for `chroma.dumpState`.
+    journal: Journal,
+    /// Colors of the workspace files as saved, read on first use by
+    /// references and code lenses, and dropped when files may have changed.
+    workspace_colors: RwLock<Option<Arc<WorkspaceColors>>>,
+}
+
+/// Literal colors of workspace files, by URI.
+type WorkspaceColors = HashMap<Uri, Arc<[ColorInformation]>>;
+
+/// Guard of [`Backend::start_indexing`].
+struct Indexing<'a>(&'a watch::Sender<usize>);
+
+impl Drop for Indexing<'_> {
+    fn drop(&mut self) {
+        self.0.send_modify(|count| *count -= 1);
+    }
+}
+
+/// Index a workspace file is read into.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum IndexKind {
+    Resources,
+    ThemeColors,
+    Variables,
+    Tokens,
+}
+
+/// Returns the files of the workspace folders to index, by index. A file may
+/// belong to several indexes.
+fn workspace_index_files(roots: &[PathBuf]) -> Vec<(IndexKind, PathBuf)> {
+    let mut files = Vec::new();
+    for root in roots {
+        let kinds = [
+            (
+                IndexKind::Resources,
+                android::workspace_resource_files(root),
+            ),
+            (
+                IndexKind::ThemeColors,
+                theme_object::workspace_source_files(root),
+            ),
+            (
+                IndexKind::Variables,
+                variables::workspace_source_files(root),
+            ),
+            (IndexKind::Tokens, tokens::workspace_token_files(root)),
+        ];
+        for (kind, paths) in kinds {
+            files.extend(paths.into_iter().map(|path| (kind, path)));
+        }
+    }
+    files
+}
+
+/// Reads a workspace file to index, skipping sources that can't define
+/// theme colors.
+fn read_index_file(kind: IndexKind, path: PathBuf) -> Option<(IndexKind, Uri, String)> {
+    let text = std::fs::read_to_string(&path).ok()?;
+    // Only sources with ARGB literals can define theme colors.
+    if kind == IndexKind::ThemeColors && !text.contains("Color(0x") {
+        return None;
+    }
+    Some((kind, Uri::from_file_path(path)?, text))
+}
+
+/// Returns the workspace files of `files` that aren't open. Open documents
+/// may have unsaved changes, so they win over disk.
+fn unopened<T>(
+    documents: &HashMap<Uri, Document>,
+    files: impl IntoIterator<Item = T>,
+    uri: impl Fn(&T) -> &Uri,
+) -> impl Iterator<Item = T> {
+    files
+        .into_iter()
+        .filter(move |file| !documents.contains_key(uri(file)))
+}
+
+impl Backend {
+    pub fn new(client: Client) -> Self {
+        Self {
+            client,
+            documents: Arc::new(RwLock::new(HashMap::new())),
+            surfaces: OnceLock::new(),
+            position_encoding: OnceLock::new(),
+            settings: Mutex::default(),
+            delta_baselines: RwLock::new(HashMap::new()),
+            blocking_jobs: Semaphore::new(
+                std::thread::available_parallelism().map_or(1, |n| n.get()),
+            ),
+            editing: tokio::sync::Mutex::new(()),
+            resources: RwLock::new(ResourceIndex::default()),
+            theme_colors: RwLock::new(ThemeIndex::default()),
+            variables: RwLock::new(VariableIndex::default()),
+            tokens: RwLock::new(TokenIndex::default()),
+            indexing: watch::Sender::new(0),
+            parsing: watch::Sender::new(HashSet::new()),
+            diagnosed_uris: RwLock::new(HashSet::new()),
+            palettes: RwLock::new(Vec::new()),
+            workspace_roots: OnceLock::new(),
+            inactive_documents: RwLock::new(HashSet::new()),
+            recency: Mutex::default(),
+            bursts: Mutex::default(),
+            work_done_progress: OnceLock::new(),
+            watch_files: OnceLock::new(),
+            reindexing: RwLock::new(HashMap::new()),
+            next_progress_token: AtomicU32::new(0),
+            journal: Journal::default(),
+            workspace_colors: RwLock::new(None),
+        }
+    }
+
+    fn settings(&self) -> Arc<Settings> {
+        let settings = self.settings.lock().unwrap_or_else(PoisonError::into_inner);
+        Arc::clone(&settings.0)
+    }
+
+    fn parse_options(&self) -> Arc<ParseOptions> {
+        let settings = self.settings.lock().unwrap_or_else(PoisonError::into_inner);
+        Arc::clone(&settings.1)
+    }
+
+    fn set_settings(&self, settings: Settings) {
+        let mut current = self.settings.lock().unwrap_or_else(PoisonError::into_inner);
+        // The Tailwind theme comes from config files rather than settings.
+        let parse_options = Arc::new(ParseOptions {
+            tailwind: Arc::clone(&current.1.tailwind),
+            ..settings.parse_options()
+        });
+        *current = (Arc::new(settings), parse_options);
+    }
+
+    fn set_tailwind_theme(&self, theme: tailwind::Theme) {
+        let mut current = self.settings.lock().unwrap_or_else(PoisonError::into_inner);
+        current.1 = Arc::new(ParseOptions {
+            tailwind: Arc::new(theme),
+            ..(*current.1).clone()
+        });
+    }
+
+    /// Rebuilds the parse options of the active documents, after the settings
+    /// or the Tailwind theme changed.
+    async fn refresh_parse_options(&self, documents: &mut HashMap<Uri, Document>) {
+        let inactive = self.inactive_documents.read().await;
+        for (uri, document) in documents.iter_mut() {
+            // Inactive documents have no text to parse until they're reopened.
+            if !inactive.contains(uri) {
+                let options = self.document_parse_options(document.language_id(), uri);
+                document.set_options(options);
+            }
+        }
+    }
+
+    /// Reads the colors of the Tailwind configs of the workspace folders.
+    /// Configs found later, in path order, win over earlier ones.
+    async fn read_tailwind_theme(&self) -> tailwind::Theme {
+        let roots = self.workspace_paths();
+        self.run_blocking(move || {
+            let mut theme = tailwind::Theme::default();
+            for root in &roots {
+                for path in workspace::files(root, tailwind::is_config) {
+                    if let Ok(text) = std::fs::read_to_string(path) {
+                        theme.merge(tailwind::Theme::from_config(&text));
+                    }
+                }
+            }
+            theme
+        })
+        .await
+        .unwrap_or_default()
+    }
+
+    /// Returns the workspace folder scope of a document.
+    fn scope(&self, uri: &Uri) -> Scope<'_> {
+        Scope::of(self.workspace_roots.get().map_or(&[], Vec::as_slice), uri)
+    }
+
+    fn read_only(&self) -> bool {
+        self.settings().read_only
+    }
+
+    /// Whether the settings activate the server on a document with this
+    /// language id and size in bytes.
+    fn activates_on(&self, language_id: &str, uri: &Uri, size: usize) -> bool {
+        self.settings()
+            .activates_on(language_id, extension(uri), size)
+    }
+
+    /// Marks `uri` as the most recently used document.
+    fn touch(&self, uri: &Uri) {
+        self.recency
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .touch(uri);
+    }
+
+    /// Drops the parsed colors of the least recently used documents while
+    /// the documents exceed `memoryBudget`. They're reparsed when requested.
+    fn fit_memory_budget(&self, documents: &mut HashMap<Uri, Document>) {
+        if let Some(budget) = self.settings().memory_budget {
+            self.recency
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .evict(documents, budget);
+        }
+    }
+
+    /// Returns a swatch of each translucent color over the first background,
+    /// to follow it, when `compositeSwatches` is set.
+    fn composite_swatches(&self, colors: &[ColorInformation]) -> Vec<ColorInformation> {
+        let settings = self.settings();
+        let Some(background) = settings
+            .backgrounds
+            .first()
+            .filter(|_| settings.composite_swatches)
+        else {
+            return Vec::new();
+        };
+        colors
+            .iter()
+            .filter(|color_info| color_info.color.alpha < 1.0)
+            .map(|color_info| ColorInformation {
+                range: Range {
+                    start: color_info.range.end,
+                    end: color_info.range.end,
+                },
+                color: composite(&color_info.color, background),
+            })
+            .collect()
+    }
+
+    /// Tracks a document the server doesn't activate on as an empty document.
+    async fn open_inactive(&self, documents: &mut HashMap<Uri, Document>, uri: Uri, version: i32) {
+        let mut document = Document::default();
+        document.set_version(version);
+        self.inactive_documents.write().await.insert(uri.clone());
+        documents.insert(uri, document);
+    }
+
+    /// Returns the parse options of a document, decorating named colors in
+    /// the configured languages.
+    fn document_parse_options(&self, language_id: &str, uri: &Uri) -> Arc<ParseOptions> {
+        let options = self.parse_options();
+        let extension = extension(uri);
+        let settings = self.settings();
+        let named_colors = settings.named_colors.contains(language_id)
+            || settings.named_colors.contains(extension);
+        let formats = settings.language_formats(language_id, extension);
+        let integer_colors = settings.integer_colors(language_id, extension);
+        let strict = settings.validation(language_id, extension) == Validation::Strict;
+        if named_colors || formats.is_some() || integer_colors.is_some() || strict {
+            Arc::new(ParseOptions {
+                named_colors,
+                formats: formats.or(options.formats.as_ref()).cloned(),
+                integer_colors,
+                strict,
+                ..(*options).clone()
+            })
+        } else {
+            options
+        }
+    }
+
+    /// Counts the caller as updating the indexes until the guard drops. Taken
+    /// before the caller's first await, so requests received later wait for it.
+    fn start_indexing(&self) -> Indexing<'_> {
+        self.indexing.send_modify(|count| *count += 1);
+        Indexing(&self.indexing)
+    }
+
+    /// Waits until no notification is updating the indexes.
+    async fn indexed(&self) {
+        // `self` owns the sender, so it can't close while waiting.
+        let _ = self
+            .indexing
+            .subscribe()
+            .wait_for(|count| *count == 0)
+            .await;
+    }
+
+    /// Runs `f` on the blocking pool, waiting for a free slot first so heavy
+    /// requests can't starve the async executor or pile up unboundedly.
+    async fn run_blocking<T, F>(&self, f: F) -> error::Result<T>
+    where
+        T: Send + 'static,
+        F: FnOnce() -> T + Send + 'static,
+    {
+        let _permit = self
+            .blocking_jobs
+            .acquire()
+            .await
+            .map_err(|err| Error::Task(err.to_string()))?;
+        tokio::task::spawn_blocking(f)
+            .await
+            .map_err(|err| Error::Task(err.to_string()))
+    }
+
+    /// Returns the encoding negotiated with the client, UTF-16 before
+    /// initialization.
+    fn position_encoding(&self) -> PositionEncoding {
+        self.position_encoding.get().copied().unwrap_or_default()
+    }
+
+    /// Runs `f` on the blocking pool with every free slot, at least one, and
+    /// passes it how many it holds, so parses splitting work across threads
+    /// don't use more than the pool allows.
+    async fn run_parallel<T, F>(&self, f: F) -> error::Result<T>
+    where
+        T: Send + 'static,
+        F: FnOnce(usize) -> T + Send + 'static,
+    {
+        let threads = self.blocking_jobs.available_permits().max(1);
+        let _permits = self
+            .blocking_jobs
+            .acquire_many(threads as u32)
+            .await
+            .map_err(|err| Error::Task(err.to_string()))?;
+        tokio::task::spawn_blocking(move || f(threads))
+            .await
+            .map_err(|err| Error::Task(err.to_string()))
+    }
+
+    /// Logs a failed `result` to the client and converts it into a JSON-RPC
+    /// error, so every handler reports failures the same way.
+    async fn reported<T>(&self, result: error::Result<T>) -> Result<T> {
+        match result {
+            Ok(value) => Ok(value),
+            Err(error) => {
+                tracing::error!("{error}");
+                self.journal.record_error(error.to_string());
+                self.client
+                    .log_message(error.message_type(), error.to_string())
+                    .await;
+                Err(error.into())
+            }
+        }
+    }
+
+    /// Runs `f` over the document store on the blocking pool.
+    async fn with_documents_blocking<T, F>(&self, f: F) -> error::Result<T>
+    where
+        T: Send + 'static,
+        F: FnOnce(&HashMap<Uri, Document>) -> T + Send + 'static,
+    {
+        // Lock before taking a blocking job permit: handlers holding the
+        // write lock may be waiting for one, as `didOpen` does.
+        let documents = Arc::clone(&self.documents).read_owned().await;
+        self.run_blocking(move || f(&documents)).await
+    }
+
+    /// Returns colors added and removed since the previous `chroma/colorsDelta`
+    /// for the document; the first call reports every color as added.
+    pub async fn colors_delta(&self, params: ColorsDeltaParams) -> Result<Versioned<ColorsDelta>> {
+        let uri = params.text_document.uri;
+        let colors = self.versioned_colors(&uri).await.map(|(_, colors)| colors);
+        let colors = self.reported(colors).await?;
+
+        let mut baselines = self.delta_baselines.write().await;
+        let previous = baselines.insert(uri, colors.clone()).unwrap_or_default();
+        Ok(Versioned::new(diff(&previous, &colors)))
+    }
+
+    /// Returns colors on the lines of the visible range, so editors can
+    /// decorate the viewport before requesting the whole document.
+    ///
+    /// Always computed inline: the viewport is small even in huge documents.
+    pub async fn viewport_colors(
+        &self,
+        params: ViewportColorsParams,
+    ) -> Result<Vec<ColorInformation>> {
+        let uri = params.text_document.uri;
+        let start = params.range.start.line as usize;
+        let end = params.range.end.line as usize;
+
+        let colors = self
+            .documents
+            .read()
+            .await
+            .get(&uri)
+            .map(|document| document.get_line_colors(start..=end))
+            .ok_or(Error::DocumentNotFound(uri));
+        self.reported(colors).await
+    }
+
+    /// Returns stable identifiers of the document's colors, so editors can
+    /// render one swatch per distinct color and format.
+    pub async fn color_monikers(
+        &self,
+        params: ColorMonikersParams,
+    ) -> Result<Versioned<ColorMonikers>> {
+        let uri = params.text_document.uri;
+        let monikers = self
+            .documents
+            .read()
+            .await
+            .get(&uri)
+            .map(|document| Versioned::new(moniker::monikers(document.matches())))
+            .ok_or(Error::DocumentNotFound(uri));
+        self.reported(monikers).await
+    }
+
+    /// Returns the ranges of the channels of functional colors, so editors
+    /// can select or nudge a single channel.
+    pub async fn color_channels(&self, params: ColorChannelsParams) -> Result<Vec<ColorChannels>> {
+        let uri = params.text_document.uri;
+        let channels = self
+            .documents
+            .read()
+            .await
+            .get(&uri)
+            .map(|document| channels::find(document, params.position))
+            .ok_or(Error::DocumentNotFound(uri));
+        self.reported(channels).await
+    }
+
+    /// Returns the colors to decorate a document with: its own, those of
+    /// references and composite swatches. Documents with neither share the
+    /// colors of the document.
+    async fn decorated_colors(&self, uri: &Uri) -> error::Result<Arc<[ColorInformation]>> {
+        // Edits arriving while a heavy document is being processed would leave
+        // decorations misaligned, so recompute until the version is stable.
+        for _ in 0..=MAX_STALE_RETRIES {
+            let (version, mut colors) = self.versioned_colors(uri).await?;
+            let Some(resolved) = self.resource_colors(uri, version).await else {
+                continue;
+            };
+            if !resolved.is_empty() {
+                let mut decorated = colors.to_vec();
+                decorated.extend(resolved);
+                decorated.sort_by_key(|color_info| color_info.range.start);
+                colors = decorated.into();
+            }
+            let swatches = self.composite_swatches(&colors);
+            if !swatches.is_empty() {
+                colors = colors.iter().cloned().chain(swatches).collect();
+            }
+            if self.document_version(uri).await == Some(version) {
+                return Ok(colors);
+            }
+        }
+        Err(Error::ContentModified(uri.clone()))
+    }
+
+    /// Returns the colors of several documents, as `textDocument/documentColor`
+    /// would, for project-wide views. Documents that aren't open are left out.
+    pub async fn document_colors(
+        &self,
+        params: DocumentColorsParams,
+    ) -> Result<Versioned<DocumentColorsResult>> {
+        let mut documents = Vec::with_capacity(params.uris.len());
+        for uri in params.uris {
+            match self.decorated_colors(&uri).await {
+                Ok(colors) => documents.push(DocumentColors { uri, colors }),
+                Err(Error::DocumentNotFound(_)) => {}
+                Err(error) => return self.reported(Err(error)).await,
+            }
+        }
+        Ok(Versioned::new(DocumentColorsResult { documents }))
+    }
+
+    /// Parses `changes` into a detached document when they replace the
+    /// content of `uri` with a text heavy to parse, releasing the documents
+    /// lock meanwhile so requests keep reading the previous version.
+    ///
+    /// The parsed document is returned only if no open, close or eviction
+    /// replaced the one the parse started from. Otherwise `changes` are left
+    /// to [`Backend::edit_document`].
+    async fn parse_detached<'a>(
+        &'a self,
+        documents: RwLockWriteGuard<'a, HashMap<Uri, Document>>,
+        uri: &Uri,
+        changes: &mut Vec<TextDocumentContentChangeEvent>,
+    ) -> (
+        RwLockWriteGuard<'a, HashMap<Uri, Document>>,
+        Option<error::Result<Document>>,
+    ) {
+        let Some(full_idx) = changes.iter().rposition(|change| change.range.is_none()) else {
+            return (documents, None);
+        };
+        let full = &changes[full_idx].text;
+        if !is_heavy(full) {
+            return (documents, None);
+        }
+        let (base, mut document) = match documents.get(uri) {
+            Some(document) => (Some(document.version()), document.detached()),
+            None if self.activates_on("", uri, full.len()) => {
+                let syntax = Syntax::detect("", uri, full);
+                let options = self.document_parse_options("", uri);
+                (None, Document::with_options("", options, syntax))
+            }
+            None => return (documents, None),
+        };
+        self.parsing.send_modify(|parsing| {
+            parsing.insert(uri.clone());
+        });
+        drop(documents);
+
+        let full = changes.split_off(full_idx);
+        let encoding = self.position_encoding();
+        let parsed = self
+            .run_parallel(move |threads| {
+                let (first, rest) = full.split_first().expect("starts with the full change");
+                document.set_text_on(&first.text, threads);
+                for change in rest {
+                    document.edit(&encoding.decode_change(&document, change));
+                }
+                (document, full)
+            })
+            .await;
+        let documents = self.documents.write().await;
+        self.parsing.send_modify(|parsing| {
+            parsing.remove(uri);
+        });
+        match parsed {
+            Ok((document, _)) if documents.get(uri).map(Document::version) == base => {
+                (documents, Some(Ok(document)))
+            }
+            Ok((_, full)) => {
+                *changes = full;
+                (documents, None)
+            }
+            Err(error) => (documents, Some(Err(error))),
+        }
+    }
+
+    /// Applies `changes` to `document`, on the blocking pool when one of them
+    /// replaces the content with a text heavy to parse. The document is left
+    /// empty if that fails.
+    ///
+    /// Heavy changes normally go through [`Backend::parse_detached`]; this
+    /// only parses them when the document changed meanwhile, with the
+    /// documents lock held.
+    async fn edit_document(
+        &self,
+        document: &mut Document,
+        changes: Vec<TextDocumentContentChangeEvent>,
+    ) -> error::Result<()> {
+        let heavy = changes
+            .iter()
+            .any(|change| change.range.is_none() && is_heavy(&change.text));
+        let encoding = self.position_encoding();
+        if !heavy {
+            for change in &changes {
+                document.edit(&encoding.decode_change(document, change));
+            }
+            return Ok(());
+        }
+
+        let mut edited = std::mem::take(document);
+        *document = self
+            .run_blocking(move || {
+                for change in &changes {
+                    edited.edit(&encoding.decode_change(&edited, change));
+                }
+                edited
+            })
+            .await?;
+        Ok(())
+    }
+
+    /// Reindexes the colors of a changed document and publishes its
+    /// diagnostics.
+    async fn settle(&self, uri: &Uri, document: &Document) {
+        self.resources.write().await.update(uri.clone(), document);
+        self.theme_colors
+            .write()
+            .await
+            .update(uri.clone(), document);
+        self.variables.write().await.update(uri.clone(), document);
+        self.tokens.write().await.update(uri.clone(), document);
+        self.publish_document_diagnostics(uri, document).await;
+    }
+
+    /// Settles a document whose burst of changes is still being applied, so
+    /// its colors reflect the changes applied so far.
+    async fn flush(&self, uri: &Uri) {
+        let unsettled = self
+            .bursts
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .take_unsettled(uri);
+        if !unsettled {
+            return;
+        }
+        let documents = self.documents.read().await;
+        if let Some(document) = documents.get(uri) {
+            self.settle(uri, document).await;
+        }
+    }
+
+    async fn document_version(&self, uri: &Uri) -> Option<i32> {
+        self.documents.read().await.get(uri).map(Document::version)
+    }
+
+    /// Returns the document's colors along with the version they were computed from.
+    async fn versioned_colors(&self, uri: &Uri) -> error::Result<(i32, Arc<[ColorInformation]>)> {
+        // `self` owns the sender, so it can't close while waiting.
+        let _ = self
+            .parsing
+            .subscribe()
+            .wait_for(|parsing| !parsing.contains(uri))
+            .await;
+        self.flush(uri).await;
+        let versioned_colors = |document: &Document| (document.version(), document.colors());
+        {
+            let documents = self.documents.read().await;
+            match documents.get(uri) {
+                Some(document) if document.line_count() <= HEAVY_LINE_COUNT => {
+                    return Ok(versioned_colors(document));
+                }
+                Some(_) => {}
+                None => return Err(Error::DocumentNotFound(uri.clone())),
+            }
+        }
+
+        let uri = uri.clone();
+        self.with_documents_blocking(move |documents| {
+            documents
+                .get(&uri)
+                .map(versioned_colors)
+                .ok_or(Error::DocumentNotFound(uri))
+        })
+        .await?
+    }
+
+    /// Indexes the color resources of the `res` directory of an Android
+    /// resource, skipping files that are already indexed.
+    async fn index_resources(&self, uri: &Uri) {
+        let uri = uri.clone();
+        let Ok(files) = self
+            .run_blocking(move || {
+                android::values_files(&uri)
+                    .into_iter()
+                    .filter_map(|path| {
+                        let text = std::fs::read_to_string(&path).ok()?;
+                        Some((Uri::from_file_path(path)?, text))
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .await
+        else {
+            return;
+        };
+
+        let mut resources = self.resources.write().await;
+        for (uri, text) in files {
+            if !resources.contains(&uri) {
+                resources.update_from_text(uri, &text);
+            }
+        }
+    }
+
+    /// Returns the colors of references to Android color resources, to
+    /// Compose or Flutter theme colors and to stylesheet variables, or `None`
+    /// once the document is no longer at `version`.
+    async fn resource_colors(&self, uri: &Uri, version: i32) -> Option<Vec<ColorInformation>> {
+        let documents = self.documents.read().await;
+        let document = documents
+            .get(uri)
+            .filter(|document| document.version() == version)?;
+        let mut resolved = self.resources.read().await.resolve(document);
+        resolved.extend(self.theme_colors.read().await.resolve(document));
+        resolved.extend(
+            self.variables
+                .read()
+                .await
+                .resolve(uri, document, self.scope(uri)),
+        );
+        Some(resolved)
+    }
+
+    /// Returns the workspace folders that are local directories.
+    fn workspace_paths(&self) -> Vec<PathBuf> {
+        self.workspace_roots
+            .get()
+            .into_iter()
+            .flatten()
+            .filter_map(|uri| Some(uri.to_file_path()?.into_owned()))
+            .collect()
+    }
+
+    /// Indexes the Android resources, the Kotlin and Dart theme colors and the
+    /// stylesheet variables of the workspace folders.
+    async fn index_workspace_resources(&self) {
+        let roots = self.workspace_paths();
+        if roots.is_empty() {
+            return;
+        }
+        let Ok(files) = self
+            .run_blocking(move || {
+                workspace_index_files(&roots)
+                    .into_iter()
+                    .filter_map(|(kind, path)| read_index_file(kind, path))
+                    .collect::<Vec<_>>()
+            })
+            .await
+        else {
+            return;
+        };
+
+        let mut resources = self.resources.write().await;
+        let mut theme_colors = self.theme_colors.write().await;
+        let mut variables = self.variables.write().await;
+        let mut tokens = self.tokens.write().await;
+        for (kind, uri, text) in files {
+            match kind {
+                IndexKind::Resources if !resources.contains(&uri) => {
+                    resources.update_from_text(uri, &text);
+                }
+                IndexKind::ThemeColors if !theme_colors.contains(&uri) => {
+                    theme_colors.update_from_text(uri, &text);
+                }
+                IndexKind::Variables if !variables.contains(&uri) => {
+                    variables.update_from_text(uri, &text);
+                }
+                IndexKind::Tokens if !tokens.contains(&uri) => {
+                    tokens.update_from_text(uri, &text);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Rebuilds the workspace indexes from disk and the open documents, for
+    /// when they drifted, e.g. after files changed outside the editor. Files
+    /// are read `index_concurrency` at a time, reporting progress, and the
+    /// indexes are left untouched if the client cancels.
+    async fn reindex_workspace(&self, token: Option<ProgressToken>) -> error::Result<String> {
+        let created = token.is_none();
+        let token = match token {
+            Some(token) => Some(token),
+            None if self.work_done_progress.get() == Some(&true) => {
+                let id = self.next_progress_token.fetch_add(1, Ordering::Relaxed);
+                Some(ProgressToken::String(format!(
+                    "{REINDEX_WORKSPACE_COMMAND}/{id}"
+                )))
+            }
+            None => None,
+        };
+        // Registered before the client learns of a token the server creates,
+        // so its cancel can't arrive first.
+        if let Some(token) = &token {
+            self.reindexing.write().await.insert(token.clone(), false);
+        }
+        let result = self.reindex_files(token.as_ref(), created).await;
+        if let Some(token) = &token {
+            self.reindexing.write().await.remove(token);
+        }
+        result
+    }
+
+    /// Runs a reindex reporting progress with `token`, once the client
+    /// accepts it when the server `created` it.
+    async fn reindex_files(
+        &self,
+        token: Option<&ProgressToken>,
+        created: bool,
+    ) -> error::Result<String> {
+        let roots = self.workspace_paths();
+        let files = self
+            .run_blocking(move || workspace_index_files(&roots))
+            .await?;
+        let concurrency = self.settings().index_concurrency();
+
+        let token = match token {
+            Some(token)
+                if created
+                    && self
+                        .client
+                        .create_work_done_progress(token.clone())
+                        .await
+                        .is_err() =>
+            {
+                None
+            }
+            token => token,
+        };
+        let progress = match token {
+            Some(token) => Some(
+                self.client
+                    .progress(token.clone(), "Reindexing colors")
+                    .with_percentage(0)
+                    .with_cancel_button()
+                    .begin()
+                    .await,
+            ),
+            None => None,
+        };
+
+        let total = files.len();
+        let mut read = Vec::with_capacity(total);
+        let mut done = 0;
+        let mut cancelled = false;
+        for batch in files.chunks(concurrency) {
+            if let Some(token) = token
+                && self.reindexing.read().await.get(token) == Some(&true)
+            {
+                cancelled = true;
+                break;
+            }
+            let mut jobs = JoinSet::new();
+            for (kind, path) in batch.iter().cloned() {
+                jobs.spawn_blocking(move || read_index_file(kind, path));
+            }
+            while let Some(file) = jobs.join_next().await {
+                read.extend(file.map_err(|err| Error::Task(err.to_string()))?);
+            }
+            done += batch.len();
+            if let Some(progress) = &progress {
+                let percentage = (done * 100 / total) as u32;
+                progress
+                    .report_with_message(format!("{done}/{total} files"), percentage, None)
+                    .await;
+            }
+        }
+        let report = if cancelled {
+            "Reindexing was cancelled; the index is unchanged.".to_string()
+        } else {
+            self.replace_indexes(read).await;
+            self.forget_workspace_colors().await;
+            format!(
+                "Reindexed {total} workspace {}.",
+                if total == 1 { "file" } else { "files" }
+            )
+        };
+        if let Some(progress) = progress {
+            progress.finish_with_message(report.clone()).await;
+        }
+        Ok(report)
+    }
+
+    /// Replaces the workspace indexes with the given files and the open
+    /// documents, then refreshes the diagnostics of the open documents.
+    async fn replace_indexes(&self, files: Vec<(IndexKind, Uri, String)>) {
+        let mut resources = ResourceIndex::default();
+        let mut theme_colors = ThemeIndex::default();
+        let mut variables = VariableIndex::default();
+        let mut tokens = TokenIndex::default();
+        let documents = self.documents.read().await;
+        for (kind, uri, text) in unopened(&documents, files, |(_, uri, _)| uri) {
+            match kind {
+                IndexKind::Resources => resources.update_from_text(uri, &text),
+                IndexKind::ThemeColors => theme_colors.update_from_text(uri, &text),
+                IndexKind::Variables => variables.update_from_text(uri, &text),
+                IndexKind::Tokens => tokens.update_from_text(uri, &text),
+            }
+        }
+        for (uri, document) in documents.iter() {
+            resources.update(uri.clone(), document);
+            theme_colors.update(uri.clone(), document);
+            variables.update(uri.clone(), document);
+            tokens.update(uri.clone(), document);
+        }
+        *self.resources.write().await = resources;
+        *self.theme_colors.write().await = theme_colors;
+        *self.variables.write().await = variables;
+        *self.tokens.write().await = tokens;
+        for (uri, document) in documents.iter() {
+            if document.syntax() == Syntax::Android {
+                self.index_resources(uri).await;
+            }
+            self.publish_document_diagnostics(uri, document).await;
+        }
+    }
+
+    /// Raises the log level while the client traces the server, on
+    /// `$/setTrace`.
+    pub async fn set_trace(&self, params: SetTraceParams) {
+        logging::set_trace(params.value);
+    }
+
+    /// Marks the progress of a running reindex as cancelled, on
+    /// `window/workDoneProgress/cancel`. Tokens of no running reindex are
+    /// ignored.
+    pub async fn cancel_progress(&self, params: WorkDoneProgressCancelParams) {
+        if let Some(cancelled) = self.reindexing.write().await.get_mut(&params.token) {
+            *cancelled = true;
+        }
+    }
+
+    /// Publishes the schema, missing reference, conformance and contrast
+    /// diagnostics of a document, or clears its missing reference,
+    /// conformance and contrast diagnostics once they're fixed.
+    async fn publish_document_diagnostics(&self, uri: &Uri, document: &Document) {
+        let settings = self.settings();
+        let mut missing = missing::diagnostics(
+            uri,
+            document,
+            &*self.variables.read().await,
+            &*self.tokens.read().await,
+            self.scope(uri),
+        );
+        if document.options().strict {
+            missing.extend(conformance::diagnostics(document));
+        }
+        if settings.contrast_diagnostics() {
+            let min_ratio = settings.contrast_level.min_ratio();
+            let issues = contrast_issues(document, min_ratio);
+            missing.extend(contrast::diagnostics(&issues, min_ratio));
+        }
+        let mut diagnosed_uris = self.diagnosed_uris.write().await;
+        let publish_missing = if missing.is_empty() {
+            diagnosed_uris.remove(uri)
+        } else {
+            diagnosed_uris.insert(uri.clone());
+            true
+        };
+        drop(diagnosed_uris);
+
+        let mut schema = schema_diagnostics(document);
+        if !settings.stylistic_lints()
+            && let Some(schema) = &mut schema
+        {
+            schema.retain(|diagnostic| diagnostic.severity != Some(DiagnosticSeverity::HINT));
+        }
+        if schema.is_none() && !publish_missing {
+            return;
+        }
+        let mut diagnostics = schema.unwrap_or_default();
+        diagnostics.extend(missing);
+        self.client
+            .publish_diagnostics(uri.clone(), diagnostics, Some(document.version()))
+            .await;
+    }
+
+    /// Returns the named colors of the indexed stylesheets, Android resources
+    /// and theme objects.
+    async fn color_definitions(&self) -> Vec<Definition> {
+        self.indexed().await;
+        let mut definitions: Vec<Definition> = self
+            .variables
+            .read()
+            .await
+            .all()
+            .map(|variable| Definition {
+                name: variable.name.clone(),
+                location: variable.location.clone(),
+                color: variable.color,
+            })
+            .collect();
+        definitions.extend(
+            self.resources
+                .read()
+                .await
+                .all()
+                .map(|resource| Definition {
+                    name: resource.name.clone(),
+                    location: resource.location.clone(),
+                    color: resource.color,
+                }),
+        );
+        definitions.extend(
+            self.theme_colors
+                .read()
+                .await
+                .all()
+                .map(|color| Definition {
+                    name: color.name.clone(),
+                    location: color.location.clone(),
+                    color: color.color,
+                }),
+        );
+        definitions
+    }
+
+    /// Loads a palette file, replacing a loaded palette of the same name in
+    /// the same workspace folder. A relative path is resolved against the
+    /// first workspace folder.
+    async fn import_palette(&self, path: PathBuf) -> error::Result<Palette> {
+        let path = match self.workspace_paths().first() {
+            Some(root) if path.is_relative() => root.join(path),
+            _ => path,
+        };
+        self.load_palette(path).await
+    }
+
+    /// Loads the palettes of the settings. A relative path is loaded from
+    /// every workspace folder that has it, so each folder gets its own.
+    async fn load_settings_palettes(&self, paths: Vec<PathBuf>) {
+        let roots = self.workspace_paths();
+        for path in paths {
+            let mut candidates: Vec<PathBuf> = if path.is_relative() {
+                roots
+                    .iter()
+                    .map(|root| root.join(&path))
+                    .filter(|candidate| candidate.is_file())
+                    .collect()
+            } else {
+                Vec::new()
+            };
+            if candidates.is_empty() {
+                // Report a missing palette against the first folder, as with imports.
+                candidates.push(match roots.first() {
+                    Some(root) if path.is_relative() => root.join(&path),
+                    _ => path,
+                });
+            }
+            for candidate in candidates {
+                // Failures are logged; other palettes still load.
+                let _ = self.reported(self.load_palette(candidate).await).await;
+            }
+        }
+    }
+
+    async fn load_palette(&self, path: PathBuf) -> error::Result<Palette> {
+        let path = std::path::absolute(&path).unwrap_or(path);
+        let uri = Uri::from_file_path(&path).ok_or_else(|| Error::InvalidPalette {
+            path: path.display().to_string(),
+            reason: "not a file path".to_string(),
+        })?;
+        let palette = self.run_blocking(move || Palette::load(&path)).await??;
+
+        let folders = self.workspace_roots.get().map_or(&[][..], Vec::as_slice);
+        let folder = workspace::folder_of(folders, &uri);
+        let mut palettes = self.palettes.write().await;
+        palettes.retain(|(loaded_uri, loaded)| {
+            loaded.name != palette.name || workspace::folder_of(folders, loaded_uri) != folder
+        });
+        palettes.push((uri, palette.clone()));
+        Ok(palette)
+    }
+
+    pub async fn status(&self, params: StatusParams) -> Result<Versioned<Status>> {
+        let documents = self.documents.read().await;
+        let status = match params.text_document {
+            Some(text_document) => documents
+                .get(&text_document.uri)
+                .map(|document| Status {
+                    documents: vec![DocumentStatus::new(text_document.uri.clone(), document)],
+                })
+                .ok_or(Error::DocumentNotFound(text_document.uri)),
+            None => {
+                let mut documents: Vec<DocumentStatus> = documents
+                    .iter()
+                    .map(|(uri, document)| DocumentStatus::new(uri.clone(), document))
+                    .collect();
+                documents.sort_by(|a, b| a.uri.as_str().cmp(b.uri.as_str()));
+                Ok(Status { documents })
+            }
+        };
+        drop(documents);
+        self.reported(status.map(Versioned::new)).await
+    }
+
+    pub async fn color_histogram(
+        &self,
+        params: ColorHistogramParams,
+    ) -> Result<Versioned<ColorHistogram>> {
+        let uri = params.text_document.map(|text_document| text_document.uri);
+        let histogram = self
+            .with_documents_blocking(move |documents| {
+                let colors: Vec<ColorInformation> = match uri {
+                    Some(uri) => documents
+                        .get(&uri)
+                        .ok_or(Error::DocumentNotFound(uri))?
+                        .get_colors(),
+                    None => documents.values().flat_map(Document::get_colors).collect(),
+                };
+                Ok(Versioned::new(colors.iter().map(|c| &c.color).collect()))
+            })
+            .await;
+        self.reported(histogram.and_then(|histogram| histogram))
+            .await
+    }
+
+    /// Times the parsers on a document, for reports of slow parsing.
+    pub async fn profile(&self, params: ProfileParams) -> Result<Versioned<DocumentProfile>> {
+        let uri = params.text_document.uri;
+        let profile = self
+            .with_documents_blocking(move |documents| {
+                let document = documents
+                    .get(&uri)
+                    .ok_or_else(|| Error::DocumentNotFound(uri.clone()))?;
+                Ok(Versioned::new(timing::profile(uri, document)))
+            })
+            .await;
+        self.reported(profile.and_then(|profile| profile)).await
+    }
+
+    /// Returns evenly spaced stops of a gradient, for previews.
+    pub async fn gradient(&self, params: GradientParams) -> Result<Vec<GradientStop>> {
+        self.reported(gradient_stops(params)).await
+    }
+}
+
+impl LanguageServer for Backend {
+    async fn initialize(&self, params: InitializeParams) -> Result<InitializeResult> {
+        let surfaces = *self
+            .surfaces
+            .get_or_init(|| ColorSurfaces::negotiate(&params.capabilities));
+        let position_encoding = *self
+            .position_encoding
+            .get_or_init(|| PositionEncoding::negotiate(&params.capabilities));
+        if let Some(trace) = params.trace {
+            logging::set_trace(trace);
+        }
+        #[allow(deprecated)]
+        let roots = match (&params.workspace_folders, &params.root_uri) {
+            (Some(folders), _) => folders.iter().map(|folder| folder.uri.clone()).collect(),
+            (None, Some(root_uri)) => vec![root_uri.clone()],
+            (None, None) => Vec::new(),
+        };
+        self.workspace_roots.get_or_init(|| roots);
+        self.watch_files.get_or_init(|| {
+            params
+                .capabilities
+                .workspace
+                .as_ref()
+                .and_then(|workspace| workspace.did_change_watched_files.as_ref())
+                .and_then(|watched_files| watched_files.dynamic_registration)
+                .unwrap_or(false)
+        });
+        self.work_done_progress.get_or_init(|| {
+            params
+                .capabilities
+                .window
+                .as_ref()
+                .and_then(|window| window.work_done_progress)
+                .unwrap_or(false)
+        });
+        let supports_prepare_rename = params
+            .capabilities
+            .text_document
+            .as_ref()
+            .and_then(|text_document| text_document.rename.as_ref())
+            .and_then(|rename| rename.prepare_support)
+            .unwrap_or(false);
+        let supports_hover = params
+            .capabilities
+            .text_document
+            .as_ref()
+            .is_some_and(|text_document| text_document.hover.is_some());
+
+        if let Some(options) = &params.initialization_options {
+            self.journal.set_config(options.clone());
+        }
+        let settings = match params.initialization_options.map(serde_json::from_value) {
+            Some(Ok(settings)) => settings,
+            Some(Err(err)) => {
+                let error = Error::from(err);
+                tracing::warn!("{error}");
+                self.client
+                    .log_message(error.message_type(), error.to_string())
+                    .await;
+                Settings::default()
+            }
+            None => Settings::default(),
+        };
+        self.set_settings(settings);
+        let settings = self.settings();
+        let read_only = settings.read_only;
+        let normalize_on_save = settings.normalize_on_save.is_some() && !read_only;
+
+        Ok(InitializeResult {
+            capabilities: ServerCapabilities {
+                // Without any way to present colors there is no point in tracking documents,
+                // unless they are normalized on save.
+                text_document_sync: (!surfaces.is_empty() || normalize_on_save).then_some(
+                    TextDocumentSyncCapability::Options(TextDocumentSyncOptions {
+                        open_close: Some(true),
+                        change: Some(TextDocumentSyncKind::INCREMENTAL),
+                        will_save_wait_until: normalize_on_save.then_some(true),
+                        ..Default::default()
+                    }),
+                ),
+                // Hover shows color details, and the stops of referenced SVG gradients.
+                hover_provider: (surfaces.hover || supports_hover)
+                    .then_some(HoverProviderCapability::Simple(true)),
+                definition_provider: Some(OneOf::Left(true)),
+                references_provider: Some(OneOf::Left(true)),
+                document_highlight_provider: Some(OneOf::Left(true)),
+                document_symbol_provider: Some(OneOf::Left(true)),
+                workspace_symbol_provider: Some(OneOf::Left(true)),
+                code_lens_provider: Some(CodeLensOptions {
+                    resolve_provider: Some(true),
+                }),
+                // Advertised regardless of `colorNameHints`, which may be turned on later.
+                inlay_hint_provider: Some(OneOf::Right(InlayHintServerCapabilities::Options(
+                    InlayHintOptions {
+                        resolve_provider: Some(true),
+                        work_done_progress_options: Default::default(),
+                    },
+                ))),
+                linked_editing_range_provider: (!read_only)
+                    .then_some(LinkedEditingRangeServerCapabilities::Simple(true)),
+                rename_provider: (!read_only).then(|| {
+                    if supports_prepare_rename {
+                        OneOf::Right(RenameOptions {
+                            prepare_provider: Some(true),
+                            work_done_progress_options: Default::default(),
+                        })
+                    } else {
+                        OneOf::Left(true)
+                    }
+                }),
+                semantic_tokens_provider: surfaces.semantic_tokens.then(|| {
+                    SemanticTokensOptions {
+                        legend: SemanticTokensLegend {
+                            token_types: vec![SemanticTokenType::new(COLOR_TOKEN_TYPE)],
+                            token_modifiers: Vec::new(),
+                        },
+                        full: Some(SemanticTokensFullOptions::Bool(true)),
+                        ..Default::default()
+                    }
+                    .into()
+                }),
+                color_provider: surfaces
+                    .document_color
+                    .then_some(ColorProviderCapability::Simple(true)),
+                completion_provider: Some(CompletionOptions {
+                    trigger_characters: Some(vec!["#".to_string(), "(".to_string()]),
+                    ..Default::default()
+                }),
+                code_action_provider: Some(CodeActionProviderCapability::Simple(true)),
+                document_link_provider: Some(DocumentLinkOptions {
+                    resolve_provider: Some(false),
+                    work_done_progress_options: Default::default(),
+                }),
+                execute_command_provider: Some(ExecuteCommandOptions {
+                    commands: vec![
+                        SUGGEST_HARMONIES_COMMAND.to_string(),
+                        CONTRAST_REPORT_COMMAND.to_string(),
+                        IMPORT_PALETTE_COMMAND.to_string(),
+                        DUPLICATE_COLORS_COMMAND.to_string(),
+                        UNUSED_COLORS_COMMAND.to_string(),
+                        UNKNOWN_COLORS_COMMAND.to_string(),
+                        REINDEX_WORKSPACE_COMMAND.to_string(),
+                        NUDGE_COLOR_COMMAND.to_string(),
+                        GENERATE_GRADIENT_COMMAND.to_string(),
+                        EXPORT_TERMINAL_THEME_COMMAND.to_string(),
+                        DUMP_STATE_COMMAND.to_string(),
+                        CONVERT_COLOR_COMMAND.to_string(),
+                        CONVERT_ALL_COLORS_COMMAND.to_string(),
+                        EXTRACT_PALETTE_COMMAND.to_string(),
+                    ],
+                    ..Default::default()
+                }),
+                // Columns are translated by `encoding::Encoded`, and those of
+                // changes as they are applied.
+                position_encoding: Some(position_encoding.kind()),
+                ..Default::default()
+            },
+            server_info: Some(ServerInfo {
+                name: "chroma-ls".to_string(),
+                version: Some(env!("CARGO_PKG_VERSION").to_string()),
+            }),
+        })
+    }
+
+    async fn initialized(&self, _: InitializedParams) {
+        let _indexing = self.start_indexing();
+        // Hold the lock so documents opened meanwhile wait for the index and
+        // the palettes.
+        let documents = self.documents.write().await;
+        self.index_workspace_resources().await;
+        self.load_settings_palettes(self.settings().palettes.clone())
+            .await;
+        self.set_tailwind_theme(self.read_tailwind_theme().await);
+        drop(documents);
+
+        if self.watch_files.get() == Some(&true) {
+            // Registration waits for the client's reply, which must not hold
+            // up the notifications that follow.
+            let client = self.client.clone();
+            tokio::spawn(async move {
+                let options = DidChangeWatchedFilesRegistrationOptions {
+                    watchers: [tailwind::CONFIG_GLOB, variables::WATCH_GLOB]
+                        .into_iter()
+                        .map(|glob| FileSystemWatcher {
+                            glob_pattern: GlobPattern::String(glob.to_string()),
+                            kind: None,
+                        })
+                        .collect(),
+                };
+                let _ = client
+                    .register_capability(vec![Registration {
+                        id: "chroma-ls/watched-files".to_string(),
+                        method: "workspace/didChangeWatchedFiles".to_string(),
+                        register_options: serde_json::to_value(options).ok(),
+                    }])
+                    .await;
+            });
+        }
+
+        if self.surfaces.get().is_some_and(ColorSurfaces::is_empty) {
+            self.client
+                .log_message(
+                    MessageType::WARNING,
+                    "Client supports neither documentColor, hover nor semantic tokens; colors will not be shown",
+                )
+                .await;
+        }
+    }
+
+    async fn shutdown(&self) -> Result<()> {
+        Ok(())
+    }
+
+    async fn did_open(&self, params: DidOpenTextDocumentParams) {
+        let _indexing = self.start_indexing();
+        let uri = params.text_document.uri;
+        let version = params.text_document.version;
+        let content = params.text_document.text;
+        let language_id = params.text_document.language_id;
+        let syntax = Syntax::detect(&language_id, &uri, &content);
+        // Lock before parsing so requests sent after didOpen wait for the document.
+        let mut documents = self.documents.write().await;
+        if !self.activates_on(&language_id, &uri, content.len()) {
+            self.open_inactive(&mut documents, uri, version).await;
+            return;
+        }
+
+        let options = self.document_parse_options(&language_id, &uri);
+        let start = Instant::now();
+        let mut document = if is_heavy(&content) {
+            match self
+                .run_parallel(move |threads| {
+                    let mut document = Document::with_options("", options, syntax);
+                    document.set_text_on(&content, threads);
+                    document
+                })
+                .await
+            {
+                Ok(document) => document,
+                Err(_) => return,
+            }
+        } else {
+            Document::with_options(&content, options, syntax)
+        };
+        tracing::debug!(
+            uri = uri.as_str(),
+            lines = document.line_count(),
+            colors = document.colors().len(),
+            duration_us = start.elapsed().as_micros() as u64,
+            "parsed document"
+        );
+        document.set_version(version);
+        document.set_language_id(language_id);
+        self.resources.write().await.update(uri.clone(), &document);
+        self.theme_colors
+            .write()
+            .await
+            .update(uri.clone(), &document);
+        self.variables.write().await.update(uri.clone(), &document);
+        self.tokens.write().await.update(uri.clone(), &document);
+        if document.syntax() == Syntax::Android {
+            // Still holding the lock, so requests sent after didOpen see the references.
+            self.index_resources(&uri).await;
+        }
+        self.publish_document_diagnostics(&uri, &document).await;
+        self.touch(&uri);
+        documents.insert(uri, document);
+        self.fit_memory_budget(&mut documents);
+    }
+
+    async fn did_change(&self, params: DidChangeTextDocumentParams) {
+        let _indexing = self.start_indexing();
+        let uri = params.text_document.uri;
+        let mut changes = params.content_changes;
+        let change_count = changes.len();
+        // Counted before waiting for the lock, so changes of a burst see the
+        // ones queued behind them.
+        let queued = Bursts::queue(&self.bursts, &uri);
+        let mut documents = self.documents.write().await;
+        let _editing = match self.editing.try_lock() {
+            Ok(editing) => editing,
+            // A heavy full change is parsed outside the documents lock, so
+            // wait for it without blocking requests.
+            Err(_) => {
+                drop(documents);
+                let editing = self.editing.lock().await;
+                documents = self.documents.write().await;
+                editing
+            }
+        };
+        if self.inactive_documents.read().await.contains(&uri) {
+            if let Some(document) = documents.get_mut(&uri) {
+                document.set_version(params.text_document.version);
+            }
+            return;
+        }
+
+        let start = Instant::now();
+        let (mut documents, detached) = self.parse_detached(documents, &uri, &mut changes).await;
+        let Ok(detached) = detached.transpose() else {
+            documents.remove(&uri);
+            return;
+        };
+        let version = params.text_document.version;
+        let document = match documents.entry(uri.clone()) {
+            Entry::Occupied(entry) if let Some(document) = detached => {
+                let slot = entry.into_mut();
+                *slot = document;
+                slot
+            }
+            Entry::Vacant(entry) if let Some(document) = detached => entry.insert(document),
+            // Versions only have to increase, as clients such as Neovim skip
+            // numbers. Edits of an older version would land on the wrong text,
+            // so the document is dropped until a full-content change or a
+            // reopen resyncs it.
+            Entry::Occupied(entry)
+                if version <= entry.get().version()
+                    && changes.iter().all(|change| change.range.is_some()) =>
+            {
+                let current = entry.get().version();
+                entry.remove();
+                drop(documents);
+                tracing::warn!(uri = uri.as_str(), version, current, "stale change");
+                self.client
+                    .log_message(
+                        MessageType::WARNING,
+                        format!(
+                            "Dropping {} after a change to version {version}, not newer than \
+                             {current}; reopen it to resync",
+                            uri.as_str()
+                        ),
+                    )
+                    .await;
+                return;
+            }
+            Entry::Occupied(entry) => {
+                let document = entry.into_mut();
+                if self.edit_document(document, changes).await.is_err() {
+                    documents.remove(&uri);
+                    return;
+                }
+                document
+            }
+            // A full-content change is enough to rebuild a document the
+            // client never opened or already closed.
+            Entry::Vacant(entry) => match changes.iter().rposition(|change| change.range.is_none())
+            {
+                Some(full_idx) if !self.activates_on("", &uri, changes[full_idx].text.len()) => {
+                    self.open_inactive(&mut documents, uri, version).await;
+                    return;
+                }
+                Some(full_idx) => {
+                    let syntax = Syntax::detect("", &uri, &changes[full_idx].text);
+                    let options = self.document_parse_options("", &uri);
+                    let mut document = Document::with_options("", options, syntax);
+                    if self
+                        .edit_document(&mut document, changes.split_off(full_idx))
+                        .await
+                        .is_err()
+                    {
+                        return;
+                    }
+                    entry.insert(document)
+                }
+                None => {
+                    drop(documents);
+                    self.client
+                        .log_message(
+                            MessageType::WARNING,
+                            format!("Ignoring didChange for unknown document {}", uri.as_str()),
+                        )
+                        .await;
+                    return;
+                }
+            },
+        };
+        tracing::trace!(
+            uri = uri.as_str(),
+            changes = change_count,
+            duration_us = start.elapsed().as_micros() as u64,
+            "reparsed document"
+        );
+        document.set_version(version);
+        // Let the rest of a burst queue up, so its last change settles the
+        // document for all of them.
+        tokio::task::yield_now().await;
+        if !queued.defer() {
+            self.settle(&uri, document).await;
+        }
+        self.touch(&uri);
+        self.fit_memory_budget(&mut documents);
+    }
+
+    /// Replaces the settings and reparses the open documents, so changes take
+    /// effect without a restart. Capabilities announced at initialization, such
+    /// as rename and completion, stay as they were.
+    async fn did_change_configuration(&self, params: DidChangeConfigurationParams) {
+        let settings = match settings_section(params.settings) {
+            // Clients that only announce a change send no settings.
+            Value::Null => return,
+            settings => {
+                self.journal.set_config(settings.clone());
+                match serde_json::from_value::<Settings>(settings) {
+                    Ok(settings) => settings,
+                    Err(err) => {
+                        // Invalid settings are reported and the previous ones kept.
+                        let _ = self.reported::<()>(Err(err.into())).await;
+                        return;
+                    }
+                }
+            }
+        };
+        let palettes = settings.palettes.clone();
+        self.set_settings(settings);
+        self.forget_workspace_colors().await;
+
+        let mut documents = self.documents.write().await;
+        self.refresh_parse_options(&mut documents).await;
+        self.load_settings_palettes(palettes).await;
+        for (uri, document) in documents.iter() {
+            self.resources.write().await.update(uri.clone(), document);
+            self.theme_colors
+                .write()
+                .await
+                .update(uri.clone(), document);
+            self.variables.write().await.update(uri.clone(), document);
+            self.tokens.write().await.update(uri.clone(), document);
+            self.publish_document_diagnostics(uri, document).await;
+        }
+        self.fit_memory_budget(&mut documents);
+    }
+
+    async fn did_change_watched_files(&self, params: DidChangeWatchedFilesParams) {
+        self.forget_workspace_colors().await;
+        let mut tailwind_config_changed = false;
+        let mut sources = Vec::new();
+        for change in params.changes {
+            let Some(path) = change.uri.to_file_path().map(|path| path.into_owned()) else {
+                continue;
+            };
+            tailwind_config_changed |= tailwind::is_config(&path);
+            if variables::is_indexed(&path) {
+                sources.push((change.uri, path));
+            }
+        }
+        if !tailwind_config_changed && sources.is_empty() {
+            return;
+        }
+        // Hold the lock so requests sent meanwhile see the new theme and
+        // variables.
+        let mut documents = self.documents.write().await;
+        let sources: Vec<_> = unopened(&documents, sources, |(uri, _)| uri).collect();
+        if !sources.is_empty() {
+            let files = self
+                .run_blocking(move || {
+                    sources
+                        .into_iter()
+                        .map(|(uri, path)| (uri, std::fs::read_to_string(path).ok()))
+                        .collect::<Vec<_>>()
+                })
+                .await
+                .unwrap_or_default();
+            let mut variables = self.variables.write().await;
+            for (uri, text) in files {
+                // Deleted files can't be read.
+                match text {
+                    Some(text) => variables.update_from_text(uri, &text),
+                    None => variables.remove(&uri),
+                }
+            }
+            drop(variables);
+            for (uri, document) in documents.iter() {
+                self.publish_document_diagnostics(uri, document).await;
+            }
+        }
+        if tailwind_config_changed {
+            self.set_tailwind_theme(self.read_tailwind_theme().await);
+            self.refresh_parse_options(&mut documents).await;
+        }
+    }
+
+    async fn did_close(&self, params: DidCloseTextDocumentParams) {
+        let uri = params.text_document.uri;
+        self.delta_baselines.write().await.remove(&uri);
+        self.inactive_documents.write().await.remove(&uri);
+        self.recency
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .remove(&uri);
+        self.bursts
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .take_unsettled(&uri);
+        let mut documents = self.documents.write().await;
+
+        documents.remove(&uri);
+        // The document may have been saved since the workspace was read.
+        self.forget_workspace_colors().await;
+    }
+
+    async fn will_save_wait_until(
+        &self,
+        params: WillSaveTextDocumentParams,
+    ) -> Result<Option<Vec<TextEdit>>> {
+        let settings = self.settings();
+        let Some(format) = settings.normalize_on_save.filter(|_| !settings.read_only) else {
+            return Ok(None);
+        };
+        let documents = self.documents.read().await;
+        Ok(documents
+            .get(&params.text_document.uri)
+            .map(|document| normalize_edits(document, format)))
+    }
+
+    async fn document_color(&self, params: DocumentColorParams) -> Result<Vec<ColorInformation>> {
+        let uri = params.text_document.uri;
+        let _timing = self.journal.time("textDocument/documentColor", Some(&uri));
+        self.touch(&uri);
+        // The trait takes the colors by value.
+        let result = self.decorated_colors(&uri).await;
+        self.reported(result.map(|colors| colors.to_vec())).await
+    }
+
+    async fn color_presentation(
+        &self,
+        params: ColorPresentationParams,
+    ) -> Result<Vec<ColorPresentation>> {
+        let _timing = self.journal.time(
+            "textDocument/colorPresentation",
+            Some(&params.text_document.uri),
+        );
+        // Picking a presentation rewrites the color. Composite swatches have
+        // empty ranges and no text of their own to rewrite.
+        if self.read_only() || params.range.start == params.range.end {
+            return Ok(Vec::new());
+        }
+        // Resolved `var()` calls would be inlined; their color is edited at
+        // the definition.
+        if self
+            .documents
+            .read()
+            .await
+            .get(&params.text_document.uri)
+            .and_then(|document| document.range_text(&params.range))
+            .is_some_and(|existing| existing.starts_with("var("))
+        {
+            return Ok(Vec::new());
+        }
+        // Asset catalog colors are rewritten component by component.
+        if let Some(document) = self.documents.read().await.get(&params.text_document.uri)
+            && document.syntax() == Syntax::ColorSet
+        {
+            let lines: Vec<&str> = document.lines().collect();
+            return Ok(colorset::presentation(&lines, params.range, &params.color)
+                .into_iter()
+                .collect());
+        }
+
+        let label = self
+            .documents
+            .read()
+            .await
+            .get(&params.text_document.uri)
+            .map(|document| presentation_label(document, &params.range, &params.color))
+            .unwrap_or_else(|| HexStyle::default().format(&params.color));
+        let mut presentations = vec![ColorPresentation {
+            label,
+            ..Default::default()
+        }];
+        if let Some(name) = self.parse_options().aliases.name_of(&params.color) {
+            presentations.push(ColorPresentation {
+                label: name.to_string(),
+                ..Default::default()
+            });
+        }
+        Ok(presentations)
+    }
+
+    async fn completion(&self, params: CompletionParams) -> Result<Option<CompletionResponse>> {
+        let uri = params.text_document_position.text_document.uri;
+        let _timing = self.journal.time("textDocument/completion", Some(&uri));
+        let options = self.parse_options();
+        let mut items: Vec<CompletionItem> = options
+            .aliases
+            .iter()
+            .map(|(name, color)| {
+                let hex = to_hex(color);
+                CompletionItem {
+                    label: name.to_string(),
+                    kind: Some(CompletionItemKind::COLOR),
+                    detail: Some(hex.clone()),
+                    // Clients render a swatch for color items documented with a hex.
+                    documentation: Some(Documentation::String(hex)),
+                    ..Default::default()
+                }
+            })
+            .collect();
+
+        let documents = self.documents.read().await;
+        if let Some(document) = documents.get(&uri) {
+            let position = params.text_document_position.position;
+            items.extend(completion::items(document, position));
+        }
+
+        // Entries of the palettes of the document's workspace folder insert
+        // their hex. Palettes load at startup while holding the document lock.
+        let scope = self.scope(&uri);
+        let palettes = self.palettes.read().await;
+        drop(documents);
+        for (_, palette) in palettes
+            .iter()
+            .filter(|(palette_uri, _)| scope.includes(palette_uri))
+        {
+            for entry in &palette.entries {
+                let hex = to_hex(&entry.color);
+                items.push(CompletionItem {
+                    label: entry.name.clone(),
+                    kind: Some(CompletionItemKind::COLOR),
+                    detail: Some(format!("{hex} ({})", palette.name)),
+                    documentation: Some(Documentation::String(hex.clone())),
+                    insert_text: Some(hex),
+                    ..Default::default()
+                });
+            }
+        }
+        Ok((!items.is_empty()).then_some(CompletionResponse::Array(items)))
+    }
+
+    async fn hover(&self, params: HoverParams) -> Result<Option<Hover>> {
+        let uri = params.text_document_position_params.text_document.uri;
+        let _timing = self.journal.time("textDocument/hover", Some(&uri));
+        let position = params.text_document_position_params.position;
+        let documents = self.documents.read().await;
+
+        let Some(document) = documents.get(&uri) else {
+            return Ok(None);
+        };
+        if let Some((range, id)) = svg::reference_at(document, position)
+            && let Some(gradient) = svg::find_gradient(document, id)
+        {
+            return Ok(Some(Hover {
+                contents: HoverContents::Markup(MarkupContent {
+                    kind: MarkupKind::Markdown,
+                    value: gradient.markdown(),
+                }),
+                range: Some(range),
+            }));
+        }
+        if let Some(reference) = theme_object::reference_at(document, position)
+            && let Some(theme_color) = self.theme_colors.read().await.get(&reference.name)
+        {
+            return Ok(Some(Hover {
+                contents: HoverContents::Markup(MarkupContent {
+                    kind: MarkupKind::Markdown,
+                    value: format!("**{}** `{}`", reference.name, to_hex(&theme_color.color)),
+                }),
+                range: Some(reference.range),
+            }));
+        }
+        // Shown even next to `textDocument/documentColor`, as color pickers
+        // rarely show values in other formats.
+        let settings = self.settings();
+        let range = Range {
+            start: position,
+            end: position,
+        };
+        Ok(document
+            .get_colors_in_range(&range)
+            .first()
+            .map(|color_info| Hover {
+                contents: HoverContents::Markup(MarkupContent {
+                    kind: MarkupKind::Markdown,
+                    value: hover::color_details(
+                        &color_info.color,
+                        &settings.backgrounds,
+                        settings.apca_hover(),
+                    ),
+                }),
+                range: Some(color_info.range),
+            }))
+    }
+
+    async fn goto_definition(
+        &self,
+        params: GotoDefinitionParams,
+    ) -> Result<Option<GotoDefinitionResponse>> {
+        let uri = params.text_document_position_params.text_document.uri;
+        let position = params.text_document_position_params.position;
+        let documents = self.documents.read().await;
+
+        let Some(document) = documents.get(&uri) else {
+            return Ok(None);
+        };
+        if let Some(reference) = android::reference_at(document, position) {
+            let resources = self.resources.read().await;
+            return Ok(resources
+                .get(reference.name)
+                .map(|resource| GotoDefinitionResponse::Scalar(resource.location.clone())));
+        }
+        if let Some(reference) = theme_object::reference_at(document, position)
+            && let Some(theme_color) = self.theme_colors.read().await.get(&reference.name)
+        {
+            return Ok(Some(GotoDefinitionResponse::Scalar(
+                theme_color.location.clone(),
+            )));
+        }
+        if tokens::is_token_file(uri.path().as_str()) {
+            let line = document.line(position.line as usize).unwrap_or_default();
+            if let Some((_, path)) = tokens::aliases(line, position.line as usize)
+                .into_iter()
+                .find(|(range, _)| range.start <= position && position <= range.end)
+            {
+                return Ok(self
+                    .tokens
+                    .read()
+                    .await
+                    .get(path, self.scope(&uri))
+                    .map(|token| GotoDefinitionResponse::Scalar(token.location.clone())));
+            }
+        }
+        Ok(svg::reference_at(document, position)
+            .and_then(|(_, id)| svg::find_gradient(document, id))
+            .map(|gradient| {
+                GotoDefinitionResponse::Scalar(Location {
+                    uri: uri.clone(),
+                    range: gradient.id_range,
+                })
+            }))
+    }
+
+    async fn document_link(&self, params: DocumentLinkParams) -> Result<Option<Vec<DocumentLink>>> {
+        let uri = params.text_document.uri;
+        let documents = self.documents.read().await;
+
+        let Some(document) = documents.get(&uri) else {
+            return Ok(None);
+        };
+        let resources = self.resources.read().await;
+        let tokens = self.tokens.read().await;
+        let check_aliases = tokens::is_token_file(uri.path().as_str());
+        let mut links = Vec::new();
+        for (line_idx, line) in document.lines().enumerate() {
+            for reference in android::references(line, line_idx) {
+                if let Some(resource) = resources.get(reference.name) {
+                    links.push(DocumentLink {
+                        range: reference.range,
+                        target: links::target(&resource.location),
+                        tooltip: Some(format!("Go to `{}`", reference.name)),
+                        data: None,
+                    });
+                }
+            }
+            if check_aliases {
+                for (range, path) in tokens::aliases(line, line_idx) {
+                    if let Some(token) = tokens.get(path, self.scope(&uri)) {
+                        links.push(DocumentLink {
+                            range,
+                            target: links::target(&token.location),
+                            tooltip: Some(format!("Go to `{path}`")),
+                            data: None,
+                        });
+                    }
+                }
+            }
+            for (range, import) in links::imports(line, line_idx) {
+                if let Some(target) = links::resolve_import(&uri, import) {
+                    links.push(DocumentLink {
+                        range,
+                        target: Some(target),
+                        tooltip: None,
+                        data: None,
+                    });
+                }
+            }
+        }
+        Ok(Some(links))
+    }
+
+    async fn references(&self, params: ReferenceParams) -> Result<Option<Vec<Location>>> {
+        let uri = &params.text_document_position.text_document.uri;
+        let _timing = self.journal.time("textDocument/references", Some(uri));
+        let result = self.color_references(params).await;
+        self.reported(result)
+            .await
+            .map(|locations| Some(locations).filter(|locations| !locations.is_empty()))
+    }
+
+    async fn document_highlight(
+        &self,
+        params: DocumentHighlightParams,
+    ) -> Result<Option<Vec<DocumentHighlight>>> {
+        let uri = params.text_document_position_params.text_document.uri;
+        let _timing = self
+            .journal
+            .time("textDocument/documentHighlight", Some(&uri));
+        let position = params.text_document_position_params.position;
+        let Ok(colors) = self.decorated_colors(&uri).await else {
+            return Ok(None);
+        };
+        // Composite swatches are empty ranges next to their color.
+        let colors: Vec<&ColorInformation> = colors
+            .iter()
+            .filter(|color_info| color_info.range.start != color_info.range.end)
+            .collect();
+        let Some(hex) = colors
+            .iter()
+            .find(|color_info| {
+                color_info.range.start <= position && position <= color_info.range.end
+            })
+            .map(|color_info| to_hex(&color_info.color))
+        else {
+            return Ok(None);
+        };
+        Ok(Some(
+            colors
+                .into_iter()
+                .filter(|color_info| to_hex(&color_info.color) == hex)
+                .map(|color_info| DocumentHighlight {
+                    range: color_info.range,
+                    kind: Some(DocumentHighlightKind::TEXT),
+                })
+                .collect(),
+        ))
+    }
+
+    async fn rename(&self, params: RenameParams) -> Result<Option<WorkspaceEdit>> {
+        if self.read_only() {
+            return Ok(None);
+        }
+        let result = match self.rename_variable(&params).await {
+            Ok(None) => self.rename_resource(params).await,
+            result => result,
+        };
+        self.reported(result).await
+    }
+
+    async fn linked_editing_range(
+        &self,
+        params: LinkedEditingRangeParams,
+    ) -> Result<Option<LinkedEditingRanges>> {
+        if self.read_only() {
+            return Ok(None);
+        }
+        let uri = params.text_document_position_params.text_document.uri;
+        let position = params.text_document_position_params.position;
+        let documents = self.documents.read().await;
+        Ok(documents
+            .get(&uri)
+            .and_then(|document| linked_editing::ranges(document, position))
+            .map(|ranges| LinkedEditingRanges {
+                ranges,
+                word_pattern: Some(linked_editing::WORD_PATTERN.to_string()),
+            }))
+    }
+
+    /// Annotates the first occurrence of each distinct color of a document
+    /// with its number of uses in the workspace, counted on resolve, and
+    /// with a lens converting it to the next CSS notation.
+    async fn code_lens(&self, params: CodeLensParams) -> Result<Option<Vec<CodeLens>>> {
+        let uri = params.text_document.uri;
+        let _timing = self.journal.time("textDocument/codeLens", Some(&uri));
+        let read_only = self.read_only();
+        let documents = self.documents.read().await;
+        let Some(document) = documents.get(&uri) else {
+            return Ok(None);
+        };
+
+        let mut seen = HashSet::new();
+        let mut lenses = Vec::new();
+        for color_info in document.get_colors() {
+            let range = color_info.range;
+            let hex = to_hex(&color_info.color);
+            if range.start == range.end || !seen.insert(hex.clone()) {
+                continue;
+            }
+            let data = UsageLensData {
+                uri: uri.clone(),
+                hex,
+            };
+            lenses.push(CodeLens {
+                range,
+                command: None,
+                data: serde_json::to_value(data).ok(),
+            });
+
+            if read_only {
+                continue;
+            }
+            let existing = document.range_text(&range).unwrap_or_default();
+            let Some(notation) = Notation::of(existing).map(Notation::next) else {
+                continue;
+            };
+            let converted = notation.format(&color_info.color, existing, document.syntax());
+            let arguments = json!({
+                "textDocument": { "uri": uri },
+                "position": range.start,
+                "notation": notation,
+            });
+            lenses.push(CodeLens {
+                range,
+                command: Some(Command {
+                    title: format!("Convert to {converted}"),
+                    command: CONVERT_COLOR_COMMAND.to_string(),
+                    arguments: Some(vec![arguments]),
+                }),
+                data: None,
+            });
+        }
+        Ok(Some(lenses))
+    }
+
+    /// Counts the uses of the color of a lens in open documents and
+    /// workspace files.
+    async fn code_lens_resolve(&self, mut lens: CodeLens) -> Result<CodeLens> {
+        let _timing = self.journal.time("codeLens/resolve", None);
+        let Some(data) = lens
+            .data
+            .take()
+            .and_then(|data| serde_json::from_value::<UsageLensData>(data).ok())
+        else {
+            return Ok(lens);
+        };
+        let locations = self.color_locations(&data.hex).await;
+        let count = self.reported(locations).await?.len();
+        lens.command = Some(Command {
+            title: format!(
+                "used {count} {} in workspace",
+                if count == 1 { "time" } else { "times" }
+            ),
+            // The count is informational; there's nothing to run.
+            command: String::new(),
+            arguments: None,
+        });
+        Ok(lens)
+    }
+
+    /// Lists the color definitions of a document for the outline, and its
+    /// distinct colors when `colorSymbols` is on.
+    async fn document_symbol(
+        &self,
+        params: DocumentSymbolParams,
+    ) -> Result<Option<DocumentSymbolResponse>> {
+        let uri = params.text_document.uri;
+        let _timing = self.journal.time("textDocument/documentSymbol", Some(&uri));
+        let distinct_colors = self.settings().color_symbols;
+        let documents = self.documents.read().await;
+        Ok(documents.get(&uri).map(|document| {
+            DocumentSymbolResponse::Nested(symbols::document_symbols(
+                document,
+                &uri,
+                distinct_colors,
+            ))
+        }))
+    }
+
+    /// Finds the color definitions of the indexed workspace by name or hex
+    /// prefix, and literal colors by hex prefix.
+    async fn symbol(
+        &self,
+        params: WorkspaceSymbolParams,
+    ) -> Result<Option<WorkspaceSymbolResponse>> {
+        let _timing = self.journal.time("workspace/symbol", None);
+        let query = SymbolQuery::parse(&params.query);
+        self.indexed().await;
+        let mut found = Vec::new();
+        for variable in self.variables.read().await.all() {
+            if query.matches_definition(&variable.name, &variable.color) {
+                found.push(symbols::definition_symbol(
+                    &variable.name,
+                    &variable.location,
+                    &variable.color,
+                ));
+            }
+        }
+        for resource in self.resources.read().await.all() {
+            if query.matches_definition(&resource.name, &resource.color) {
+                found.push(symbols::definition_symbol(
+                    &resource.name,
+                    &resource.location,
+                    &resource.color,
+                ));
+            }
+        }
+        for member in self.theme_colors.read().await.all() {
+            if query.matches_definition(&member.name, &member.color) {
+                found.push(symbols::definition_symbol(
+                    &member.name,
+                    &member.location,
+                    &member.color,
+                ));
+            }
+        }
+
+        if matches!(query, SymbolQuery::Hex(_)) {
+            let files = self.file_colors(true).await;
+            for (file_uri, colors) in self.reported(files).await? {
+                for color_info in colors.iter() {
+                    if query.matches_color(&color_info.color) {
+                        let location = Location::new(file_uri.clone(), color_info.range);
+                        found.push(symbols::color_symbol(location, &color_info.color));
+                    }
+                }
+            }
+        }
+        symbols::sort_workspace_symbols(&mut found);
+        found.truncate(MAX_WORKSPACE_SYMBOLS);
+        Ok(Some(WorkspaceSymbolResponse::Nested(found)))
+    }
+
+    /// Names the hex colors of a range when `colorNameHints` is on.
+    async fn inlay_hint(&self, params: InlayHintParams) -> Result<Option<Vec<InlayHint>>> {
+        let uri = params.text_document.uri;
+        let _timing = self.journal.time("textDocument/inlayHint", Some(&uri));
+        if !self.settings().color_name_hints {
+            return Ok(None);
+        }
+        let documents = self.documents.read().await;
+        Ok(documents
+            .get(&uri)
+            .map(|document| inlay_hint::hints(document, &params.range)))
+    }
+
+    async fn inlay_hint_resolve(&self, hint: InlayHint) -> Result<InlayHint> {
+        let _timing = self.journal.time("inlayHint/resolve", None);
+        Ok(inlay_hint::resolve(hint))
+    }
+
+    async fn prepare_rename(
+        &self,
+        params: TextDocumentPositionParams,
+    ) -> Result<Option<PrepareRenameResponse>> {
+        if self.read_only() {
+            return Ok(None);
+        }
+        let uri = params.text_document.uri;
+        let position = params.position;
+        let documents = self.documents.read().await;
+        if let Some((range, _)) = self.variables.read().await.variable_at(&uri, position) {
+            return Ok(Some(PrepareRenameResponse::Range(range)));
+        }
+        let Some(document) = documents.get(&uri) else {
+            return Ok(None);
+        };
+        let range = match android::reference_at(document, position) {
+            Some(reference) => Some(reference.name_range),
+            None => self
+                .resources
+                .read()
+                .await
+                .definition_at(&uri, position)
+                .map(|resource| resource.location.range),
+        };
+        Ok(range.map(PrepareRenameResponse::Range))
+    }
+
+    async fn semantic_tokens_full(
+        &self,
+        params: SemanticTokensParams,
+    ) -> Result<Option<SemanticTokensResult>> {
+        let uri = params.text_document.uri;
+        let _timing = self
+            .journal
+            .time("textDocument/semanticTokens/full", Some(&uri));
+        let tokens = self
+            .documents
+            .read()
+            .await
+            .get(&uri)
+            .map(|document| {
+                Some(
+                    SemanticTokens {
+                        result_id: None,
+                        data: encode_semantic_tokens(
+                            &document.get_colors(),
+                            |line| {
+                                document
+                                    .line(line as usize)
+                                    .map_or(0, |text| text.encode_utf16().count() as u32)
+                            },
+                            0,
+                        ),
+                    }
+                    .into(),
+                )
+            })
+            .ok_or(Error::DocumentNotFound(uri));
+        self.reported(tokens).await
+    }
+
+    async fn code_action(&self, params: CodeActionParams) -> Result<Option<CodeActionResponse>> {
+        let uri = params.text_document.uri;
+        let _timing = self.journal.time("textDocument/codeAction", Some(&uri));
+        let documents = self.documents.read().await;
+
+        let Some(document) = documents.get(&uri) else {
+            return Ok(None);
+        };
+
+        let mut actions = Vec::new();
+        let range = params.range;
+        for line_idx in range.start.line..=range.end.line {
+            let Some(line) = document.line(line_idx as usize) else {
+                break;
+            };
+            for color_info in named::find(line, line_idx as usize) {
+                if color_info.range.end >= range.start && color_info.range.start <= range.end {
+                    let hex = to_hex(&color_info.color);
+                    let title = format!("Replace with {hex}");
+                    actions.push(replace_action(&uri, title, color_info.range, hex));
+                }
+            }
+        }
+        for color_match in document.get_matches_in_range(&range) {
+            if color_match.format == ColorFormat::Hex
+                && let Some(name) = named::name_of(&color_match.info.color)
+            {
+                let title = format!("Replace with {name}");
+                actions.push(replace_action(
+                    &uri,
+                    title,
+                    color_match.info.range,
+                    name.to_string(),
+                ));
+            }
+        }
+
+        let custom_bit_depth = self.settings().snap_bit_depth.filter(|&bits| bits != 4);
+        let snaps: Vec<Snap> = [Snap::WebSafe, Snap::Bits(4)]
+            .into_iter()
+            .chain(custom_bit_depth.map(Snap::Bits))
+            .collect();
+        let loaded = self.palettes.read().await;
+        let scope = self.scope(&uri);
+        let palettes: Vec<&Palette> = loaded
+            .iter()
+            .filter(|(palette_uri, _)| scope.includes(palette_uri))
+            .map(|(_, palette)| palette)
+            .collect();
+        for color_info in document.get_colors_in_range(&range) {
+            let hex = to_hex(&color_info.color);
+            for snap in &snaps {
+                let snapped = to_hex(&snap.apply(&color_info.color));
+                if snapped != hex {
+                    let title = format!("Snap to {}: {snapped}", snap.name());
+                    actions.push(replace_action(&uri, title, color_info.range, snapped));
+                }
+            }
+            for palette in palettes.iter() {
+                let Some(entry) = palette.nearest(&color_info.color) else {
+                    continue;
+                };
+                let snapped = to_hex(&Color {
+                    alpha: color_info.color.alpha,
+                    ..entry.color
+                });
+                if snapped != hex {
+                    let title = format!("Snap to {} ({}): {snapped}", palette.name, entry.name);
+                    actions.push(replace_action(&uri, title, color_info.range, snapped));
+                }
+            }
+        }
+        drop(palettes);
+        drop(loaded);
+
+        if self.settings().cvd_actions() {
+            for color_info in document.get_colors_in_range(&range) {
+                for deficiency in Deficiency::ALL {
+                    let simulated = to_hex(&deficiency.simulate(&color_info.color));
+                    let title = format!("Simulate {}: {simulated}", deficiency.name());
+                    actions.push(replace_action(&uri, title, color_info.range, simulated));
+                }
+            }
+        }
+
+        let variable_kind = VariableKind::detect(document.language_id(), extension(&uri));
+        // Harmonies are inserted as variables, so only where there are any.
+        if let Some(kind) = variable_kind {
+            for color_info in document.get_colors_in_range(&params.range) {
+                let line_idx = color_info.range.end.line;
+                let line = document.line(line_idx as usize).unwrap_or_default();
+                let indent: String = line.chars().take_while(|c| c.is_whitespace()).collect();
+                let line_ending = document.line_ending().as_str();
+                // Insert at the end of the color's line so it works without a trailing newline.
+                let end_of_line = Position {
+                    line: line_idx,
+                    character: line.encode_utf16().count() as u32,
+                };
+
+                for harmony in Harmony::ALL {
+                    let text: String = harmony
+                        .colors(&color_info.color)
+                        .iter()
+                        .enumerate()
+                        .map(|(i, color)| {
+                            let name = format!("{}-{}", harmony.name(), i + 1);
+                            format!(
+                                "{line_ending}{indent}{}",
+                                kind.declare(&name, &to_hex(color))
+                            )
+                        })
+                        .collect();
+                    let edit = TextEdit {
+                        range: Range {
+                            start: end_of_line,
+                            end: end_of_line,
+                        },
+                        new_text: text,
+                    };
+
+                    actions.push(CodeActionOrCommand::CodeAction(CodeAction {
+                        title: format!(
+                            "Insert {} harmony of {}",
+                            harmony.name().replace('-', " "),
+                            to_hex(&color_info.color)
+                        ),
+                        kind: Some(CodeActionKind::REFACTOR),
+                        edit: Some(WorkspaceEdit {
+                            changes: Some(HashMap::from([(uri.clone(), vec![edit])])),
+                            ..Default::default()
+                        }),
+                        ..Default::default()
+                    }));
+                }
+            }
+        }
+
+        if let Some(kind) = variable_kind
+            && let Some((title, edits)) = extract::extract(document, &range, kind)
+        {
+            actions.push(CodeActionOrCommand::CodeAction(CodeAction {
+                title,
+                kind: Some(CodeActionKind::REFACTOR_EXTRACT),
+                edit: Some(WorkspaceEdit {
+                    changes: Some(HashMap::from([(uri.clone(), edits)])),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            }));
+        }
+
+        let variables = self.variables.read().await;
+        let unused_code = NumberOrString::String(unused::DIAGNOSTIC_CODE.to_string());
+        for diagnostic in &params.context.diagnostics {
+            if diagnostic.code.as_ref() != Some(&unused_code) {
+                continue;
+            }
+            let Some(variable) = variables.definition_at(&uri, diagnostic.range.start) else {
+                continue;
+            };
+            let line_idx = variable.location.range.start.line;
+            let line = document.line(line_idx as usize).unwrap_or_default();
+            let edit = TextEdit {
+                range: variables::declaration_range(line, variable.location.range),
+                new_text: String::new(),
+            };
+            actions.push(CodeActionOrCommand::CodeAction(CodeAction {
+                title: format!("Remove unused `{}`", variable.name),
+                kind: Some(CodeActionKind::QUICKFIX),
+                diagnostics: Some(vec![diagnostic.clone()]),
+                edit: Some(WorkspaceEdit {
+                    changes: Some(HashMap::from([(uri.clone(), vec![edit])])),
+                    ..Default::default()
+                }),
+                is_preferred: Some(true),
+                ..Default::default()
+            }));
+        }
+        drop(variables);
+
+        for notation in Notation::ALL {
+            let edits = convert::all_edits(document, notation);
+            if edits.is_empty() {
+                continue;
+            }
+            actions.push(CodeActionOrCommand::CodeAction(CodeAction {
+                title: format!("Convert all colors to {}", notation.name()),
+                kind: Some(CodeActionKind::new(CONVERT_ALL_COLORS_KIND)),
+                edit: Some(WorkspaceEdit {
+                    changes: Some(HashMap::from([(uri.clone(), edits)])),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            }));
+        }
+
+        if document.syntax() == Syntax::Json(JsonSchema::VsCodeTheme) {
+            actions.push(CodeActionOrCommand::Command(Command {
+                title: "Check theme contrast".to_string(),
+                command: CONTRAST_REPORT_COMMAND.to_string(),
+                arguments: Some(vec![Value::String(uri.as_str().to_string())]),
+            }));
+        }
+        if self.read_only() {
+            actions.retain(|action| match action {
+                CodeActionOrCommand::CodeAction(action) => action.edit.is_none(),
+                CodeActionOrCommand::Command(_) => true,
+            });
+        }
+        Ok(Some(actions))
+    }
+
+    async fn execute_command(&self, params: ExecuteCommandParams) -> Result<Option<LSPAny>> {
+        let _timing = self.journal.time("workspace/executeCommand", None);
+        let result = self.run_command(params).await;
+        self.reported(result).await
+    }
+}
+
+impl Backend {
+    /// Returns the color decorated at `position`, or of the stylesheet
+    /// variable or Android color resource defined there.
+    async fn color_at(&self, uri: &Uri, position: Position) -> Option<Color> {
+        let decorated = self.decorated_colors(uri).await.ok()?;
+        if let Some(color_info) = decorated.iter().find(|color_info| {
+            let range = color_info.range;
+            range.start != range.end && range.start <= position && position <= range.end
+        }) {
+            return Some(color_info.color);
+        }
+        if let Some(variable) = self.variables.read().await.definition_at(uri, position) {
+            return Some(variable.color);
+        }
+        self.resources
+            .read()
+            .await
+            .definition_at(uri, position)
+            .map(|resource| resource.color)
+    }
+
+    /// Returns the locations of every color with the same hex value as the
+    /// one at the position: decorations of the open documents, including
+    /// resolved references, and literal colors of the other files of the
+    /// workspace folders, ordered by file and position.
+    async fn color_references(&self, params: ReferenceParams) -> error::Result<Vec<Location>> {
+        let uri = params.text_document_position.text_document.uri;
+        let position = params.text_document_position.position;
+        let Some(color) = self.color_at(&uri, position).await else {
+            return Ok(Vec::new());
+        };
+        let hex = to_hex(&color);
+
+        let mut locations = self.color_locations(&hex).await?;
+        if !params.context.include_declaration {
+            locations.retain(|location| {
+                location.uri != uri
+                    || !(location.range.start <= position && position <= location.range.end)
+            });
+        }
+        Ok(locations)
+    }
+
+    /// Returns the locations of a color in open documents and workspace
+    /// files, sorted by URI and position.
+    async fn color_locations(&self, hex: &str) -> error::Result<Vec<Location>> {
+        let mut locations = Vec::new();
+        for (file_uri, colors) in self.file_colors(true).await? {
+            locations.extend(
+                colors
+                    .iter()
+                    .filter(|color_info| to_hex(&color_info.color) == hex)
+                    .map(|color_info| Location {
+                        uri: file_uri.clone(),
+                        range: color_info.range,
+                    }),
+            );
+        }
+        locations
+            .sort_by(|a, b| (a.uri.as_str(), a.range.start).cmp(&(b.uri.as_str(), b.range.start)));
+        locations.dedup();
+        Ok(locations)
+    }
+
+    /// Returns the colors of the open documents, as decorated but without
+    /// swatches, then, with `workspace`, those of the workspace files that
+    /// aren't open, as saved.
+    async fn file_colors(
+        &self,
+        workspace: bool,
+    ) -> error::Result<Vec<(Uri, Arc<[ColorInformation]>)>> {
+        let open: Vec<Uri> = self.documents.read().await.keys().cloned().collect();
+        let mut files = Vec::with_capacity(open.len());
+        for uri in open {
+            // Documents closed meanwhile are left out.
+            let Ok(colors) = self.decorated_colors(&uri).await else {
+                continue;
+            };
+            let colors = colors
+                .iter()
+                .filter(|color_info| color_info.range.start != color_info.range.end)
+                .cloned()
+                .collect();
+            files.push((uri, colors));
+        }
+        if workspace {
+            let workspace_colors = self.workspace_file_colors().await?;
+            let documents = self.documents.read().await;
+            files.extend(
+                unopened(&documents, workspace_colors.iter(), |(uri, _)| uri)
+                    .map(|(uri, colors)| (uri.clone(), Arc::clone(colors))),
+            );
+        }
+        Ok(files)
+    }
+
+    /// Returns the colors of the files of the workspace folders as saved,
+    /// reading them on first use. Only the files the indexes are built from
+    /// are read, skipping those past `maxFileSize` before reading them.
+    async fn workspace_file_colors(&self) -> error::Result<Arc<WorkspaceColors>> {
+        if let Some(colors) = self.workspace_colors.read().await.as_ref() {
+            return Ok(Arc::clone(colors));
+        }
+        // Hold the lock so concurrent requests read the files once.
+        let mut workspace_colors = self.workspace_colors.write().await;
+        if let Some(colors) = workspace_colors.as_ref() {
+            return Ok(Arc::clone(colors));
+        }
+
+        // The files the indexes are built from, past the directories they skip
+        // and within the size and languages the server activates on.
+        let roots = self.workspace_paths();
+        let settings = self.settings();
+        let paths = self
+            .run_blocking(move || {
+                let mut paths: Vec<PathBuf> = workspace_index_files(&roots)
+                    .into_iter()
+                    .map(|(_, path)| path)
+                    .collect();
+                // A file may be indexed for several kinds.
+                paths.sort();
+                paths.dedup();
+                paths.retain(|path| {
+                    let extension = path
+                        .extension()
+                        .and_then(|extension| extension.to_str())
+                        .unwrap_or("");
+                    std::fs::metadata(path).is_ok_and(|metadata| {
+                        settings.activates_on(extension, extension, metadata.len() as usize)
+                    })
+                });
+                paths
+            })
+            .await?;
+        let files: Vec<(Uri, PathBuf, Arc<ParseOptions>)> = paths
+            .into_iter()
+            .filter_map(|path| {
+                let file_uri = Uri::from_file_path(&path)?;
+                let options = self.document_parse_options(extension(&file_uri), &file_uri);
+                Some((file_uri, path, options))
+            })
+            .collect();
+        let colors = self
+            .run_blocking(move || {
+                let mut colors = HashMap::new();
+                for (file_uri, path, options) in files {
+                    let Ok(text) = std::fs::read_to_string(&path) else {
+                        continue;
+                    };
+                    let extension = extension(&file_uri);
+                    let syntax = Syntax::detect(extension, &file_uri, &text);
+                    let file_colors = Document::with_options(&text, options, syntax).colors();
+                    if !file_colors.is_empty() {
+                        colors.insert(file_uri, file_colors);
+                    }
+                }
+                colors
+            })
+            .await?;
+        let colors = Arc::new(colors);
+        *workspace_colors = Some(Arc::clone(&colors));
+        Ok(colors)
+    }
+
+    /// Drops the colors read from workspace files, for when they may have
+    /// changed on disk.
+    async fn forget_workspace_colors(&self) {
+        *self.workspace_colors.write().await = None;
+    }
+
+    /// Renames the stylesheet color variable defined or used at the position,
+    /// across all indexed files.
+    async fn rename_variable(&self, params: &RenameParams) -> error::Result<Option<WorkspaceEdit>> {
+        let uri = &params.text_document_position.text_document.uri;
+        let position = params.text_document_position.position;
+        self.indexed().await;
+        let variables = self.variables.read().await;
+        let Some((_, name)) = variables.variable_at(uri, position) else {
+            return Ok(None);
+        };
+        let new_name = variables::renamed(name, &params.new_name)
+            .ok_or_else(|| Error::InvalidVariableName(params.new_name.clone()))?;
+
+        let changes = variables
+            .rename_locations(name)
+            .into_iter()
+            .map(|(uri, ranges)| {
+                let edits = ranges
+                    .into_iter()
+                    .map(|range| TextEdit {
+                        range,
+                        new_text: new_name.clone(),
+                    })
+                    .collect();
+                (uri, edits)
+            })
+            .collect();
+        Ok(Some(WorkspaceEdit {
+            changes: Some(changes),
+            ..Default::default()
+        }))
+    }
+
+    /// Renames the Android color resource referenced or defined at the
+    /// position, across all indexed files.
+    async fn rename_resource(&self, params: RenameParams) -> error::Result<Option<WorkspaceEdit>> {
+        let uri = params.text_document_position.text_document.uri;
+        let position = params.text_document_position.position;
+        if params.new_name.is_empty() || !params.new_name.chars().all(android::is_name_char) {
+            return Err(Error::InvalidResourceName(params.new_name));
+        }
+
+        let documents = self.documents.read().await;
+        let document = documents
+            .get(&uri)
+            .ok_or_else(|| Error::DocumentNotFound(uri.clone()))?;
+        let resources = self.resources.read().await;
+        let name = match android::reference_at(document, position) {
+            Some(reference) => reference.name.to_string(),
+            None => match resources.definition_at(&uri, position) {
+                Some(resource) => resource.name.clone(),
+                None => return Ok(None),
+            },
+        };
+
+        let changes = resources
+            .rename_locations(&name)
+            .into_iter()
+            .map(|(uri, ranges)| {
+                let edits = ranges
+                    .into_iter()
+                    .map(|range| TextEdit {
+                        range,
+                        new_text: params.new_name.clone(),
+                    })
+                    .collect();
+                (uri, edits)
+            })
+            .collect();
+        Ok(Some(WorkspaceEdit {
+            changes: Some(changes),
+            ..Default::default()
+        }))
+    }
+
+    /// Returns the edits that move a channel of the color at the position,
+    /// keeping its spelling, or none in read-only mode.
+    async fn nudge_color(&self, params: NudgeColorParams) -> error::Result<Vec<TextEdit>> {
+        if self.read_only() {
+            return Ok(Vec::new());
+        }
+        let uri = params.text_document.uri;
+        let documents = self.documents.read().await;
+        let document = documents
+            .get(&uri)
+            .ok_or_else(|| Error::DocumentNotFound(uri.clone()))?;
+        let position = Range {
+            start: params.position,
+            end: params.position,
+        };
+        let Some(color_info) = document.get_colors_in_range(&position).into_iter().next() else {
+            return Ok(Vec::new());
+        };
+        let Some(channel) = params
+            .channel
+            .or_else(|| channels::at(document, params.position).and_then(Channel::named))
+        else {
+            return Ok(Vec::new());
+        };
+        let color = channel.nudge(&color_info.color, params.step);
+        let range = color_info.range;
+        if document.syntax() == Syntax::ColorSet {
+            let lines: Vec<&str> = document.lines().collect();
+            return Ok(colorset::presentation(&lines, range, &color)
+                .and_then(|presentation| presentation.additional_text_edits)
+                .unwrap_or_default());
+        }
+        Ok(vec![TextEdit {
+            range,
+            new_text: presentation_label(document, &range, &color),
+        }])
+    }
+
+    async fn run_command(&self, params: ExecuteCommandParams) -> error::Result<Option<LSPAny>> {
+        match params.command.as_str() {
+            SUGGEST_HARMONIES_COMMAND => {
+                let color: Color =
+                    first_argument(SUGGEST_HARMONIES_COMMAND, params.arguments, "a color")?;
+
+                let sets: Map<String, Value> = Harmony::ALL
+                    .iter()
+                    .map(|harmony| {
+                        let hexes: Vec<String> =
+                            harmony.colors(&color).iter().map(to_hex).collect();
+                        (harmony.name().to_string(), hexes.into())
+                    })
+                    .collect();
+                Ok(Some(Value::Object(sets)))
+            }
+            CONTRAST_REPORT_COMMAND => {
+                let uri: Uri =
+                    first_argument(CONTRAST_REPORT_COMMAND, params.arguments, "a document URI")?;
+
+                let (report, diagnostics) = {
+                    let documents = self.documents.read().await;
+                    let document = documents
+                        .get(&uri)
+                        .ok_or_else(|| Error::DocumentNotFound(uri.clone()))?;
+
+                    let min_ratio = self.settings().contrast_level.min_ratio();
+                    let issues = contrast_issues(document, min_ratio);
+                    (
+                        markdown_report(&issues, min_ratio),
+                        contrast::diagnostics(&issues, min_ratio),
+                    )
+                };
+
+                self.client
+                    .publish_diagnostics(uri, diagnostics, None)
+                    .await;
+                Ok(Some(report.into()))
+            }
+            IMPORT_PALETTE_COMMAND => {
+                let path: String =
+                    first_argument(IMPORT_PALETTE_COMMAND, params.arguments, "a palette path")?;
+                // Accept `file:` URIs as well as plain paths.
+                let path = match path.parse::<Uri>() {
+                    Ok(uri) if path.starts_with("file:") => uri
+                        .to_file_path()
+                        .map(|path| path.into_owned())
+                        .unwrap_or_else(|| PathBuf::from(&path)),
+                    _ => PathBuf::from(path),
+                };
+
+                let palette = self.import_palette(path).await?;
+                Ok(Some(json!({
+                    "name": palette.name,
+                    "colors": palette.entries.len(),
+                })))
+            }
+            DUPLICATE_COLORS_COMMAND => {
+                let definitions = self.color_definitions().await;
+                // Clear hints of definitions that are no longer duplicated.
+                let uris: Vec<Uri> = definitions
+                    .iter()
+                    .map(|definition| definition.location.uri.clone())
+                    .collect();
+                let duplicates = duplicates::find(definitions);
+                let mut diagnostics = duplicates::diagnostics(&duplicates);
+                for uri in uris {
+                    diagnostics.entry(uri).or_default();
+                }
+                for (uri, diagnostics) in diagnostics {
+                    self.client
+                        .publish_diagnostics(uri, diagnostics, None)
+                        .await;
+                }
+                Ok(Some(duplicates::markdown_report(&duplicates).into()))
+            }
+            UNUSED_COLORS_COMMAND => {
+                self.indexed().await;
+                let variables = self.variables.read().await;
+                let unused = variables.unused();
+                let mut diagnostics = unused::diagnostics(&unused);
+                // Clear hints of variables that are referenced by now.
+                for variable in variables.all() {
+                    diagnostics
+                        .entry(variable.location.uri.clone())
+                        .or_default();
+                }
+                let report = unused::markdown_report(&unused);
+                drop(variables);
+                for (uri, diagnostics) in diagnostics {
+                    self.client
+                        .publish_diagnostics(uri, diagnostics, None)
+                        .await;
+                }
+                Ok(Some(report.into()))
+            }
+            UNKNOWN_COLORS_COMMAND => {
+                let params: UnknownColorsParams = if params.arguments.is_empty() {
+                    UnknownColorsParams::default()
+                } else {
+                    first_argument(
+                        UNKNOWN_COLORS_COMMAND,
+                        params.arguments,
+                        "an optional text document",
+                    )?
+                };
+                let uri = params.text_document.map(|text_document| text_document.uri);
+                let unknown = self
+                    .with_documents_blocking(move |documents| {
+                        let mut unknown: Vec<(Uri, Vec<unknown::UnknownColor>)> = match uri {
+                            Some(uri) => {
+                                let document = documents
+                                    .get(&uri)
+                                    .ok_or(Error::DocumentNotFound(uri.clone()))?;
+                                vec![(uri, unknown::find(document))]
+                            }
+                            None => documents
+                                .iter()
+                                .map(|(uri, document)| (uri.clone(), unknown::find(document)))
+                                .collect(),
+                        };
+                        unknown.sort_by(|a, b| a.0.as_str().cmp(b.0.as_str()));
+                        Ok(unknown)
+                    })
+                    .await
+                    .and_then(|unknown| unknown)?;
+                Ok(Some(unknown::markdown_report(&unknown).into()))
+            }
+            REINDEX_WORKSPACE_COMMAND => {
+                let token = params.work_done_progress_params.work_done_token;
+                Ok(Some(self.reindex_workspace(token).await?.into()))
+            }
+            NUDGE_COLOR_COMMAND => {
+                let nudge: NudgeColorParams = first_argument(
+                    NUDGE_COLOR_COMMAND,
+                    params.arguments,
+                    "a document, position, channel and step",
+                )?;
+                Ok(Some(json!(self.nudge_color(nudge).await?)))
+            }
+            GENERATE_GRADIENT_COMMAND => {
+                let params: GradientParams = first_argument(
+                    GENERATE_GRADIENT_COMMAND,
+                    params.arguments,
+                    "two colors and a number of stops",
+                )?;
+                let hexes: Vec<String> = gradient_stops(params)?
+                    .into_iter()
+                    .map(|stop| stop.hex)
+                    .collect();
+                Ok(Some(hexes.into()))
+            }
+            EXPORT_TERMINAL_THEME_COMMAND => {
+                let params: ExportTerminalThemeParams = first_argument(
+                    EXPORT_TERMINAL_THEME_COMMAND,
+                    params.arguments,
+                    "a document and a theme format",
+                )?;
+                let uri = params.text_document.uri;
+                let documents = self.documents.read().await;
+                let document = documents
+                    .get(&uri)
+                    .ok_or_else(|| Error::DocumentNotFound(uri.clone()))?;
+                let theme = terminal_theme::from_document(document)
+                    .ok_or_else(|| Error::NoColors(uri.clone()))?;
+                let name = params.name.unwrap_or_else(|| {
+                    let path = uri.path().as_str();
+                    let file = path.rsplit('/').next().unwrap_or(path);
+                    file.split('.').next().unwrap_or(file).to_string()
+                });
+                Ok(Some(theme.render(params.format, &name).into()))
+            }
+            CONVERT_COLOR_COMMAND => {
+                let params: ConvertColorParams = first_argument(
+                    CONVERT_COLOR_COMMAND,
+                    params.arguments,
+                    "a document, a position and a notation",
+                )?;
+                if self.read_only() {
+                    return Ok(None);
+                }
+                let uri = params.text_document.uri;
+                let edits = {
+                    let documents = self.documents.read().await;
+                    let document = documents
+                        .get(&uri)
+                        .ok_or_else(|| Error::DocumentNotFound(uri.clone()))?;
+                    document
+                        .get_colors()
+                        .into_iter()
+                        .find(|color_info| {
+                            color_info.range.start <= params.position
+                                && params.position <= color_info.range.end
+                        })
+                        .map(|color_info| {
+                            convert::edits(document, &color_info.color, params.notation)
+                        })
+                        .unwrap_or_default()
+                };
+                if edits.is_empty() {
+                    return Ok(Some(false.into()));
+                }
+                let edit = WorkspaceEdit {
+                    changes: Some(HashMap::from([(uri, edits)])),
+                    ..Default::default()
+                };
+                // Lenses run commands without applying their result, so the
+                // server applies the edit itself.
+                let applied = self
+                    .client
+                    .apply_edit(edit)
+                    .await
+                    .is_ok_and(|response| response.applied);
+                Ok(Some(applied.into()))
+            }
+            CONVERT_ALL_COLORS_COMMAND => {
+                let params: ConvertAllColorsParams = first_argument(
+                    CONVERT_ALL_COLORS_COMMAND,
+                    params.arguments,
+                    "a document and a notation",
+                )?;
+                if self.read_only() {
+                    return Ok(None);
+                }
+                let uri = params.text_document.uri;
+                let documents = self.documents.read().await;
+                let document = documents
+                    .get(&uri)
+                    .ok_or_else(|| Error::DocumentNotFound(uri.clone()))?;
+                let edit = WorkspaceEdit {
+                    changes: Some(HashMap::from([(
+                        uri.clone(),
+                        convert::all_edits(document, params.notation),
+                    )])),
+                    ..Default::default()
+                };
+                Ok(Some(json!(edit)))
+            }
+            EXTRACT_PALETTE_COMMAND => {
+                let params: ExtractPaletteParams = if params.arguments.is_empty() {
+                    ExtractPaletteParams::default()
+                } else {
+                    first_argument(
+                        EXTRACT_PALETTE_COMMAND,
+                        params.arguments,
+                        "optional palette options",
+                    )?
+                };
+                let files = self.file_colors(params.workspace).await?;
+                let palette =
+                    ExtractedPalette::new(files.iter().map(|(uri, colors)| (uri, &colors[..])));
+                Ok(Some(json!(Versioned::new(palette))))
+            }
+            DUMP_STATE_COMMAND => {
+                let mut documents: Vec<DocumentStatus> = self
+                    .documents
+                    .read()
+                    .await
+                    .iter()
+                    .map(|(uri, document)| DocumentStatus::new(uri.clone(), document))
+                    .collect();
+                documents.sort_by(|a, b| a.uri.as_str().cmp(b.uri.as_str()));
+                let folders = self.workspace_roots.get().cloned().unwrap_or_default();
+                let dump = self.journal.dump(documents, folders);
+                let home = std::env::var_os("HOME").map(PathBuf::from);
+                let path = self
+                    .run_blocking(move || dump.write(&std::env::temp_dir(), home.as_deref()))
+                    .await??;
+                Ok(Some(path.to_string_lossy().into_owned().into()))
+            }
+            command => Err(Error::UnknownCommand(command.to_string())),
+        }
+    }
+}
+
+/// Returns the settings of `workspace/didChangeConfiguration`, which clients
+/// send either as is or under the server's name.
+fn settings_section(mut settings: Value) -> Value {
+    for section in ["chroma", "chroma-ls"] {
+        if let Some(section) = settings.get_mut(section) {
+            return section.take();
+        }
+    }
+    settings
+}
+
+/// Whether parsing `text` is heavy enough to run on the blocking pool.
+fn is_heavy(text: &str) -> bool {
+    text.lines().count() > HEAVY_LINE_COUNT
+}
+
+/// Returns the extension of a document's path, or an empty string.
+fn extension(uri: &Uri) -> &str {
+    uri.path()
+        .as_str()
+        .rsplit_once('.')
+        .map_or("", |(_, extension)| extension)
+}
+
+/// Reports invalid values of color fields in JSON documents with a recognized
+/// `$schema`, or `None` for other documents.
+fn schema_diagnostics(document: &Document) -> Option<Vec<Diagnostic>> {
+    match document.syntax() {
+        Syntax::Json(schema) => Some(schema.diagnostics(document)),
+        _ => None,
+    }
+}
+
+/// Formats `color` in the spelling already used at `range`.
+fn presentation_label(document: &Document, range: &Range, color: &Color) -> String {
+    let existing = document.range_text(range).unwrap_or_default();
+    if document.syntax() == Syntax::Unity && existing.starts_with('{') {
+        unity_map(color)
+    } else if document.syntax() == Syntax::Unreal && existing.starts_with('(') {
+        unreal_struct(color, existing.contains('.'))
+    } else if existing.starts_with("rgb") {
+        css_rgb(color, existing)
+    } else if existing.starts_with("hsl") {
+        css_hsl(color, existing)
+    } else if let Some(class) = tailwind::format(color, existing, &document.options().tailwind) {
+        class
+    } else if let Some(order) = document.options().integer_colors
+        && (existing.starts_with("0x") || existing.starts_with("0X"))
+        && document.syntax() != Syntax::ColorConstructor
+    {
+        integer::format(color, order, existing)
+    } else {
+        HexStyle::infer(existing, document.syntax()).format(color)
+    }
+}
+
+/// Builds an action that replaces the text at `range` with `new_text`.
+fn replace_action(uri: &Uri, title: String, range: Range, new_text: String) -> CodeActionOrCommand {
+    CodeActionOrCommand::CodeAction(CodeAction {
+        title,
+        kind: Some(CodeActionKind::REFACTOR_REWRITE),
+        edit: Some(WorkspaceEdit {
+            changes: Some(HashMap::from([(
+                uri.clone(),
+                vec![TextEdit { range, new_text }],
+            )])),
+            ..Default::default()
+        }),
+        ..Default::default()
+    })
+}
+
+/// Deserializes the first argument of `command`.
+fn first_argument<T: DeserializeOwned>(
+    command: &'static str,
+    arguments: Vec<Value>,
+    expected: &'static str,
+) -> error::Result<T> {
+    arguments
+        .into_iter()
+        .next()
+        .and_then(|arg| serde_json::from_value(arg).ok())
+        .ok_or(Error::InvalidArgument { command, expected })
+}
+
+fn gradient_stops(params: GradientParams) -> error::Result<Vec<GradientStop>> {
+    if !(2..=gradient::MAX_STOPS).contains(&params.stops) {
+        return Err(Error::InvalidStopCount(params.stops));
+    }
+    Ok(gradient::interpolate(
+        &params.from,
+        &params.to,
+        params.space,
+        params.hue,
+        params.stops,
+    ))
+}
+
+/// Builds the LSP service with all custom requests registered, translating
+/// positions to the encoding negotiated with the client.
+pub fn build_service() -> (Encoded<Shared<LspService<Backend>>>, Encoded<ClientSocket>) {
+    let (service, socket) = LspService::build(Backend::new)
+        .custom_method("chroma/colorChannels", Backend::color_channels)
+        .custom_method("chroma/colorHistogram", Backend::color_histogram)
+        .custom_method("chroma/colorMonikers", Backend::color_monikers)
+        .custom_method("chroma/colorsDelta", Backend::colors_delta)
+        .custom_method("chroma/documentColors", Backend::document_colors)
+        .custom_method("chroma/gradient", Backend::gradient)
+        .custom_method("chroma/profile", Backend::profile)
+        .custom_method("chroma/status", Backend::status)
+        .custom_method("chroma/viewportColors", Backend::viewport_colors)
+        .custom_method("window/workDoneProgress/cancel", Backend::cancel_progress)
+        .custom_method("$/setTrace", Backend::set_trace)
+        .finish();
+    let documents = Arc::clone(&service.inner().documents);
+    encoding::encoded(service, socket, documents)
+}
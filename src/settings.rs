@@ -0,0 +1,190 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::PathBuf;
+
+use serde::Deserialize;
+
+use crate::alias::Aliases;
+use crate::color::{ColorFormat, ParseOptions};
+use crate::composite::Backgrounds;
+use crate::conformance::Validation;
+use crate::contrast::ContrastLevel;
+use crate::heuristics::KeyPatterns;
+use crate::integer::ChannelOrder;
+use crate::named::NamedColorLanguages;
+use crate::normalize::CanonicalFormat;
+
+/// Server settings, read from `InitializeParams.initialization_options`.
+#[derive(Debug, Default, Clone, PartialEq, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct Settings {
+    /// Named colors decorated, completed and offered as presentations.
+    pub aliases: Aliases,
+    /// Patterns of YAML/TOML keys whose bare hex or named values are colors.
+    pub color_keys: KeyPatterns,
+    /// Opaque backgrounds translucent colors are previewed over on hover.
+    pub backgrounds: Backgrounds,
+    /// Adds a swatch of translucent colors over the first background next
+    /// to their own.
+    pub composite_swatches: bool,
+    /// Language ids or file extensions of documents where CSS named colors
+    /// are decorated as whole words.
+    pub named_colors: NamedColorLanguages,
+    /// Shows the CSS named color of hex colors, or the closest one, as inlay
+    /// hints.
+    pub color_name_hints: bool,
+    /// Lists each distinct color of a document as a symbol, after its color
+    /// definitions.
+    pub color_symbols: bool,
+    /// Bit depth offered by the snapping code action, next to the web-safe
+    /// palette and 4-bit channels.
+    pub snap_bit_depth: Option<u8>,
+    /// Palette files (`.gpl`, `.kpl` or Inkscape `.svg`) offered as snapping
+    /// targets. Relative paths are resolved against the first workspace folder.
+    pub palettes: Vec<PathBuf>,
+    /// Format hex colors are rewritten to before save, if any.
+    pub normalize_on_save: Option<CanonicalFormat>,
+    /// Most files read at once by `chroma.reindexWorkspace`.
+    pub index_concurrency: Option<usize>,
+    /// Formats whose colors are decorated, or all formats when unset.
+    pub formats: Option<BTreeSet<ColorFormat>>,
+    /// Formats decorated in documents of a language id or file extension,
+    /// replacing `formats` for them.
+    pub language_formats: BTreeMap<String, BTreeSet<ColorFormat>>,
+    /// Channel order of `0x` integer literals decorated as colors in
+    /// documents of a language id or file extension.
+    pub integer_colors: BTreeMap<String, ChannelOrder>,
+    /// How color functions are validated in documents of a language id or
+    /// file extension.
+    pub validation: BTreeMap<String, Validation>,
+    /// Largest document, in bytes, the server activates on.
+    pub max_file_size: Option<usize>,
+    /// Approximate memory, in bytes, the parsed colors of open documents may
+    /// take before those of the least recently used are dropped.
+    pub memory_budget: Option<usize>,
+    /// Language ids or file extensions of documents the server activates on,
+    /// or all documents when unset.
+    pub languages: Option<Vec<String>>,
+    /// Disables features that edit documents, such as rename, conversions and
+    /// normalizing on save, to safely audit vendored or generated trees.
+    pub read_only: bool,
+    /// Bundled profile the settings below default to.
+    pub preset: Option<Preset>,
+    /// Reports foregrounds below `contrast_level` contrast with their
+    /// background as warnings while documents change: theme keys, CSS rules
+    /// and Vim highlight groups.
+    pub contrast_diagnostics: Option<bool>,
+    /// WCAG level contrast diagnostics and reports check against.
+    pub contrast_level: ContrastLevel,
+    /// Shows the APCA contrast of colors as text over each of `backgrounds`
+    /// on hover.
+    pub apca_hover: Option<bool>,
+    /// Offers code actions replacing colors with how they look under color
+    /// vision deficiencies.
+    pub cvd_actions: Option<bool>,
+    /// Reports stylistic hints, such as likely `#AARRGGBB` colors in VS Code
+    /// color themes.
+    pub stylistic_lints: Option<bool>,
+}
+
+/// A bundled profile of settings.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub enum Preset {
+    /// For accessibility reviews: contrast diagnostics, APCA hovers and color
+    /// vision deficiency actions, without stylistic lints.
+    #[serde(rename = "a11y")]
+    A11y,
+}
+
+impl Settings {
+    pub fn parse_options(&self) -> ParseOptions {
+        ParseOptions {
+            aliases: self.aliases.clone(),
+            color_keys: self.color_keys.clone(),
+            named_colors: false,
+            formats: self.formats.clone(),
+            integer_colors: None,
+            strict: false,
+            tailwind: Default::default(),
+        }
+    }
+
+    /// Returns the formats set in `language_formats` for a document with this
+    /// language id or extension, preferring the language id.
+    pub fn language_formats(
+        &self,
+        language_id: &str,
+        extension: &str,
+    ) -> Option<&BTreeSet<ColorFormat>> {
+        by_language(&self.language_formats, language_id, extension)
+    }
+
+    /// Returns the channel order set in `integer_colors` for a document with
+    /// this language id or extension, preferring the language id.
+    pub fn integer_colors(&self, language_id: &str, extension: &str) -> Option<ChannelOrder> {
+        by_language(&self.integer_colors, language_id, extension).copied()
+    }
+
+    /// Returns the validation set in `validation` for a document with this
+    /// language id or extension, preferring the language id.
+    pub fn validation(&self, language_id: &str, extension: &str) -> Validation {
+        by_language(&self.validation, language_id, extension)
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Whether the server activates on a document with this language id or
+    /// extension and size in bytes. Other documents are tracked without
+    /// colors, diagnostics or index entries.
+    pub fn activates_on(&self, language_id: &str, extension: &str, size: usize) -> bool {
+        let language = self.languages.as_ref().is_none_or(|languages| {
+            languages.iter().any(|language| {
+                language.eq_ignore_ascii_case(language_id)
+                    || language.eq_ignore_ascii_case(extension)
+            })
+        });
+        language && self.max_file_size.is_none_or(|max| size <= max)
+    }
+
+    pub fn contrast_diagnostics(&self) -> bool {
+        self.contrast_diagnostics.unwrap_or(self.is_a11y())
+    }
+
+    pub fn apca_hover(&self) -> bool {
+        self.apca_hover.unwrap_or(self.is_a11y())
+    }
+
+    pub fn cvd_actions(&self) -> bool {
+        self.cvd_actions.unwrap_or(self.is_a11y())
+    }
+
+    pub fn stylistic_lints(&self) -> bool {
+        self.stylistic_lints.unwrap_or(!self.is_a11y())
+    }
+
+    fn is_a11y(&self) -> bool {
+        self.preset == Some(Preset::A11y)
+    }
+
+    /// Returns how many files are read at once when reindexing, by default
+    /// half the available CPUs so a rescan doesn't saturate the machine.
+    pub fn index_concurrency(&self) -> usize {
+        self.index_concurrency
+            .unwrap_or_else(|| std::thread::available_parallelism().map_or(1, |n| n.get() / 2))
+            .max(1)
+    }
+}
+
+/// Returns the value of `map` for a language id or, failing that, a file
+/// extension, ignoring case.
+fn by_language<'a, T>(
+    map: &'a BTreeMap<String, T>,
+    language_id: &str,
+    extension: &str,
+) -> Option<&'a T> {
+    let find = |language: &str| {
+        map.iter()
+            .find(|(covered, _)| covered.eq_ignore_ascii_case(language))
+            .map(|(_, value)| value)
+    };
+    find(language_id).or_else(|| find(extension))
+}
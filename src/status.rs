@@ -0,0 +1,75 @@
+use std::collections::BTreeMap;
+
+use serde::Serialize;
+use tower_lsp_server::ls_types::Uri;
+
+use crate::color::ColorFormat;
+use crate::document::Document;
+
+/// Result of the `chroma/status` request.
+#[derive(Debug, Default, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Status {
+    pub documents: Vec<DocumentStatus>,
+}
+
+/// Parser counters of an open document.
+#[derive(Debug, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct DocumentStatus {
+    pub uri: Uri,
+    pub version: i32,
+    pub lines: usize,
+    pub colors: usize,
+    /// Number of matches produced by each format parser.
+    pub formats: BTreeMap<ColorFormat, usize>,
+    /// Approximate memory held by the document, in bytes.
+    pub memory: usize,
+    /// Whether the parsed colors were dropped to fit `memoryBudget`.
+    pub evicted: bool,
+}
+
+impl DocumentStatus {
+    pub fn new(uri: Uri, document: &Document) -> Self {
+        // Read before counting, which reparses evicted documents.
+        let (memory, evicted) = (document.approximate_size(), document.is_evicted());
+        let formats = document.format_counts();
+        Self {
+            uri,
+            version: document.version(),
+            lines: document.line_count(),
+            colors: formats.values().sum(),
+            formats,
+            memory,
+            evicted,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use tower_lsp_server::ls_types::Uri;
+
+    use crate::color::ColorFormat;
+    use crate::document::Document;
+    use crate::status::DocumentStatus;
+
+    #[test]
+    fn counts_matches_per_format() {
+        let mut document = Document::from("#FF0000 #00FF00\nnone\n#0000FF80\n");
+        let status = DocumentStatus::new(Uri::from_str("file:///a.css").unwrap(), &document);
+
+        assert_eq!(status.lines, 4);
+        assert_eq!(status.colors, 3);
+        assert_eq!(status.formats[&ColorFormat::Hex], 3);
+        assert!(!status.evicted);
+
+        document.evict();
+        let evicted = DocumentStatus::new(Uri::from_str("file:///a.css").unwrap(), &document);
+        assert!(evicted.evicted);
+        assert!(evicted.memory < status.memory);
+        assert_eq!(evicted.colors, 3);
+    }
+}
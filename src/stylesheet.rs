@@ -0,0 +1,217 @@
+//! Color functions of SCSS, Sass and LESS stylesheets, e.g.
+//! `darken($primary, 10%)` or `fade(@brand, 50%)`, evaluated with the
+//! variables defined in the document.
+
+use std::collections::HashMap;
+
+use tower_lsp_server::ls_types::{Color, ColorInformation};
+
+use crate::color::{ColorFormat, ColorMatch, parse_line_colors};
+use crate::colorspace::{from_hsl, to_hsl};
+use crate::desktop::{function_calls, split_args};
+use crate::heuristics::line_range;
+use crate::named;
+
+/// Sass and LESS color functions, which are decorated with the color they
+/// compute.
+const FUNCTIONS: [&str; 14] = [
+    "darken",
+    "lighten",
+    "saturate",
+    "desaturate",
+    "rgb",
+    "rgba",
+    "fade",
+    "transparentize",
+    "fade-out",
+    "fadeout",
+    "opacify",
+    "fade-in",
+    "fadein",
+    "mix",
+];
+
+/// Color variables of a stylesheet, keyed by name with their `$` or `@`.
+pub type Variables = HashMap<String, Color>;
+
+/// Collects the `$name: value;` and `@name: value;` definitions whose value
+/// evaluates to a color. Later definitions override earlier ones, and values
+/// may refer to variables defined before them.
+pub fn variables<'a>(lines: impl IntoIterator<Item = &'a str>) -> Variables {
+    let mut variables = Variables::new();
+    for line in lines {
+        let line = line.trim();
+        if !line.starts_with(['$', '@']) {
+            continue;
+        }
+        let Some((name, value)) = line.split_once(':') else {
+            continue;
+        };
+        // Skip at-rules such as `@media (min-width: 600px)`.
+        if !name[1..]
+            .chars()
+            .all(|c| c.is_alphanumeric() || c == '-' || c == '_')
+        {
+            continue;
+        }
+        let value = value.trim().trim_end_matches(';');
+        let value = value
+            .trim_end()
+            .trim_end_matches("!default")
+            .trim_end_matches("!global");
+        if let Some(color) = evaluate(value, &variables) {
+            variables.insert(name.to_string(), color);
+        }
+    }
+    variables
+}
+
+/// Finds calls of color functions in a line whose arguments evaluate, e.g.
+/// `mix(#fff, $accent, 20%)`.
+pub fn functions(line: &str, line_idx: usize, variables: &Variables) -> Vec<ColorMatch> {
+    function_calls(line, &FUNCTIONS)
+        .into_iter()
+        .filter_map(|call| {
+            Some(ColorMatch {
+                info: ColorInformation {
+                    range: line_range(line, line_idx, call.clone()),
+                    color: evaluate(&line[call], variables)?,
+                },
+                format: ColorFormat::Heuristic,
+            })
+        })
+        .collect()
+}
+
+/// Evaluates a color expression: a variable, hex, a CSS color, a named color
+/// or a color function.
+fn evaluate(expr: &str, variables: &Variables) -> Option<Color> {
+    let expr = expr.trim();
+    if let Some(color) = variables.get(expr) {
+        return Some(*color);
+    }
+    if let Some((name, args)) = expr.split_once('(')
+        && let Some(args) = args.strip_suffix(')')
+        && let Some(color) = call(name.trim(), &split_args(args), variables)
+    {
+        return Some(color);
+    }
+
+    match parse_line_colors(expr, 0).as_slice() {
+        [color_info] if color_info.range.end.character as usize == expr.len() => {
+            Some(color_info.color)
+        }
+        _ => named::lookup(expr),
+    }
+}
+
+/// Evaluates a call of a color function.
+fn call(name: &str, args: &[&str], variables: &Variables) -> Option<Color> {
+    let color = |idx: usize| evaluate(args.get(idx)?, variables);
+    let percentage = |idx: usize| percentage(args.get(idx)?);
+    let fraction = |idx: usize| fraction(args.get(idx)?);
+    let with_alpha = |color: Color, alpha: f32| Color {
+        alpha: alpha.clamp(0.0, 1.0),
+        ..color
+    };
+    match (name, args.len()) {
+        ("darken", 2) => Some(adjust(color(0)?, 0.0, -percentage(1)?)),
+        ("lighten", 2) => Some(adjust(color(0)?, 0.0, percentage(1)?)),
+        ("saturate", 2) => Some(adjust(color(0)?, percentage(1)?, 0.0)),
+        ("desaturate", 2) => Some(adjust(color(0)?, -percentage(1)?, 0.0)),
+        ("rgb" | "rgba" | "fade", 2) => Some(with_alpha(color(0)?, fraction(1)?)),
+        ("transparentize" | "fade-out" | "fadeout", 2) => {
+            let color = color(0)?;
+            Some(with_alpha(color, color.alpha - fraction(1)?))
+        }
+        ("opacify" | "fade-in" | "fadein", 2) => {
+            let color = color(0)?;
+            Some(with_alpha(color, color.alpha + fraction(1)?))
+        }
+        ("mix", 2 | 3) => {
+            let weight = if args.len() == 3 { percentage(2)? } else { 0.5 };
+            let (a, b) = (color(0)?, color(1)?);
+            let mix = |a: f32, b: f32| (a * weight + b * (1.0 - weight)).clamp(0.0, 1.0);
+            Some(Color {
+                red: mix(a.red, b.red),
+                green: mix(a.green, b.green),
+                blue: mix(a.blue, b.blue),
+                alpha: mix(a.alpha, b.alpha),
+            })
+        }
+        _ => None,
+    }
+}
+
+/// Shifts saturation and lightness by absolute amounts, as Sass does.
+fn adjust(color: Color, saturation: f32, lightness: f32) -> Color {
+    let (hue, s, l) = to_hsl(&color);
+    from_hsl(
+        hue,
+        (s + saturation).clamp(0.0, 1.0),
+        (l + lightness).clamp(0.0, 1.0),
+        color.alpha,
+    )
+}
+
+/// Parses an amount like `10%` or `10` as a fraction, 0.1.
+fn percentage(arg: &str) -> Option<f32> {
+    let arg = arg.trim();
+    let value: f32 = arg.strip_suffix('%').unwrap_or(arg).parse().ok()?;
+    value.is_finite().then_some(value / 100.0)
+}
+
+/// Parses an opacity like `.5` or `50%` as a fraction, 0.5.
+fn fraction(arg: &str) -> Option<f32> {
+    let arg = arg.trim();
+    if arg.ends_with('%') {
+        return percentage(arg);
+    }
+    let value: f32 = arg.parse().ok()?;
+    value.is_finite().then_some(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::color::{ColorFormat, to_hex};
+    use crate::stylesheet::{functions, variables};
+
+    #[test]
+    fn evaluates_with_variables() {
+        let variables = variables([
+            "$primary: #3366cc !default;",
+            "@brand: rebeccapurple;",
+            "$accent: darken($primary, 10%);",
+            "@media (min-width: 600px) {",
+        ]);
+        assert_eq!(variables.len(), 3);
+        assert_eq!(to_hex(&variables["$accent"]), "#2952A3");
+
+        let summary = |line| {
+            functions(line, 0, &variables)
+                .into_iter()
+                .map(|m| {
+                    assert_eq!(m.format, ColorFormat::Heuristic);
+                    let range = m.info.range;
+                    (
+                        range.start.character,
+                        range.end.character,
+                        to_hex(&m.info.color),
+                    )
+                })
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            summary("  color: rgba($primary, .5); border: fade(@brand, 50%);"),
+            [
+                (9, 27, "#3366CC80".to_string()),
+                (37, 54, "#66339980".to_string()),
+            ]
+        );
+        assert_eq!(
+            summary("  background: mix(#fff, $accent, 20%);"),
+            [(14, 37, "#5474B6".to_string())]
+        );
+        assert_eq!(summary("  color: lighten($unknown, 10%);"), []);
+    }
+}
@@ -0,0 +1,152 @@
+//! Navigation of SVG gradients referenced by `url(#id)` paint values.
+
+use tower_lsp_server::ls_types::{ColorInformation, Position, Range};
+
+use crate::color::to_hex;
+use crate::document::Document;
+use crate::heuristics::line_range;
+
+/// SVG elements that define gradients.
+const GRADIENT_ELEMENTS: [&str; 2] = ["linearGradient", "radialGradient"];
+
+/// A gradient element and the colors of its stops.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Gradient {
+    pub element: &'static str,
+    pub id: String,
+    /// Range of the value of the `id` attribute.
+    pub id_range: Range,
+    pub stops: Vec<ColorInformation>,
+}
+
+impl Gradient {
+    /// Renders the gradient and its stops as Markdown.
+    pub fn markdown(&self) -> String {
+        let mut markdown = format!("**{}** `{}`\n", self.element, self.id);
+        for stop in &self.stops {
+            markdown.push_str(&format!("\n- `{}`", to_hex(&stop.color)));
+        }
+        markdown
+    }
+}
+
+/// Returns the `url(#id)` reference at `position`, as the range of the whole
+/// `url(...)` and the id.
+pub fn reference_at(document: &Document, position: Position) -> Option<(Range, &str)> {
+    let line = document.line(position.line as usize)?;
+    line.match_indices("url(#").find_map(|(start, open)| {
+        let id_start = start + open.len();
+        let id_len = line[id_start..].find(')')?;
+        let range = line_range(line, position.line as usize, start..id_start + id_len + 1);
+        (range.start <= position && position <= range.end)
+            .then(|| (range, &line[id_start..id_start + id_len]))
+    })
+}
+
+/// Finds the gradient element with `id` in the document.
+pub fn find_gradient(document: &Document, id: &str) -> Option<Gradient> {
+    // Gradient element whose start tag is open at the current point, if any.
+    let mut element = None;
+    for (line_idx, line) in document.lines().enumerate() {
+        let mut offset = 0;
+        while offset < line.len() {
+            let rest = &line[offset..];
+            let tag = rest.find('<');
+            let attribute = ["id=\"", "id='"]
+                .iter()
+                .filter_map(|quote| Some((rest.find(quote)?, quote.len())))
+                .min();
+
+            match (tag, attribute) {
+                (Some(tag), attribute) if attribute.is_none_or(|(idx, _)| tag < idx) => {
+                    let name = &rest[tag + 1..];
+                    element = GRADIENT_ELEMENTS
+                        .into_iter()
+                        .find(|gradient| name.starts_with(gradient));
+                    offset += tag + 1;
+                }
+                (_, Some((idx, quote_len))) => {
+                    let value_start = offset + idx + quote_len;
+                    let value_len = line[value_start..].find(['"', '\'']).unwrap_or(0);
+                    offset = value_start + value_len;
+                    // Skip `stop-id=` and similar attributes.
+                    let preceded_by_name = line[..value_start - quote_len]
+                        .chars()
+                        .next_back()
+                        .is_some_and(|c| c.is_alphanumeric() || c == '-' || c == ':');
+                    if let Some(element) = element
+                        && !preceded_by_name
+                        && &line[value_start..offset] == id
+                    {
+                        let id_range = line_range(line, line_idx, value_start..offset);
+                        return Some(Gradient {
+                            element,
+                            id: id.to_string(),
+                            id_range,
+                            stops: stops(document, element, id_range.end),
+                        });
+                    }
+                }
+                _ => break,
+            }
+        }
+    }
+    None
+}
+
+/// Returns the colors between `start` and the closing tag of `element`.
+fn stops(document: &Document, element: &str, start: Position) -> Vec<ColorInformation> {
+    let closing_tag = format!("</{element}");
+    let end_line = (start.line as usize..document.line_count())
+        .find(|&line_idx| {
+            document
+                .line(line_idx)
+                .is_some_and(|line| line.contains(&closing_tag))
+        })
+        .unwrap_or(start.line as usize);
+
+    document
+        .get_colors()
+        .into_iter()
+        .filter(|color_info| {
+            color_info.range.start >= start && color_info.range.start.line as usize <= end_line
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use tower_lsp_server::ls_types::Position;
+
+    use crate::document::Document;
+    use crate::svg::{find_gradient, reference_at};
+
+    const ICON: &str = r##"<svg>
+  <defs>
+    <linearGradient
+        id="sky">
+      <stop offset="0" stop-color="#87CEEB"/>
+      <stop offset="1" stop-color="#1E90FF"/>
+    </linearGradient>
+    <radialGradient id="sun"><stop stop-color="#FFD700"/></radialGradient>
+  </defs>
+  <rect fill="url(#sky)"/>
+  <circle fill="#FFD700"/>
+</svg>"##;
+
+    #[test]
+    fn resolves_gradient_stops() {
+        let document = Document::from(ICON);
+        let (range, id) = reference_at(&document, Position::new(9, 17)).unwrap();
+        assert_eq!((range.start.character, range.end.character), (14, 23));
+
+        let gradient = find_gradient(&document, id).unwrap();
+        assert_eq!(gradient.id_range.start, Position::new(3, 12));
+        assert_eq!(
+            gradient.markdown(),
+            "**linearGradient** `sky`\n\n- `#87CEEB`\n- `#1E90FF`"
+        );
+        assert_eq!(find_gradient(&document, "sun").unwrap().stops.len(), 1);
+        assert_eq!(find_gradient(&document, "moon"), None);
+    }
+}
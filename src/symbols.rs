@@ -0,0 +1,377 @@
+//! Color definitions as symbols, for `textDocument/documentSymbol` and
+//! `workspace/symbol`.
+
+use std::collections::HashMap;
+
+use tower_lsp_server::ls_types::{
+    Color, DocumentSymbol, Location, OneOf, Position, Range, SymbolKind, Uri, WorkspaceSymbol,
+};
+
+use crate::android;
+use crate::color::to_hex;
+use crate::contrast::key_before;
+use crate::document::Document;
+use crate::heuristics::{Syntax, line_range};
+use crate::theme_object;
+use crate::variables;
+
+/// Most symbols returned by `workspace/symbol`.
+pub const MAX_WORKSPACE_SYMBOLS: usize = 500;
+
+/// A `workspace/symbol` query.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SymbolQuery {
+    /// A hex prefix such as `#33`, matched against the value of definitions
+    /// and literal colors, ignoring case.
+    Hex(String),
+    /// Text matched against the names of definitions, ignoring case. Empty
+    /// queries match all definitions.
+    Name(String),
+}
+
+impl SymbolQuery {
+    pub fn parse(query: &str) -> Self {
+        let query = query.trim();
+        if query.starts_with('#') && query[1..].chars().all(|c| c.is_ascii_hexdigit()) {
+            SymbolQuery::Hex(query.to_ascii_uppercase())
+        } else {
+            SymbolQuery::Name(query.to_lowercase())
+        }
+    }
+
+    /// Whether a definition named `name` holding `color` matches.
+    pub fn matches_definition(&self, name: &str, color: &Color) -> bool {
+        match self {
+            SymbolQuery::Hex(_) => self.matches_color(color),
+            SymbolQuery::Name(text) => name.to_lowercase().contains(text.as_str()),
+        }
+    }
+
+    /// Whether a literal color matches, which only hex queries do.
+    pub fn matches_color(&self, color: &Color) -> bool {
+        match self {
+            SymbolQuery::Hex(prefix) => to_hex(color).starts_with(prefix.as_str()),
+            SymbolQuery::Name(_) => false,
+        }
+    }
+}
+
+/// Returns the workspace symbol of a definition, with its value as container
+/// so pickers show it next to the name.
+pub fn definition_symbol(name: &str, location: &Location, color: &Color) -> WorkspaceSymbol {
+    WorkspaceSymbol {
+        name: name.to_string(),
+        kind: SymbolKind::VARIABLE,
+        tags: None,
+        container_name: Some(to_hex(color)),
+        location: OneOf::Left(location.clone()),
+        data: None,
+    }
+}
+
+/// Returns the workspace symbol of a literal color.
+pub fn color_symbol(location: Location, color: &Color) -> WorkspaceSymbol {
+    WorkspaceSymbol {
+        name: to_hex(color),
+        kind: SymbolKind::CONSTANT,
+        tags: None,
+        container_name: None,
+        location: OneOf::Left(location),
+        data: None,
+    }
+}
+
+/// Sorts workspace symbols by definitions first, then by file and position.
+pub fn sort_workspace_symbols(symbols: &mut [WorkspaceSymbol]) {
+    fn key(symbol: &WorkspaceSymbol) -> (bool, &str, Position) {
+        let (uri, start) = match &symbol.location {
+            OneOf::Left(location) => (&location.uri, location.range.start),
+            OneOf::Right(location) => (&location.uri, Position::default()),
+        };
+        (symbol.kind != SymbolKind::VARIABLE, uri.as_str(), start)
+    }
+    symbols.sort_by(|a, b| key(a).cmp(&key(b)));
+}
+
+/// Returns the color definitions of a document: variables, Android color
+/// resources and theme object members, and the keys assigned colors in
+/// config and theme files, grouped by their `[section]`. With
+/// `distinct_colors`, each distinct color follows, at its first use.
+pub fn document_symbols(
+    document: &Document,
+    uri: &Uri,
+    distinct_colors: bool,
+) -> Vec<DocumentSymbol> {
+    let mut symbols: Vec<DocumentSymbol> = variables::definitions(uri, document)
+        .into_iter()
+        .map(|variable| (variable.name, variable.location, variable.color))
+        .chain(match document.syntax() {
+            Syntax::Android => android::definitions(uri, document)
+                .into_iter()
+                .map(|resource| (resource.name, resource.location, resource.color))
+                .collect(),
+            Syntax::ColorConstructor => theme_object::definitions(uri, document)
+                .into_iter()
+                .map(|member| (member.name, member.location, member.color))
+                .collect(),
+            _ => Vec::new(),
+        })
+        .map(|(name, location, color)| definition(document, name, location, &color))
+        .collect();
+    if is_keyed(document.syntax()) {
+        symbols.extend(keyed_colors(document));
+    }
+    symbols.sort_by_key(|symbol| symbol.range.start);
+
+    if distinct_colors {
+        let colors: Vec<_> = document
+            .get_colors()
+            .into_iter()
+            .map(|color_info| (to_hex(&color_info.color), color_info.range))
+            .collect();
+        let mut uses: HashMap<&str, usize> = HashMap::new();
+        for (hex, _) in &colors {
+            *uses.entry(hex).or_default() += 1;
+        }
+        for (hex, range) in &colors {
+            // Only the first use is left in the map.
+            let Some(count) = uses.remove(hex.as_str()) else {
+                continue;
+            };
+            symbols.push(symbol(
+                hex.clone(),
+                format!("{count} {}", if count == 1 { "use" } else { "uses" }),
+                SymbolKind::CONSTANT,
+                *range,
+                *range,
+            ));
+        }
+    }
+    symbols
+}
+
+/// Whether colors of the syntax are assigned to keys, as in config files.
+fn is_keyed(syntax: Syntax) -> bool {
+    matches!(
+        syntax,
+        Syntax::KeyValue
+            | Syntax::Helix
+            | Syntax::Starship
+            | Syntax::Zellij
+            | Syntax::Alacritty
+            | Syntax::WezTerm
+            | Syntax::Ghostty
+            | Syntax::SublimeColorScheme
+            | Syntax::Json(_)
+    )
+}
+
+/// Returns the keys assigned colors, as children of the `[section]` they
+/// are in, if any.
+fn keyed_colors(document: &Document) -> Vec<DocumentSymbol> {
+    let mut symbols: Vec<DocumentSymbol> = Vec::new();
+    let mut section: Option<DocumentSymbol> = None;
+    let mut colors = document.get_colors().into_iter().peekable();
+    for (line_idx, line) in document.lines().enumerate() {
+        let trimmed = line.trim();
+        if trimmed.starts_with('[') && trimmed.ends_with(']') {
+            symbols.extend(section.take().filter(|section| section.children.is_some()));
+            let name = trimmed.trim_matches(['[', ']']).trim();
+            let start = line.len() - line.trim_start().len();
+            let range = line_range(line, line_idx, start..start + trimmed.len());
+            section = Some(symbol(
+                name.to_string(),
+                String::new(),
+                SymbolKind::NAMESPACE,
+                range,
+                range,
+            ));
+        }
+
+        while let Some(color_info) =
+            colors.next_if(|color_info| color_info.range.start.line as usize == line_idx)
+        {
+            if color_info.range.start == color_info.range.end {
+                continue;
+            }
+            let Some(key) = key_before(line, color_info.range.start.character as usize) else {
+                continue;
+            };
+            let indent = line.len() - line.trim_start().len();
+            let range = Range::new(
+                Position::new(line_idx as u32, indent as u32),
+                color_info.range.end,
+            );
+            let child = symbol(
+                key,
+                to_hex(&color_info.color),
+                SymbolKind::PROPERTY,
+                range,
+                color_info.range,
+            );
+            match &mut section {
+                Some(section) => {
+                    section.range.end = child.range.end;
+                    section.children.get_or_insert_with(Vec::new).push(child);
+                }
+                None => symbols.push(child),
+            }
+        }
+    }
+    symbols.extend(section.filter(|section| section.children.is_some()));
+    symbols
+}
+
+/// Returns the symbol of a named definition, spanning the rest of its line.
+fn definition(
+    document: &Document,
+    name: String,
+    location: Location,
+    color: &Color,
+) -> DocumentSymbol {
+    let line_idx = location.range.start.line as usize;
+    let line = document.line(line_idx).unwrap_or_default();
+    let end = line.trim_end().len();
+    let range = Range::new(
+        location.range.start,
+        line_range(line, line_idx, end..end).end,
+    );
+    symbol(
+        name,
+        to_hex(color),
+        SymbolKind::VARIABLE,
+        range,
+        location.range,
+    )
+}
+
+fn symbol(
+    name: String,
+    detail: String,
+    kind: SymbolKind,
+    range: Range,
+    selection_range: Range,
+) -> DocumentSymbol {
+    #[allow(deprecated)]
+    DocumentSymbol {
+        name,
+        detail: (!detail.is_empty()).then_some(detail),
+        kind,
+        tags: None,
+        deprecated: None,
+        range,
+        selection_range,
+        children: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tower_lsp_server::ls_types::{DocumentSymbol, SymbolKind, Uri};
+
+    use crate::color::parse_line_colors;
+    use crate::document::Document;
+    use crate::heuristics::Syntax;
+    use crate::symbols::{SymbolQuery, document_symbols};
+
+    fn outline(symbols: &[DocumentSymbol]) -> Vec<(String, Option<&str>, SymbolKind, u32)> {
+        symbols
+            .iter()
+            .flat_map(|symbol| std::iter::once(symbol).chain(symbol.children.iter().flatten()))
+            .map(|symbol| {
+                (
+                    symbol.name.clone(),
+                    symbol.detail.as_deref(),
+                    symbol.kind,
+                    symbol.range.start.line,
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn lists_variables_and_distinct_colors() {
+        let uri: Uri = "file:///theme.css".parse().unwrap();
+        let document = Document::from(
+            ":root {\n  --primary: #336699;\n}\na { color: #336699; fill: #FFF; }\n",
+        );
+        let symbols = document_symbols(&document, &uri, false);
+        assert_eq!(
+            outline(&symbols),
+            [(
+                "--primary".to_string(),
+                Some("#336699"),
+                SymbolKind::VARIABLE,
+                1
+            )]
+        );
+        assert_eq!(symbols[0].range.end.character, 21);
+
+        let symbols = document_symbols(&document, &uri, true);
+        let colors: Vec<_> = outline(&symbols)[1..]
+            .iter()
+            .map(|(name, detail, _, line)| (name.clone(), detail.unwrap(), *line))
+            .collect();
+        assert_eq!(
+            colors,
+            [
+                ("#336699".to_string(), "2 uses", 1),
+                ("#FFFFFF".to_string(), "1 use", 3),
+            ]
+        );
+    }
+
+    #[test]
+    fn parses_queries() {
+        let white = parse_line_colors("#FFFFFF", 0)[0].color;
+        let query = SymbolQuery::parse("#ff");
+        assert_eq!(query, SymbolQuery::Hex("#FF".to_string()));
+        assert!(query.matches_color(&white));
+        assert!(query.matches_definition("--bg", &white));
+        assert!(!SymbolQuery::parse("#00").matches_color(&white));
+
+        let query = SymbolQuery::parse("Brand-Blue");
+        assert!(query.matches_definition("--brand-blue", &white));
+        assert!(!query.matches_definition("$brand-red", &white));
+        assert!(!query.matches_color(&white));
+        assert!(SymbolQuery::parse("").matches_definition("@accent", &white));
+    }
+
+    #[test]
+    fn groups_keys_by_section() {
+        let uri: Uri = "file:///alacritty.toml".parse().unwrap();
+        let document = Document::with_options(
+            "accent = \"#FF8800\"\n[colors.primary]\nbackground = \"#101010\"\n\
+             foreground = \"#E0E0E0\"\n[window]\nopacity = 0.9\n",
+            Default::default(),
+            Syntax::KeyValue,
+        );
+        let symbols = document_symbols(&document, &uri, false);
+        assert_eq!(
+            outline(&symbols),
+            [
+                (
+                    "accent".to_string(),
+                    Some("#FF8800"),
+                    SymbolKind::PROPERTY,
+                    0
+                ),
+                ("colors.primary".to_string(), None, SymbolKind::NAMESPACE, 1),
+                (
+                    "background".to_string(),
+                    Some("#101010"),
+                    SymbolKind::PROPERTY,
+                    2
+                ),
+                (
+                    "foreground".to_string(),
+                    Some("#E0E0E0"),
+                    SymbolKind::PROPERTY,
+                    3
+                ),
+            ]
+        );
+        // Sections span their colors.
+        assert_eq!(symbols[1].range.end.line, 3);
+    }
+}
@@ -0,0 +1,840 @@
+//! Colors of Tailwind CSS utility classes, such as `bg-red-500` or
+//! `border-emerald-50/80`, from the default palette and the colors of the
+//! project's `tailwind.config.js`.
+
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use tower_lsp_server::ls_types::{Color, ColorInformation};
+
+use crate::color::{parse_color, to_hex};
+use crate::heuristics::line_range;
+
+/// Shades of each palette color, in the order of [`PALETTE`] values.
+const SHADES: [&str; 11] = [
+    "50", "100", "200", "300", "400", "500", "600", "700", "800", "900", "950",
+];
+
+/// The default palette of Tailwind CSS v3.
+const PALETTE: [(&str, [u32; 11]); 22] = [
+    (
+        "slate",
+        [
+            0xf8fafc, 0xf1f5f9, 0xe2e8f0, 0xcbd5e1, 0x94a3b8, 0x64748b, 0x475569, 0x334155,
+            0x1e293b, 0x0f172a, 0x020617,
+        ],
+    ),
+    (
+        "gray",
+        [
+            0xf9fafb, 0xf3f4f6, 0xe5e7eb, 0xd1d5db, 0x9ca3af, 0x6b7280, 0x4b5563, 0x374151,
+            0x1f2937, 0x111827, 0x030712,
+        ],
+    ),
+    (
+        "zinc",
+        [
+            0xfafafa, 0xf4f4f5, 0xe4e4e7, 0xd4d4d8, 0xa1a1aa, 0x71717a, 0x52525b, 0x3f3f46,
+            0x27272a, 0x18181b, 0x09090b,
+        ],
+    ),
+    (
+        "neutral",
+        [
+            0xfafafa, 0xf5f5f5, 0xe5e5e5, 0xd4d4d4, 0xa3a3a3, 0x737373, 0x525252, 0x404040,
+            0x262626, 0x171717, 0x0a0a0a,
+        ],
+    ),
+    (
+        "stone",
+        [
+            0xfafaf9, 0xf5f5f4, 0xe7e5e4, 0xd6d3d1, 0xa8a29e, 0x78716c, 0x57534e, 0x44403c,
+            0x292524, 0x1c1917, 0x0c0a09,
+        ],
+    ),
+    (
+        "red",
+        [
+            0xfef2f2, 0xfee2e2, 0xfecaca, 0xfca5a5, 0xf87171, 0xef4444, 0xdc2626, 0xb91c1c,
+            0x991b1b, 0x7f1d1d, 0x450a0a,
+        ],
+    ),
+    (
+        "orange",
+        [
+            0xfff7ed, 0xffedd5, 0xfed7aa, 0xfdba74, 0xfb923c, 0xf97316, 0xea580c, 0xc2410c,
+            0x9a3412, 0x7c2d12, 0x431407,
+        ],
+    ),
+    (
+        "amber",
+        [
+            0xfffbeb, 0xfef3c7, 0xfde68a, 0xfcd34d, 0xfbbf24, 0xf59e0b, 0xd97706, 0xb45309,
+            0x92400e, 0x78350f, 0x451a03,
+        ],
+    ),
+    (
+        "yellow",
+        [
+            0xfefce8, 0xfef9c3, 0xfef08a, 0xfde047, 0xfacc15, 0xeab308, 0xca8a04, 0xa16207,
+            0x854d0e, 0x713f12, 0x422006,
+        ],
+    ),
+    (
+        "lime",
+        [
+            0xf7fee7, 0xecfccb, 0xd9f99d, 0xbef264, 0xa3e635, 0x84cc16, 0x65a30d, 0x4d7c0f,
+            0x3f6212, 0x365314, 0x1a2e05,
+        ],
+    ),
+    (
+        "green",
+        [
+            0xf0fdf4, 0xdcfce7, 0xbbf7d0, 0x86efac, 0x4ade80, 0x22c55e, 0x16a34a, 0x15803d,
+            0x166534, 0x14532d, 0x052e16,
+        ],
+    ),
+    (
+        "emerald",
+        [
+            0xecfdf5, 0xd1fae5, 0xa7f3d0, 0x6ee7b7, 0x34d399, 0x10b981, 0x059669, 0x047857,
+            0x065f46, 0x064e3b, 0x022c22,
+        ],
+    ),
+    (
+        "teal",
+        [
+            0xf0fdfa, 0xccfbf1, 0x99f6e4, 0x5eead4, 0x2dd4bf, 0x14b8a6, 0x0d9488, 0x0f766e,
+            0x115e59, 0x134e4a, 0x042f2e,
+        ],
+    ),
+    (
+        "cyan",
+        [
+            0xecfeff, 0xcffafe, 0xa5f3fc, 0x67e8f9, 0x22d3ee, 0x06b6d4, 0x0891b2, 0x0e7490,
+            0x155e75, 0x164e63, 0x083344,
+        ],
+    ),
+    (
+        "sky",
+        [
+            0xf0f9ff, 0xe0f2fe, 0xbae6fd, 0x7dd3fc, 0x38bdf8, 0x0ea5e9, 0x0284c7, 0x0369a1,
+            0x075985, 0x0c4a6e, 0x082f49,
+        ],
+    ),
+    (
+        "blue",
+        [
+            0xeff6ff, 0xdbeafe, 0xbfdbfe, 0x93c5fd, 0x60a5fa, 0x3b82f6, 0x2563eb, 0x1d4ed8,
+            0x1e40af, 0x1e3a8a, 0x172554,
+        ],
+    ),
+    (
+        "indigo",
+        [
+            0xeef2ff, 0xe0e7ff, 0xc7d2fe, 0xa5b4fc, 0x818cf8, 0x6366f1, 0x4f46e5, 0x4338ca,
+            0x3730a3, 0x312e81, 0x1e1b4b,
+        ],
+    ),
+    (
+        "violet",
+        [
+            0xf5f3ff, 0xede9fe, 0xddd6fe, 0xc4b5fd, 0xa78bfa, 0x8b5cf6, 0x7c3aed, 0x6d28d9,
+            0x5b21b6, 0x4c1d95, 0x2e1065,
+        ],
+    ),
+    (
+        "purple",
+        [
+            0xfaf5ff, 0xf3e8ff, 0xe9d5ff, 0xd8b4fe, 0xc084fc, 0xa855f7, 0x9333ea, 0x7e22ce,
+            0x6b21a8, 0x581c87, 0x3b0764,
+        ],
+    ),
+    (
+        "fuchsia",
+        [
+            0xfdf4ff, 0xfae8ff, 0xf5d0fe, 0xf0abfc, 0xe879f9, 0xd946ef, 0xc026d3, 0xa21caf,
+            0x86198f, 0x701a75, 0x4a044e,
+        ],
+    ),
+    (
+        "pink",
+        [
+            0xfdf2f8, 0xfce7f3, 0xfbcfe8, 0xf9a8d4, 0xf472b6, 0xec4899, 0xdb2777, 0xbe185d,
+            0x9d174d, 0x831843, 0x500724,
+        ],
+    ),
+    (
+        "rose",
+        [
+            0xfff1f2, 0xffe4e6, 0xfecdd3, 0xfda4af, 0xfb7185, 0xf43f5e, 0xe11d48, 0xbe123c,
+            0x9f1239, 0x881337, 0x4c0519,
+        ],
+    ),
+];
+
+/// Utilities that take a color, longest first so `ring-offset-red-500` isn't
+/// read as `ring` with color `offset-red-500`.
+const UTILITIES: [&str; 25] = [
+    "ring-offset",
+    "placeholder",
+    "decoration",
+    "border-x",
+    "border-y",
+    "border-t",
+    "border-r",
+    "border-b",
+    "border-l",
+    "border-s",
+    "border-e",
+    "outline",
+    "divide",
+    "border",
+    "accent",
+    "shadow",
+    "stroke",
+    "caret",
+    "text",
+    "fill",
+    "ring",
+    "from",
+    "via",
+    "bg",
+    "to",
+];
+
+/// Attributes whose values are class lists, as in HTML, JSX, Vue (`:class`),
+/// Astro (`class:list`) and twin.macro (`tw`).
+const CLASS_ATTRIBUTES: [&str; 4] = ["className", "class:list", "class", "tw"];
+
+/// Calls of class name helpers whose string arguments are class lists.
+const CLASS_HELPERS: [&str; 5] = ["clsx(", "cn(", "cva(", "twMerge(", "classNames("];
+
+/// Extensions of Tailwind config files, including JSON exports of them.
+const CONFIG_EXTENSIONS: [&str; 7] = ["js", "cjs", "mjs", "ts", "cts", "mts", "json"];
+
+/// Glob of Tailwind config files, watched for changes.
+pub const CONFIG_GLOB: &str = "**/tailwind.config.{js,cjs,mjs,ts,cts,mts,json}";
+
+/// Returns the color of a default palette name such as `red-500`, `black` or
+/// `white`.
+pub fn lookup(name: &str) -> Option<Color> {
+    let rgb = match name {
+        "black" => 0x000000,
+        "white" => 0xffffff,
+        _ => {
+            let (color, shade) = name.rsplit_once('-')?;
+            let (_, shades) = PALETTE.iter().find(|(palette, _)| *palette == color)?;
+            shades[SHADES.iter().position(|&known| known == shade)?]
+        }
+    };
+    let channel = |shift: u32| ((rgb >> shift) & 0xff) as f32 / 255.0;
+    Some(Color {
+        red: channel(16),
+        green: channel(8),
+        blue: channel(0),
+        alpha: 1.0,
+    })
+}
+
+/// Finds color utility classes in the class lists of a line: attribute
+/// values, `@apply` rules and string arguments of class name helpers, so
+/// words like `text-red-500` in prose aren't decorated.
+pub fn find(line: &str, line_idx: usize, theme: &Theme) -> Vec<ColorInformation> {
+    let mut colors: Vec<ColorInformation> = class_lists(line)
+        .into_iter()
+        .flat_map(|list| classes(line, list))
+        .filter_map(|(start, class)| {
+            let (_, color) = utility_color(class, theme)?;
+            Some(ColorInformation {
+                range: line_range(line, line_idx, start..start + class.len()),
+                color,
+            })
+        })
+        .collect();
+    colors.sort_by_key(|color| color.range.start);
+    colors.dedup_by_key(|color| color.range.start);
+    colors
+}
+
+/// Returns the byte ranges of the class lists of a line.
+fn class_lists(line: &str) -> Vec<std::ops::Range<usize>> {
+    let is_word_char = |c: char| c.is_alphanumeric() || c == '-' || c == '_';
+    let mut lists = Vec::new();
+    for attribute in CLASS_ATTRIBUTES {
+        for (start, _) in line.match_indices(attribute) {
+            if line[..start].chars().next_back().is_some_and(is_word_char) {
+                continue;
+            }
+            let rest = line[start + attribute.len()..].trim_start();
+            let Some(value) = rest.strip_prefix('=') else {
+                continue;
+            };
+            let value = value.trim_start();
+            let value = value.strip_prefix('{').unwrap_or(value).trim_start();
+            let offset = line.len() - value.len();
+            lists.extend(quoted(value, offset));
+        }
+    }
+    for (start, _) in line.match_indices("@apply") {
+        let list_start = start + "@apply".len();
+        let len = line[list_start..]
+            .find([';', '}'])
+            .unwrap_or(line.len() - list_start);
+        lists.push(list_start..list_start + len);
+    }
+    for helper in CLASS_HELPERS {
+        for (start, _) in line.match_indices(helper) {
+            if line[..start].chars().next_back().is_some_and(is_word_char) {
+                continue;
+            }
+            // Every string up to the closing parenthesis, or the end of the line.
+            let mut rest_start = start + helper.len();
+            let mut depth = 1;
+            while let Some(c) = line[rest_start..].chars().next() {
+                match c {
+                    '(' => depth += 1,
+                    ')' if depth == 1 => break,
+                    ')' => depth -= 1,
+                    '"' | '\'' | '`' => {
+                        let Some(list) = quoted(&line[rest_start..], rest_start) else {
+                            break;
+                        };
+                        rest_start = list.end + 1;
+                        lists.push(list);
+                        continue;
+                    }
+                    _ => {}
+                }
+                rest_start += c.len_utf8();
+            }
+        }
+    }
+    lists
+}
+
+/// Returns the byte range, offset by `offset`, of the contents of the
+/// string `s` starts with, if it is closed on the line.
+fn quoted(s: &str, offset: usize) -> Option<std::ops::Range<usize>> {
+    let quote = s.chars().next().filter(|c| matches!(c, '"' | '\'' | '`'))?;
+    let len = s[1..].find(quote)?;
+    Some(offset + 1..offset + 1 + len)
+}
+
+/// Splits a class list into classes, returning the utility of each, after
+/// its variants (`hover:`, `md:`) and important modifier (`!`), with its
+/// byte index in `line`.
+fn classes(line: &str, list: std::ops::Range<usize>) -> Vec<(usize, &str)> {
+    let mut classes = Vec::new();
+    let mut idx = list.start;
+    for class in line[list].split(char::is_whitespace) {
+        let start = idx;
+        idx += class.len() + 1;
+        if class.is_empty() {
+            continue;
+        }
+        let utility_start = class.rfind(':').map_or(0, |colon| colon + 1);
+        let utility = &class[utility_start..];
+        let important = usize::from(utility.starts_with('!'));
+        classes.push((start + utility_start + important, &utility[important..]));
+    }
+    classes
+}
+
+/// Returns the utility of a class such as `bg-red-500` or `text-black/50`,
+/// e.g. `bg`, with its color and opacity.
+fn utility_color(class: &str, theme: &Theme) -> Option<(&'static str, Color)> {
+    let (name, opacity) = match class.split_once('/') {
+        Some((name, opacity)) => (name, Some(opacity)),
+        None => (class, None),
+    };
+    let (utility, mut color) = UTILITIES.iter().find_map(|&utility| {
+        let color = name
+            .strip_prefix(utility)?
+            .strip_prefix('-')
+            .and_then(|name| theme.lookup(name))?;
+        Some((utility, color))
+    })?;
+    if let Some(opacity) = opacity {
+        color.alpha = match opacity
+            .strip_prefix('[')
+            .and_then(|value| value.strip_suffix(']'))
+        {
+            Some(percentage) if percentage.ends_with('%') => {
+                percentage.strip_suffix('%')?.parse::<f32>().ok()? / 100.0
+            }
+            Some(value) => value.parse().ok()?,
+            None => opacity.parse::<u8>().ok().filter(|&value| value <= 100)? as f32 / 100.0,
+        };
+        if !(0.0..=1.0).contains(&color.alpha) {
+            return None;
+        }
+    }
+    Some((utility, color))
+}
+
+/// Formats `color` as a class of the utility of `existing`: a class of the
+/// theme when the color is in it, or an arbitrary value otherwise, with an
+/// opacity modifier when translucent. Returns `None` when `existing` isn't a
+/// color utility class.
+pub fn format(color: &Color, existing: &str, theme: &Theme) -> Option<String> {
+    let (utility, _) = utility_color(existing, theme)?;
+    let opaque = Color {
+        alpha: 1.0,
+        ..*color
+    };
+    let hex = to_hex(&opaque);
+    let name = theme
+        .names()
+        .into_iter()
+        .find(|name| {
+            theme
+                .lookup(name)
+                .is_some_and(|known| to_hex(&known) == hex)
+        })
+        .unwrap_or_else(|| format!("[{}]", hex.to_ascii_lowercase()));
+    let percentage = (color.alpha.clamp(0.0, 1.0) * 100.0).round();
+    Some(if percentage < 100.0 {
+        format!("{utility}-{name}/{percentage}")
+    } else {
+        format!("{utility}-{name}")
+    })
+}
+
+/// Colors of a project's Tailwind config by name, e.g. `brand-600`, from
+/// `theme.colors`, which replaces the default palette, and
+/// `theme.extend.colors`.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Theme {
+    colors: BTreeMap<String, Color>,
+    replaces_defaults: bool,
+}
+
+impl Theme {
+    /// Reads the colors of a config file, either JavaScript or TypeScript
+    /// source or a JSON export. The source isn't run: only literal colors,
+    /// nested objects and references to default palette colors, such as
+    /// `colors.sky`, are understood.
+    pub fn from_config(text: &str) -> Self {
+        let mut theme = Theme::default();
+        let Some(entries) = theme_object(text) else {
+            return theme;
+        };
+        if let Some(Value::Object(colors)) = get(&entries, "colors") {
+            // Spreading the default palette, as in `{ ...colors, brand }`, keeps it.
+            theme.replaces_defaults = colors.iter().all(|(key, _)| key != SPREAD);
+            flatten("", &Value::Object(colors.clone()), &mut theme.colors);
+        }
+        if let Some(Value::Object(extend)) = get(&entries, "extend")
+            && let Some(colors @ Value::Object(_)) = get(extend, "colors")
+        {
+            flatten("", colors, &mut theme.colors);
+        }
+        theme
+    }
+
+    /// Adds the colors of `other`, which win over those with the same names.
+    pub fn merge(&mut self, other: Theme) {
+        self.colors.extend(other.colors);
+        self.replaces_defaults |= other.replaces_defaults;
+    }
+
+    /// Returns the color of a name such as `brand-600` or `red-500`.
+    pub fn lookup(&self, name: &str) -> Option<Color> {
+        match self.colors.get(name) {
+            Some(color) => Some(*color),
+            None if self.replaces_defaults => None,
+            None => lookup(name),
+        }
+    }
+
+    /// Returns the color names of the theme, those of the config first.
+    fn names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.colors.keys().cloned().collect();
+        if !self.replaces_defaults {
+            names.extend(["black".to_string(), "white".to_string()]);
+            names.extend(PALETTE.iter().flat_map(|(palette, _)| {
+                SHADES.iter().map(move |shade| format!("{palette}-{shade}"))
+            }));
+        }
+        names
+    }
+}
+
+/// Whether `path` is a Tailwind config file.
+pub fn is_config(path: &Path) -> bool {
+    let name = path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or("");
+    name.strip_prefix("tailwind.config.")
+        .is_some_and(|extension| CONFIG_EXTENSIONS.contains(&extension))
+}
+
+/// Key under which spread entries of objects, such as `...colors`, are kept.
+const SPREAD: &str = "...";
+
+/// A value of a JavaScript object literal.
+#[derive(Debug, Clone, PartialEq)]
+enum Value {
+    String(String),
+    Object(Vec<(String, Value)>),
+    /// Any other expression, as written.
+    Expression(String),
+}
+
+/// Returns the value of `key` in the entries of an object.
+fn get<'a>(entries: &'a [(String, Value)], key: &str) -> Option<&'a Value> {
+    entries
+        .iter()
+        .find(|(entry_key, _)| entry_key == key)
+        .map(|(_, value)| value)
+}
+
+/// Adds the colors of `value` to `colors`, named by joining the keys leading
+/// to them with `-`. `DEFAULT` keys name the color of their object itself.
+fn flatten(prefix: &str, value: &Value, colors: &mut BTreeMap<String, Color>) {
+    match value {
+        Value::String(text) => {
+            if let Some(color) = parse_color(text.trim()) {
+                colors.insert(prefix.to_string(), color);
+            }
+        }
+        Value::Object(entries) => {
+            for (key, value) in entries {
+                let name = match (prefix, key.as_str()) {
+                    (_, SPREAD) | ("", "DEFAULT") => continue,
+                    (prefix, "DEFAULT") => prefix.to_string(),
+                    ("", key) => key.to_string(),
+                    (prefix, key) => format!("{prefix}-{key}"),
+                };
+                flatten(&name, value, colors);
+            }
+        }
+        // A default palette color, e.g. `colors.sky` or `colors.white`.
+        Value::Expression(expression) => {
+            let Some((_, family)) = expression.rsplit_once('.') else {
+                return;
+            };
+            if let Some(color) = lookup(family) {
+                colors.insert(prefix.to_string(), color);
+            } else if PALETTE.iter().any(|(palette, _)| *palette == family) {
+                for shade in SHADES {
+                    if let Some(color) = lookup(&format!("{family}-{shade}")) {
+                        colors.insert(format!("{prefix}-{shade}"), color);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Finds the object of the `theme` key of a config and returns its entries.
+fn theme_object(text: &str) -> Option<Vec<(String, Value)>> {
+    text.match_indices("theme").find_map(|(start, _)| {
+        let before = text[..start].chars().next_back();
+        if before.is_some_and(|c| c.is_alphanumeric() || matches!(c, '_' | '$' | '.')) {
+            return None;
+        }
+        let mut reader = Reader {
+            text,
+            pos: start + "theme".len(),
+        };
+        if reader.peek().is_some_and(|c| c == '"' || c == '\'') {
+            reader.pos += 1;
+        }
+        reader.skip_trivia();
+        if reader.peek() != Some(':') {
+            return None;
+        }
+        reader.pos += 1;
+        reader.skip_trivia();
+        reader.object()
+    })
+}
+
+/// Reads JavaScript object literals, which JSON is a subset of.
+struct Reader<'a> {
+    text: &'a str,
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn peek(&self) -> Option<char> {
+        self.text[self.pos..].chars().next()
+    }
+
+    fn rest(&self) -> &'a str {
+        &self.text[self.pos..]
+    }
+
+    /// Skips whitespace and comments.
+    fn skip_trivia(&mut self) {
+        loop {
+            let rest = self.rest();
+            let trimmed = rest.trim_start();
+            self.pos += rest.len() - trimmed.len();
+            if trimmed.starts_with("//") {
+                self.pos += trimmed.find('\n').unwrap_or(trimmed.len());
+            } else if let Some(comment) = trimmed.strip_prefix("/*") {
+                self.pos += comment.find("*/").map_or(trimmed.len(), |end| end + 4);
+            } else {
+                return;
+            }
+        }
+    }
+
+    /// Reads the object starting at the cursor, or `None` if it's malformed.
+    fn object(&mut self) -> Option<Vec<(String, Value)>> {
+        if self.peek() != Some('{') {
+            return None;
+        }
+        self.pos += 1;
+        let mut entries = Vec::new();
+        loop {
+            self.skip_trivia();
+            match self.peek()? {
+                '}' => {
+                    self.pos += 1;
+                    return Some(entries);
+                }
+                ',' => {
+                    self.pos += 1;
+                    continue;
+                }
+                _ if self.rest().starts_with(SPREAD) => {
+                    self.pos += SPREAD.len();
+                    entries.push((SPREAD.to_string(), Value::Expression(self.expression())));
+                }
+                _ => {
+                    let key = self.key()?;
+                    self.skip_trivia();
+                    if self.peek() == Some(':') {
+                        self.pos += 1;
+                        entries.push((key, self.value()?));
+                    } else if self.peek() == Some('(') {
+                        // A method; its body isn't a color.
+                        self.expression();
+                    }
+                }
+            }
+            self.skip_trivia();
+            match self.peek()? {
+                ',' | '}' => {}
+                _ => return None,
+            }
+        }
+    }
+
+    /// Reads a key: an identifier, a number, a string or a computed key.
+    fn key(&mut self) -> Option<String> {
+        match self.peek()? {
+            '"' | '\'' | '`' => self.string(),
+            '[' => {
+                self.pos += 1;
+                let key = self.expression();
+                (self.peek() == Some(']')).then(|| self.pos += 1)?;
+                Some(key)
+            }
+            _ => {
+                let len = self
+                    .rest()
+                    .find(|c: char| !(c.is_alphanumeric() || matches!(c, '_' | '$' | '.')))
+                    .unwrap_or(self.rest().len());
+                let key = self.rest()[..len].to_string();
+                self.pos += len;
+                (!key.is_empty()).then_some(key)
+            }
+        }
+    }
+
+    /// Reads a value up to the next `,` or `}` of its object.
+    fn value(&mut self) -> Option<Value> {
+        self.skip_trivia();
+        let start = self.pos;
+        match self.peek()? {
+            '{' => self.object().map(Value::Object),
+            '"' | '\'' | '`' => {
+                let string = self.string()?;
+                self.skip_trivia();
+                if matches!(self.peek(), Some(',' | '}')) && !string.contains("${") {
+                    Some(Value::String(string))
+                } else {
+                    self.pos = start;
+                    Some(Value::Expression(self.expression()))
+                }
+            }
+            _ => Some(Value::Expression(self.expression())),
+        }
+    }
+
+    /// Reads the string starting at the cursor, without unescaping.
+    fn string(&mut self) -> Option<String> {
+        let quote = self.peek()?;
+        let mut escaped = false;
+        for (idx, c) in self.rest().char_indices().skip(1) {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                c if c == quote => {
+                    let string = self.rest()[1..idx].to_string();
+                    self.pos += idx + 1;
+                    return Some(string);
+                }
+                _ => {}
+            }
+        }
+        None
+    }
+
+    /// Reads an expression up to the next `,`, `}`, `]` or `)` outside
+    /// brackets and strings, returning it trimmed.
+    fn expression(&mut self) -> String {
+        let start = self.pos;
+        let mut depth = 0;
+        while let Some(c) = self.peek() {
+            match c {
+                '"' | '\'' | '`' => {
+                    if self.string().is_none() {
+                        self.pos = self.text.len();
+                    }
+                    continue;
+                }
+                '(' | '[' | '{' => depth += 1,
+                ')' | ']' | '}' | ',' if depth == 0 => break,
+                ')' | ']' | '}' => depth -= 1,
+                _ => {}
+            }
+            self.pos += c.len_utf8();
+        }
+        self.text[start..self.pos].trim().to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::color::{parse_color, to_hex};
+    use crate::tailwind::{Theme, find, format, lookup};
+
+    fn found(line: &str) -> Vec<(u32, u32, String)> {
+        find(line, 0, &Theme::default())
+            .iter()
+            .map(|info| {
+                (
+                    info.range.start.character,
+                    info.range.end.character,
+                    to_hex(&info.color),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn palette() {
+        assert_eq!(to_hex(&lookup("red-500").unwrap()), "#EF4444");
+        assert_eq!(to_hex(&lookup("slate-950").unwrap()), "#020617");
+        assert_eq!(to_hex(&lookup("white").unwrap()), "#FFFFFF");
+        assert_eq!(lookup("red-550"), None);
+        assert_eq!(lookup("brand-500"), None);
+    }
+
+    #[test]
+    fn classes_in_class_contexts() {
+        assert_eq!(
+            found(r#"<div class="p-4 bg-red-500 hover:text-slate-300 border-emerald-50/80">"#),
+            [
+                (16, 26, "#EF4444".to_string()),
+                (33, 47, "#CBD5E1".to_string()),
+                (48, 68, "#ECFDF5CC".to_string()),
+            ]
+        );
+        assert_eq!(
+            found(r#"<p className={cn("md:!ring-offset-sky-50", isOn && 'to-white/[.5]')} />"#),
+            [
+                (22, 40, "#F0F9FF".to_string()),
+                (52, 65, "#FFFFFF80".to_string()),
+            ]
+        );
+        assert_eq!(
+            found(".btn { @apply bg-blue-600 text-white; }"),
+            [
+                (14, 25, "#2563EB".to_string()),
+                (26, 36, "#FFFFFF".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn ignores_other_contexts() {
+        assert_eq!(found("Use bg-red-500 for errors."), []);
+        assert_eq!(
+            found(r#"<div id="bg-red-500" data-class="text-black">"#),
+            []
+        );
+        assert_eq!(
+            found(r#"<div class="bg-red-500/120 text-brand-500 bg-[#fff]">"#),
+            []
+        );
+    }
+
+    #[test]
+    fn formats_as_classes() {
+        let red = parse_color("#EF4444").unwrap();
+        assert_eq!(
+            format(&red, "bg-slate-50", &Theme::default()).as_deref(),
+            Some("bg-red-500")
+        );
+        let translucent = parse_color("#12345680").unwrap();
+        assert_eq!(
+            format(&translucent, "ring-offset-white/20", &Theme::default()).as_deref(),
+            Some("ring-offset-[#123456]/50")
+        );
+        assert_eq!(format(&red, "#FFFFFF", &Theme::default()), None);
+    }
+
+    #[test]
+    fn reads_config_colors() {
+        let theme = Theme::from_config(
+            r##"/** @type {import('tailwindcss').Config} */
+const colors = require('tailwindcss/colors')
+
+module.exports = {
+  content: ['./src/**/*.{html,js}'],
+  theme: {
+    extend: {
+      colors: {
+        // Brand colors
+        brand: { DEFAULT: '#1a73e8', 600: "#1557b0", 'on-dark': 'rgb(255 255 255 / 50%)' },
+        accent: colors.sky,
+        muted: `var(--muted)`,
+      },
+    },
+  },
+  plugins: [],
+}
+"##,
+        );
+        let hex = |name| theme.lookup(name).as_ref().map(to_hex);
+        assert_eq!(hex("brand").as_deref(), Some("#1A73E8"));
+        assert_eq!(hex("brand-600").as_deref(), Some("#1557B0"));
+        assert_eq!(hex("brand-on-dark").as_deref(), Some("#FFFFFF80"));
+        assert_eq!(hex("accent-500").as_deref(), Some("#0EA5E9"));
+        assert_eq!(hex("muted"), None);
+        assert_eq!(hex("red-500").as_deref(), Some("#EF4444"));
+        let brand = parse_color("#1557B0").unwrap();
+        assert_eq!(
+            format(&brand, "text-red-500", &theme).as_deref(),
+            Some("text-brand-600")
+        );
+
+        let theme = Theme::from_config(r##"{ "theme": { "colors": { "ink": "#111111" } } }"##);
+        assert_eq!(
+            theme.lookup("ink").as_ref().map(to_hex).as_deref(),
+            Some("#111111")
+        );
+        assert_eq!(theme.lookup("red-500"), None);
+        let theme =
+            Theme::from_config("export default { theme: { colors: { ...colors, ink: '#111' } } }");
+        assert!(theme.lookup("red-500").is_some());
+    }
+}
@@ -0,0 +1,322 @@
+//! Export of the colors of a document as a terminal theme, for
+//! `chroma.exportTerminalTheme`.
+
+use serde::Deserialize;
+use tower_lsp_server::ls_types::Color;
+
+use crate::ansi::{ansi256, index_of};
+use crate::contrast::{key_before, relative_luminance};
+use crate::document::Document;
+
+/// Base16 color feeding each of the 16 terminal colors, as in the usual
+/// base16 shell templates.
+const BASE16_SLOTS: [usize; 16] = [
+    0x0, 0x8, 0xB, 0xA, 0xD, 0xE, 0xC, 0x5, 0x3, 0x8, 0xB, 0xA, 0xD, 0xE, 0xC, 0x7,
+];
+
+/// Windows Terminal names of the 8 normal colors, by index.
+const WINDOWS_TERMINAL_NAMES: [&str; 8] = [
+    "black", "red", "green", "yellow", "blue", "purple", "cyan", "white",
+];
+
+/// File format of an exported theme.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ThemeFormat {
+    /// `kitty.conf` color settings.
+    Kitty,
+    /// iTerm2 `.itermcolors` property list.
+    Iterm2,
+    /// Windows Terminal color scheme JSON.
+    WindowsTerminal,
+}
+
+/// The colors of a terminal theme.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TerminalTheme {
+    pub foreground: Color,
+    pub background: Color,
+    pub cursor: Color,
+    /// The 8 normal colors followed by the 8 bright ones.
+    pub ansi: [Color; 16],
+}
+
+/// Colors assigned to theme keys of a document.
+#[derive(Default)]
+struct Slots {
+    foreground: Option<Color>,
+    background: Option<Color>,
+    cursor: Option<Color>,
+    ansi: [Option<Color>; 16],
+    base16: [Option<Color>; 16],
+}
+
+/// Builds a terminal theme from the colors of a document, or `None` if it has
+/// no colors.
+///
+/// Colors assigned to terminal keys are kept: `color0` to `color15`, names
+/// such as `red`, `brightRed` or `terminal.ansiRed`, `foreground`,
+/// `background` and `cursor`. A name repeated after its normal color, as in
+/// the bright section of an Alacritty config, is the bright color. Base16
+/// schemes are mapped from `base00` to `base0F`. Colors still unassigned take
+/// the color of the document closest to the xterm default, the background
+/// the darkest and the foreground the lightest.
+pub fn from_document(document: &Document) -> Option<TerminalTheme> {
+    let mut distinct: Vec<Color> = Vec::new();
+    let mut slots = Slots::default();
+    for color_info in document.get_colors() {
+        let color = opaque(color_info.color);
+        if !distinct.contains(&color) {
+            distinct.push(color);
+        }
+        let Some(line) = document.line(color_info.range.start.line as usize) else {
+            continue;
+        };
+        if let Some(key) = key_before(line, color_info.range.start.character as usize) {
+            slots.assign(&key, color);
+        }
+    }
+
+    let darkest = *distinct
+        .iter()
+        .min_by(|a, b| relative_luminance(a).total_cmp(&relative_luminance(b)))?;
+    let lightest = *distinct
+        .iter()
+        .max_by(|a, b| relative_luminance(a).total_cmp(&relative_luminance(b)))?;
+    let base16 = |idx: usize| slots.base16[idx];
+    let foreground = slots.foreground.or(base16(0x5)).unwrap_or(lightest);
+    let ansi = std::array::from_fn(|idx| {
+        slots.ansi[idx]
+            .or(base16(BASE16_SLOTS[idx]))
+            .unwrap_or_else(|| nearest(&distinct, &ansi256(idx as u8)))
+    });
+    Some(TerminalTheme {
+        foreground,
+        background: slots.background.or(base16(0x0)).unwrap_or(darkest),
+        cursor: slots.cursor.unwrap_or(foreground),
+        ansi,
+    })
+}
+
+impl Slots {
+    fn assign(&mut self, key: &str, color: Color) {
+        let name: String = key
+            .rsplit('.')
+            .next()
+            .unwrap_or(key)
+            .chars()
+            .filter(|c| c.is_alphanumeric())
+            .collect::<String>()
+            .to_ascii_lowercase();
+        let name = name.strip_prefix("ansi").unwrap_or(&name);
+        match name {
+            "foreground" | "fg" => self.foreground = Some(color),
+            "background" | "bg" => self.background = Some(color),
+            "cursor" | "cursorcolor" => self.cursor = Some(color),
+            _ => {
+                if let Some(idx) = name
+                    .strip_prefix("base0")
+                    .and_then(|digit| usize::from_str_radix(digit, 16).ok())
+                    .filter(|_| name.len() == 6)
+                {
+                    self.base16[idx] = Some(color);
+                } else if let Some(idx) = name
+                    .strip_prefix("color")
+                    .and_then(|n| n.parse::<usize>().ok())
+                    .filter(|&idx| idx < 16)
+                {
+                    self.ansi[idx] = Some(color);
+                } else if let Some(idx) = ansi_index(name) {
+                    let idx = if idx < 8 && self.ansi[idx].is_some() {
+                        idx + 8
+                    } else {
+                        idx
+                    };
+                    self.ansi[idx].get_or_insert(color);
+                }
+            }
+        }
+    }
+}
+
+/// Returns the index of a color name such as `red` or `brightred`.
+fn ansi_index(name: &str) -> Option<usize> {
+    let index = match name.strip_prefix("bright") {
+        Some(base) => index_of(&format!("bright-{base}")),
+        None => index_of(name),
+    };
+    index.map(usize::from)
+}
+
+fn opaque(color: Color) -> Color {
+    Color {
+        alpha: 1.0,
+        ..color
+    }
+}
+
+/// Returns the color of `colors` closest to `color` in RGB.
+fn nearest(colors: &[Color], color: &Color) -> Color {
+    let distance = |other: &Color| {
+        (other.red - color.red).powi(2)
+            + (other.green - color.green).powi(2)
+            + (other.blue - color.blue).powi(2)
+    };
+    *colors
+        .iter()
+        .min_by(|a, b| distance(a).total_cmp(&distance(b)))
+        .unwrap_or(color)
+}
+
+impl TerminalTheme {
+    /// Renders the theme in `format`. `name` names the Windows Terminal
+    /// scheme.
+    pub fn render(&self, format: ThemeFormat, name: &str) -> String {
+        match format {
+            ThemeFormat::Kitty => self.kitty(),
+            ThemeFormat::Iterm2 => self.iterm2(),
+            ThemeFormat::WindowsTerminal => self.windows_terminal(name),
+        }
+    }
+
+    fn kitty(&self) -> String {
+        let mut conf = format!(
+            "foreground {}\nbackground {}\ncursor {}\n",
+            hex(&self.foreground),
+            hex(&self.background),
+            hex(&self.cursor)
+        );
+        for (idx, color) in self.ansi.iter().enumerate() {
+            conf.push_str(&format!("color{idx} {}\n", hex(color)));
+        }
+        conf
+    }
+
+    fn iterm2(&self) -> String {
+        let mut entries: Vec<(String, &Color)> = self
+            .ansi
+            .iter()
+            .enumerate()
+            .map(|(idx, color)| (format!("Ansi {idx} Color"), color))
+            .collect();
+        entries.push(("Background Color".to_string(), &self.background));
+        entries.push(("Cursor Color".to_string(), &self.cursor));
+        entries.push(("Foreground Color".to_string(), &self.foreground));
+
+        let mut plist = String::from(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+             <!DOCTYPE plist PUBLIC \"-//Apple//DTD PLIST 1.0//EN\" \
+             \"http://www.apple.com/DTDs/PropertyList-1.0.dtd\">\n\
+             <plist version=\"1.0\">\n<dict>\n",
+        );
+        for (key, color) in entries {
+            plist.push_str(&format!(
+                "\t<key>{key}</key>\n\
+                 \t<dict>\n\
+                 \t\t<key>Alpha Component</key>\n\t\t<real>1</real>\n\
+                 \t\t<key>Blue Component</key>\n\t\t<real>{}</real>\n\
+                 \t\t<key>Color Space</key>\n\t\t<string>sRGB</string>\n\
+                 \t\t<key>Green Component</key>\n\t\t<real>{}</real>\n\
+                 \t\t<key>Red Component</key>\n\t\t<real>{}</real>\n\
+                 \t</dict>\n",
+                color.blue, color.green, color.red
+            ));
+        }
+        plist.push_str("</dict>\n</plist>\n");
+        plist
+    }
+
+    fn windows_terminal(&self, name: &str) -> String {
+        let mut entries = vec![
+            ("name".to_string(), name.to_string()),
+            ("foreground".to_string(), hex(&self.foreground)),
+            ("background".to_string(), hex(&self.background)),
+            ("cursorColor".to_string(), hex(&self.cursor)),
+        ];
+        for (idx, color) in self.ansi.iter().enumerate() {
+            let base = WINDOWS_TERMINAL_NAMES[idx % 8];
+            let key = if idx < 8 {
+                base.to_string()
+            } else {
+                format!("bright{}{}", base[..1].to_ascii_uppercase(), &base[1..])
+            };
+            entries.push((key, hex(color)));
+        }
+        let fields: Vec<String> = entries
+            .into_iter()
+            .map(|(key, value)| format!("    \"{key}\": {}", serde_json::Value::from(value)))
+            .collect();
+        format!("{{\n{}\n}}\n", fields.join(",\n"))
+    }
+}
+
+/// Returns the hex value of a color without alpha, which terminals ignore.
+fn hex(color: &Color) -> String {
+    crate::color::to_hex(&opaque(*color))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::color::to_hex;
+    use crate::document::Document;
+    use crate::terminal_theme::{ThemeFormat, from_document};
+
+    #[test]
+    fn maps_terminal_keys() {
+        let document = Document::from(
+            "[colors.primary]\nbackground = \"#101010\"\nforeground = \"#E0E0E0\"\n\
+             [colors.normal]\nred = \"#CC0000\"\n[colors.bright]\nred = \"#FF5555\"\n\
+             color4 #0000AA\n",
+        );
+        let theme = from_document(&document).unwrap();
+        assert_eq!(to_hex(&theme.background), "#101010");
+        assert_eq!(to_hex(&theme.foreground), "#E0E0E0");
+        assert_eq!(to_hex(&theme.cursor), "#E0E0E0");
+        assert_eq!(to_hex(&theme.ansi[1]), "#CC0000");
+        assert_eq!(to_hex(&theme.ansi[9]), "#FF5555");
+        assert_eq!(to_hex(&theme.ansi[4]), "#0000AA");
+        // Unassigned colors take the closest color of the document.
+        assert_eq!(to_hex(&theme.ansi[0]), "#101010");
+        assert_eq!(to_hex(&theme.ansi[15]), "#E0E0E0");
+    }
+
+    #[test]
+    fn maps_base16_schemes() {
+        let base16: String = (0..16)
+            .map(|idx| format!("base0{idx:X}: \"{:02X}{:02X}{:02X}\"\n", idx, idx, idx))
+            .collect();
+        let document = Document::from(base16.replace(": \"", ": \"#").as_str());
+        let theme = from_document(&document).unwrap();
+        assert_eq!(to_hex(&theme.background), "#000000");
+        assert_eq!(to_hex(&theme.foreground), "#050505");
+        assert_eq!(to_hex(&theme.ansi[1]), "#080808");
+        assert_eq!(to_hex(&theme.ansi[8]), "#030303");
+        assert_eq!(to_hex(&theme.ansi[15]), "#070707");
+    }
+
+    #[test]
+    fn renders_formats() {
+        let document = Document::from("background: #000000;\nforeground: #FFFFFF;\n");
+        let theme = from_document(&document).unwrap();
+
+        let kitty = theme.render(ThemeFormat::Kitty, "Test");
+        assert!(kitty.starts_with("foreground #FFFFFF\nbackground #000000\ncursor #FFFFFF\n"));
+        assert!(kitty.contains("\ncolor15 #FFFFFF\n"));
+
+        let plist = theme.render(ThemeFormat::Iterm2, "Test");
+        assert!(plist.contains("<key>Ansi 15 Color</key>"));
+        assert!(plist.contains("<key>Red Component</key>\n\t\t<real>1</real>"));
+
+        let scheme: serde_json::Value =
+            serde_json::from_str(&theme.render(ThemeFormat::WindowsTerminal, "Test")).unwrap();
+        assert_eq!(scheme["name"], "Test");
+        assert_eq!(scheme["background"], "#000000");
+        assert_eq!(scheme["brightPurple"], "#FFFFFF");
+        assert_eq!(scheme["black"], "#000000");
+    }
+
+    #[test]
+    fn no_colors() {
+        assert_eq!(from_document(&Document::from("a {}")), None);
+    }
+}
@@ -0,0 +1,339 @@
+//! Colors of Kotlin (Jetpack Compose) and Dart (Flutter) theme objects:
+//! `Color(0xAARRGGBB)` literals, `val`s or `static const`s holding them in
+//! `object AppColors` or `class AppColors`, and `ThemeData(...)` color fields,
+//! referenced as `AppColors.primary` or `Theme.of(context).primaryColor`.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use tower_lsp_server::ls_types::{Color, ColorInformation, Location, Position, Range, Uri};
+
+use crate::color::{ColorFormat, ColorMatch, parse_line_colors};
+use crate::document::Document;
+use crate::heuristics::{Syntax, line_range};
+use crate::workspace;
+
+/// Extensions of indexed source files.
+const SOURCE_EXTENSIONS: [&str; 3] = ["kt", "kts", "dart"];
+
+/// Flutter's theme lookup, whose fields are indexed as `ThemeData.field`.
+const THEME_LOOKUP: &str = "Theme.of(context).";
+
+/// Finds `Color(0xAARRGGBB)` literals, decorating the `0x...` argument.
+pub fn argb_literals(line: &str, line_idx: usize) -> Vec<ColorMatch> {
+    line.match_indices("Color(0x")
+        .filter_map(|(start, call)| {
+            let literal_start = start + "Color(".len();
+            let digits = &line[start + call.len()..];
+            let length = digits.chars().take_while(char::is_ascii_hexdigit).count();
+            if length != 8 {
+                return None;
+            }
+            let rgba = format!("#{}{}", &digits[2..8], &digits[..2]);
+            Some(ColorMatch {
+                info: ColorInformation {
+                    range: line_range(line, line_idx, literal_start..literal_start + 10),
+                    color: parse_line_colors(&rgba, 0).first()?.color,
+                },
+                format: ColorFormat::Hex,
+            })
+        })
+        .collect()
+}
+
+/// A color held by a member of a theme object.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ThemeColor {
+    /// Qualified name, e.g. `AppColors.primary` or `ThemeData.primaryColor`.
+    pub name: String,
+    /// Location of the member name.
+    pub location: Location,
+    pub color: Color,
+}
+
+/// Returns the theme colors defined in a Kotlin or Dart document.
+pub fn definitions(uri: &Uri, document: &Document) -> Vec<ThemeColor> {
+    let mut definitions = Vec::new();
+    // Enclosing `object` or `class` names, with the brace depth of their body.
+    let mut owners: Vec<(&str, i32)> = Vec::new();
+    let mut depth = 0;
+    // Parenthesis depth within a `ThemeData(` call, if one is open.
+    let mut theme_data: Option<i32> = None;
+    for (line_idx, line) in document.lines().enumerate() {
+        if let Some(owner) = declared_owner(line)
+            && line.contains('{')
+        {
+            owners.push((owner, depth + 1));
+        }
+        let call_start = line.find("ThemeData(");
+        if call_start.is_some() {
+            theme_data = Some(0);
+        }
+
+        if let Some(color) = argb_literals(line, line_idx).first() {
+            let literal = line.find("Color(0x").unwrap_or_default();
+            let (name, owner) = match theme_data {
+                Some(_) => (member_name(&line[..literal], ':'), "ThemeData"),
+                None => (
+                    member_name(&line[..literal], '='),
+                    owners.last().map_or("", |(owner, _)| owner),
+                ),
+            };
+            if let Some((name_idx, name)) = name
+                && !owner.is_empty()
+            {
+                definitions.push(ThemeColor {
+                    name: format!("{owner}.{name}"),
+                    location: Location {
+                        uri: uri.clone(),
+                        range: line_range(line, line_idx, name_idx..name_idx + name.len()),
+                    },
+                    color: color.info.color,
+                });
+            }
+        }
+
+        if let Some(parens) = &mut theme_data {
+            let rest = &line[call_start.unwrap_or(0)..];
+            *parens += rest.matches('(').count() as i32 - rest.matches(')').count() as i32;
+            if *parens <= 0 {
+                theme_data = None;
+            }
+        }
+        depth += line.matches('{').count() as i32 - line.matches('}').count() as i32;
+        owners.retain(|&(_, body_depth)| body_depth <= depth);
+    }
+    definitions
+}
+
+/// Returns the name declared by `object Name` or `class Name` in a line.
+fn declared_owner(line: &str) -> Option<&str> {
+    let mut words = line.split(|c: char| !is_identifier_char(c));
+    words.find(|word| *word == "object" || *word == "class")?;
+    words.find(|word| !word.is_empty())
+}
+
+/// Returns the identifier that `before` assigns with `separator`, e.g.
+/// `primary` in `val primary: Color =` or in `primaryColor:`, with its byte
+/// index.
+fn member_name(before: &str, separator: char) -> Option<(usize, &str)> {
+    let mut left = before.trim_end().strip_suffix(separator)?;
+    // Skip a Kotlin type annotation, `val primary: Color`.
+    if separator == '='
+        && let Some((name, _)) = left.rsplit_once(':')
+    {
+        left = name;
+    }
+    let left = left.trim_end();
+    let start = left
+        .rfind(|c: char| !is_identifier_char(c))
+        .map_or(0, |idx| idx + 1);
+    let name = &left[start..];
+    name.chars()
+        .next()
+        .is_some_and(|c| c.is_alphabetic() || c == '_')
+        .then_some((start, name))
+}
+
+fn is_identifier_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+/// A reference to a theme color.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Reference {
+    pub range: Range,
+    /// Qualified name of the referenced member.
+    pub name: String,
+}
+
+/// Returns `Owner.member` and `Theme.of(context).member` references in a
+/// line, where `Owner` starts with an uppercase letter.
+pub fn references(line: &str, line_idx: usize) -> Vec<Reference> {
+    let mut references = Vec::new();
+    let mut idx = 0;
+    while idx < line.len() {
+        let rest = &line[idx..];
+        let Some(c) = rest.chars().next() else {
+            break;
+        };
+        let at_word_start = !line[..idx]
+            .chars()
+            .next_back()
+            .is_some_and(|c| is_identifier_char(c) || c == '.');
+        if !at_word_start || !c.is_uppercase() {
+            idx += c.len_utf8();
+            continue;
+        }
+
+        let (owner_len, owner) = match rest.strip_prefix(THEME_LOOKUP) {
+            Some(_) => (THEME_LOOKUP.len() - 1, "ThemeData"),
+            None => {
+                let len = rest
+                    .find(|c: char| !is_identifier_char(c))
+                    .unwrap_or(rest.len());
+                (len, &rest[..len])
+            }
+        };
+        let member = rest[owner_len..].strip_prefix('.').map(|after| {
+            let len = after
+                .find(|c: char| !is_identifier_char(c))
+                .unwrap_or(after.len());
+            &after[..len]
+        });
+        match member {
+            Some(member) if !member.is_empty() => {
+                let end = idx + owner_len + 1 + member.len();
+                references.push(Reference {
+                    range: line_range(line, line_idx, idx..end),
+                    name: format!("{owner}.{member}"),
+                });
+                idx = end;
+            }
+            _ => idx += owner_len.max(1),
+        }
+    }
+    references
+}
+
+/// Returns the reference at `position` in a document.
+pub fn reference_at(document: &Document, position: Position) -> Option<Reference> {
+    let line = document.line(position.line as usize)?;
+    references(line, position.line as usize)
+        .into_iter()
+        .find(|reference| reference.range.start <= position && position <= reference.range.end)
+}
+
+/// Returns the Kotlin and Dart source files under `root`.
+pub fn workspace_source_files(root: &Path) -> Vec<PathBuf> {
+    workspace::files(root, |path| {
+        path.extension()
+            .and_then(|extension| extension.to_str())
+            .is_some_and(|extension| SOURCE_EXTENSIONS.contains(&extension))
+    })
+}
+
+/// Theme colors, by file.
+#[derive(Debug, Default)]
+pub struct ThemeIndex {
+    definitions: HashMap<Uri, Vec<ThemeColor>>,
+}
+
+impl ThemeIndex {
+    pub fn contains(&self, uri: &Uri) -> bool {
+        self.definitions.contains_key(uri)
+    }
+
+    /// Indexes the theme colors of a Kotlin or Dart document.
+    pub fn update(&mut self, uri: Uri, document: &Document) {
+        if document.syntax() != Syntax::ColorConstructor {
+            return;
+        }
+        let definitions = definitions(&uri, document);
+        if definitions.is_empty() {
+            self.definitions.remove(&uri);
+        } else {
+            self.definitions.insert(uri, definitions);
+        }
+    }
+
+    /// Indexes a source file read from disk.
+    pub fn update_from_text(&mut self, uri: Uri, text: &str) {
+        let document = Document::with_options(text, Default::default(), Syntax::ColorConstructor);
+        self.update(uri, &document);
+    }
+
+    /// Returns the theme color named `name`, looked up by file URI so the
+    /// result doesn't depend on the order files were indexed in.
+    pub fn get(&self, name: &str) -> Option<&ThemeColor> {
+        self.definitions
+            .iter()
+            .filter_map(|(uri, colors)| {
+                Some((uri, colors.iter().find(|color| color.name == name)?))
+            })
+            .min_by(|(a, _), (b, _)| a.as_str().cmp(b.as_str()))
+            .map(|(_, color)| color)
+    }
+
+    /// Returns all indexed theme colors.
+    pub fn all(&self) -> impl Iterator<Item = &ThemeColor> {
+        self.definitions.values().flatten()
+    }
+
+    /// Resolves the references of a Kotlin or Dart document.
+    pub fn resolve(&self, document: &Document) -> Vec<ColorInformation> {
+        if self.definitions.is_empty() || document.syntax() != Syntax::ColorConstructor {
+            return Vec::new();
+        }
+        document
+            .lines()
+            .enumerate()
+            .flat_map(|(line_idx, line)| {
+                references(line, line_idx)
+                    .into_iter()
+                    .filter_map(|reference| {
+                        Some(ColorInformation {
+                            range: reference.range,
+                            color: self.get(&reference.name)?.color,
+                        })
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tower_lsp_server::ls_types::Uri;
+
+    use crate::color::to_hex;
+    use crate::document::Document;
+    use crate::heuristics::Syntax;
+    use crate::theme_object::{ThemeIndex, argb_literals, references};
+
+    #[test]
+    fn parses_argb_literals() {
+        let colors = argb_literals("val scrim = Color(0x801A73E8)", 0);
+        assert_eq!(colors.len(), 1);
+        assert_eq!(colors[0].info.range.start.character, 18);
+        assert_eq!(colors[0].info.range.end.character, 28);
+        assert_eq!(to_hex(&colors[0].info.color), "#1A73E880");
+        assert_eq!(argb_literals("Color(0x1A73E8)", 0), []);
+    }
+
+    #[test]
+    fn resolves_kotlin_and_dart_members() {
+        let mut index = ThemeIndex::default();
+        index.update_from_text(
+            "file:///app/ui/Color.kt".parse().unwrap(),
+            "object AppColors {\n    val primary: Color = Color(0xFF1A73E8)\n}\nval Unscoped = Color(0xFF000000)\n",
+        );
+        index.update_from_text(
+            "file:///lib/theme.dart".parse::<Uri>().unwrap(),
+            "class Brand {\n  static const Color accent = Color(0xFFFF5722);\n}\n\nfinal theme = ThemeData(\n  primaryColor: Color(0xFF3F51B5),\n);\n",
+        );
+
+        let primary = index.get("AppColors.primary").unwrap();
+        assert_eq!(primary.location.range.start.line, 1);
+        assert_eq!(primary.location.range.start.character, 8);
+        assert_eq!(to_hex(&index.get("Brand.accent").unwrap().color), "#FF5722");
+        assert_eq!(
+            to_hex(&index.get("ThemeData.primaryColor").unwrap().color),
+            "#3F51B5"
+        );
+        assert_eq!(index.get("AppColors.Unscoped"), None);
+
+        let screen = Document::with_options(
+            "Text(color = AppColors.primary, bg = Theme.of(context).primaryColor)",
+            Default::default(),
+            Syntax::ColorConstructor,
+        );
+        let colors = index.resolve(&screen);
+        assert_eq!(colors.len(), 2);
+        assert_eq!(colors[0].range.start.character, 13);
+        assert_eq!(colors[0].range.end.character, 30);
+        assert_eq!(to_hex(&colors[1].color), "#3F51B5");
+        assert_eq!(references("x.Foo.bar", 0), []);
+    }
+}
@@ -0,0 +1,156 @@
+//! Timing of the color parsers on a document, for `chroma/profile`.
+
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+use tower_lsp_server::ls_types::Uri;
+
+use crate::color::{Stopwatch, parse_line_timed};
+use crate::document::Document;
+
+/// Number of lines per bucket of the profile.
+pub const BUCKET_LINES: usize = 100;
+
+/// Result of the `chroma/profile` request. Times are in nanoseconds, from a
+/// fresh parse of the document.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DocumentProfile {
+    pub uri: Uri,
+    pub version: i32,
+    pub lines: usize,
+    /// Syntax the document is parsed with, e.g. `Scss`.
+    pub syntax: String,
+    /// Time to build the document with its colors.
+    pub total: u64,
+    /// Time spent in each parser, slowest first. `document` is the time
+    /// spent outside the line parsers, including multi-line constructs.
+    pub parsers: Vec<ParserTime>,
+    /// Time spent in the line parsers on each bucket of lines.
+    pub buckets: Vec<LineBucket>,
+}
+
+#[derive(Debug, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ParserTime {
+    pub parser: &'static str,
+    pub time: u64,
+}
+
+/// Lines `start_line` to `end_line`, excluded.
+#[derive(Debug, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct LineBucket {
+    pub start_line: usize,
+    pub end_line: usize,
+    pub time: u64,
+}
+
+/// Stopwatch adding up the time of each parser.
+#[derive(Default)]
+struct Timings {
+    parsers: BTreeMap<&'static str, Duration>,
+    /// Time of the line being parsed.
+    line: Duration,
+}
+
+impl Stopwatch for Timings {
+    fn time<T>(&mut self, parser: &'static str, parse: impl FnOnce() -> T) -> T {
+        let start = Instant::now();
+        let result = parse();
+        let elapsed = start.elapsed();
+        *self.parsers.entry(parser).or_default() += elapsed;
+        self.line += elapsed;
+        result
+    }
+}
+
+/// Parses a document again, timing each parser and each bucket of
+/// `BUCKET_LINES` lines.
+pub fn profile(uri: Uri, document: &Document) -> DocumentProfile {
+    let text = document.to_string();
+    let start = Instant::now();
+    let rebuilt = Document::with_options(
+        &text,
+        Arc::new(document.options().clone()),
+        document.syntax(),
+    );
+    let total = start.elapsed();
+    drop(rebuilt);
+
+    let mut timings = Timings::default();
+    let mut buckets = Vec::new();
+    let mut lines = document.lines();
+    for start_line in (0..document.line_count()).step_by(BUCKET_LINES) {
+        let end_line = (start_line + BUCKET_LINES).min(document.line_count());
+        timings.line = Duration::ZERO;
+        for line in lines.by_ref().take(end_line - start_line) {
+            parse_line_timed(line, 0, document.options(), document.syntax(), &mut timings);
+        }
+        buckets.push(LineBucket {
+            start_line,
+            end_line,
+            time: nanos(timings.line),
+        });
+    }
+
+    let lines: Duration = timings.parsers.values().sum();
+    let mut parsers: Vec<ParserTime> = timings
+        .parsers
+        .into_iter()
+        .chain([("document", total.saturating_sub(lines))])
+        .map(|(parser, time)| ParserTime {
+            parser,
+            time: nanos(time),
+        })
+        .collect();
+    parsers.sort_by(|a, b| b.time.cmp(&a.time).then(a.parser.cmp(b.parser)));
+    DocumentProfile {
+        uri,
+        version: document.version(),
+        lines: document.line_count(),
+        syntax: format!("{:?}", document.syntax()),
+        total: nanos(total),
+        parsers,
+        buckets,
+    }
+}
+
+fn nanos(duration: Duration) -> u64 {
+    u64::try_from(duration.as_nanos()).unwrap_or(u64::MAX)
+}
+
+#[cfg(test)]
+mod tests {
+    use tower_lsp_server::ls_types::Uri;
+
+    use crate::document::Document;
+    use crate::timing::profile;
+
+    #[test]
+    fn times_parsers_and_buckets() {
+        let text = "a { color: #fff; background: rgb(0 0 0); }\n".repeat(250);
+        let document = Document::from(text.as_str());
+        let uri: Uri = "file:///a.css".parse().unwrap();
+        let profile = profile(uri, &document);
+
+        assert_eq!(profile.lines, 251);
+        let buckets: Vec<_> = profile
+            .buckets
+            .iter()
+            .map(|bucket| (bucket.start_line, bucket.end_line))
+            .collect();
+        assert_eq!(buckets, [(0, 100), (100, 200), (200, 251)]);
+        let mut parsers: Vec<_> = profile.parsers.iter().map(|parser| parser.parser).collect();
+        parsers.sort();
+        assert_eq!(
+            parsers,
+            [
+                "alias", "ansi", "document", "function", "hex", "merge", "syntax", "tailwind"
+            ]
+        );
+        assert!(profile.parsers.windows(2).all(|w| w[0].time >= w[1].time));
+    }
+}
@@ -0,0 +1,288 @@
+//! Design token files, in the W3C format (`"$value"`) or the Style Dictionary
+//! format (`"value"`), and `{color.brand.primary}` aliases between tokens.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use serde_json::{Map, Value};
+use tower_lsp_server::ls_types::{Location, Position, Range, Uri};
+
+use crate::document::Document;
+use crate::heuristics::line_range;
+use crate::workspace::{self, Scope};
+
+/// Whether `path` is a design token file: `*.tokens.json`, `*.tokens`, or JSON
+/// in a `tokens` directory.
+pub fn is_token_file(path: &str) -> bool {
+    let path = path.to_ascii_lowercase();
+    path.ends_with(".tokens.json")
+        || path.ends_with(".tokens")
+        || (path.ends_with(".json") && path.contains("/tokens/"))
+}
+
+/// A design token.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Token {
+    /// Dotted path, e.g. `color.brand.primary`.
+    pub path: String,
+    /// Location of the token's key.
+    pub location: Location,
+}
+
+/// Returns the tokens of a token file, or `None` if it isn't valid JSON.
+pub fn definitions(uri: &Uri, text: &str) -> Option<Vec<Token>> {
+    let positions = key_positions(text);
+    Some(
+        paths(text)?
+            .into_iter()
+            .map(|path| {
+                let start = positions.get(&path).copied().unwrap_or_default();
+                let key_len = path.rsplit('.').next().unwrap_or_default();
+                let end = Position {
+                    line: start.line,
+                    character: start.character + key_len.encode_utf16().count() as u32 + 2,
+                };
+                Token {
+                    path,
+                    location: Location {
+                        uri: uri.clone(),
+                        range: Range { start, end },
+                    },
+                }
+            })
+            .collect(),
+    )
+}
+
+/// Returns the dotted paths of the tokens of a token file, e.g.
+/// `color.brand.primary`, or `None` if it isn't valid JSON.
+pub fn paths(text: &str) -> Option<Vec<String>> {
+    let Value::Object(root) = serde_json::from_str(text).ok()? else {
+        return Some(Vec::new());
+    };
+    let mut paths = Vec::new();
+    collect_paths(&root, "", &mut paths);
+    Some(paths)
+}
+
+fn collect_paths(group: &Map<String, Value>, prefix: &str, paths: &mut Vec<String>) {
+    for (key, value) in group {
+        // `$type`, `$description` and other properties aren't tokens.
+        let Value::Object(child) = value else {
+            continue;
+        };
+        if key.starts_with('$') {
+            continue;
+        }
+        let path = format!("{prefix}{key}");
+        if child.contains_key("$value") || child.contains_key("value") {
+            paths.push(path);
+        } else {
+            collect_paths(child, &format!("{path}."), paths);
+        }
+    }
+}
+
+/// Returns the positions of the quoted keys of JSON objects, by dotted path.
+/// Keys of objects in arrays are skipped, since tokens are never in arrays.
+fn key_positions(text: &str) -> HashMap<String, Position> {
+    let mut positions = HashMap::new();
+    // Keys of the enclosing objects, `None` for arrays.
+    let mut stack: Vec<Option<String>> = Vec::new();
+    let mut key: Option<String> = None;
+    let mut position = Position::default();
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        let start = position;
+        advance(&mut position, c);
+        match c {
+            '"' => {
+                let mut string = String::new();
+                while let Some(c) = chars.next() {
+                    advance(&mut position, c);
+                    match c {
+                        '"' => break,
+                        '\\' => {
+                            if let Some(escaped) = chars.next() {
+                                advance(&mut position, escaped);
+                                string.push(escaped);
+                            }
+                        }
+                        _ => string.push(c),
+                    }
+                }
+                while let Some(&c) = chars.peek().filter(|c| c.is_whitespace()) {
+                    advance(&mut position, c);
+                    chars.next();
+                }
+                if chars.peek() == Some(&':') {
+                    let parents: Option<Vec<&str>> =
+                        stack.iter().skip(1).map(|key| key.as_deref()).collect();
+                    if let Some(mut parents) = parents {
+                        parents.push(&string);
+                        positions.insert(parents.join("."), start);
+                    }
+                    key = Some(string);
+                }
+            }
+            '{' => stack.push(Some(key.take().unwrap_or_default())),
+            '[' => {
+                key = None;
+                stack.push(None);
+            }
+            '}' | ']' => {
+                stack.pop();
+            }
+            ',' => key = None,
+            _ => {}
+        }
+    }
+    positions
+}
+
+fn advance(position: &mut Position, c: char) {
+    if c == '\n' {
+        position.line += 1;
+        position.character = 0;
+    } else {
+        position.character += c.len_utf16() as u32;
+    }
+}
+
+/// Returns the `{group.token}` aliases of a line, with the ranges of the
+/// aliases including their braces.
+pub fn aliases(line: &str, line_idx: usize) -> Vec<(Range, &str)> {
+    let mut aliases = Vec::new();
+    let mut idx = 0;
+    while let Some(offset) = line[idx..].find('{') {
+        let start = idx + offset;
+        idx = start + 1;
+        let Some(len) = line[start + 1..].find('}') else {
+            break;
+        };
+        let path = &line[start + 1..start + 1 + len];
+        let is_path = path.contains('.')
+            && path.split('.').all(|segment| {
+                !segment.is_empty()
+                    && segment
+                        .chars()
+                        .all(|c| c.is_alphanumeric() || c == '-' || c == '_')
+            });
+        if is_path {
+            let end = start + len + 2;
+            aliases.push((line_range(line, line_idx, start..end), path));
+            idx = end;
+        }
+    }
+    aliases
+}
+
+/// Returns the token files under `root`.
+pub fn workspace_token_files(root: &Path) -> Vec<PathBuf> {
+    workspace::files(root, |path| {
+        path.to_str()
+            .is_some_and(|path| is_token_file(&path.replace('\\', "/")))
+    })
+}
+
+/// Design tokens, by token file.
+#[derive(Debug, Default)]
+pub struct TokenIndex {
+    tokens: HashMap<Uri, Vec<Token>>,
+}
+
+impl TokenIndex {
+    pub fn contains(&self, uri: &Uri) -> bool {
+        self.tokens.contains_key(uri)
+    }
+
+    /// Indexes the tokens of a document, if it's a token file.
+    pub fn update(&mut self, uri: Uri, document: &Document) {
+        if !is_token_file(uri.path().as_str()) {
+            return;
+        }
+        let text: Vec<&str> = document.lines().collect();
+        self.update_from_text(uri, &text.join("\n"));
+    }
+
+    /// Indexes a token file read from disk. The previous tokens are kept while
+    /// the file isn't valid JSON, e.g. in the middle of an edit.
+    pub fn update_from_text(&mut self, uri: Uri, text: &str) {
+        if let Some(tokens) = definitions(&uri, text) {
+            self.tokens.insert(uri, tokens);
+        }
+    }
+
+    /// Returns the paths of the indexed tokens that apply in `scope`.
+    pub fn all<'a>(&'a self, scope: Scope<'a>) -> impl Iterator<Item = &'a str> {
+        self.tokens
+            .iter()
+            .filter(move |(uri, _)| scope.includes(uri))
+            .flat_map(|(_, tokens)| tokens)
+            .map(|token| token.path.as_str())
+    }
+
+    pub fn is_defined(&self, path: &str, scope: Scope) -> bool {
+        self.all(scope).any(|defined| defined == path)
+    }
+
+    /// Returns the token at `path` that applies in `scope`. Definitions are
+    /// looked up by file URI, so the result doesn't depend on the order files
+    /// were indexed in.
+    pub fn get(&self, path: &str, scope: Scope) -> Option<&Token> {
+        self.tokens
+            .iter()
+            .filter(|(uri, _)| scope.includes(uri))
+            .filter_map(|(uri, tokens)| {
+                Some((uri, tokens.iter().find(|token| token.path == path)?))
+            })
+            .min_by(|(a, _), (b, _)| a.as_str().cmp(b.as_str()))
+            .map(|(_, token)| token)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tokens::{aliases, definitions, is_token_file, paths};
+
+    #[test]
+    fn finds_token_paths() {
+        let paths = paths(
+            r##"{
+              "color": {
+                "$type": "color",
+                "brand": { "primary": { "$value": "#1a73e8" } },
+                "link": { "value": "{color.brand.primary}" }
+              }
+            }"##,
+        )
+        .unwrap();
+        assert_eq!(paths, ["color.brand.primary", "color.link"]);
+        assert!(is_token_file("/design/tokens/colors.json"));
+        assert!(is_token_file("/design/colors.tokens.json"));
+        assert!(!is_token_file("/design/package.json"));
+
+        let uri = "file:///design/colors.tokens.json".parse().unwrap();
+        let tokens = definitions(
+            &uri,
+            "{\n  \"color\": {\n    \"list\": [{ \"a\": 1 }],\n    \"brand\": { \"$value\": \"#1a73e8\" }\n  }\n}",
+        )
+        .unwrap();
+        let range = tokens[0].location.range;
+        assert_eq!(tokens[0].path, "color.brand");
+        assert_eq!((range.start.line, range.start.character), (3, 4));
+        assert_eq!(range.end.character, 11);
+    }
+
+    #[test]
+    fn finds_aliases() {
+        let aliases: Vec<_> = aliases(
+            r#""$value": "{color.brand.primary}", "x": "{ not.a path }", "y": "{user}""#,
+            0,
+        )
+        .into_iter()
+        .map(|(range, path)| (range.start.character, range.end.character, path))
+        .collect();
+        assert_eq!(aliases, [(11, 32, "color.brand.primary")]);
+    }
+}
@@ -0,0 +1,116 @@
+//! Transports clients reach the server over. Listeners serve each connection
+//! with its own server, so clients don't share documents or settings.
+
+use std::io;
+use std::path::Path;
+
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::net::TcpListener;
+use tower_lsp_server::Server;
+
+use crate::server::build_service;
+
+/// Serves a single client over stdin and stdout.
+pub async fn stdio() {
+    serve(tokio::io::stdin(), tokio::io::stdout()).await;
+}
+
+/// Serves a client over a connection until it exits or disconnects.
+pub async fn serve(read: impl AsyncRead + Unpin, write: impl AsyncWrite) {
+    let (service, socket) = build_service();
+    Server::new(read, write, socket).serve(service).await;
+}
+
+/// Serves each client connecting to a TCP listener, until accepting fails.
+pub async fn tcp(listener: TcpListener) -> io::Result<()> {
+    loop {
+        let (stream, peer) = listener.accept().await?;
+        tracing::info!(%peer, "client connected");
+        let (read, write) = stream.into_split();
+        tokio::spawn(serve(read, write));
+    }
+}
+
+/// Serves each client connecting to a Unix socket at `path`, until accepting
+/// fails.
+#[cfg(unix)]
+pub async fn socket(path: &Path) -> io::Result<()> {
+    use std::os::unix::fs::FileTypeExt;
+
+    // A socket left by a previous run would fail the bind. One that refuses
+    // connections is stale, while another server may still be listening on
+    // the others.
+    if std::fs::symlink_metadata(path).is_ok_and(|metadata| metadata.file_type().is_socket()) {
+        match tokio::net::UnixStream::connect(path).await {
+            Ok(_) => {
+                return Err(io::Error::new(
+                    io::ErrorKind::AddrInUse,
+                    format!("a server is already listening on {}", path.display()),
+                ));
+            }
+            Err(error) if error.kind() == io::ErrorKind::ConnectionRefused => {
+                std::fs::remove_file(path)?;
+            }
+            Err(_) => {}
+        }
+    }
+    let listener = tokio::net::UnixListener::bind(path)?;
+    loop {
+        let (stream, _) = listener.accept().await?;
+        let (read, write) = stream.into_split();
+        tokio::spawn(serve(read, write));
+    }
+}
+
+/// Serves each client connecting to a named pipe at `path`, such as
+/// `\\.\pipe\chroma-ls`, until accepting fails.
+#[cfg(windows)]
+pub async fn socket(path: &Path) -> io::Result<()> {
+    use tokio::net::windows::named_pipe::ServerOptions;
+
+    let mut server = ServerOptions::new()
+        .first_pipe_instance(true)
+        .create(path)?;
+    loop {
+        server.connect().await?;
+        // Create the next instance before serving, so clients never find the
+        // pipe missing.
+        let connected = std::mem::replace(&mut server, ServerOptions::new().create(path)?);
+        let (read, write) = tokio::io::split(connected);
+        tokio::spawn(serve(read, write));
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use std::io;
+
+    use crate::transport::socket;
+
+    #[tokio::test]
+    async fn replaces_stale_sockets_only() {
+        let path = std::env::temp_dir().join(format!("chroma-ls-{}.sock", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+
+        // Still listened on by another server.
+        let listener = std::os::unix::net::UnixListener::bind(&path).unwrap();
+        let error = socket(&path).await.unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::AddrInUse);
+
+        // Left behind once that server is gone.
+        drop(listener);
+        let server = tokio::spawn({
+            let path = path.clone();
+            async move { socket(&path).await }
+        });
+        let connected = loop {
+            if let Ok(stream) = tokio::net::UnixStream::connect(&path).await {
+                break stream;
+            }
+            tokio::task::yield_now().await;
+        };
+        drop(connected);
+        server.abort();
+        std::fs::remove_file(&path).unwrap();
+    }
+}
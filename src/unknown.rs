@@ -0,0 +1,400 @@
+//! Strings that look like colors but aren't decorated, such as `#ff00zz`,
+//! `rgb 255 0 0` or colors of a disabled format, with the setting or fix
+//! that would make them colors.
+
+use std::ops::Range as ByteRange;
+
+use serde_json::json;
+use tower_lsp_server::ls_types::{Range, Uri};
+
+use crate::color::{ColorFormat, ParseOptions, function_calls, parse_line};
+use crate::conformance;
+use crate::desktop;
+use crate::document::Document;
+use crate::heuristics::line_range;
+
+/// Functions of color syntaxes that aren't parsed.
+const UNSUPPORTED_FUNCTIONS: [&str; 7] = [
+    "color",
+    "color-mix",
+    "device-cmyk",
+    "cmyk",
+    "hsv",
+    "hsva",
+    "hsb",
+];
+
+/// Names of the parsed color functions, which may be written without
+/// parentheses by mistake or in prose.
+const FUNCTION_NAMES: [&str; 9] = [
+    "rgb", "rgba", "hsl", "hsla", "hwb", "lab", "lch", "oklab", "oklch",
+];
+
+/// Why a color-like string isn't decorated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Pattern {
+    /// A color of a format disabled by `formats` or `languageFormats`.
+    DisabledFormat(ColorFormat),
+    /// `#` followed by digits that aren't all hex or of a wrong count.
+    MalformedHex,
+    /// A color function name followed by bare components, e.g. `rgb 255 0 0`.
+    BareFunction,
+    /// A color function that isn't parsed, e.g. `color(display-p3 1 0 0)`.
+    UnsupportedFunction,
+    /// A parsed color function whose arguments don't parse.
+    InvalidArguments,
+    /// A `0x` literal of 6 or 8 hex digits, outside `integerColors` languages.
+    IntegerLiteral,
+    /// A quoted string of 6 or 8 hex digits without `#`.
+    BareHex,
+}
+
+impl Pattern {
+    /// Describes the pattern.
+    pub fn description(self) -> String {
+        match self {
+            Pattern::DisabledFormat(format) => format!("{} color", format_name(format)),
+            Pattern::MalformedHex => "Malformed hex color".to_string(),
+            Pattern::BareFunction => "Color function without parentheses".to_string(),
+            Pattern::UnsupportedFunction => "Unsupported color function".to_string(),
+            Pattern::InvalidArguments => "Color function with invalid arguments".to_string(),
+            Pattern::IntegerLiteral => "Integer literal".to_string(),
+            Pattern::BareHex => "Hex digits without `#`".to_string(),
+        }
+    }
+
+    /// Suggests how to get the string decorated, if it's meant as a color.
+    pub fn suggestion(self) -> String {
+        match self {
+            Pattern::DisabledFormat(format) => format!(
+                "Add {} to `formats` or to `languageFormats` for this language",
+                format_name(format)
+            ),
+            Pattern::MalformedHex => "Use 3, 4, 6 or 8 hex digits".to_string(),
+            Pattern::BareFunction => "Wrap the components in parentheses".to_string(),
+            Pattern::UnsupportedFunction => {
+                "Convert it to `rgb()`, `hsl()` or `oklch()`".to_string()
+            }
+            Pattern::InvalidArguments => {
+                "Use numbers, percentages or angles for the components".to_string()
+            }
+            Pattern::IntegerLiteral => "Set `integerColors` for this language".to_string(),
+            Pattern::BareHex => "Add the key to `colorKeys`".to_string(),
+        }
+    }
+}
+
+/// Returns the name of a format in settings, quoted, e.g. `"function"`.
+fn format_name(format: ColorFormat) -> String {
+    json!(format).to_string().replace('"', "`")
+}
+
+/// A color-like string that isn't decorated.
+#[derive(Debug, Clone, PartialEq)]
+pub struct UnknownColor {
+    pub range: Range,
+    pub text: String,
+    pub pattern: Pattern,
+}
+
+/// Finds the color-like strings of a document that no enabled format
+/// decorates, ordered by position.
+pub fn find(document: &Document) -> Vec<UnknownColor> {
+    let options = document.options();
+    let all_formats = ParseOptions {
+        formats: None,
+        ..options.clone()
+    };
+    let mut unknown = Vec::new();
+    for (line_idx, line) in document.lines().enumerate() {
+        let decorated = document.get_line_colors(line_idx..=line_idx);
+        let mut line_unknown: Vec<UnknownColor> = Vec::new();
+        let mut push = |range: Range, text: &str, pattern| {
+            let overlaps = |other: &Range| range.start < other.end && other.start < range.end;
+            let taken = decorated.iter().any(|color| overlaps(&color.range))
+                || line_unknown.iter().any(|other| overlaps(&other.range));
+            if !taken {
+                line_unknown.push(UnknownColor {
+                    range,
+                    text: text.to_string(),
+                    pattern,
+                });
+            }
+        };
+
+        for color_match in parse_line(line, line_idx, &all_formats, document.syntax()) {
+            if !options.enables(color_match.format) {
+                let text = document
+                    .range_text(&color_match.info.range)
+                    .unwrap_or_default();
+                push(
+                    color_match.info.range,
+                    text,
+                    Pattern::DisabledFormat(color_match.format),
+                );
+            }
+        }
+        for (_, call, args) in function_calls(line) {
+            if !conformance::unresolvable(&line[args]) {
+                push(
+                    line_range(line, line_idx, call.clone()),
+                    &line[call],
+                    Pattern::InvalidArguments,
+                );
+            }
+        }
+        let patterns = [
+            (unsupported_functions(line), Pattern::UnsupportedFunction),
+            (bare_functions(line), Pattern::BareFunction),
+            (malformed_hexes(line), Pattern::MalformedHex),
+            (bare_hexes(line), Pattern::BareHex),
+        ];
+        for (ranges, pattern) in patterns {
+            for range in ranges {
+                push(
+                    line_range(line, line_idx, range.clone()),
+                    &line[range],
+                    pattern,
+                );
+            }
+        }
+        if options.integer_colors.is_none() {
+            for range in integer_literals(line) {
+                let text = &line[range.clone()];
+                push(
+                    line_range(line, line_idx, range),
+                    text,
+                    Pattern::IntegerLiteral,
+                );
+            }
+        }
+        line_unknown.sort_by_key(|unknown| unknown.range.start);
+        unknown.extend(line_unknown);
+    }
+    unknown
+}
+
+/// Whether the character before byte `idx` of `line` continues a word.
+fn after_word(line: &str, idx: usize) -> bool {
+    line[..idx]
+        .chars()
+        .next_back()
+        .is_some_and(|c| c.is_alphanumeric() || c == '-' || c == '_')
+}
+
+/// Returns the byte length of the run of alphanumerics at the start of `s`.
+fn alphanumeric_len(s: &str) -> usize {
+    s.find(|c: char| !c.is_ascii_alphanumeric())
+        .unwrap_or(s.len())
+}
+
+/// Finds calls of unsupported color functions.
+fn unsupported_functions(line: &str) -> Vec<ByteRange<usize>> {
+    let mut ranges = Vec::new();
+    for name in UNSUPPORTED_FUNCTIONS {
+        for (start, _) in line.match_indices(name) {
+            let open = start + name.len();
+            if after_word(line, start) || !line[open..].starts_with('(') {
+                continue;
+            }
+            if let Some(close) = desktop::closing_paren(&line[open..]) {
+                ranges.push(start..open + close + 1);
+            }
+        }
+    }
+    ranges
+}
+
+/// Finds color function names followed by three or four numeric
+/// components without parentheses, e.g. `rgb 255 0 0` or `hsl: 0, 100%, 50%`.
+fn bare_functions(line: &str) -> Vec<ByteRange<usize>> {
+    let mut ranges = Vec::new();
+    for name in FUNCTION_NAMES {
+        for (start, _) in line.match_indices(name) {
+            let mut end = start + name.len();
+            if after_word(line, start) || line[end..].starts_with(['a', '(']) {
+                continue;
+            }
+            let mut components = 0;
+            loop {
+                let rest = &line[end..];
+                let component = rest.trim_start_matches([' ', '\t', ',', ':']);
+                let len = component
+                    .find(|c: char| !(c.is_ascii_digit() || matches!(c, '.' | '%')))
+                    .unwrap_or(component.len());
+                if len == 0 || !component.starts_with(|c: char| c.is_ascii_digit() || c == '.') {
+                    break;
+                }
+                // Components must be separated from the name and each other.
+                if component.len() == rest.len() {
+                    break;
+                }
+                end += rest.len() - component.len() + len;
+                components += 1;
+            }
+            if (3..=4).contains(&components) {
+                ranges.push(start..end);
+            }
+        }
+    }
+    ranges
+}
+
+/// Finds `#` followed by a run of alphanumerics that is almost a hex color:
+/// 3, 4, 6 or 8 characters with a digit and a few that aren't hex, like
+/// `#ff00zz`, or 5 or 7 hex digits, like `#ff00f`. Preprocessor directives
+/// and issue numbers don't qualify.
+fn malformed_hexes(line: &str) -> Vec<ByteRange<usize>> {
+    line.match_indices('#')
+        .filter_map(|(start, _)| {
+            let digits = &line[start + 1..start + 1 + alphanumeric_len(&line[start + 1..])];
+            let hex = digits.chars().filter(char::is_ascii_hexdigit).count();
+            let has_digit = digits.chars().any(|c| c.is_ascii_digit());
+            let has_letter = digits.chars().any(|c| c.is_ascii_alphabetic());
+            let malformed = match digits.len() {
+                3 | 4 | 6 | 8 => has_digit && hex < digits.len() && hex * 2 >= digits.len(),
+                5 | 7 => has_digit && has_letter && hex == digits.len(),
+                _ => false,
+            };
+            malformed.then(|| start..start + 1 + digits.len())
+        })
+        .collect()
+}
+
+/// Finds quoted strings of exactly 6 or 8 hex digits with both digits and
+/// letters, such as `"1d2021"`.
+fn bare_hexes(line: &str) -> Vec<ByteRange<usize>> {
+    let mut ranges = Vec::new();
+    for quote in ['"', '\''] {
+        for (start, _) in line.match_indices(quote) {
+            let rest = &line[start + 1..];
+            let len = alphanumeric_len(rest);
+            let digits = &rest[..len];
+            if matches!(len, 6 | 8)
+                && rest[len..].starts_with(quote)
+                && digits.chars().all(|c| c.is_ascii_hexdigit())
+                && digits.chars().any(|c| c.is_ascii_digit())
+                && digits.chars().any(|c| c.is_ascii_alphabetic())
+            {
+                ranges.push(start + 1..start + 1 + len);
+            }
+        }
+    }
+    ranges
+}
+
+/// Finds `0x` literals of exactly 6 or 8 hex digits.
+fn integer_literals(line: &str) -> Vec<ByteRange<usize>> {
+    line.match_indices("0x")
+        .chain(line.match_indices("0X"))
+        .filter_map(|(start, _)| {
+            let len = alphanumeric_len(&line[start + 2..]);
+            let digits = &line[start + 2..start + 2 + len];
+            (!after_word(line, start)
+                && matches!(len, 6 | 8)
+                && digits.chars().all(|c| c.is_ascii_hexdigit()))
+            .then(|| start..start + 2 + len)
+        })
+        .collect()
+}
+
+/// Renders the color-like strings of each document as a Markdown summary.
+pub fn markdown_report(unknown: &[(Uri, Vec<UnknownColor>)]) -> String {
+    let count: usize = unknown.iter().map(|(_, colors)| colors.len()).sum();
+    if count == 0 {
+        return "No color-like strings are left undecorated.\n".to_string();
+    }
+    let count = match count {
+        1 => "1 string looks".to_string(),
+        n => format!("{n} strings look"),
+    };
+    let mut report = format!(
+        "{count} like a color but isn't decorated.\n\n\
+         | Location | Text | Pattern | Suggestion |\n\
+         | --- | --- | --- | --- |\n"
+    );
+    for (uri, colors) in unknown {
+        for color in colors {
+            report.push_str(&format!(
+                "| {}:{}:{} | `{}` | {} | {} |\n",
+                uri.as_str(),
+                color.range.start.line + 1,
+                color.range.start.character + 1,
+                color.text.replace('|', "\\|").replace('`', "'"),
+                color.pattern.description(),
+                color.pattern.suggestion()
+            ));
+        }
+    }
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+    use std::sync::Arc;
+
+    use crate::color::{ColorFormat, ParseOptions};
+    use crate::document::Document;
+    use crate::heuristics::Syntax;
+    use crate::unknown::{Pattern, find, markdown_report};
+
+    #[test]
+    fn finds_color_like_strings() {
+        let options = ParseOptions {
+            formats: Some(BTreeSet::from([ColorFormat::Hex])),
+            ..Default::default()
+        };
+        let document = Document::with_options(
+            "#define RED #ff00zz // see #1234\n\
+             a = rgb 255 0 0; b = color(display-p3 1 0 0); c = hsl(10px 0 0)\n\
+             d = { tint: \"1d2021\", mask: 0xFF00FF, ok: #ff0000, fn: rgb(0 0 0) }\n\
+             e = #ff00f; f = rgb(var(--r) 0 0); #region\n",
+            Arc::new(options),
+            Syntax::Plain,
+        );
+        let unknown = find(&document);
+        let found: Vec<(u32, &str, Pattern)> = unknown
+            .iter()
+            .map(|unknown| {
+                (
+                    unknown.range.start.line,
+                    unknown.text.as_str(),
+                    unknown.pattern,
+                )
+            })
+            .collect();
+        assert_eq!(
+            found,
+            [
+                (0, "#ff00zz", Pattern::MalformedHex),
+                (1, "rgb 255 0 0", Pattern::BareFunction),
+                (1, "color(display-p3 1 0 0)", Pattern::UnsupportedFunction),
+                (1, "hsl(10px 0 0)", Pattern::InvalidArguments),
+                (2, "1d2021", Pattern::BareHex),
+                (2, "0xFF00FF", Pattern::IntegerLiteral),
+                (
+                    2,
+                    "rgb(0 0 0)",
+                    Pattern::DisabledFormat(ColorFormat::Function)
+                ),
+                (3, "#ff00f", Pattern::MalformedHex),
+            ]
+        );
+    }
+
+    #[test]
+    fn reports_with_suggestions() {
+        let document = Document::with_options(
+            "a = rgb 255 0 0\n",
+            Arc::new(ParseOptions::default()),
+            Syntax::Plain,
+        );
+        let uri = "file:///a.txt".parse().unwrap();
+        let report = markdown_report(&[(uri, find(&document))]);
+        assert!(report.starts_with("1 string looks like a color but isn't decorated."));
+        assert!(report.contains(
+            "| file:///a.txt:1:5 | `rgb 255 0 0` | Color function without parentheses | Wrap the components in parentheses |"
+        ));
+    }
+}
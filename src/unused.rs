@@ -0,0 +1,62 @@
+//! Stylesheet color variables that are defined but never referenced in the
+//! workspace, which usually linger in token files after a redesign.
+
+use std::collections::HashMap;
+
+use tower_lsp_server::ls_types::{
+    Diagnostic, DiagnosticSeverity, DiagnosticTag, NumberOrString, Uri,
+};
+
+use crate::color::to_hex;
+use crate::variables::ColorVariable;
+
+/// Code of unused variable diagnostics, which have a quick fix removing the
+/// definition.
+pub const DIAGNOSTIC_CODE: &str = "unused-color";
+
+/// Returns a hint on each unused variable, by file.
+pub fn diagnostics(unused: &[&ColorVariable]) -> HashMap<Uri, Vec<Diagnostic>> {
+    let mut diagnostics: HashMap<Uri, Vec<Diagnostic>> = HashMap::new();
+    for variable in unused {
+        diagnostics
+            .entry(variable.location.uri.clone())
+            .or_default()
+            .push(Diagnostic {
+                range: variable.location.range,
+                severity: Some(DiagnosticSeverity::HINT),
+                code: Some(NumberOrString::String(DIAGNOSTIC_CODE.to_string())),
+                source: Some("chroma-ls".to_string()),
+                message: format!("`{}` is never referenced", variable.name),
+                tags: Some(vec![DiagnosticTag::UNNECESSARY]),
+                ..Default::default()
+            });
+    }
+    diagnostics
+}
+
+/// Renders the unused variables as a Markdown summary.
+pub fn markdown_report(unused: &[&ColorVariable]) -> String {
+    if unused.is_empty() {
+        return "Every color variable is referenced.\n".to_string();
+    }
+
+    let count = match unused.len() {
+        1 => "1 color variable is".to_string(),
+        n => format!("{n} color variables are"),
+    };
+    let mut report = format!(
+        "{count} never referenced.\n\n\
+         | Name | Color | File |\n\
+         | --- | --- | --- |\n"
+    );
+    for variable in unused {
+        report.push_str(&format!(
+            "| `{}` | {} | {}:{} |\n",
+            variable.name,
+            to_hex(&variable.color),
+            variable.location.uri.as_str(),
+            variable.location.range.start.line + 1
+        ));
+    }
+    report
+}
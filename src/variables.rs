@@ -0,0 +1,803 @@
+//! Color variables of stylesheets: CSS custom properties like
+//! `--brand: #1a73e8` and SCSS, Sass or LESS variables like `$brand` and
+//! `@brand`, indexed with their references across the workspace.
+
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::path::{Path, PathBuf};
+
+use tower_lsp_server::ls_types::{Color, ColorInformation, Location, Position, Range, Uri};
+
+use crate::document::{Document, utf16_to_byte_index};
+use crate::heuristics::{Syntax, line_range};
+use crate::links;
+use crate::workspace::{self, Scope};
+
+/// Extensions of indexed files: stylesheets, and web sources that may refer
+/// to their variables.
+const INDEXED_EXTENSIONS: [&str; 12] = [
+    "css", "scss", "sass", "less", "html", "vue", "svelte", "astro", "js", "jsx", "ts", "tsx",
+];
+
+/// Most aliases followed to resolve a variable, which also stops reference
+/// cycles.
+const MAX_ALIAS_DEPTH: usize = 8;
+
+/// A color assigned to a stylesheet variable.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ColorVariable {
+    /// Name with its `--`, `$` or `@` prefix.
+    pub name: String,
+    /// Location of the name.
+    pub location: Location,
+    pub color: Color,
+}
+
+/// Returns the variables of a document whose value starts with a color.
+/// Custom properties may be declared anywhere in a line, `$` and `@`
+/// variables only at its start.
+pub fn definitions(uri: &Uri, document: &Document) -> Vec<ColorVariable> {
+    let mut definitions = Vec::new();
+    for (line_idx, line) in document.lines().enumerate() {
+        let indent = line.len() - line.trim_start().len();
+        let mut names: Vec<usize> = line
+            .match_indices("--")
+            .map(|(idx, _)| idx)
+            .filter(|&idx| {
+                !line[..idx]
+                    .chars()
+                    .next_back()
+                    .is_some_and(|c| is_name_char(c) || c == '-')
+            })
+            .collect();
+        if line[indent..].starts_with(['$', '@']) {
+            names.insert(0, indent);
+        }
+
+        let colors = document.get_line_colors(line_idx..=line_idx);
+        for name_start in names {
+            let prefix_len = if line[name_start..].starts_with("--") {
+                2
+            } else {
+                1
+            };
+            let name_len = line[name_start + prefix_len..]
+                .find(|c: char| !is_name_char(c))
+                .unwrap_or(line.len() - name_start - prefix_len);
+            let name_end = name_start + prefix_len + name_len;
+            let Some(value) = line[name_end..].trim_start().strip_prefix(':') else {
+                continue;
+            };
+            if name_len == 0 {
+                continue;
+            }
+            let value_start = line.len() - value.trim_start().len();
+            let value_range = line_range(line, line_idx, value_start..value_start);
+            let Some(color) = colors
+                .iter()
+                .find(|color_info| color_info.range.start == value_range.start)
+            else {
+                continue;
+            };
+            definitions.push(ColorVariable {
+                name: line[name_start..name_end].to_string(),
+                location: Location {
+                    uri: uri.clone(),
+                    range: line_range(line, line_idx, name_start..name_end),
+                },
+                color: color.color,
+            });
+        }
+    }
+    definitions
+}
+
+fn is_name_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '-' || c == '_'
+}
+
+/// Returns the `--name`, `$name` and `@name` tokens of a line, including the
+/// names of definitions, with the ranges of the names.
+pub fn references(line: &str, line_idx: usize) -> Vec<(Range, String)> {
+    tokens(line)
+        .into_iter()
+        .map(|token| {
+            (
+                line_range(line, line_idx, token.clone()),
+                line[token].to_string(),
+            )
+        })
+        .collect()
+}
+
+/// Returns the byte ranges of the `--name`, `$name` and `@name` tokens of a
+/// line.
+fn tokens(line: &str) -> Vec<std::ops::Range<usize>> {
+    let mut tokens = Vec::new();
+    let mut idx = 0;
+    while let Some(offset) = line[idx..].find(['-', '$', '@']) {
+        let start = idx + offset;
+        let at_word_start = !line[..start]
+            .chars()
+            .next_back()
+            .is_some_and(|c| is_name_char(c) || c == '$' || c == '@');
+        match leading_name(&line[start..]) {
+            Some(name) if at_word_start => {
+                tokens.push(start..start + name.len());
+                idx = start + name.len();
+            }
+            _ => idx = start + 1,
+        }
+    }
+    tokens
+}
+
+/// Returns the `--name`, `$name` or `@name` token `text` starts with.
+fn leading_name(text: &str) -> Option<&str> {
+    let prefix_len = if text.starts_with("--") {
+        2
+    } else if text.starts_with(['$', '@']) {
+        1
+    } else {
+        return None;
+    };
+    let name_len = text[prefix_len..]
+        .find(|c: char| !is_name_char(c))
+        .unwrap_or(text.len() - prefix_len);
+    (name_len > 0).then(|| &text[..prefix_len + name_len])
+}
+
+/// Returns the custom properties declared in a line, with any value: names
+/// followed by `:`, or quoted like in `style.setProperty("--gap", ...)`.
+pub fn declarations(line: &str) -> Vec<&str> {
+    references(line, 0)
+        .into_iter()
+        .filter_map(|(range, name)| {
+            let start = utf16_to_byte_index(line, range.start.character as usize);
+            let end = start + name.len();
+            let declared = line[end..].trim_start().starts_with(':')
+                || line[..start].ends_with(['"', '\''])
+                || line[..start].ends_with('`');
+            (name.starts_with("--") && declared).then(|| &line[start..end])
+        })
+        .collect()
+}
+
+/// Returns the custom properties used by `var(--name)` calls without a
+/// fallback, with the ranges of the names.
+pub fn var_references(line: &str, line_idx: usize) -> Vec<(Range, &str)> {
+    var_calls(line)
+        .into_iter()
+        .map(|(_, name)| (line_range(line, line_idx, name.clone()), &line[name]))
+        .collect()
+}
+
+/// Returns the byte ranges of the `var(--name)` calls of a line without a
+/// fallback, and of their names.
+fn var_calls(line: &str) -> Vec<(std::ops::Range<usize>, std::ops::Range<usize>)> {
+    line.match_indices("var(")
+        .filter(|(idx, _)| !line[..*idx].chars().next_back().is_some_and(is_name_char))
+        .filter_map(|(idx, call)| {
+            let args_start = idx + call.len();
+            let args = &line[args_start..];
+            let name_start = args_start + (args.len() - args.trim_start().len());
+            let name_len = line[name_start..]
+                .strip_prefix("--")?
+                .find(|c: char| !is_name_char(c))
+                .unwrap_or(line.len() - name_start - 2)
+                + 2;
+            let name_end = name_start + name_len;
+            let close = name_end + line[name_end..].find(|c: char| !c.is_whitespace())?;
+            (name_len > 2 && line[close..].starts_with(')'))
+                .then_some((idx..close + 1, name_start..name_end))
+        })
+        .collect()
+}
+
+/// Returns the variables of a line defined as another variable, e.g.
+/// `--link: var(--brand)` or `$accent: $brand !default`, with the names they
+/// refer to.
+pub fn aliases(line: &str) -> Vec<(&str, &str)> {
+    let indent = line.len() - line.trim_start().len();
+    tokens(line)
+        .into_iter()
+        .filter(|name| name.start == indent || line[name.clone()].starts_with("--"))
+        .filter_map(|name| {
+            let value = line[name.end..]
+                .trim_start()
+                .strip_prefix(':')?
+                .trim_start();
+            let (target, rest) = match value.strip_prefix("var(") {
+                Some(args) => {
+                    let args = args.trim_start();
+                    let target = leading_name(args).filter(|target| target.starts_with("--"))?;
+                    let rest = args[target.len()..].trim_start().strip_prefix(')')?;
+                    (target, rest)
+                }
+                None => {
+                    let target = leading_name(value).filter(|target| !target.starts_with("--"))?;
+                    (target, &value[target.len()..])
+                }
+            };
+            let rest = rest.trim_start();
+            (rest.is_empty() || rest.starts_with([';', '}', '!'])).then(|| (&line[name], target))
+        })
+        .collect()
+}
+
+/// Returns the candidate files of the stylesheets a document imports, in
+/// import order.
+pub fn imports(uri: &Uri, document: &Document) -> Vec<Uri> {
+    document
+        .lines()
+        .flat_map(|line| links::imports(line, 0))
+        .flat_map(|(_, path)| links::import_candidates(uri, path))
+        .filter_map(Uri::from_file_path)
+        .collect()
+}
+
+/// Returns the new name of the variable `name` renamed to `new_name`, which
+/// may leave out the `--`, `$` or `@` prefix, or `None` if it isn't a valid
+/// name of the same kind.
+pub fn renamed(name: &str, new_name: &str) -> Option<String> {
+    let prefix = if name.starts_with("--") {
+        "--"
+    } else {
+        &name[..1]
+    };
+    let bare = new_name.strip_prefix(prefix).unwrap_or(new_name);
+    (!bare.is_empty() && bare.chars().all(is_name_char)).then(|| format!("{prefix}{bare}"))
+}
+
+/// Returns the range to delete to remove the declaration whose name is at
+/// `name_range`: the declaration up to its `;` or the closing `}` of its
+/// block, or the whole line when nothing else is on it.
+pub fn declaration_range(line: &str, name_range: Range) -> Range {
+    let line_idx = name_range.start.line;
+    let start = utf16_to_byte_index(line, name_range.start.character as usize);
+    let end = match line[start..].find([';', '}']) {
+        Some(idx) if line[start + idx..].starts_with(';') => start + idx + 1,
+        Some(idx) => start + idx,
+        None => line.len(),
+    };
+    let end = end + (line[end..].len() - line[end..].trim_start().len());
+    if line[..start].trim().is_empty() && line[end..].trim().is_empty() {
+        return Range {
+            start: Position {
+                line: line_idx,
+                character: 0,
+            },
+            end: Position {
+                line: line_idx + 1,
+                character: 0,
+            },
+        };
+    }
+    line_range(line, line_idx as usize, start..end)
+}
+
+/// Glob of the indexed files, watched for changes made outside the editor.
+pub const WATCH_GLOB: &str = "**/*.{css,scss,sass,less,html,vue,svelte,astro,js,jsx,ts,tsx}";
+
+/// Whether the file at `path` is a stylesheet or web source to index.
+pub fn is_indexed(path: &Path) -> bool {
+    path.extension()
+        .and_then(|extension| extension.to_str())
+        .is_some_and(|extension| INDEXED_EXTENSIONS.contains(&extension))
+}
+
+/// Returns the stylesheets and web sources under `root`.
+pub fn workspace_source_files(root: &Path) -> Vec<PathBuf> {
+    workspace::files(root, is_indexed)
+}
+
+/// Stylesheet color variables and references to them, by file.
+#[derive(Debug, Default)]
+pub struct VariableIndex {
+    definitions: HashMap<Uri, Vec<ColorVariable>>,
+    /// Name ranges and names of variable tokens, including definitions.
+    references: HashMap<Uri, Vec<(Range, String)>>,
+    /// Custom properties declared with any value.
+    declarations: HashMap<Uri, Vec<String>>,
+    /// Variables defined as another variable, with the names they refer to.
+    aliases: HashMap<Uri, Vec<(String, String)>>,
+    /// Candidate files of imported stylesheets.
+    imports: HashMap<Uri, Vec<Uri>>,
+}
+
+/// Colors of the variables in scope of a document, and the names of the
+/// variables aliases refer to. The first definition of a name wins.
+#[derive(Default)]
+struct Resolver {
+    colors: HashMap<String, Color>,
+    aliases: HashMap<String, String>,
+}
+
+impl Resolver {
+    /// Adds the definitions and aliases of a file, or only its custom
+    /// properties.
+    fn add<'a>(
+        &mut self,
+        definitions: impl IntoIterator<Item = &'a ColorVariable>,
+        aliases: impl IntoIterator<Item = &'a (String, String)>,
+        custom_properties_only: bool,
+    ) {
+        let wanted = |name: &str| !custom_properties_only || name.starts_with("--");
+        for variable in definitions {
+            if wanted(&variable.name) {
+                self.colors
+                    .entry(variable.name.clone())
+                    .or_insert(variable.color);
+            }
+        }
+        for (name, target) in aliases {
+            if wanted(name) {
+                self.aliases
+                    .entry(name.clone())
+                    .or_insert_with(|| target.clone());
+            }
+        }
+    }
+
+    fn color<'a>(&'a self, mut name: &'a str) -> Option<Color> {
+        for _ in 0..MAX_ALIAS_DEPTH {
+            if let Some(color) = self.colors.get(name) {
+                return Some(*color);
+            }
+            name = self.aliases.get(name)?;
+        }
+        None
+    }
+}
+
+impl VariableIndex {
+    pub fn contains(&self, uri: &Uri) -> bool {
+        self.definitions.contains_key(uri)
+            || self.references.contains_key(uri)
+            || self.declarations.contains_key(uri)
+            || self.aliases.contains_key(uri)
+            || self.imports.contains_key(uri)
+    }
+
+    /// Drops a file from the index, e.g. once deleted.
+    pub fn remove(&mut self, uri: &Uri) {
+        self.definitions.remove(uri);
+        self.references.remove(uri);
+        self.declarations.remove(uri);
+        self.aliases.remove(uri);
+        self.imports.remove(uri);
+    }
+
+    /// Indexes the color variables of a document and its variable tokens.
+    pub fn update(&mut self, uri: Uri, document: &Document) {
+        let definitions = definitions(&uri, document);
+        if definitions.is_empty() {
+            self.definitions.remove(&uri);
+        } else {
+            self.definitions.insert(uri.clone(), definitions);
+        }
+        let references: Vec<(Range, String)> = document
+            .lines()
+            .enumerate()
+            .flat_map(|(line_idx, line)| references(line, line_idx))
+            .collect();
+        if references.is_empty() {
+            self.references.remove(&uri);
+        } else {
+            self.references.insert(uri.clone(), references);
+        }
+        let declarations: Vec<String> = document
+            .lines()
+            .flat_map(|line| declarations(line))
+            .map(str::to_string)
+            .collect();
+        if declarations.is_empty() {
+            self.declarations.remove(&uri);
+        } else {
+            self.declarations.insert(uri.clone(), declarations);
+        }
+        let aliases: Vec<(String, String)> = document
+            .lines()
+            .flat_map(|line| aliases(line))
+            .map(|(name, target)| (name.to_string(), target.to_string()))
+            .collect();
+        if aliases.is_empty() {
+            self.aliases.remove(&uri);
+        } else {
+            self.aliases.insert(uri.clone(), aliases);
+        }
+        let imports = imports(&uri, document);
+        if imports.is_empty() {
+            self.imports.remove(&uri);
+        } else {
+            self.imports.insert(uri, imports);
+        }
+    }
+
+    /// Indexes a stylesheet read from disk.
+    pub fn update_from_text(&mut self, uri: Uri, text: &str) {
+        let syntax = Syntax::detect("", &uri, text);
+        let document = Document::with_options(text, Default::default(), syntax);
+        self.update(uri, &document);
+    }
+
+    /// Resolves the variable usages of a document: `var(--name)` calls
+    /// without a fallback and `$name` or `@name` tokens other than
+    /// definitions. Variables are looked up in the document, then in the
+    /// stylesheets it imports, directly or not, and custom properties also
+    /// in all other indexed files of the `scope`. Usages inside decorated
+    /// colors, such as `darken($brand, 10%)`, are left to them.
+    pub fn resolve(&self, uri: &Uri, document: &Document, scope: Scope) -> Vec<ColorInformation> {
+        let mut resolver = Resolver::default();
+        let local_aliases: Vec<(String, String)> = document
+            .lines()
+            .flat_map(|line| aliases(line))
+            .map(|(name, target)| (name.to_string(), target.to_string()))
+            .collect();
+        resolver.add(&definitions(uri, document), &local_aliases, false);
+
+        let mut visited = HashSet::from([uri]);
+        let local_imports = imports(uri, document);
+        let mut queue: VecDeque<&Uri> = local_imports.iter().collect();
+        while let Some(import) = queue.pop_front() {
+            if !visited.insert(import) {
+                continue;
+            }
+            resolver.add(
+                self.definitions.get(import).into_iter().flatten(),
+                self.aliases.get(import).into_iter().flatten(),
+                false,
+            );
+            queue.extend(self.imports.get(import).into_iter().flatten());
+        }
+        let mut others: Vec<&Uri> = self
+            .definitions
+            .keys()
+            .chain(self.aliases.keys())
+            .filter(|other| !visited.contains(other) && scope.includes(other))
+            .collect();
+        others.sort_by_key(|other| other.as_str());
+        others.dedup();
+        for other in others {
+            resolver.add(
+                self.definitions.get(other).into_iter().flatten(),
+                self.aliases.get(other).into_iter().flatten(),
+                true,
+            );
+        }
+
+        let mut resolved = Vec::new();
+        for (line_idx, line) in document.lines().enumerate() {
+            let calls = var_calls(line);
+            let mut usages: Vec<_> = calls
+                .iter()
+                .map(|(call, name)| (call.clone(), &line[name.clone()]))
+                .collect();
+            usages.extend(
+                tokens(line)
+                    .into_iter()
+                    .filter(|token| {
+                        !line[token.clone()].starts_with("--")
+                            && !line[token.end..].trim_start().starts_with(':')
+                    })
+                    .map(|token| (token.clone(), &line[token])),
+            );
+            let colors = document.get_line_colors(line_idx..=line_idx);
+            for (bytes, name) in usages {
+                let range = line_range(line, line_idx, bytes);
+                let decorated = colors.iter().any(|color_info| {
+                    color_info.range.start < range.end && range.start < color_info.range.end
+                });
+                if let Some(color) = resolver.color(name).filter(|_| !decorated) {
+                    resolved.push(ColorInformation { range, color });
+                }
+            }
+        }
+        resolved.sort_by_key(|color_info| color_info.range.start);
+        resolved
+    }
+
+    /// Returns all indexed variables.
+    pub fn all(&self) -> impl Iterator<Item = &ColorVariable> {
+        self.definitions.values().flatten()
+    }
+
+    /// Returns the names of all declared custom properties, with duplicates.
+    pub fn custom_properties(&self) -> impl Iterator<Item = &str> {
+        self.declarations.values().flatten().map(String::as_str)
+    }
+
+    /// Returns the variable whose name is at `position` in `uri`.
+    pub fn definition_at(&self, uri: &Uri, position: Position) -> Option<&ColorVariable> {
+        self.definitions.get(uri)?.iter().find(|variable| {
+            let range = variable.location.range;
+            range.start <= position && position <= range.end
+        })
+    }
+
+    /// Returns the range and name of the color variable token at `position`
+    /// in `uri`, a definition or a usage of a variable defined or aliased in
+    /// an indexed file.
+    pub fn variable_at(&self, uri: &Uri, position: Position) -> Option<(Range, &str)> {
+        let (range, name) = self
+            .references
+            .get(uri)?
+            .iter()
+            .find(|(range, _)| range.start <= position && position <= range.end)?;
+        let defined = self.all().any(|variable| variable.name == *name)
+            || self
+                .aliases
+                .values()
+                .flatten()
+                .any(|(alias, _)| alias == name);
+        defined.then_some((*range, name.as_str()))
+    }
+
+    /// Returns the ranges to edit when renaming the variable `name`: its
+    /// definitions and all indexed tokens of the same name, in order.
+    pub fn rename_locations(&self, name: &str) -> HashMap<Uri, Vec<Range>> {
+        let mut locations: HashMap<Uri, Vec<Range>> = HashMap::new();
+        for (uri, references) in &self.references {
+            for (range, _) in references.iter().filter(|(_, reference)| reference == name) {
+                locations.entry(uri.clone()).or_default().push(*range);
+            }
+        }
+        for ranges in locations.values_mut() {
+            ranges.sort_by_key(|range| range.start);
+        }
+        locations
+    }
+
+    /// Returns the variables whose name appears nowhere but in definitions,
+    /// ordered by file and position.
+    pub fn unused(&self) -> Vec<&ColorVariable> {
+        let defined_at = |uri: &Uri, range: &Range| {
+            self.definitions.get(uri).is_some_and(|variables| {
+                variables
+                    .iter()
+                    .any(|variable| variable.location.range == *range)
+            })
+        };
+        let mut unused: Vec<&ColorVariable> = self
+            .all()
+            .filter(|variable| {
+                !self.references.iter().any(|(uri, references)| {
+                    references
+                        .iter()
+                        .any(|(range, name)| *name == variable.name && !defined_at(uri, range))
+                })
+            })
+            .collect();
+        unused.sort_by(|a, b| {
+            (a.location.uri.as_str(), a.location.range.start)
+                .cmp(&(b.location.uri.as_str(), b.location.range.start))
+        });
+        unused
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tower_lsp_server::ls_types::{Position, Uri};
+
+    use crate::color::to_hex;
+    use crate::document::Document;
+    use crate::heuristics::Syntax;
+    use crate::variables::{
+        VariableIndex, aliases, declaration_range, declarations, definitions, references, renamed,
+        var_references,
+    };
+    use crate::workspace::Scope;
+
+    #[test]
+    fn finds_custom_properties_and_variables() {
+        let uri = "file:///styles/theme.scss".parse().unwrap();
+        let document = Document::with_options(
+            ":root { --brand: #1a73e8; --gap: 4px; --fg:#fff }\n$accent: rgb(255, 87, 34) !default;\n  @muted : #999;\n.a { color: var(--brand); }\n",
+            Default::default(),
+            Syntax::Scss,
+        );
+        let variables: Vec<_> = definitions(&uri, &document)
+            .into_iter()
+            .map(|variable| {
+                let start = variable.location.range.start;
+                (
+                    variable.name,
+                    start.line,
+                    start.character,
+                    to_hex(&variable.color),
+                )
+            })
+            .collect();
+        assert_eq!(
+            variables,
+            [
+                ("--brand".to_string(), 0, 8, "#1A73E8".to_string()),
+                ("--fg".to_string(), 0, 38, "#FFFFFF".to_string()),
+                ("$accent".to_string(), 1, 0, "#FF5722".to_string()),
+                ("@muted".to_string(), 2, 2, "#999999".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn finds_unused_variables() {
+        let mut index = VariableIndex::default();
+        index.update_from_text(
+            "file:///styles/tokens.css".parse().unwrap(),
+            ":root {\n  --brand: #1a73e8;\n  --unused: #ff5722;\n  --link: var(--brand);\n}\n",
+        );
+        index.update_from_text(
+            "file:///src/App.vue".parse().unwrap(),
+            "<a :style=\"{ color: 'var(--link)' }\">\n",
+        );
+        let unused: Vec<&str> = index
+            .unused()
+            .into_iter()
+            .map(|variable| variable.name.as_str())
+            .collect();
+        assert_eq!(unused, ["--unused"]);
+
+        let names: Vec<String> = references("a { color: $x; } @media print { --y: 1 }", 0)
+            .into_iter()
+            .map(|(_, name)| name)
+            .collect();
+        assert_eq!(names, ["$x", "@media", "--y"]);
+    }
+
+    #[test]
+    fn declaration_ranges() {
+        let line = "  --unused: #ff5722;";
+        let name = references(line, 3)[0].0;
+        let range = declaration_range(line, name);
+        assert_eq!((range.start.line, range.start.character), (3, 0));
+        assert_eq!((range.end.line, range.end.character), (4, 0));
+
+        let line = ":root { --a: red; --b: blue }";
+        let name = references(line, 0)[1].0;
+        let range = declaration_range(line, name);
+        assert_eq!((range.start.character, range.end.character), (18, 28));
+    }
+
+    #[test]
+    fn custom_property_uses() {
+        assert_eq!(
+            declarations(":root { --gap: 4px } el.style.setProperty('--x', 1); var(--y)"),
+            ["--gap", "--x"]
+        );
+        let uses: Vec<_> = var_references("color: var( --fg ); background: var(--bg, #fff)", 0)
+            .into_iter()
+            .map(|(range, name)| (range.start.character, name))
+            .collect();
+        assert_eq!(uses, [(12, "--fg")]);
+    }
+
+    #[test]
+    fn resolves_custom_properties() {
+        let uri = "file:///styles/app.css".parse().unwrap();
+        let document = Document::with_options(
+            ".a { color: var(--primary); border: 1px solid var( --link ) }\n:root {\n  --primary: #336699;\n  --link: var(--primary);\n  --loop: var(--loop);\n}\n.b { color: var(--loop); fill: var(--gap, red) }\n",
+            Default::default(),
+            Syntax::Plain,
+        );
+        let resolved: Vec<_> = VariableIndex::default()
+            .resolve(&uri, &document, Scope::default())
+            .into_iter()
+            .map(|color_info| {
+                let range = color_info.range;
+                (
+                    range.start.line,
+                    range.start.character,
+                    range.end.character,
+                    to_hex(&color_info.color),
+                )
+            })
+            .collect();
+        assert_eq!(
+            resolved,
+            [
+                (0, 12, 26, "#336699".to_string()),
+                (0, 46, 59, "#336699".to_string()),
+                (3, 10, 24, "#336699".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn resolves_variables_across_files() {
+        let mut index = VariableIndex::default();
+        index.update_from_text(
+            "file:///styles/_tokens.scss".parse().unwrap(),
+            "$brand: #1a73e8;\n$accent: $brand !default;\n",
+        );
+        index.update_from_text(
+            "file:///styles/theme.css".parse().unwrap(),
+            ":root { --surface: #fafafa; }\n",
+        );
+        index.update_from_text(
+            "file:///other/colors.scss".parse().unwrap(),
+            "$muted: #999;\n",
+        );
+        let uri = "file:///styles/app.scss".parse().unwrap();
+        let document = Document::with_options(
+            "@use 'tokens';\n$ink: #000;\n.a { color: $accent; background: var(--surface); border-color: $muted; fill: darken($ink, 10%); }\n",
+            Default::default(),
+            Syntax::Scss,
+        );
+        let resolved: Vec<_> = index
+            .resolve(&uri, &document, Scope::default())
+            .into_iter()
+            .map(|color_info| {
+                let range = color_info.range;
+                (
+                    range.start.character,
+                    range.end.character,
+                    to_hex(&color_info.color),
+                )
+            })
+            .collect();
+        assert_eq!(
+            resolved,
+            [
+                (12, 19, "#1A73E8".to_string()),
+                (33, 47, "#FAFAFA".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn renames_variables() {
+        let mut index = VariableIndex::default();
+        let tokens: Uri = "file:///_tokens.scss".parse().unwrap();
+        let app: Uri = "file:///app.scss".parse().unwrap();
+        index.update_from_text(
+            tokens.clone(),
+            "$brand: #1a73e8;\n$accent: $brand !default;\n",
+        );
+        index.update_from_text(
+            app.clone(),
+            "@use 'tokens';\n.a { color: $brand; gap: $brandish; }\n",
+        );
+
+        let position = Position {
+            line: 1,
+            character: 14,
+        };
+        let (range, name) = index.variable_at(&app, position).unwrap();
+        assert_eq!(name, "$brand");
+        assert_eq!((range.start.character, range.end.character), (12, 18));
+        // `@use` isn't a variable.
+        assert_eq!(index.variable_at(&app, Position::default()), None);
+
+        let locations = index.rename_locations("$brand");
+        let ranges = |uri: &Uri| -> Vec<_> {
+            locations[uri]
+                .iter()
+                .map(|range| (range.start.line, range.start.character))
+                .collect()
+        };
+        assert_eq!(ranges(&tokens), [(0, 0), (1, 9)]);
+        assert_eq!(ranges(&app), [(1, 12)]);
+
+        assert_eq!(renamed("$brand", "primary").as_deref(), Some("$primary"));
+        assert_eq!(renamed("$brand", "$primary").as_deref(), Some("$primary"));
+        assert_eq!(
+            renamed("--brand", "--primary").as_deref(),
+            Some("--primary")
+        );
+        assert_eq!(renamed("--brand", "primary color"), None);
+        assert_eq!(renamed("@brand", ""), None);
+    }
+
+    #[test]
+    fn finds_aliases() {
+        assert_eq!(
+            aliases(":root { --link: var( --brand ); --gap: var(--x, 4px) }"),
+            [("--link", "--brand")]
+        );
+        assert_eq!(
+            aliases("$accent: $brand !default;"),
+            [("$accent", "$brand")]
+        );
+        assert!(aliases("$accent: darken($brand, 10%);").is_empty());
+    }
+}
@@ -0,0 +1,103 @@
+//! Scanning of workspace folders for files to index.
+
+use std::path::{Path, PathBuf};
+
+use tower_lsp_server::ls_types::Uri;
+
+/// Directories skipped when scanning a workspace.
+const SKIPPED_DIRS: [&str; 3] = ["build", "node_modules", "target"];
+
+/// Returns the files under `root` for which `wanted` holds, sorted, skipping
+/// hidden and build output directories.
+pub fn files(root: &Path, wanted: impl Fn(&Path) -> bool) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    let mut dirs = vec![root.to_path_buf()];
+    while let Some(dir) = dirs.pop() {
+        let Ok(entries) = std::fs::read_dir(&dir) else {
+            continue;
+        };
+        for path in entries.flatten().map(|entry| entry.path()) {
+            let name = path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .unwrap_or("");
+            if path.is_dir() {
+                if !name.starts_with('.') && !SKIPPED_DIRS.contains(&name) {
+                    dirs.push(path);
+                }
+            } else if wanted(&path) {
+                files.push(path);
+            }
+        }
+    }
+    files.sort();
+    files
+}
+
+/// Returns the deepest workspace folder containing `uri`.
+pub fn folder_of<'a>(folders: &'a [Uri], uri: &Uri) -> Option<&'a Uri> {
+    folders
+        .iter()
+        .filter(|folder| contains(folder, uri))
+        .max_by_key(|folder| folder.path().as_str().len())
+}
+
+fn contains(folder: &Uri, uri: &Uri) -> bool {
+    let folder = folder.path().as_str().trim_end_matches('/');
+    uri.path()
+        .as_str()
+        .strip_prefix(folder)
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+}
+
+/// The workspace folder a document belongs to. Palettes and design tokens
+/// defined in a folder only apply to documents of the same folder, while
+/// those outside every folder apply everywhere, as does everything to
+/// documents outside every folder.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Scope<'a> {
+    folders: &'a [Uri],
+    folder: Option<&'a Uri>,
+}
+
+impl<'a> Scope<'a> {
+    pub fn of(folders: &'a [Uri], uri: &Uri) -> Self {
+        Self {
+            folders,
+            folder: folder_of(folders, uri),
+        }
+    }
+
+    /// Whether a definition in the file at `uri` applies in this scope.
+    pub fn includes(&self, uri: &Uri) -> bool {
+        match (self.folder, folder_of(self.folders, uri)) {
+            (Some(folder), Some(defined_in)) => folder == defined_in,
+            _ => true,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tower_lsp_server::ls_types::Uri;
+
+    use crate::workspace::{Scope, folder_of};
+
+    #[test]
+    fn scopes_to_folders() {
+        let uri = |s: &str| -> Uri { s.parse().unwrap() };
+        let folders = [
+            uri("file:///web"),
+            uri("file:///web/admin"),
+            uri("file:///app"),
+        ];
+        let scope = Scope::of(&folders, &uri("file:///web/admin/main.css"));
+        assert!(scope.includes(&uri("file:///web/admin/tokens.json")));
+        assert!(!scope.includes(&uri("file:///web/tokens.json")));
+        assert!(scope.includes(&uri("file:///shared/brand.gpl")));
+        assert_eq!(folder_of(&folders, &uri("file:///application/a.css")), None);
+
+        let outside = Scope::of(&folders, &uri("file:///tmp/scratch.css"));
+        assert!(outside.includes(&uri("file:///app/tokens.json")));
+    }
+}
@@ -0,0 +1,225 @@
+//! Snapshots of color extraction over real-world files, so new formats can't
+//! silently change results on existing ones.
+
+use std::fmt::Write;
+use std::io::{BufRead, BufReader, Read, Write as _};
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+use serde_json::json;
+
+use chroma_ls::color::to_hex;
+use chroma_ls::document::Document;
+use chroma_ls::scan;
+
+fn extract(source: &str) -> String {
+    let document = Document::from(source);
+    let mut output = String::new();
+    for color_info in document.get_colors() {
+        let range = color_info.range;
+        writeln!(
+            output,
+            "{}:{}-{}:{} {}",
+            range.start.line,
+            range.start.character,
+            range.end.line,
+            range.end.character,
+            to_hex(&color_info.color)
+        )
+        .unwrap();
+    }
+    output
+}
+
+macro_rules! corpus_test {
+    ($name:ident, $file:literal) => {
+        #[test]
+        fn $name() {
+            let source = include_str!(concat!("corpus/", $file));
+            insta::assert_snapshot!(extract(source));
+        }
+    };
+}
+
+corpus_test!(bootstrap_css, "bootstrap.css");
+corpus_test!(alacritty_toml, "alacritty.toml");
+corpus_test!(kitty_conf, "kitty.conf");
+corpus_test!(shader_frag, "shader.frag");
+corpus_test!(xresources, "Xresources");
+corpus_test!(i3_config, "i3.config");
+corpus_test!(package_lock_json, "package-lock.json");
+corpus_test!(shadows_css, "shadows.css");
+
+/// Files under `corpus/none` must produce no colors, as lockfiles, hashes and
+/// base64 blobs are full of runs that look like hex.
+#[test]
+fn no_false_positives() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/corpus/none");
+    let files = scan::files(&[dir]);
+    assert!(!files.is_empty());
+    for path in files {
+        let colors = scan::scan_file(&path).unwrap();
+        assert_eq!(colors, [], "{}", path.display());
+    }
+}
+
+#[test]
+fn scan_expect_none_fails_on_colors() {
+    let scan = |path: &str| {
+        Command::new(env!("CARGO_BIN_EXE_chroma-ls"))
+            .args(["scan", "--expect-none", path])
+            .current_dir(env!("CARGO_MANIFEST_DIR"))
+            .output()
+            .unwrap()
+    };
+    assert!(scan("tests/corpus/none").status.success());
+    let output = scan("tests/corpus/shadows.css");
+    assert_eq!(output.status.code(), Some(1));
+    assert!(
+        String::from_utf8_lossy(&output.stdout)
+            .starts_with("tests/corpus/shadows.css:2:25 #00000066\n")
+    );
+}
+
+#[test]
+fn scan_json_output() {
+    let output = Command::new(env!("CARGO_BIN_EXE_chroma-ls"))
+        .args(["scan", "--json", "tests/corpus/shadows.css"])
+        .current_dir(env!("CARGO_MANIFEST_DIR"))
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let results: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(results["schemaVersion"], 1);
+    let file = &results["files"][0];
+    assert_eq!(file["path"], "tests/corpus/shadows.css");
+    assert_eq!(file["colors"][0]["hex"], "#00000066");
+    assert_eq!(file["colors"][0]["range"]["start"]["line"], 1);
+    assert_eq!(file["colors"][0]["range"]["start"]["character"], 24);
+}
+
+#[test]
+fn version_and_unknown_flags() {
+    let run = |args: &[&str]| {
+        Command::new(env!("CARGO_BIN_EXE_chroma-ls"))
+            .args(args)
+            .output()
+            .unwrap()
+    };
+    let output = run(&["--version"]);
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        format!("chroma-ls {}\n", env!("CARGO_PKG_VERSION"))
+    );
+
+    let output = run(&["--stdio", "--verbose"]);
+    assert_eq!(output.status.code(), Some(2));
+    assert!(
+        String::from_utf8_lossy(&output.stderr)
+            .starts_with("chroma-ls: unknown flag --verbose\nusage:")
+    );
+}
+
+#[test]
+fn converts_files() {
+    let dir = std::env::temp_dir().join(format!("chroma-ls-convert-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("a.css");
+    std::fs::write(&path, "a { color: #FF0000; fill: red; }\n").unwrap();
+    let run = |args: &[&str]| {
+        Command::new(env!("CARGO_BIN_EXE_chroma-ls"))
+            .args(["convert"])
+            .args(args)
+            .arg(&path)
+            .output()
+            .unwrap()
+    };
+
+    let output = run(&["--to", "hsl", "--stdout"]);
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "a { color: hsl(0 100% 50%); fill: red; }\n"
+    );
+    assert_eq!(
+        std::fs::read_to_string(&path).unwrap(),
+        "a { color: #FF0000; fill: red; }\n"
+    );
+
+    let output = run(&["--to=oklch"]);
+    assert!(output.status.success());
+    assert_eq!(
+        std::fs::read_to_string(&path).unwrap(),
+        "a { color: oklch(62.8% 0.2577 29.23); fill: red; }\n"
+    );
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn logs_to_file() {
+    let log = std::env::temp_dir().join(format!("chroma-ls-{}.log", std::process::id()));
+    let mut child = Command::new(env!("CARGO_BIN_EXE_chroma-ls"))
+        .args(["--stdio", "--log-level", "debug", "--log-file"])
+        .arg(&log)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let mut stdin = child.stdin.take().unwrap();
+    let mut stdout = BufReader::new(child.stdout.take().unwrap());
+    let mut send = |method: &str, id: Option<u32>, params: serde_json::Value| {
+        let mut body = json!({ "jsonrpc": "2.0", "method": method, "params": params });
+        if let Some(id) = id {
+            body["id"] = json!(id);
+        }
+        let body = body.to_string();
+        write!(stdin, "Content-Length: {}\r\n\r\n{body}", body.len()).unwrap();
+    };
+    let mut receive = || {
+        let mut length = 0;
+        let mut header = String::new();
+        while header != "\r\n" {
+            header.clear();
+            assert_ne!(stdout.read_line(&mut header).unwrap(), 0);
+            if let Some(value) = header.strip_prefix("Content-Length: ") {
+                length = value.trim().parse().unwrap();
+            }
+        }
+        let mut body = vec![0; length];
+        stdout.read_exact(&mut body).unwrap();
+        serde_json::from_slice::<serde_json::Value>(&body).unwrap()
+    };
+
+    let uri = "file:///test.css";
+    send("initialize", Some(0), json!({ "capabilities": {} }));
+    receive();
+    send("initialized", None, json!({}));
+    let text_document =
+        json!({ "uri": uri, "languageId": "css", "version": 0, "text": "a { color: #FFF; }\n" });
+    send(
+        "textDocument/didOpen",
+        None,
+        json!({ "textDocument": text_document }),
+    );
+    send(
+        "textDocument/documentColor",
+        Some(1),
+        json!({ "textDocument": { "uri": uri } }),
+    );
+    // `exit` stops the server right away, so wait for the colors first.
+    while receive()["id"] != 1 {}
+    send("shutdown", Some(2), json!(null));
+    send("exit", None, json!(null));
+    // Editors close stdin along with `exit`.
+    drop(stdin);
+    assert!(child.wait().unwrap().success());
+
+    let logged = std::fs::read_to_string(&log).unwrap();
+    std::fs::remove_file(&log).unwrap();
+    assert!(logged.contains("parsed document"), "{logged}");
+    assert!(
+        logged.contains("handled request method=\"textDocument/documentColor\""),
+        "{logged}"
+    );
+}
@@ -0,0 +1,13 @@
+#version 330 core
+#define PALETTE_SIZE 4
+#define ACCENT 0xFF8800
+
+uniform vec3 u_tint; // default #336699
+out vec4 frag_color;
+
+// Colors picked from #FFAA00 and #00AAFFCC references.
+const vec3 SKY = vec3(0.529, 0.808, 0.922);
+
+void main() {
+    frag_color = vec4(SKY * u_tint, 1.0);
+}
@@ -0,0 +1,2903 @@
+mod support;
+
+use serde_json::{Value, json};
+
+use chroma_ls::transport;
+use support::{TestClient, Workspace, file_uri};
+
+const URI: &str = "file:///test.css";
+
+#[tokio::test]
+async fn initialize_advertises_incremental_sync() {
+    let mut client = TestClient::start();
+    let result = client
+        .initialize(json!({ "textDocument": { "colorProvider": {} } }))
+        .await;
+
+    assert_eq!(result["capabilities"]["textDocumentSync"]["change"], 2);
+    assert_eq!(result["capabilities"]["colorProvider"], true);
+    assert_eq!(result["serverInfo"]["name"], "chroma-ls");
+}
+
+#[tokio::test]
+async fn initialize_without_color_provider_falls_back_to_hover() {
+    let mut client = TestClient::start();
+    let result = client
+        .initialize(json!({ "textDocument": { "hover": {} } }))
+        .await;
+
+    assert_eq!(result["capabilities"]["colorProvider"], json!(null));
+    assert_eq!(result["capabilities"]["hoverProvider"], true);
+}
+
+#[tokio::test]
+async fn hover_shows_color_details() {
+    let mut client = TestClient::start();
+    client
+        .initialize(json!({ "textDocument": { "colorProvider": {}, "hover": {} } }))
+        .await;
+    client.did_open(URI, "a { color: #FF0000; }\n").await;
+
+    let response = client
+        .request(
+            "textDocument/hover",
+            json!({
+                "textDocument": { "uri": URI },
+                "position": { "line": 0, "character": 14 },
+            }),
+        )
+        .await;
+    let value = response["result"]["contents"]["value"].as_str().unwrap();
+    assert!(value.contains("| HSL | `hsl(0 100% 50%)` |"));
+    assert!(value.contains("| OKLCH | `oklch(62.8% 0.2577 29.23)` |"));
+    assert!(value.ends_with("| Nearest named | `red` |"));
+    assert_eq!(response["result"]["range"]["start"]["character"], 11);
+}
+
+#[tokio::test]
+async fn translucent_colors_composited_over_backgrounds() {
+    let mut client = TestClient::start();
+    client
+        .initialize_with_options(
+            json!({ "textDocument": { "colorProvider": {}, "hover": {} } }),
+            json!({ "backgrounds": ["#1e1e1e", "#ffffff"], "compositeSwatches": true }),
+        )
+        .await;
+    client
+        .did_open(URI, "a { color: rgba(255, 255, 255, 0.5); }\n")
+        .await;
+
+    let colors = client.document_color(URI).await;
+    assert_eq!(colors.as_array().unwrap().len(), 2);
+    assert_eq!(colors[1]["range"]["start"], colors[0]["range"]["end"]);
+    assert_eq!(colors[1]["range"]["end"], colors[0]["range"]["end"]);
+    assert_eq!(colors[1]["color"]["alpha"], 1.0);
+
+    let response = client
+        .request(
+            "textDocument/hover",
+            json!({
+                "textDocument": { "uri": URI },
+                "position": { "line": 0, "character": 14 },
+            }),
+        )
+        .await;
+    let value = response["result"]["contents"]["value"].as_str().unwrap();
+    assert!(value.ends_with("| Over `#1E1E1E` | `#8F8F8F` |\n| Over `#FFFFFF` | `#FFFFFF` |"));
+}
+
+#[tokio::test]
+async fn nudge_color_keeps_spelling() {
+    let mut client = TestClient::initialized().await;
+    client
+        .did_open(URI, "a { color: rgb(26, 115, 232); border: #1a73e8; }\n")
+        .await;
+    let mut nudge = async |character: u32, channel: &str, step: f32| {
+        let response = client
+            .request(
+                "workspace/executeCommand",
+                json!({
+                    "command": "chroma.nudgeColor",
+                    "arguments": [{
+                        "textDocument": { "uri": URI },
+                        "position": { "line": 0, "character": character },
+                        "channel": channel,
+                        "step": step,
+                    }],
+                }),
+            )
+            .await;
+        response["result"].clone()
+    };
+
+    let edits = nudge(15, "red", 1.0).await;
+    assert_eq!(edits[0]["newText"], "rgb(27, 115, 232)");
+    assert_eq!(edits[0]["range"]["start"]["character"], 11);
+    let edits = nudge(40, "alpha", -50.0).await;
+    assert_eq!(edits[0]["newText"], "#1a73e880");
+    assert_eq!(nudge(2, "hue", 10.0).await, json!([]));
+}
+
+#[tokio::test]
+async fn completes_used_colors_after_hash() {
+    let mut client = TestClient::start();
+    let result = client
+        .initialize(json!({ "textDocument": { "colorProvider": {} } }))
+        .await;
+    assert_eq!(
+        result["capabilities"]["completionProvider"]["triggerCharacters"],
+        json!(["#", "("])
+    );
+    let uri = "file:///a.css";
+    client
+        .did_open(uri, "a { color: #1a73e8; }\nb { color: #; }\n")
+        .await;
+
+    let response = client
+        .request(
+            "textDocument/completion",
+            json!({
+                "textDocument": { "uri": uri },
+                "position": { "line": 1, "character": 12 },
+            }),
+        )
+        .await;
+    let items = response["result"].as_array().unwrap();
+    assert_eq!(items.len(), 1);
+    assert_eq!(items[0]["label"], "#1A73E8");
+    assert_eq!(items[0]["kind"], 16);
+    assert_eq!(items[0]["textEdit"]["range"]["start"]["character"], 11);
+}
+
+#[tokio::test]
+async fn gradient_stops_in_requested_space() {
+    let mut client = TestClient::initialized().await;
+    let red = json!({ "red": 1.0, "green": 0.0, "blue": 0.0, "alpha": 1.0 });
+    let blue = json!({ "red": 0.0, "green": 0.0, "blue": 1.0, "alpha": 1.0 });
+
+    let response = client
+        .request(
+            "chroma/gradient",
+            json!({ "from": red, "to": blue, "space": "hsl", "hue": "longer", "stops": 3 }),
+        )
+        .await;
+    let stops = response["result"].as_array().unwrap();
+    assert_eq!(stops[1]["offset"], 0.5);
+    assert_eq!(stops[1]["hex"], "#00FF00");
+    assert_eq!(stops[1]["color"]["green"], 1.0);
+
+    let response = client
+        .request(
+            "workspace/executeCommand",
+            json!({
+                "command": "chroma.generateGradient",
+                "arguments": [{ "from": red, "to": blue, "stops": 3 }],
+            }),
+        )
+        .await;
+    assert_eq!(response["result"], json!(["#FF0000", "#800080", "#0000FF"]));
+
+    let response = client
+        .request(
+            "chroma/gradient",
+            json!({ "from": red, "to": blue, "stops": 1 }),
+        )
+        .await;
+    assert_eq!(response["error"]["code"], -32602);
+}
+
+#[tokio::test]
+async fn integer_colors_in_configured_channel_order() {
+    let mut client = TestClient::start();
+    client
+        .initialize_with_options(
+            json!({ "textDocument": { "colorProvider": {} } }),
+            json!({ "integerColors": { "c": "rgba" } }),
+        )
+        .await;
+    let uri = "file:///a.c";
+    client.did_open(uri, "uint32_t tint = 0xff000080u;\n").await;
+    client.did_open(URI, "x = 0xff000080\n").await;
+    assert_eq!(client.document_color(URI).await, json!([]));
+
+    let colors = client.document_color(uri).await;
+    assert_eq!(colors.as_array().unwrap().len(), 1);
+    assert_eq!(colors[0]["range"]["start"]["character"], 16);
+    assert_eq!(colors[0]["range"]["end"]["character"], 26);
+    assert_eq!(colors[0]["color"]["red"], 1.0);
+
+    let response = client
+        .request(
+            "textDocument/colorPresentation",
+            json!({
+                "textDocument": { "uri": uri },
+                "color": { "red": 0.0, "green": 0.0, "blue": 1.0, "alpha": 1.0 },
+                "range": colors[0]["range"],
+            }),
+        )
+        .await;
+    assert_eq!(response["result"][0]["label"], "0x0000ffff");
+}
+
+#[tokio::test]
+async fn presentations_keep_rgb_notation() {
+    let mut client = TestClient::initialized().await;
+    client
+        .did_open(
+            URI,
+            "a { color: rgb(255, 0, 0); border-color: rgba(255, 0, 0, 0.5); }\n",
+        )
+        .await;
+    let colors = client.document_color(URI).await;
+    assert_eq!(colors.as_array().unwrap().len(), 2);
+
+    let mut labels = Vec::new();
+    for color_info in colors.as_array().unwrap() {
+        let mut color = color_info["color"].clone();
+        color["blue"] = json!(1.0);
+        let response = client
+            .request(
+                "textDocument/colorPresentation",
+                json!({
+                    "textDocument": { "uri": URI },
+                    "color": color,
+                    "range": color_info["range"],
+                }),
+            )
+            .await;
+        labels.push(response["result"][0]["label"].clone());
+    }
+    assert_eq!(labels, ["rgb(255, 0, 255)", "rgba(255, 0, 255, 0.5)"]);
+}
+
+#[tokio::test]
+async fn channel_ranges_target_nudges() {
+    let mut client = TestClient::initialized().await;
+    client
+        .did_open(URI, "a { color: hsl(210 50% 40%); }\n")
+        .await;
+
+    let response = client
+        .request(
+            "chroma/colorChannels",
+            json!({
+                "textDocument": { "uri": URI },
+                "position": { "line": 0, "character": 20 },
+            }),
+        )
+        .await;
+    let channels = &response["result"][0]["channels"];
+    assert_eq!(channels[1]["name"], "saturation");
+    assert_eq!(channels[1]["range"]["start"]["character"], 19);
+    assert_eq!(channels[1]["range"]["end"]["character"], 22);
+
+    let response = client
+        .request(
+            "workspace/executeCommand",
+            json!({
+                "command": "chroma.nudgeColor",
+                "arguments": [{
+                    "textDocument": { "uri": URI },
+                    "position": { "line": 0, "character": 20 },
+                    "step": 10.0,
+                }],
+            }),
+        )
+        .await;
+    assert_eq!(response["result"][0]["newText"], "hsl(210 60% 40%)");
+}
+
+#[tokio::test]
+async fn tailwind_classes_in_class_attributes() {
+    let mut client = TestClient::initialized().await;
+    let uri = "file:///a.html";
+    client
+        .did_open(
+            uri,
+            "<p class=\"bg-red-500 p-4\">text-red-500</p>\n<p class='md:text-white/50'></p>\n",
+        )
+        .await;
+
+    let colors = client.document_color(uri).await;
+    assert_eq!(colors.as_array().unwrap().len(), 2);
+    assert_eq!(colors[0]["range"]["start"]["character"], 10);
+    assert_eq!(colors[0]["range"]["end"]["character"], 20);
+    assert_eq!(colors[1]["range"]["start"]["character"], 13);
+    assert_eq!(colors[1]["color"]["alpha"], 0.5);
+
+    let response = client
+        .request(
+            "textDocument/colorPresentation",
+            json!({
+                "textDocument": { "uri": uri },
+                "color": { "red": 0.0, "green": 0.0, "blue": 0.0, "alpha": 1.0 },
+                "range": colors[0]["range"],
+            }),
+        )
+        .await;
+    assert_eq!(response["result"][0]["label"], "bg-black");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn strict_validation_reports_nonconforming_colors() {
+    let mut client = TestClient::start();
+    client
+        .initialize_with_options(
+            json!({ "textDocument": { "colorProvider": {} } }),
+            json!({ "validation": { "css": "strict" } }),
+        )
+        .await;
+    client
+        .did_open(
+            URI,
+            "a { color: rgb(255 0 0); background: rgb(300, 0%, 0); }\n",
+        )
+        .await;
+
+    let diagnostics = client.notification("textDocument/publishDiagnostics").await;
+    let diagnostics = diagnostics["params"]["diagnostics"].as_array().unwrap();
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(
+        diagnostics[0]["message"],
+        "`300` is out of range for red, expected 0 to 255"
+    );
+    assert_eq!(diagnostics[0]["code"], "nonconforming-color");
+    assert_eq!(diagnostics[0]["range"]["start"]["character"], 37);
+
+    let colors = client.document_color(URI).await;
+    assert_eq!(colors.as_array().unwrap().len(), 1);
+    assert_eq!(colors[0]["range"]["start"]["character"], 11);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn tailwind_config_colors_reload_on_change() {
+    let workspace = Workspace::new("tailwind");
+    let config = workspace.write(
+        "tailwind.config.js",
+        "module.exports = {\n  theme: { extend: { colors: { brand: { 600: '#1557b0' } } } },\n}\n",
+    );
+
+    let mut client = TestClient::start();
+    client
+        .initialize_with_params(json!({
+            "capabilities": {
+                "textDocument": { "colorProvider": {} },
+                "workspace": { "didChangeWatchedFiles": { "dynamicRegistration": true } },
+            },
+            "rootUri": workspace.root_uri(),
+        }))
+        .await;
+    let uri = "file:///a.html";
+    client
+        .did_open(uri, "<p class=\"bg-brand-600 text-red-500\"></p>\n")
+        .await;
+
+    let colors = client.document_color(uri).await;
+    assert_eq!(colors.as_array().unwrap().len(), 2);
+    assert_eq!(colors[0]["range"]["start"]["character"], 10);
+    assert_eq!(colors[0]["color"]["red"], (0x15 as f32 / 255.0) as f64);
+
+    std::fs::write(
+        &config,
+        "module.exports = {\n  theme: { extend: { colors: { brand: { 600: '#ff0000' } } } },\n}\n",
+    )
+    .unwrap();
+    client
+        .notify(
+            "workspace/didChangeWatchedFiles",
+            json!({ "changes": [{ "uri": file_uri(&config), "type": 2 }] }),
+        )
+        .await;
+    let colors = client.document_color(uri).await;
+    assert_eq!(colors[0]["color"]["red"], 1.0);
+}
+
+#[tokio::test]
+async fn unknown_color_report() {
+    let mut client = TestClient::initialized().await;
+    client
+        .did_open(URI, "a { color: #ff00zz; border: 1px solid #fff; }\n")
+        .await;
+
+    let response = client
+        .request(
+            "workspace/executeCommand",
+            json!({
+                "command": "chroma.findUnknownColors",
+                "arguments": [{ "textDocument": { "uri": URI } }],
+            }),
+        )
+        .await;
+    let report = response["result"].as_str().unwrap();
+    assert!(report.starts_with("1 string looks like a color but isn't decorated."));
+    assert!(report.contains("| file:///test.css:1:12 | `#ff00zz` | Malformed hex color |"));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn custom_property_usages_resolve() {
+    let mut client = TestClient::initialized().await;
+    client
+        .did_open(
+            URI,
+            ".a { color: var(--primary); }\n:root { --primary: #336699; }\n",
+        )
+        .await;
+
+    let colors = client.document_color(URI).await;
+    assert_eq!(colors.as_array().unwrap().len(), 2);
+    assert_eq!(colors[0]["range"]["start"]["character"], 12);
+    assert_eq!(colors[0]["range"]["end"]["character"], 26);
+    assert_eq!(colors[0]["color"], colors[1]["color"]);
+
+    let response = client
+        .request(
+            "textDocument/colorPresentation",
+            json!({
+                "textDocument": { "uri": URI },
+                "color": { "red": 0.0, "green": 0.0, "blue": 0.0, "alpha": 1.0 },
+                "range": colors[0]["range"],
+            }),
+        )
+        .await;
+    assert_eq!(response["result"], json!([]));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn imported_variables_resolve_and_reload() {
+    let workspace = Workspace::new("imports");
+    let tokens = workspace.write("_tokens.scss", "$brand: #1a73e8;\n");
+
+    let mut client = TestClient::start();
+    client
+        .initialize_with_params(json!({
+            "capabilities": { "textDocument": { "colorProvider": {} } },
+            "rootUri": workspace.root_uri(),
+        }))
+        .await;
+    let uri = workspace.uri("app.scss");
+    client
+        .did_open(&uri, "@use \"tokens\";\n.a { color: $brand; }\n")
+        .await;
+
+    let colors = client.document_color(&uri).await;
+    assert_eq!(colors.as_array().unwrap().len(), 1);
+    assert_eq!(colors[0]["range"]["start"]["line"], 1);
+    assert_eq!(colors[0]["range"]["start"]["character"], 12);
+    assert_eq!(colors[0]["color"]["red"], (0x1a as f32 / 255.0) as f64);
+
+    std::fs::write(&tokens, "$brand: #ff0000;\n").unwrap();
+    client
+        .notify(
+            "workspace/didChangeWatchedFiles",
+            json!({ "changes": [{ "uri": file_uri(&tokens), "type": 2 }] }),
+        )
+        .await;
+    let colors = client.document_color(&uri).await;
+    assert_eq!(colors[0]["color"]["red"], 1.0);
+
+    std::fs::remove_file(&tokens).unwrap();
+    client
+        .notify(
+            "workspace/didChangeWatchedFiles",
+            json!({ "changes": [{ "uri": file_uri(&tokens), "type": 3 }] }),
+        )
+        .await;
+    let colors = client.document_color(&uri).await;
+    assert_eq!(colors, json!([]));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn references_to_a_color_value() {
+    let workspace = Workspace::new("references");
+    workspace.write("theme.css", "b { color: #336699; fill: #fff; }\n");
+
+    let mut client = TestClient::start();
+    let result = client
+        .initialize_with_params(json!({
+            "capabilities": { "textDocument": { "colorProvider": {} } },
+            "rootUri": workspace.root_uri(),
+        }))
+        .await;
+    assert_eq!(result["capabilities"]["referencesProvider"], true);
+    let uri = workspace.uri("app.css");
+    client
+        .did_open(
+            &uri,
+            ":root { --brand: #336699; }\na { color: var(--brand); border-color: rgb(51 102 153); }\n",
+        )
+        .await;
+
+    let references = |include_declaration: bool| {
+        json!({
+            "textDocument": { "uri": uri },
+            "position": { "line": 0, "character": 20 },
+            "context": { "includeDeclaration": include_declaration },
+        })
+    };
+    let response = client
+        .request("textDocument/references", references(true))
+        .await;
+    let locations: Vec<(String, u64, u64)> = response["result"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|location| {
+            let start = &location["range"]["start"];
+            (
+                location["uri"]
+                    .as_str()
+                    .unwrap()
+                    .rsplit('/')
+                    .next()
+                    .unwrap()
+                    .to_string(),
+                start["line"].as_u64().unwrap(),
+                start["character"].as_u64().unwrap(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        locations,
+        [
+            ("app.css".to_string(), 0, 17),
+            ("app.css".to_string(), 1, 11),
+            ("app.css".to_string(), 1, 39),
+            ("theme.css".to_string(), 0, 11),
+        ]
+    );
+
+    let response = client
+        .request("textDocument/references", references(false))
+        .await;
+    assert_eq!(response["result"].as_array().unwrap().len(), 3);
+}
+
+#[tokio::test]
+async fn memory_budget_evicts_least_recently_used() {
+    let mut client = TestClient::start();
+    client
+        .initialize_with_options(
+            json!({ "textDocument": { "colorProvider": {} } }),
+            json!({ "memoryBudget": 1 }),
+        )
+        .await;
+    for name in ["a", "b", "c"] {
+        client
+            .did_open(&format!("file:///{name}.css"), "a { color: #FF0000; }\n")
+            .await;
+    }
+    let evicted = async |client: &mut TestClient, uri: &str| {
+        let response = client
+            .request("chroma/status", json!({ "textDocument": { "uri": uri } }))
+            .await;
+        response["result"]["documents"][0]["evicted"].clone()
+    };
+    assert_eq!(evicted(&mut client, "file:///a.css").await, true);
+    assert_eq!(evicted(&mut client, "file:///b.css").await, true);
+    assert_eq!(evicted(&mut client, "file:///c.css").await, false);
+
+    let colors = client.document_color("file:///a.css").await;
+    assert_eq!(colors[0]["range"]["start"]["character"], 11);
+    assert_eq!(evicted(&mut client, "file:///a.css").await, false);
+}
+
+#[tokio::test]
+async fn document_color_after_open_and_change() {
+    let mut client = TestClient::initialized().await;
+    client.did_open(URI, "a { color: #FF0000; }\n").await;
+
+    let colors = client.document_color(URI).await;
+    assert_eq!(
+        colors,
+        json!([{
+            "range": {
+                "start": { "line": 0, "character": 11 },
+                "end": { "line": 0, "character": 18 },
+            },
+            "color": { "red": 1.0, "green": 0.0, "blue": 0.0, "alpha": 1.0 },
+        }])
+    );
+
+    client
+        .did_change(
+            URI,
+            1,
+            json!([{
+                "range": {
+                    "start": { "line": 0, "character": 0 },
+                    "end": { "line": 0, "character": 0 },
+                },
+                "text": "\n",
+            }]),
+        )
+        .await;
+    let colors = client.document_color(URI).await;
+    assert_eq!(colors[0]["range"]["start"]["line"], 1);
+}
+
+#[tokio::test]
+async fn full_sync_change_replaces_colors() {
+    let mut client = TestClient::initialized().await;
+    client.did_open(URI, "#FF0000").await;
+    client
+        .did_change(URI, 1, json!([{ "text": "#00FF00 #0000FF" }]))
+        .await;
+
+    let colors = client.document_color(URI).await;
+    assert_eq!(colors.as_array().unwrap().len(), 2);
+    assert_eq!(colors[0]["color"]["green"], 1.0);
+}
+
+#[tokio::test]
+async fn stale_change_drops_document() {
+    let mut client = TestClient::initialized().await;
+    client.did_open(URI, "#FF0000").await;
+    let insert = json!([{
+        "range": {
+            "start": { "line": 0, "character": 0 },
+            "end": { "line": 0, "character": 0 },
+        },
+        "text": "#00FF00 ",
+    }]);
+    // Versions may skip numbers.
+    client.did_change(URI, 5, insert.clone()).await;
+    assert_eq!(
+        client.document_color(URI).await.as_array().unwrap().len(),
+        2
+    );
+
+    client.did_change(URI, 5, insert).await;
+    let response = client
+        .request(
+            "textDocument/documentColor",
+            json!({ "textDocument": { "uri": URI } }),
+        )
+        .await;
+    assert_eq!(response["error"]["code"], -32603);
+
+    client
+        .did_change(URI, 6, json!([{ "text": "#0000FF" }]))
+        .await;
+    let colors = client.document_color(URI).await;
+    assert_eq!(colors[0]["color"]["blue"], 1.0);
+}
+
+#[tokio::test]
+async fn utf8_position_encoding() {
+    let mut client = TestClient::start();
+    let result = client
+        .initialize(json!({
+            "general": { "positionEncodings": ["utf-16", "utf-8"] },
+            "textDocument": { "colorProvider": {} },
+        }))
+        .await;
+    assert_eq!(result["capabilities"]["positionEncoding"], "utf-8");
+
+    // `é` is two bytes but a single UTF-16 code unit.
+    client.did_open(URI, "/* é */ a { color: #FF0000; }").await;
+    let colors = client.document_color(URI).await;
+    assert_eq!(colors[0]["range"]["start"]["character"], 20);
+    assert_eq!(colors[0]["range"]["end"]["character"], 27);
+
+    client
+        .did_change(
+            URI,
+            1,
+            json!([{
+                "range": {
+                    "start": { "line": 0, "character": 21 },
+                    "end": { "line": 0, "character": 27 },
+                },
+                "text": "00FF00",
+            }]),
+        )
+        .await;
+    let colors = client.document_color(URI).await;
+    assert_eq!(colors[0]["color"]["green"], 1.0);
+    assert_eq!(colors[0]["range"]["start"]["character"], 20);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn utf8_positions_in_unopened_files() {
+    let workspace = Workspace::new("utf8-references");
+    let theme = workspace.write("theme.css", "/* é */ b { color: #336699; }\n");
+
+    let mut client = TestClient::start();
+    client
+        .initialize_with_params(json!({
+            "capabilities": {
+                "general": { "positionEncodings": ["utf-8"] },
+                "textDocument": { "colorProvider": {} },
+            },
+            "rootUri": workspace.root_uri(),
+        }))
+        .await;
+    let uri = workspace.uri("app.css");
+    client.did_open(&uri, "a { color: #336699; }\n").await;
+
+    let references = json!({
+        "textDocument": { "uri": uri },
+        "position": { "line": 0, "character": 12 },
+        "context": { "includeDeclaration": true },
+    });
+    let theme_start = |response: &Value| {
+        response["result"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|location| location["uri"] == file_uri(&theme))
+            .unwrap()["range"]["start"]["character"]
+            .clone()
+    };
+    // `é` is two bytes but a single UTF-16 code unit.
+    let response = client
+        .request("textDocument/references", references.clone())
+        .await;
+    assert_eq!(theme_start(&response), 20);
+
+    // The file is read again once the client reports it changed.
+    std::fs::write(&theme, "/* éé */ b { color: #336699; }\n").unwrap();
+    client
+        .notify(
+            "workspace/didChangeWatchedFiles",
+            json!({ "changes": [{ "uri": file_uri(&theme), "type": 2 }] }),
+        )
+        .await;
+    let response = client.request("textDocument/references", references).await;
+    assert_eq!(theme_start(&response), 22);
+}
+
+#[tokio::test]
+async fn burst_of_changes_settles_once() {
+    let mut client = TestClient::initialized().await;
+    let uri = "file:///themes/dim.json";
+    let theme = |background: &str| {
+        format!(
+            r#"{{ "$schema": "vscode://schemas/color-theme", "colors": {{ "editor.background": "{background}" }} }}"#
+        )
+    };
+    client.did_open(uri, &theme("#1E1E1E")).await;
+    client.notification("textDocument/publishDiagnostics").await;
+    client.notifications.clear();
+
+    // Sent together, so the server reads them as one burst.
+    for (version, background) in [(1, "#2E2E2E"), (2, "#3E3E3E"), (3, "#4E4E4E")] {
+        client
+            .did_change(uri, version, json!([{ "text": theme(background) }]))
+            .await;
+    }
+    let colors = client.document_color(uri).await;
+    assert_eq!(colors[0]["color"]["red"], json!(0x4E as f32 / 255.0));
+
+    // Changes publish in order, so skipped versions would come first.
+    let published = client.notification("textDocument/publishDiagnostics").await;
+    assert_eq!(published["params"]["version"], 3);
+}
+
+#[tokio::test]
+async fn document_color_for_unknown_document_is_an_error() {
+    let mut client = TestClient::initialized().await;
+    let response = client
+        .request(
+            "textDocument/documentColor",
+            json!({ "textDocument": { "uri": URI } }),
+        )
+        .await;
+
+    assert_eq!(response["error"]["code"], -32603);
+}
+
+#[tokio::test]
+async fn color_histogram_custom_request() {
+    let mut client = TestClient::initialized().await;
+    client.did_open(URI, "#FF0000 #EE0000").await;
+
+    let response = client
+        .request(
+            "chroma/colorHistogram",
+            json!({ "textDocument": { "uri": URI } }),
+        )
+        .await;
+    assert_eq!(response["result"]["total"], 2);
+}
+
+#[tokio::test]
+async fn status_custom_request() {
+    let mut client = TestClient::initialized().await;
+    client.did_open(URI, "#FF0000\n#00FF0080\n").await;
+
+    let response = client.request("chroma/status", json!({})).await;
+    assert_eq!(response["result"]["schemaVersion"], 1);
+    let document = &response["result"]["documents"][0];
+    assert_eq!(document["uri"], URI);
+    assert_eq!(document["colors"], 2);
+    assert_eq!(document["formats"]["hex"], 2);
+}
+
+#[tokio::test]
+async fn profile_custom_request() {
+    let mut client = TestClient::initialized().await;
+    client
+        .did_open(URI, &"a { color: #FF0000; }\n".repeat(150))
+        .await;
+
+    let response = client
+        .request("chroma/profile", json!({ "textDocument": { "uri": URI } }))
+        .await;
+    let profile = &response["result"];
+    assert_eq!(profile["uri"], URI);
+    // The empty line after the final newline counts.
+    assert_eq!(profile["lines"], 151);
+    assert_eq!(profile["buckets"][1]["startLine"], 100);
+    assert_eq!(profile["buckets"][1]["endLine"], 151);
+    let parsers = profile["parsers"].as_array().unwrap();
+    assert!(parsers.iter().any(|parser| parser["parser"] == "hex"));
+
+    let response = client
+        .request(
+            "chroma/profile",
+            json!({ "textDocument": { "uri": "file:///missing.css" } }),
+        )
+        .await;
+    assert!(response["error"].is_object());
+}
+
+#[tokio::test]
+async fn document_colors_custom_request() {
+    let mut client = TestClient::initialized().await;
+    client
+        .did_open("file:///a.css", "a { color: #FF0000; }\n")
+        .await;
+    client
+        .did_open("file:///b.css", "b { color: #00FF00; fill: #0000FF; }\n")
+        .await;
+
+    let response = client
+        .request(
+            "chroma/documentColors",
+            json!({ "uris": ["file:///b.css", "file:///closed.css", "file:///a.css"] }),
+        )
+        .await;
+    assert_eq!(response["result"]["schemaVersion"], 1);
+    let documents = response["result"]["documents"].as_array().unwrap();
+    assert_eq!(documents.len(), 2);
+    assert_eq!(documents[0]["uri"], "file:///b.css");
+    assert_eq!(documents[0]["colors"].as_array().unwrap().len(), 2);
+    assert_eq!(documents[1]["uri"], "file:///a.css");
+    assert_eq!(
+        documents[1]["colors"],
+        client.document_color("file:///a.css").await
+    );
+}
+
+#[tokio::test]
+async fn export_terminal_theme_command() {
+    let mut client = TestClient::initialized().await;
+    let uri = "file:///themes/dusk.conf";
+    client
+        .did_open(
+            uri,
+            "background #101010\nforeground #E0E0E0\ncolor1 #CC0000\n",
+        )
+        .await;
+
+    let export = |format: &str| {
+        json!({
+            "command": "chroma.exportTerminalTheme",
+            "arguments": [{ "textDocument": { "uri": uri }, "format": format }],
+        })
+    };
+    let response = client
+        .request("workspace/executeCommand", export("kitty"))
+        .await;
+    let kitty = response["result"].as_str().unwrap();
+    assert!(kitty.starts_with("foreground #E0E0E0\nbackground #101010\n"));
+    assert!(kitty.contains("\ncolor1 #CC0000\n"));
+
+    let response = client
+        .request("workspace/executeCommand", export("windowsTerminal"))
+        .await;
+    let scheme: serde_json::Value =
+        serde_json::from_str(response["result"].as_str().unwrap()).unwrap();
+    assert_eq!(scheme["name"], "dusk");
+    assert_eq!(scheme["red"], "#CC0000");
+
+    let response = client
+        .request("workspace/executeCommand", export("xterm"))
+        .await;
+    assert_eq!(response["error"]["code"], -32602);
+}
+
+#[tokio::test]
+async fn rename_custom_property() {
+    let mut client = TestClient::start();
+    let result = client
+        .initialize(json!({
+            "textDocument": { "colorProvider": {}, "rename": { "prepareSupport": true } },
+        }))
+        .await;
+    assert_eq!(
+        result["capabilities"]["renameProvider"]["prepareProvider"],
+        true
+    );
+    client
+        .did_open("file:///theme.css", ":root { --brand: #1a73e8; }\n")
+        .await;
+    client
+        .did_open("file:///app.css", ".a { color: var(--brand); }\n")
+        .await;
+
+    let position = json!({ "line": 0, "character": 18 });
+    let response = client
+        .request(
+            "textDocument/prepareRename",
+            json!({ "textDocument": { "uri": "file:///app.css" }, "position": position }),
+        )
+        .await;
+    assert_eq!(response["result"]["start"]["character"], 16);
+    assert_eq!(response["result"]["end"]["character"], 23);
+
+    let rename = |new_name: &str| {
+        json!({
+            "textDocument": { "uri": "file:///app.css" },
+            "position": position,
+            "newName": new_name,
+        })
+    };
+    let response = client
+        .request("textDocument/rename", rename("primary"))
+        .await;
+    let changes = &response["result"]["changes"];
+    assert_eq!(changes["file:///theme.css"][0]["newText"], "--primary");
+    assert_eq!(
+        changes["file:///theme.css"][0]["range"]["start"]["character"],
+        8
+    );
+    assert_eq!(
+        changes["file:///app.css"][0]["range"]["start"]["character"],
+        16
+    );
+
+    let response = client
+        .request("textDocument/rename", rename("primary color"))
+        .await;
+    assert_eq!(response["error"]["code"], -32602);
+}
+
+#[tokio::test]
+async fn a11y_preset() {
+    let mut client = TestClient::start();
+    client
+        .initialize_with_options(
+            json!({ "textDocument": { "colorProvider": {}, "hover": {} } }),
+            json!({ "preset": "a11y" }),
+        )
+        .await;
+    let uri = "file:///themes/dim.json";
+    client
+        .did_open(
+            uri,
+            r##"{
+  "$schema": "vscode://schemas/color-theme",
+  "colors": {
+    "editor.background": "#1E1E1E",
+    "editor.foreground": "#3A3A3A",
+    "badge.background": "#FF007ACC"
+  }
+}
+"##,
+        )
+        .await;
+
+    // The contrast warning is reported, the `#AARRGGBB` hint isn't.
+    let diagnostics = client.notification("textDocument/publishDiagnostics").await;
+    let diagnostics = diagnostics["params"]["diagnostics"].as_array().unwrap();
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0]["range"]["start"]["line"], 4);
+    assert!(
+        diagnostics[0]["message"]
+            .as_str()
+            .unwrap()
+            .contains("against `editor.background`")
+    );
+
+    let position = json!({ "line": 4, "character": 27 });
+    let response = client
+        .request(
+            "textDocument/hover",
+            json!({ "textDocument": { "uri": uri }, "position": position }),
+        )
+        .await;
+    let hover = response["result"]["contents"]["value"].as_str().unwrap();
+    assert!(hover.contains("| APCA on `#FFFFFF` | `Lc "));
+
+    let response = client
+        .request(
+            "textDocument/codeAction",
+            json!({
+                "textDocument": { "uri": uri },
+                "range": { "start": position, "end": position },
+                "context": { "diagnostics": [] },
+            }),
+        )
+        .await;
+    let titles: Vec<&str> = response["result"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .filter_map(|action| action["title"].as_str())
+        .collect();
+    assert!(titles.contains(&"Simulate protanopia: #3A3A3A"));
+    assert!(
+        titles
+            .iter()
+            .any(|title| title.starts_with("Simulate tritanopia"))
+    );
+}
+
+#[tokio::test]
+async fn linked_editing_of_identical_hex_colors() {
+    let mut client = TestClient::initialized().await;
+    client
+        .did_open(
+            URI,
+            "a { color: #1A73E8; }\nb { fill: #1A73E8; stroke: #FF0000; }\n",
+        )
+        .await;
+
+    let linked_at = |line: u32, character: u32| {
+        json!({
+            "textDocument": { "uri": URI },
+            "position": { "line": line, "character": character },
+        })
+    };
+    let response = client
+        .request("textDocument/linkedEditingRange", linked_at(1, 12))
+        .await;
+    let ranges = response["result"]["ranges"].as_array().unwrap();
+    assert_eq!(ranges.len(), 2);
+    assert_eq!(ranges[0]["start"], json!({ "line": 0, "character": 11 }));
+    assert_eq!(ranges[1]["start"], json!({ "line": 1, "character": 10 }));
+    assert_eq!(response["result"]["wordPattern"], "#[0-9A-Fa-f]{0,8}");
+
+    let response = client
+        .request("textDocument/linkedEditingRange", linked_at(1, 30))
+        .await;
+    assert_eq!(response["result"], json!(null));
+}
+
+#[tokio::test]
+async fn document_highlight_of_equal_colors() {
+    let mut client = TestClient::initialized().await;
+    client
+        .did_open(
+            URI,
+            "a { color: #f00; }\nb { fill: rgb(255, 0, 0); stroke: #FF0001; }\nc { color: red; }\n",
+        )
+        .await;
+
+    let response = client
+        .request(
+            "textDocument/documentHighlight",
+            json!({
+                "textDocument": { "uri": URI },
+                "position": { "line": 0, "character": 12 },
+            }),
+        )
+        .await;
+    let starts: Vec<&Value> = response["result"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|highlight| &highlight["range"]["start"])
+        .collect();
+    assert_eq!(
+        starts,
+        [
+            &json!({ "line": 0, "character": 11 }),
+            &json!({ "line": 1, "character": 10 }),
+            &json!({ "line": 2, "character": 11 }),
+        ]
+    );
+    assert_eq!(response["result"][0]["kind"], 1);
+}
+
+#[tokio::test]
+async fn dump_state_command() {
+    let mut client = TestClient::start();
+    client
+        .initialize_with_options(
+            json!({ "textDocument": { "colorProvider": {} } }),
+            json!({ "backgrounds": ["#ffffff"] }),
+        )
+        .await;
+    client.did_open(URI, "a { color: #FF0000; }\n").await;
+    client.document_color(URI).await;
+    let response = client
+        .request(
+            "workspace/executeCommand",
+            json!({ "command": "chroma.generateGradient", "arguments": [] }),
+        )
+        .await;
+    assert_eq!(response["error"]["code"], -32602);
+
+    let response = client
+        .request(
+            "workspace/executeCommand",
+            json!({ "command": "chroma.dumpState" }),
+        )
+        .await;
+    let path = response["result"].as_str().unwrap();
+    let dump: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(path).unwrap()).unwrap();
+    std::fs::remove_file(path).unwrap();
+    assert_eq!(dump["schemaVersion"], 1);
+    assert_eq!(dump["config"], json!({ "backgrounds": ["#ffffff"] }));
+    assert_eq!(dump["documents"][0]["uri"], URI);
+    assert_eq!(dump["documents"][0]["colors"], 1);
+    assert_eq!(
+        dump["recentRequests"][0]["method"],
+        "textDocument/documentColor"
+    );
+    assert_eq!(dump["recentRequests"][0]["uri"], URI);
+    assert!(
+        dump["recentErrors"][0]["message"]
+            .as_str()
+            .unwrap()
+            .starts_with("chroma.generateGradient expects")
+    );
+    // The text of documents is left out.
+    assert!(!dump.to_string().contains("#FF0000"));
+}
+
+#[tokio::test]
+async fn contrast_diagnostics_of_css_rules() {
+    let mut client = TestClient::start();
+    client
+        .initialize_with_options(
+            json!({ "textDocument": { "colorProvider": {} } }),
+            json!({ "contrastDiagnostics": true, "contrastLevel": "AAA" }),
+        )
+        .await;
+    client
+        .did_open(
+            URI,
+            ".hint {\n  color: #767676;\n  background-color: #FFFFFF;\n}\n",
+        )
+        .await;
+
+    let diagnostics = client.notification("textDocument/publishDiagnostics").await;
+    let diagnostics = diagnostics["params"]["diagnostics"].as_array().unwrap();
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0]["range"]["start"]["line"], 1);
+    assert_eq!(
+        diagnostics[0]["message"],
+        "Contrast 4.54:1 against `background-color` (#FFFFFF) is below 7:1"
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn references_skip_unindexed_and_large_files() {
+    let workspace = Workspace::new("references-filter");
+    workspace.write("theme.css", "b { color: #336699; }\n");
+    workspace.write("notes.txt", "#336699\n");
+    workspace.write("generated.css", &"i { color: #336699; }\n".repeat(100));
+
+    let mut client = TestClient::start();
+    client
+        .initialize_with_params(json!({
+            "capabilities": { "textDocument": { "colorProvider": {} } },
+            "initializationOptions": { "maxFileSize": 1000 },
+            "rootUri": workspace.root_uri(),
+        }))
+        .await;
+    let uri = workspace.uri("app.css");
+    client.did_open(&uri, "a { color: #336699; }\n").await;
+
+    let response = client
+        .request(
+            "textDocument/references",
+            json!({
+                "textDocument": { "uri": uri },
+                "position": { "line": 0, "character": 12 },
+                "context": { "includeDeclaration": true },
+            }),
+        )
+        .await;
+    let uris: Vec<&str> = response["result"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|location| location["uri"].as_str().unwrap())
+        .collect();
+    assert_eq!(uris, [uri, workspace.uri("theme.css")]);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn code_lenses_count_uses_and_convert() {
+    let workspace = Workspace::new("code-lens");
+    workspace.write("theme.css", "b { color: #336699; }\n");
+
+    let mut client = TestClient::start();
+    let result = client
+        .initialize_with_params(json!({
+            "capabilities": { "textDocument": { "colorProvider": {} } },
+            "rootUri": workspace.root_uri(),
+        }))
+        .await;
+    assert_eq!(
+        result["capabilities"]["codeLensProvider"]["resolveProvider"],
+        true
+    );
+    let uri = workspace.uri("app.css");
+    client
+        .did_open(
+            &uri,
+            "a { color: #336699; border-color: rgb(51, 102, 153); }\n",
+        )
+        .await;
+
+    let response = client
+        .request(
+            "textDocument/codeLens",
+            json!({ "textDocument": { "uri": uri } }),
+        )
+        .await;
+    let lenses = response["result"].as_array().unwrap();
+    assert_eq!(lenses.len(), 2);
+    assert_eq!(lenses[0]["command"], Value::Null);
+    let convert = &lenses[1]["command"];
+    assert_eq!(convert["title"], "Convert to rgb(51 102 153)");
+
+    let response = client.request("codeLens/resolve", lenses[0].clone()).await;
+    assert_eq!(
+        response["result"]["command"]["title"],
+        "used 3 times in workspace"
+    );
+
+    let response = client
+        .request(
+            "workspace/executeCommand",
+            json!({ "command": convert["command"], "arguments": convert["arguments"] }),
+        )
+        .await;
+    assert_eq!(response["result"], true);
+    let edits = &client.requests[0]["params"]["edit"]["changes"][&uri];
+    assert_eq!(
+        edits,
+        &json!([{
+            "range": {
+                "start": { "line": 0, "character": 11 },
+                "end": { "line": 0, "character": 18 },
+            },
+            "newText": "rgb(51 102 153)",
+        }])
+    );
+}
+
+#[tokio::test]
+async fn convert_all_colors() {
+    let mut client = TestClient::initialized().await;
+    client
+        .did_open(
+            URI,
+            "a { color: #FF0000; fill: rgb(0 0 255); stroke: red; }\n",
+        )
+        .await;
+
+    let response = client
+        .request(
+            "workspace/executeCommand",
+            json!({
+                "command": "chroma.convertAllColors",
+                "arguments": [{ "textDocument": { "uri": URI }, "notation": "hsl" }],
+            }),
+        )
+        .await;
+    let new_texts: Vec<&str> = response["result"]["changes"][URI]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|edit| edit["newText"].as_str().unwrap())
+        .collect();
+    assert_eq!(new_texts, ["hsl(0 100% 50%)", "hsl(240 100% 50%)"]);
+
+    let response = client
+        .request(
+            "textDocument/codeAction",
+            json!({
+                "textDocument": { "uri": URI },
+                "range": { "start": { "line": 0, "character": 0 }, "end": { "line": 0, "character": 0 } },
+                "context": { "diagnostics": [], "only": ["source"] },
+            }),
+        )
+        .await;
+    let titles: Vec<&str> = response["result"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .filter(|action| action["kind"] == "source.convertAllColors")
+        .map(|action| action["title"].as_str().unwrap())
+        .collect();
+    assert_eq!(
+        titles,
+        [
+            "Convert all colors to hex",
+            "Convert all colors to rgb()",
+            "Convert all colors to hsl()",
+            "Convert all colors to oklch()",
+        ]
+    );
+}
+
+#[tokio::test]
+async fn harmony_actions_declare_variables_of_the_language() {
+    let mut client = TestClient::initialized().await;
+    let mut triadic = Vec::new();
+    for uri in ["file:///a.scss", "file:///a.css", "file:///a.py"] {
+        client.did_open(uri, "a {\n  color: #FF0000;\n}\n").await;
+        let response = client
+            .request(
+                "textDocument/codeAction",
+                json!({
+                    "textDocument": { "uri": uri },
+                    "range": { "start": { "line": 1, "character": 12 }, "end": { "line": 1, "character": 12 } },
+                    "context": { "diagnostics": [] },
+                }),
+            )
+            .await;
+        triadic.push(
+            response["result"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .find(|action| action["title"] == "Insert triadic harmony of #FF0000")
+                .map(|action| action["edit"]["changes"][uri][0]["newText"].clone()),
+        );
+    }
+    assert_eq!(
+        triadic,
+        [
+            Some(json!(
+                "\n  $triadic-1: #FF0000;\n  $triadic-2: #00FF00;\n  $triadic-3: #0000FF;"
+            )),
+            Some(json!(
+                "\n  --triadic-1: #FF0000;\n  --triadic-2: #00FF00;\n  --triadic-3: #0000FF;"
+            )),
+            None,
+        ]
+    );
+}
+
+#[tokio::test]
+async fn extract_color_to_custom_property() {
+    let mut client = TestClient::initialized().await;
+    client
+        .did_open(URI, "a { color: #1A73E8; }\nb { fill: #1a73e8; }\n")
+        .await;
+
+    let response = client
+        .request(
+            "textDocument/codeAction",
+            json!({
+                "textDocument": { "uri": URI },
+                "range": { "start": { "line": 1, "character": 12 }, "end": { "line": 1, "character": 12 } },
+                "context": { "diagnostics": [] },
+            }),
+        )
+        .await;
+    let action = response["result"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|action| action["kind"] == "refactor.extract")
+        .unwrap();
+    assert_eq!(action["title"], "Extract to custom property `--color-1`");
+    let new_texts: Vec<&str> = action["edit"]["changes"][URI]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|edit| edit["newText"].as_str().unwrap())
+        .collect();
+    assert_eq!(
+        new_texts,
+        [
+            ":root {\n  --color-1: #1a73e8;\n}\n\n",
+            "var(--color-1)",
+            "var(--color-1)",
+        ]
+    );
+}
+
+#[tokio::test]
+async fn color_name_inlay_hints() {
+    let mut client = TestClient::start();
+    client
+        .initialize_with_options(
+            json!({ "textDocument": { "colorProvider": {} } }),
+            json!({ "colorNameHints": true }),
+        )
+        .await;
+    client
+        .did_open(URI, "a { color: #DC143C; fill: #DC143D; }\n")
+        .await;
+
+    let response = client
+        .request(
+            "textDocument/inlayHint",
+            json!({
+                "textDocument": { "uri": URI },
+                "range": { "start": { "line": 0, "character": 0 }, "end": { "line": 1, "character": 0 } },
+            }),
+        )
+        .await;
+    let hints = response["result"].as_array().unwrap();
+    let labels: Vec<&str> = hints
+        .iter()
+        .map(|hint| hint["label"].as_str().unwrap())
+        .collect();
+    assert_eq!(labels, ["⟵ crimson", "⟵ ~crimson"]);
+    assert_eq!(hints[0]["position"], json!({ "line": 0, "character": 18 }));
+
+    let response = client.request("inlayHint/resolve", hints[0].clone()).await;
+    assert_eq!(
+        response["result"]["tooltip"]["value"],
+        "`#DC143C` is the CSS named color `crimson`."
+    );
+}
+
+#[tokio::test]
+async fn color_definitions_as_document_symbols() {
+    let mut client = TestClient::initialized().await;
+    client
+        .did_open(URI, ":root {\n  --primary: #336699;\n  --gap: 4px;\n}\n")
+        .await;
+
+    let response = client
+        .request(
+            "textDocument/documentSymbol",
+            json!({ "textDocument": { "uri": URI } }),
+        )
+        .await;
+    assert_eq!(
+        response["result"],
+        json!([{
+            "name": "--primary",
+            "detail": "#336699",
+            "kind": 13,
+            "range": { "start": { "line": 1, "character": 2 }, "end": { "line": 1, "character": 21 } },
+            "selectionRange": { "start": { "line": 1, "character": 2 }, "end": { "line": 1, "character": 11 } },
+        }])
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn extract_palette_command() {
+    let workspace = Workspace::new("palette");
+    workspace.write("theme.css", "b { color: #336699; fill: #FFF; }\n");
+
+    let mut client = TestClient::start();
+    client
+        .initialize_with_params(json!({
+            "capabilities": { "textDocument": { "colorProvider": {} } },
+            "rootUri": workspace.root_uri(),
+        }))
+        .await;
+    let uri = workspace.uri("app.css");
+    client
+        .did_open(
+            &uri,
+            "a { color: #336699; border-color: rgb(51 102 153); }\n",
+        )
+        .await;
+
+    let extract =
+        |arguments: Value| json!({ "command": "chroma.extractPalette", "arguments": arguments });
+    let response = client
+        .request("workspace/executeCommand", extract(json!([])))
+        .await;
+    let palette = &response["result"];
+    assert_eq!(palette["schemaVersion"], 1);
+    assert_eq!(palette["colors"].as_array().unwrap().len(), 1);
+    assert_eq!(palette["colors"][0]["hex"], "#336699");
+    assert_eq!(palette["colors"][0]["count"], 2);
+    assert_eq!(palette["colors"][0]["locations"][1]["uri"], uri);
+
+    let response = client
+        .request(
+            "workspace/executeCommand",
+            extract(json!([{ "workspace": true }])),
+        )
+        .await;
+    let counts: Vec<(&str, u64)> = response["result"]["colors"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|color| {
+            (
+                color["hex"].as_str().unwrap(),
+                color["count"].as_u64().unwrap(),
+            )
+        })
+        .collect();
+    assert_eq!(counts, [("#336699", 3), ("#FFFFFF", 1)]);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn workspace_symbols_by_name_and_hex() {
+    let workspace = Workspace::new("symbols");
+    workspace.write(
+        "tokens.css",
+        ":root {\n  --brand-blue: #336699;\n  --brand-red: #CC3333;\n}\n",
+    );
+
+    let mut client = TestClient::start();
+    client
+        .initialize_with_params(json!({
+            "capabilities": { "textDocument": { "colorProvider": {} } },
+            "rootUri": workspace.root_uri(),
+        }))
+        .await;
+    let uri = workspace.uri("app.css");
+    client.did_open(&uri, "a { color: #333; }\n").await;
+
+    let symbols = |response: &Value| -> Vec<(String, String, String)> {
+        response["result"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|symbol| {
+                let file = symbol["location"]["uri"].as_str().unwrap();
+                (
+                    symbol["name"].as_str().unwrap().to_string(),
+                    symbol["containerName"]
+                        .as_str()
+                        .unwrap_or_default()
+                        .to_string(),
+                    file.rsplit('/').next().unwrap().to_string(),
+                )
+            })
+            .collect()
+    };
+    let response = client
+        .request("workspace/symbol", json!({ "query": "brand-blue" }))
+        .await;
+    assert_eq!(
+        symbols(&response),
+        [(
+            "--brand-blue".to_string(),
+            "#336699".to_string(),
+            "tokens.css".to_string()
+        )]
+    );
+
+    let response = client
+        .request("workspace/symbol", json!({ "query": "#33" }))
+        .await;
+    let names: Vec<String> = symbols(&response)
+        .into_iter()
+        .map(|(name, _, file)| format!("{name} {file}"))
+        .collect();
+    assert_eq!(
+        names,
+        [
+            "--brand-blue tokens.css",
+            "#333333 app.css",
+            "#336699 tokens.css",
+        ]
+    );
+}
+
+#[tokio::test]
+async fn tcp_connections_get_their_own_server() {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(transport::tcp(listener));
+
+    let mut clients = Vec::new();
+    for _ in 0..2 {
+        let (read, write) = tokio::net::TcpStream::connect(addr)
+            .await
+            .unwrap()
+            .into_split();
+        let mut client = TestClient::connect(read, write);
+        client
+            .initialize(json!({ "textDocument": { "colorProvider": {} } }))
+            .await;
+        clients.push(client);
+    }
+    clients[0].did_open(URI, "a { color: #FF0000; }\n").await;
+
+    let document_colors = json!({ "textDocument": { "uri": URI } });
+    let response = clients[0]
+        .request("textDocument/documentColor", document_colors.clone())
+        .await;
+    assert_eq!(response["result"].as_array().unwrap().len(), 1);
+    // The document is only open for the first client.
+    let response = clients[1]
+        .request("textDocument/documentColor", document_colors)
+        .await;
+    assert_eq!(response["error"]["code"], -32603);
+}
+
+#[tokio::test]
+async fn aliases_from_initialization_options() {
+    let mut client = TestClient::start();
+    let result = client
+        .initialize_with_options(
+            json!({ "textDocument": { "colorProvider": {} } }),
+            json!({ "aliases": { "brand-blue": "#1a73e8" } }),
+        )
+        .await;
+    assert!(result["capabilities"]["completionProvider"].is_object());
+
+    client.did_open(URI, "a { color: brand-blue; }\n").await;
+    let colors = client.document_color(URI).await;
+    assert_eq!(colors[0]["range"]["start"]["character"], 11);
+    assert_eq!(colors[0]["range"]["end"]["character"], 21);
+
+    let response = client
+        .request(
+            "textDocument/colorPresentation",
+            json!({
+                "textDocument": { "uri": URI },
+                "color": colors[0]["color"],
+                "range": colors[0]["range"],
+            }),
+        )
+        .await;
+    assert_eq!(
+        response["result"],
+        json!([{ "label": "#1A73E8" }, { "label": "brand-blue" }])
+    );
+}
+
+#[tokio::test]
+async fn named_color_code_actions() {
+    let mut client = TestClient::initialized().await;
+    client
+        .did_open(URI, "a { color: rebeccapurple; background: #00FF00; }\n")
+        .await;
+
+    let response = client
+        .request(
+            "textDocument/codeAction",
+            json!({
+                "textDocument": { "uri": URI },
+                "range": {
+                    "start": { "line": 0, "character": 0 },
+                    "end": { "line": 0, "character": 48 },
+                },
+                "context": { "diagnostics": [] },
+            }),
+        )
+        .await;
+    let titles: Vec<&str> = response["result"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .filter_map(|action| action["title"].as_str())
+        .filter(|title| title.starts_with("Replace with"))
+        .collect();
+    assert_eq!(titles, ["Replace with #663399", "Replace with lime"]);
+}
+
+#[tokio::test]
+async fn named_colors_per_language() {
+    let mut client = TestClient::initialized().await;
+    client
+        .did_open(URI, "a { color: tomato; } /* information */\n")
+        .await;
+    let colors = client.document_color(URI).await;
+    assert_eq!(colors.as_array().unwrap().len(), 1);
+    assert_eq!(colors[0]["range"]["start"]["character"], 11);
+
+    let mut client = TestClient::start();
+    client
+        .initialize_with_options(
+            json!({ "textDocument": { "colorProvider": {} } }),
+            json!({ "namedColors": ["md"] }),
+        )
+        .await;
+    client.did_open(URI, "a { color: tomato; }\n").await;
+    client.did_open("file:///notes.md", "Use tomato.\n").await;
+    assert_eq!(client.document_color(URI).await, json!([]));
+    let colors = client.document_color("file:///notes.md").await;
+    assert_eq!(colors[0]["range"]["start"]["character"], 4);
+}
+
+#[tokio::test]
+async fn snap_code_actions() {
+    let mut client = TestClient::start();
+    client
+        .initialize_with_options(
+            json!({ "textDocument": { "colorProvider": {} } }),
+            json!({ "snapBitDepth": 2 }),
+        )
+        .await;
+    client.did_open(URI, "#1A73E8").await;
+
+    let response = client
+        .request(
+            "textDocument/codeAction",
+            json!({
+                "textDocument": { "uri": URI },
+                "range": {
+                    "start": { "line": 0, "character": 0 },
+                    "end": { "line": 0, "character": 0 },
+                },
+                "context": { "diagnostics": [] },
+            }),
+        )
+        .await;
+    let titles: Vec<&str> = response["result"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .filter_map(|action| action["title"].as_str())
+        .filter(|title| title.starts_with("Snap to"))
+        .collect();
+    assert_eq!(
+        titles,
+        [
+            "Snap to web-safe palette: #3366FF",
+            "Snap to 4-bit channels: #2277EE",
+            "Snap to 2-bit channels: #0055FF",
+        ]
+    );
+}
+
+#[tokio::test]
+async fn imported_palette_snap_code_action() {
+    let workspace = Workspace::new("palette-import");
+    let path = workspace.write(
+        "brand.gpl",
+        "GIMP Palette\nName: Brand\n#\n 26 115 232\tBlue\n255 255 255\tWhite\n",
+    );
+
+    let mut client = TestClient::initialized().await;
+    let response = client
+        .request(
+            "workspace/executeCommand",
+            json!({ "command": "chroma.importPalette", "arguments": [path] }),
+        )
+        .await;
+    assert_eq!(response["result"], json!({ "name": "Brand", "colors": 2 }));
+
+    client.did_open(URI, "a { color: #2070E0; }").await;
+    let response = client
+        .request(
+            "textDocument/codeAction",
+            json!({
+                "textDocument": { "uri": URI },
+                "range": {
+                    "start": { "line": 0, "character": 12 },
+                    "end": { "line": 0, "character": 12 },
+                },
+                "context": { "diagnostics": [] },
+            }),
+        )
+        .await;
+    let titles: Vec<&str> = response["result"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .filter_map(|action| action["title"].as_str())
+        .filter(|title| title.starts_with("Snap to Brand"))
+        .collect();
+    assert_eq!(titles, ["Snap to Brand (Blue): #1A73E8"]);
+}
+
+#[tokio::test]
+async fn normalize_on_save() {
+    let mut client = TestClient::start();
+    let result = client
+        .initialize_with_options(
+            json!({ "textDocument": { "colorProvider": {} } }),
+            json!({ "normalizeOnSave": "lowerHex" }),
+        )
+        .await;
+    assert_eq!(
+        result["capabilities"]["textDocumentSync"]["willSaveWaitUntil"],
+        true
+    );
+    client.did_open(URI, "a { color: #1A73E8; }").await;
+
+    let response = client
+        .request(
+            "textDocument/willSaveWaitUntil",
+            json!({ "textDocument": { "uri": URI }, "reason": 1 }),
+        )
+        .await;
+    assert_eq!(response["result"][0]["newText"], "#1a73e8");
+    assert_eq!(response["result"][0]["range"]["start"]["character"], 11);
+}
+
+#[tokio::test]
+async fn bare_values_of_color_keys_in_toml() {
+    let mut client = TestClient::initialized().await;
+    let uri = "file:///theme.toml";
+    client
+        .did_open(
+            uri,
+            "[palette]\nfg = \"1a73e8\"\nbg = \"black\"\nname = \"red\"\n",
+        )
+        .await;
+
+    let colors = client.document_color(uri).await;
+    let lines: Vec<_> = colors
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|color| color["range"]["start"]["line"].clone())
+        .collect();
+    assert_eq!(lines, [1, 2]);
+}
+
+#[tokio::test]
+async fn starship_profile() {
+    let mut client = TestClient::initialized().await;
+    let uri = "file:///home/user/.config/starship.toml";
+    client
+        .did_open(
+            uri,
+            "[git_branch]\nstyle = \"bold fg:208\"\nsymbol = \"red\"\n",
+        )
+        .await;
+
+    let colors = client.document_color(uri).await;
+    assert_eq!(colors.as_array().unwrap().len(), 1);
+    assert_eq!(
+        colors[0]["range"]["start"],
+        json!({ "line": 1, "character": 17 })
+    );
+}
+
+#[tokio::test]
+async fn presentation_keeps_alacritty_spelling() {
+    let mut client = TestClient::initialized().await;
+    let uri = "file:///home/user/.config/alacritty/alacritty.toml";
+    client
+        .did_open(uri, "[colors.primary]\nbackground = '0x1d2021'\n")
+        .await;
+
+    let colors = client.document_color(uri).await;
+    let response = client
+        .request(
+            "textDocument/colorPresentation",
+            json!({
+                "textDocument": { "uri": uri },
+                "color": { "red": 1.0, "green": 0.0, "blue": 0.0, "alpha": 1.0 },
+                "range": colors[0]["range"],
+            }),
+        )
+        .await;
+    assert_eq!(response["result"][0]["label"], "0xff0000");
+}
+
+#[tokio::test]
+async fn json_schema_color_fields() {
+    let mut client = TestClient::initialized().await;
+    let uri = "file:///app/manifest.json";
+    client
+        .did_open(
+            uri,
+            "{\n  \"$schema\": \"https://json.schemastore.org/web-manifest-combined.json\",\n  \"name\": \"#1a73e8\",\n  \"theme_color\": \"#1a73e8\",\n  \"background_color\": \"navy-ish\"\n}\n",
+        )
+        .await;
+
+    let colors = client.document_color(uri).await;
+    assert_eq!(colors.as_array().unwrap().len(), 1);
+    assert_eq!(colors[0]["range"]["start"]["line"], 3);
+
+    let diagnostics = client.notification("textDocument/publishDiagnostics").await;
+    assert_eq!(
+        diagnostics["params"]["diagnostics"][0]["message"],
+        "`background_color` expects a color, found `navy-ish`"
+    );
+}
+
+#[tokio::test]
+async fn vscode_theme_contrast_report() {
+    let mut client = TestClient::initialized().await;
+    let uri = "file:///themes/dark.color-theme.json";
+    client
+        .did_open(
+            uri,
+            r##"{
+  "colors": { "editor.background": "#1E1E1E" },
+  "tokenColors": [{ "scope": "comment", "settings": { "foreground": "#3A3A3A" } }]
+}
+"##,
+        )
+        .await;
+
+    let response = client
+        .request(
+            "textDocument/codeAction",
+            json!({
+                "textDocument": { "uri": uri },
+                "range": {
+                    "start": { "line": 0, "character": 0 },
+                    "end": { "line": 0, "character": 0 },
+                },
+                "context": { "diagnostics": [] },
+            }),
+        )
+        .await;
+    let command = response["result"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|action| action["title"] == "Check theme contrast")
+        .expect("contrast report must be offered for VS Code themes")
+        .clone();
+
+    let report = client
+        .request(
+            "workspace/executeCommand",
+            json!({ "command": command["command"], "arguments": command["arguments"] }),
+        )
+        .await;
+    assert!(
+        report["result"]
+            .as_str()
+            .unwrap()
+            .contains("| `foreground` #3A3A3A | `editor.background` #1E1E1E |")
+    );
+}
+
+#[tokio::test]
+async fn svg_gradient_reference_definition() {
+    let mut client = TestClient::initialized().await;
+    let uri = "file:///icon.svg";
+    client
+        .did_open(
+            uri,
+            "<linearGradient id=\"sky\"><stop stop-color=\"#87CEEB\"/></linearGradient>\n<rect fill=\"url(#sky)\"/>\n",
+        )
+        .await;
+
+    let response = client
+        .request(
+            "textDocument/definition",
+            json!({
+                "textDocument": { "uri": uri },
+                "position": { "line": 1, "character": 18 },
+            }),
+        )
+        .await;
+    assert_eq!(
+        response["result"]["range"],
+        json!({
+            "start": { "line": 0, "character": 20 },
+            "end": { "line": 0, "character": 23 },
+        })
+    );
+}
+
+#[tokio::test]
+async fn ios_colorset_components() {
+    let mut client = TestClient::initialized().await;
+    let uri = "file:///App/Assets.xcassets/Brand.colorset/Contents.json";
+    client
+        .did_open(
+            uri,
+            "{\n  \"colors\" : [ { \"color\" : {\n    \"components\" : {\n      \"red\" : \"0x1A\",\n      \"green\" : \"0x73\",\n      \"blue\" : \"0xE8\"\n    }\n  } } ]\n}\n",
+        )
+        .await;
+
+    let colors = client.document_color(uri).await;
+    assert_eq!(colors.as_array().unwrap().len(), 1);
+    assert_eq!(colors[0]["range"]["start"]["line"], 2);
+
+    client
+        .did_change(
+            uri,
+            2,
+            json!([{
+                "range": {
+                    "start": { "line": 3, "character": 17 },
+                    "end": { "line": 3, "character": 19 },
+                },
+                "text": "FF",
+            }]),
+        )
+        .await;
+    let colors = client.document_color(uri).await;
+    assert_eq!(colors[0]["color"]["red"], 1.0);
+
+    let response = client
+        .request(
+            "textDocument/colorPresentation",
+            json!({
+                "textDocument": { "uri": uri },
+                "color": { "red": 0.0, "green": 0.0, "blue": 0.0, "alpha": 1.0 },
+                "range": colors[0]["range"],
+            }),
+        )
+        .await;
+    let edits = &response["result"][0]["additionalTextEdits"];
+    assert_eq!(edits.as_array().unwrap().len(), 3);
+    assert_eq!(edits[0]["newText"], "0x00");
+}
+
+#[tokio::test]
+async fn scss_functions_follow_variables() {
+    let mut client = TestClient::initialized().await;
+    let uri = "file:///styles/buttons.scss";
+    client
+        .did_open(
+            uri,
+            "$brand: #000000;\n.button {\n  color: rgba($brand, .5);\n}\n",
+        )
+        .await;
+
+    let colors = client.document_color(uri).await;
+    assert_eq!(colors.as_array().unwrap().len(), 2);
+    assert_eq!(colors[1]["range"]["start"]["line"], 2);
+    assert_eq!(colors[1]["color"]["alpha"], 0.5);
+
+    client
+        .did_change(
+            uri,
+            2,
+            json!([{
+                "range": {
+                    "start": { "line": 0, "character": 9 },
+                    "end": { "line": 0, "character": 15 },
+                },
+                "text": "FFFFFF",
+            }]),
+        )
+        .await;
+    let colors = client.document_color(uri).await;
+    assert_eq!(colors[1]["color"]["red"], 1.0);
+}
+
+#[tokio::test]
+async fn duplicate_colors_report() {
+    let mut client = TestClient::initialized().await;
+    client
+        .did_open(
+            "file:///web/tokens.css",
+            ":root {\n  --brand: #1a73e8;\n  --link: #1A73E8;\n}\n",
+        )
+        .await;
+    client
+        .did_open(
+            "file:///app/src/main/res/values/colors.xml",
+            "<resources>\n  <color name=\"primary\">#1A73E8</color>\n  <color name=\"accent\">#FF5722</color>\n</resources>\n",
+        )
+        .await;
+
+    let report = client
+        .request(
+            "workspace/executeCommand",
+            json!({ "command": "chroma.findDuplicateColors", "arguments": [] }),
+        )
+        .await;
+    let report = report["result"].as_str().unwrap();
+    assert!(report.starts_with("1 color is defined under more than one name."));
+    assert!(report.contains("| #1A73E8 | `primary`, `--brand`, `--link` |"));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn unused_color_variables() {
+    let mut client = TestClient::initialized().await;
+    client
+        .did_open(
+            "file:///web/tokens.css",
+            ":root {\n  --brand: #1a73e8;\n  --unused: #ff5722;\n}\n",
+        )
+        .await;
+    client
+        .did_open("file:///web/app.css", "a { color: var(--brand); }\n")
+        .await;
+
+    let report = client
+        .request(
+            "workspace/executeCommand",
+            json!({ "command": "chroma.findUnusedColors", "arguments": [] }),
+        )
+        .await;
+    let report = report["result"].as_str().unwrap();
+    assert!(report.starts_with("1 color variable is never referenced."));
+    assert!(report.contains("| `--unused` | #FF5722 | file:///web/tokens.css:3 |"));
+
+    let diagnostic = json!({
+        "range": {
+            "start": { "line": 2, "character": 2 },
+            "end": { "line": 2, "character": 10 },
+        },
+        "code": "unused-color",
+        "message": "`--unused` is never referenced",
+    });
+    let response = client
+        .request(
+            "textDocument/codeAction",
+            json!({
+                "textDocument": { "uri": "file:///web/tokens.css" },
+                "range": diagnostic["range"],
+                "context": { "diagnostics": [diagnostic] },
+            }),
+        )
+        .await;
+    let action = response["result"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|action| action["title"] == "Remove unused `--unused`")
+        .unwrap();
+    assert_eq!(action["kind"], "quickfix");
+    let edit = &action["edit"]["changes"]["file:///web/tokens.css"][0];
+    assert_eq!(edit["range"]["start"], json!({ "line": 2, "character": 0 }));
+    assert_eq!(edit["range"]["end"], json!({ "line": 3, "character": 0 }));
+    assert_eq!(edit["newText"], "");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn missing_references_suggest_names() {
+    let mut client = TestClient::initialized().await;
+    client
+        .did_open(
+            "file:///web/tokens.css",
+            ":root {\n  --brand: #1a73e8;\n  --gap: 4px;\n}\n",
+        )
+        .await;
+    client
+        .did_open(
+            "file:///web/app.css",
+            "a {\n  color: var(--brnad);\n  margin: var(--gap);\n}\n",
+        )
+        .await;
+
+    let diagnostics = client.notification("textDocument/publishDiagnostics").await;
+    assert_eq!(diagnostics["params"]["uri"], "file:///web/app.css");
+    let diagnostics = diagnostics["params"]["diagnostics"].as_array().unwrap();
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(
+        diagnostics[0]["message"],
+        "`--brnad` is not declared; did you mean `--brand`?"
+    );
+    assert_eq!(diagnostics[0]["code"], "missing-reference");
+    assert_eq!(
+        diagnostics[0]["range"]["start"],
+        json!({ "line": 1, "character": 13 })
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn document_links() {
+    let workspace = Workspace::new("links");
+    workspace.write("_colors.scss", "$brand: #1a73e8;\n");
+
+    let mut client = TestClient::initialized().await;
+    client
+        .did_open(
+            "file:///design/colors.tokens.json",
+            "{\n  \"color\": {\n    \"primary\": { \"$value\": \"#1a73e8\" },\n    \"link\": { \"$value\": \"{color.primary}\" }\n  }\n}\n",
+        )
+        .await;
+    client
+        .did_open(
+            "file:///app/src/main/res/values/colors.xml",
+            "<resources>\n  <color name=\"accent\">#FF5722</color>\n  <color name=\"tint\">@color/accent</color>\n</resources>\n",
+        )
+        .await;
+    let stylesheet = workspace.uri("main.scss");
+    client
+        .did_open(&stylesheet, "@use \"colors\";\n@import \"missing\";\n")
+        .await;
+
+    let links = |response: Value| response["result"].as_array().unwrap().clone();
+    let response = client
+        .request(
+            "textDocument/documentLink",
+            json!({ "textDocument": { "uri": "file:///design/colors.tokens.json" } }),
+        )
+        .await;
+    let token_links = links(response);
+    assert_eq!(token_links.len(), 1);
+    assert_eq!(
+        token_links[0]["target"],
+        "file:///design/colors.tokens.json#L3,5"
+    );
+    assert_eq!(
+        token_links[0]["range"]["start"],
+        json!({ "line": 3, "character": 25 })
+    );
+
+    let response = client
+        .request(
+            "textDocument/documentLink",
+            json!({ "textDocument": { "uri": "file:///app/src/main/res/values/colors.xml" } }),
+        )
+        .await;
+    let resource_links = links(response);
+    assert_eq!(resource_links.len(), 1);
+    assert_eq!(
+        resource_links[0]["target"],
+        "file:///app/src/main/res/values/colors.xml#L2,16"
+    );
+
+    let response = client
+        .request(
+            "textDocument/documentLink",
+            json!({ "textDocument": { "uri": stylesheet } }),
+        )
+        .await;
+    let import_links = links(response);
+    assert_eq!(import_links.len(), 1);
+    assert_eq!(import_links[0]["target"], workspace.uri("_colors.scss"));
+
+    let response = client
+        .request(
+            "textDocument/definition",
+            json!({
+                "textDocument": { "uri": "file:///design/colors.tokens.json" },
+                "position": { "line": 3, "character": 26 },
+            }),
+        )
+        .await;
+    assert_eq!(
+        response["result"]["range"]["start"],
+        json!({ "line": 2, "character": 4 })
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn change_to_unknown_document() {
+    let mut client = TestClient::initialized().await;
+    client
+        .did_change(
+            "file:///closed.css",
+            2,
+            json!([{
+                "range": {
+                    "start": { "line": 0, "character": 0 },
+                    "end": { "line": 0, "character": 0 },
+                },
+                "text": "#fff",
+            }]),
+        )
+        .await;
+    let message = client.notification("window/logMessage").await;
+    assert_eq!(
+        message["params"]["message"],
+        "Ignoring didChange for unknown document file:///closed.css"
+    );
+    assert!(client.document_color("file:///closed.css").await.is_null());
+
+    client
+        .did_change(
+            "file:///reopened.css",
+            3,
+            json!([{ "text": "a { color: #ff0000; }\n" }]),
+        )
+        .await;
+    let colors = client.document_color("file:///reopened.css").await;
+    assert_eq!(colors[0]["range"]["start"]["character"], 11);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn color_monikers() {
+    let mut client = TestClient::initialized().await;
+    client
+        .did_open(
+            "file:///a.css",
+            "a { color: #ff0000; }\nb { color: #FF0000; }\n",
+        )
+        .await;
+    let response = client
+        .request(
+            "chroma/colorMonikers",
+            json!({ "textDocument": { "uri": "file:///a.css" } }),
+        )
+        .await;
+    let colors = response["result"]["colors"].as_array().unwrap();
+    assert_eq!(colors.len(), 2);
+    assert_eq!(colors[0]["id"], colors[1]["id"]);
+    assert_eq!(
+        colors[1]["range"]["start"],
+        json!({ "line": 1, "character": 11 })
+    );
+    let id = colors[0]["id"].as_str().unwrap();
+    assert_eq!(response["result"]["swatches"][id]["red"], 1.0);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn android_color_resource_references() {
+    let workspace = Workspace::new("android");
+    workspace.write(
+        "res/values/colors.xml",
+        "<resources>\n  <color name=\"scrim\">#801A73E8</color>\n</resources>\n",
+    );
+
+    let mut client = TestClient::initialized().await;
+    let uri = workspace.uri("res/layout/main.xml");
+    client
+        .did_open(&uri, "<View android:background=\"@color/scrim\"/>\n")
+        .await;
+
+    let colors = client.document_color(&uri).await;
+    assert_eq!(colors[0]["range"]["start"]["character"], 26);
+    let alpha = colors[0]["color"]["alpha"].as_f64().unwrap();
+    assert_eq!((alpha * 255.0).round(), 128.0);
+
+    let response = client
+        .request(
+            "textDocument/definition",
+            json!({
+                "textDocument": { "uri": uri },
+                "position": { "line": 0, "character": 30 },
+            }),
+        )
+        .await;
+    assert_eq!(
+        response["result"]["uri"],
+        workspace.uri("res/values/colors.xml")
+    );
+    assert_eq!(response["result"]["range"]["start"]["line"], 1);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn android_resource_rename_across_modules() {
+    let workspace = Workspace::new("modules");
+    let colors_xml = workspace.write(
+        "lib/src/main/res/values/colors.xml",
+        "<color name=\"brand\">#1A73E8</color>\n",
+    );
+    let layout_xml = workspace.write(
+        "app/src/main/res/layout/main.xml",
+        "<View android:tint=\"@color/brand\"/>\n",
+    );
+
+    let mut client = TestClient::start();
+    client
+        .initialize_with_params(json!({
+            "capabilities": { "textDocument": { "colorProvider": {} } },
+            "rootUri": workspace.root_uri(),
+        }))
+        .await;
+    let kotlin_uri = "file:///app/src/main/java/Theme.kt";
+    client
+        .did_open(kotlin_uri, "val tint = getColor(R.color.brand)\n")
+        .await;
+
+    let colors = client.document_color(kotlin_uri).await;
+    assert_eq!(colors[0]["range"]["start"]["character"], 20);
+
+    let response = client
+        .request(
+            "textDocument/rename",
+            json!({
+                "textDocument": { "uri": kotlin_uri },
+                "position": { "line": 0, "character": 30 },
+                "newName": "brand_primary",
+            }),
+        )
+        .await;
+    let changes = &response["result"]["changes"];
+    assert_eq!(changes.as_object().unwrap().len(), 3);
+    assert_eq!(
+        changes[file_uri(&colors_xml)][0]["range"]["start"],
+        json!({ "line": 0, "character": 13 })
+    );
+    assert_eq!(
+        changes[file_uri(&layout_xml)][0]["newText"],
+        "brand_primary"
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn compose_theme_object_references() {
+    let workspace = Workspace::new("compose");
+    let colors_kt = workspace.write(
+        "app/src/main/java/ui/Color.kt",
+        "object AppColors {\n    val primary = Color(0xFF1A73E8)\n}\n",
+    );
+
+    let mut client = TestClient::start();
+    client
+        .initialize_with_params(json!({
+            "capabilities": { "textDocument": { "colorProvider": {} } },
+            "rootUri": workspace.root_uri(),
+        }))
+        .await;
+    let screen_uri = "file:///app/src/main/java/ui/Screen.kt";
+    client
+        .did_open(
+            screen_uri,
+            "Text(color = AppColors.primary, background = Color(0x801A73E8))\n",
+        )
+        .await;
+
+    let colors = client.document_color(screen_uri).await;
+    assert_eq!(colors.as_array().unwrap().len(), 2);
+    assert_eq!(colors[0]["range"]["start"]["character"], 13);
+    let alpha = colors[1]["color"]["alpha"].as_f64().unwrap();
+    assert_eq!((alpha * 255.0).round(), 128.0);
+
+    let response = client
+        .request(
+            "textDocument/hover",
+            json!({
+                "textDocument": { "uri": screen_uri },
+                "position": { "line": 0, "character": 20 },
+            }),
+        )
+        .await;
+    assert_eq!(
+        response["result"]["contents"]["value"],
+        "**AppColors.primary** `#1A73E8`"
+    );
+
+    let response = client
+        .request(
+            "textDocument/definition",
+            json!({
+                "textDocument": { "uri": screen_uri },
+                "position": { "line": 0, "character": 20 },
+            }),
+        )
+        .await;
+    assert_eq!(response["result"]["uri"], file_uri(&colors_kt));
+    assert_eq!(
+        response["result"]["range"]["start"],
+        json!({ "line": 1, "character": 8 })
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn large_document_is_processed_on_blocking_pool() {
+    let mut client = TestClient::initialized().await;
+    let text = "a { color: #FF0000; }\n".repeat(10_000);
+    client.did_open(URI, &text).await;
+
+    let colors = client.document_color(URI).await;
+    assert_eq!(colors.as_array().unwrap().len(), 10_000);
+    assert_eq!(colors[9_999]["range"]["start"]["line"], 9_999);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn colors_follow_changes_received_mid_computation() {
+    let mut client = TestClient::initialized().await;
+    let text = "a { color: #FF0000; }\n".repeat(6_000);
+    client.did_open(URI, &text).await;
+    let insert = |text: &str| {
+        json!([{
+            "range": {
+                "start": { "line": 0, "character": 0 },
+                "end": { "line": 0, "character": 0 },
+            },
+            "text": text,
+        }])
+    };
+
+    // The second change may land while the colors of the first are computed.
+    client.did_change(URI, 2, insert("x")).await;
+    let id = client
+        .send_request(
+            "textDocument/documentColor",
+            json!({ "textDocument": { "uri": URI } }),
+        )
+        .await;
+    client.did_change(URI, 3, insert("yy")).await;
+    let response = client.response(id).await;
+    let colors = response["result"].as_array().unwrap();
+    assert_eq!(colors.len(), 6_000);
+    assert_eq!(colors[0]["range"]["start"]["character"], 14);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn large_full_change_is_processed_on_blocking_pool() {
+    let mut client = TestClient::initialized().await;
+    client.did_open(URI, "a { color: #FF0000; }\n").await;
+    let text = "a { color: #00FF00; }\n".repeat(12_000);
+    client.did_change(URI, 1, json!([{ "text": text }])).await;
+
+    let colors = client.document_color(URI).await;
+    assert_eq!(colors.as_array().unwrap().len(), 12_000);
+    assert_eq!(colors[11_999]["color"]["green"], 1.0);
+
+    // Rebuilds a closed document too.
+    client
+        .notify(
+            "textDocument/didClose",
+            json!({ "textDocument": { "uri": URI } }),
+        )
+        .await;
+    client.did_change(URI, 2, json!([{ "text": text }])).await;
+    let colors = client.document_color(URI).await;
+    assert_eq!(colors.as_array().unwrap().len(), 12_000);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn changes_after_large_full_change_apply_to_its_text() {
+    let mut client = TestClient::initialized().await;
+    client.did_open(URI, "a { color: #FF0000; }\n").await;
+    let text = "a { color: #00FF00; }\n".repeat(12_000);
+    client.did_change(URI, 2, json!([{ "text": text }])).await;
+    // Sent while the full change is parsed outside the documents lock.
+    client
+        .did_change(
+            URI,
+            3,
+            json!([{
+                "range": {
+                    "start": { "line": 11_999, "character": 11 },
+                    "end": { "line": 11_999, "character": 18 },
+                },
+                "text": "#0000FF",
+            }]),
+        )
+        .await;
+
+    let colors = client.document_color(URI).await;
+    assert_eq!(colors.as_array().unwrap().len(), 12_000);
+    assert_eq!(colors[0]["color"]["green"], 1.0);
+    assert_eq!(colors[11_999]["color"]["blue"], 1.0);
+}
+
+#[tokio::test]
+async fn viewport_colors_custom_request() {
+    let mut client = TestClient::initialized().await;
+    client
+        .did_open(URI, "#FF0000\n#00FF00\n#0000FF\n#FFFFFF\n")
+        .await;
+
+    let response = client
+        .request(
+            "chroma/viewportColors",
+            json!({
+                "textDocument": { "uri": URI },
+                "range": {
+                    "start": { "line": 2, "character": 0 },
+                    "end": { "line": 3, "character": 0 },
+                },
+            }),
+        )
+        .await;
+    let lines: Vec<_> = response["result"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|c| c["range"]["start"]["line"].as_u64().unwrap())
+        .collect();
+    assert_eq!(lines, [2, 3]);
+}
+
+#[tokio::test]
+async fn colors_delta_reports_changes_since_previous_call() {
+    let mut client = TestClient::initialized().await;
+    client.did_open(URI, "#FF0000\n#00FF00\n").await;
+
+    let params = json!({ "textDocument": { "uri": URI } });
+    let first = client.request("chroma/colorsDelta", params.clone()).await;
+    assert_eq!(first["result"]["added"].as_array().unwrap().len(), 2);
+    assert_eq!(first["result"]["removed"], json!([]));
+
+    client
+        .did_change(URI, 1, json!([{ "text": "#FF0000\n#0000FF\n" }]))
+        .await;
+    let second = client.request("chroma/colorsDelta", params).await;
+    assert_eq!(second["result"]["added"][0]["color"]["blue"], 1.0);
+    assert_eq!(second["result"]["removed"][0]["color"]["green"], 1.0);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn reindex_workspace() {
+    let workspace = Workspace::new("reindex");
+    let tokens_css = workspace.write("styles/tokens.css", ":root { --brand: #1a73e8; }\n");
+
+    let mut client = TestClient::start();
+    client
+        .initialize_with_params(json!({
+            "capabilities": {
+                "textDocument": { "colorProvider": {} },
+                "window": { "workDoneProgress": true },
+            },
+            "initializationOptions": { "indexConcurrency": 1 },
+            "rootUri": workspace.root_uri(),
+        }))
+        .await;
+    client
+        .did_open("file:///web/app.css", "a { color: var(--late); }\n")
+        .await;
+    let published = client.notification("textDocument/publishDiagnostics").await;
+    assert_eq!(
+        published["params"]["diagnostics"].as_array().unwrap().len(),
+        1
+    );
+
+    // Changed outside the editor, so only a rescan picks it up.
+    std::fs::write(
+        &tokens_css,
+        ":root { --brand: #1a73e8; --late: #ff5722; }\n",
+    )
+    .unwrap();
+    // Cancelled as soon as the client learns of the token.
+    let id = client
+        .send_request(
+            "workspace/executeCommand",
+            json!({ "command": "chroma.reindexWorkspace" }),
+        )
+        .await;
+    let create = client
+        .server_request("window/workDoneProgress/create")
+        .await;
+    client
+        .notify(
+            "window/workDoneProgress/cancel",
+            json!({ "token": create["params"]["token"] }),
+        )
+        .await;
+    // Replies are handled ahead of notifications, so the cancel is let
+    // through first.
+    client.document_color("file:///web/app.css").await;
+    client.reply(&create, Value::Null).await;
+    let response = client.response(id).await;
+    assert_eq!(
+        response["result"],
+        "Reindexing was cancelled; the index is unchanged."
+    );
+
+    // A cancel for a token of no running reindex is ignored.
+    client
+        .notify("window/workDoneProgress/cancel", json!({ "token": "scan" }))
+        .await;
+    let response = client
+        .request(
+            "workspace/executeCommand",
+            json!({ "command": "chroma.reindexWorkspace", "workDoneToken": "scan" }),
+        )
+        .await;
+    assert_eq!(response["result"], "Reindexed 1 workspace file.");
+    let progress = client.notification("$/progress").await;
+    assert_eq!(progress["params"]["value"]["kind"], "begin");
+    assert_eq!(progress["params"]["value"]["cancellable"], true);
+
+    // The missing reference is cleared by the rescan.
+    let published = client
+        .notifications
+        .iter()
+        .rfind(|message| message["method"] == "textDocument/publishDiagnostics")
+        .unwrap();
+    assert_eq!(published["params"]["diagnostics"], json!([]));
+}
+
+#[tokio::test]
+async fn settings_limit_formats_languages_and_size() {
+    let mut client = TestClient::start();
+    client
+        .initialize_with_options(
+            json!({ "textDocument": { "colorProvider": {} } }),
+            json!({ "formats": ["function"], "maxFileSize": 40, "languages": ["css"] }),
+        )
+        .await;
+
+    client.did_open(URI, "a{color:#F00;b:rgb(0,0,255)}").await;
+    let colors = client.document_color(URI).await;
+    assert_eq!(colors.as_array().unwrap().len(), 1);
+    assert_eq!(colors[0]["color"]["blue"], 1.0);
+
+    client.did_open("file:///app.ts", "rgb(0,0,255)").await;
+    client
+        .did_change("file:///app.ts", 1, json!([{ "text": "rgb(255,0,0)" }]))
+        .await;
+    assert_eq!(client.document_color("file:///app.ts").await, json!([]));
+
+    let large = format!("/* {} */ rgb(0,0,255)", "-".repeat(40));
+    client.did_open("file:///large.css", &large).await;
+    assert_eq!(client.document_color("file:///large.css").await, json!([]));
+}
+
+#[tokio::test]
+async fn language_formats_select_parsers() {
+    let mut client = TestClient::start();
+    client
+        .initialize_with_options(
+            json!({ "textDocument": { "colorProvider": {} } }),
+            json!({
+                "formats": ["hex"],
+                "languageFormats": { "css": ["hex", "function"], "md": [] },
+            }),
+        )
+        .await;
+    let text = "#F00 rgb(0,0,255)";
+    for (uri, language_id) in [
+        ("file:///a.css", "css"),
+        ("file:///lib.rs", "rust"),
+        ("file:///README.md", "markdown"),
+        ("file:///notes.MD", "plaintext"),
+    ] {
+        client
+            .notify(
+                "textDocument/didOpen",
+                json!({
+                    "textDocument": { "uri": uri, "languageId": language_id, "version": 0, "text": text }
+                }),
+            )
+            .await;
+    }
+    let count = async |client: &mut TestClient, uri| {
+        client.document_color(uri).await.as_array().unwrap().len()
+    };
+    assert_eq!(count(&mut client, "file:///a.css").await, 2);
+    assert_eq!(count(&mut client, "file:///lib.rs").await, 1);
+    assert_eq!(count(&mut client, "file:///README.md").await, 0);
+    assert_eq!(count(&mut client, "file:///notes.MD").await, 0);
+}
+
+#[tokio::test]
+async fn configuration_change_reparses_documents() {
+    let mut client = TestClient::start();
+    client
+        .initialize_with_options(
+            json!({ "textDocument": { "colorProvider": {} } }),
+            json!({ "formats": ["function"] }),
+        )
+        .await;
+    client.did_open(URI, "a{color:#F00;b:rgb(0,0,255)}").await;
+    assert_eq!(
+        client.document_color(URI).await.as_array().unwrap().len(),
+        1
+    );
+
+    client
+        .notify(
+            "workspace/didChangeConfiguration",
+            json!({ "settings": { "chroma": { "formats": ["hex", "function"] } } }),
+        )
+        .await;
+    assert_eq!(
+        client.document_color(URI).await.as_array().unwrap().len(),
+        2
+    );
+
+    client
+        .notify(
+            "workspace/didChangeConfiguration",
+            json!({ "settings": { "formats": 5 } }),
+        )
+        .await;
+    let message = client.notification("window/logMessage").await;
+    assert_eq!(message["params"]["type"], 2);
+    assert_eq!(
+        client.document_color(URI).await.as_array().unwrap().len(),
+        2
+    );
+}
+
+#[tokio::test]
+async fn read_only_mode_disables_edits() {
+    let mut client = TestClient::start();
+    let result = client
+        .initialize_with_options(
+            json!({ "textDocument": { "colorProvider": {} } }),
+            json!({ "readOnly": true, "normalizeOnSave": "lowerHex" }),
+        )
+        .await;
+    assert_eq!(result["capabilities"]["renameProvider"], json!(null));
+    assert_eq!(
+        result["capabilities"]["textDocumentSync"]["willSaveWaitUntil"],
+        json!(null)
+    );
+
+    client
+        .did_open(URI, "a { color: red; background: #FF0000; }\n")
+        .await;
+    let colors = client.document_color(URI).await;
+    assert_eq!(colors.as_array().unwrap().len(), 2);
+
+    let response = client
+        .request(
+            "textDocument/colorPresentation",
+            json!({
+                "textDocument": { "uri": URI },
+                "color": colors[1]["color"],
+                "range": colors[1]["range"],
+            }),
+        )
+        .await;
+    assert_eq!(response["result"], json!([]));
+
+    let response = client
+        .request(
+            "textDocument/codeAction",
+            json!({
+                "textDocument": { "uri": URI },
+                "range": colors[0]["range"],
+                "context": { "diagnostics": [] },
+            }),
+        )
+        .await;
+    let edits = response["result"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .filter(|action| !action["edit"].is_null())
+        .count();
+    assert_eq!(edits, 0);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn palettes_and_tokens_scoped_to_workspace_folders() {
+    let workspace = Workspace::new("folders");
+    let palette = |entry: &str| format!("GIMP Palette\nName: Brand\n#\n{entry}\n");
+    workspace.write("web/brand.gpl", &palette(" 26 115 232\tBlue"));
+    workspace.write("app/brand.gpl", &palette("255   0   0\tRed"));
+    workspace.write(
+        "app/colors.tokens.json",
+        r##"{ "color": { "primary": { "$value": "#ff0000" } } }"##,
+    );
+
+    let mut client = TestClient::start();
+    client
+        .initialize_with_params(json!({
+            "capabilities": { "textDocument": { "colorProvider": {} } },
+            "initializationOptions": { "palettes": ["brand.gpl"] },
+            "workspaceFolders": [
+                { "uri": workspace.uri("web"), "name": "web" },
+                { "uri": workspace.uri("app"), "name": "app" },
+            ],
+        }))
+        .await;
+    let main_css = workspace.uri("web/main.css");
+    client.did_open(&main_css, "a { color: #2070E0; }\n").await;
+
+    let response = client
+        .request(
+            "textDocument/completion",
+            json!({
+                "textDocument": { "uri": main_css },
+                "position": { "line": 0, "character": 11 },
+            }),
+        )
+        .await;
+    let labels: Vec<&str> = response["result"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|item| item["label"].as_str().unwrap())
+        .collect();
+    assert_eq!(labels, ["Blue"]);
+
+    let response = client
+        .request(
+            "textDocument/codeAction",
+            json!({
+                "textDocument": { "uri": main_css },
+                "range": {
+                    "start": { "line": 0, "character": 12 },
+                    "end": { "line": 0, "character": 12 },
+                },
+                "context": { "diagnostics": [] },
+            }),
+        )
+        .await;
+    let titles: Vec<&str> = response["result"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .filter_map(|action| action["title"].as_str())
+        .filter(|title| title.starts_with("Snap to Brand"))
+        .collect();
+    assert_eq!(titles, ["Snap to Brand (Blue): #1A73E8"]);
+
+    // Tokens of the app folder don't resolve aliases in the web folder.
+    let web_tokens = workspace.uri("web/theme.tokens.json");
+    client
+        .did_open(
+            &web_tokens,
+            r#"{ "link": { "$value": "{color.primary}" } }"#,
+        )
+        .await;
+    let published = client.notification("textDocument/publishDiagnostics").await;
+    assert_eq!(published["params"]["uri"], web_tokens);
+    assert_eq!(
+        published["params"]["diagnostics"][0]["message"],
+        "`{color.primary}` doesn't resolve to a token"
+    );
+}
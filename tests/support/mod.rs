@@ -0,0 +1,269 @@
+//! In-process LSP client that drives the server over in-memory streams, and
+//! temporary workspace folders to point it at.
+
+use std::path::{Path, PathBuf};
+
+use serde_json::{Value, json};
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader};
+use tower_lsp_server::ls_types::Uri;
+
+use chroma_ls::transport;
+
+const BUFFER_SIZE: usize = 64 * 1024;
+
+pub struct TestClient {
+    writer: Box<dyn AsyncWrite + Unpin + Send>,
+    reader: BufReader<Box<dyn AsyncRead + Unpin + Send>>,
+    next_id: i64,
+    /// Notifications received from the server, in order.
+    pub notifications: Vec<Value>,
+    /// Requests received from the server, in order.
+    pub requests: Vec<Value>,
+}
+
+impl TestClient {
+    /// Spawns a server task connected to a new client.
+    pub fn start() -> Self {
+        let (client_writer, server_reader) = tokio::io::duplex(BUFFER_SIZE);
+        let (server_writer, client_reader) = tokio::io::duplex(BUFFER_SIZE);
+
+        tokio::spawn(transport::serve(server_reader, server_writer));
+        Self::connect(client_reader, client_writer)
+    }
+
+    /// Creates a client talking to a server over the given streams.
+    pub fn connect(
+        reader: impl AsyncRead + Unpin + Send + 'static,
+        writer: impl AsyncWrite + Unpin + Send + 'static,
+    ) -> Self {
+        Self {
+            writer: Box::new(writer),
+            reader: BufReader::new(Box::new(reader)),
+            next_id: 0,
+            notifications: Vec::new(),
+            requests: Vec::new(),
+        }
+    }
+
+    /// Starts a server and completes the initialize handshake with a client
+    /// that supports `textDocument/documentColor`.
+    pub async fn initialized() -> Self {
+        let mut client = Self::start();
+        client
+            .initialize(json!({ "textDocument": { "colorProvider": {} } }))
+            .await;
+        client
+    }
+
+    /// Runs the initialize handshake and returns the `InitializeResult`.
+    pub async fn initialize(&mut self, capabilities: Value) -> Value {
+        self.initialize_with_options(capabilities, Value::Null)
+            .await
+    }
+
+    /// Runs the initialize handshake with `initializationOptions` and returns
+    /// the `InitializeResult`.
+    pub async fn initialize_with_options(&mut self, capabilities: Value, options: Value) -> Value {
+        self.initialize_with_params(
+            json!({ "capabilities": capabilities, "initializationOptions": options }),
+        )
+        .await
+    }
+
+    /// Runs the initialize handshake with the given `InitializeParams` and
+    /// returns the `InitializeResult`.
+    pub async fn initialize_with_params(&mut self, params: Value) -> Value {
+        let response = self.request("initialize", params).await;
+        self.notify("initialized", json!({})).await;
+        response["result"].clone()
+    }
+
+    /// Opens a document whose language id is its file extension, e.g. `css`.
+    pub async fn did_open(&mut self, uri: &str, text: &str) {
+        let language_id = uri
+            .rsplit_once('.')
+            .map_or("plaintext", |(_, extension)| extension);
+        self.notify(
+            "textDocument/didOpen",
+            json!({
+                "textDocument": { "uri": uri, "languageId": language_id, "version": 0, "text": text }
+            }),
+        )
+        .await;
+    }
+
+    pub async fn did_change(&mut self, uri: &str, version: i32, changes: Value) {
+        self.notify(
+            "textDocument/didChange",
+            json!({
+                "textDocument": { "uri": uri, "version": version },
+                "contentChanges": changes,
+            }),
+        )
+        .await;
+    }
+
+    /// Returns the `result` of `textDocument/documentColor`.
+    pub async fn document_color(&mut self, uri: &str) -> Value {
+        self.request(
+            "textDocument/documentColor",
+            json!({ "textDocument": { "uri": uri } }),
+        )
+        .await["result"]
+            .clone()
+    }
+
+    /// Sends a request and returns the whole response, including `error`.
+    pub async fn request(&mut self, method: &str, params: Value) -> Value {
+        let id = self.send_request(method, params).await;
+        self.response(id).await
+    }
+
+    /// Sends a request without waiting for its response, returning its id.
+    pub async fn send_request(&mut self, method: &str, params: Value) -> i64 {
+        self.next_id += 1;
+        let id = self.next_id;
+        self.send(json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params }))
+            .await;
+        id
+    }
+
+    /// Waits for the response to the request with `id`.
+    pub async fn response(&mut self, id: i64) -> Value {
+        loop {
+            let message = self.recv().await;
+            match (message.get("id"), message.get("method")) {
+                (Some(message_id), None) if *message_id == json!(id) => return message,
+                // Server-to-client request: accept edits and reply to others
+                // with an empty result.
+                (Some(message_id), Some(method)) => {
+                    let result = if method == "workspace/applyEdit" {
+                        json!({ "applied": true })
+                    } else {
+                        Value::Null
+                    };
+                    let reply = json!({ "jsonrpc": "2.0", "id": message_id, "result": result });
+                    self.send(reply).await;
+                    self.requests.push(message);
+                }
+                _ => self.notifications.push(message),
+            }
+        }
+    }
+
+    /// Returns the first notification with `method`, waiting for it if it
+    /// hasn't been received yet.
+    pub async fn notification(&mut self, method: &str) -> Value {
+        if let Some(message) = self.notifications.iter().find(|m| m["method"] == method) {
+            return message.clone();
+        }
+        loop {
+            let message = self.recv().await;
+            if message["method"] == method {
+                self.notifications.push(message.clone());
+                return message;
+            }
+            self.notifications.push(message);
+        }
+    }
+
+    /// Waits for a request from the server with `method`, leaving the reply
+    /// to the caller.
+    pub async fn server_request(&mut self, method: &str) -> Value {
+        loop {
+            let message = self.recv().await;
+            if message["method"] == method && message.get("id").is_some() {
+                return message;
+            }
+            self.notifications.push(message);
+        }
+    }
+
+    /// Replies to a request from the server.
+    pub async fn reply(&mut self, request: &Value, result: Value) {
+        self.send(json!({ "jsonrpc": "2.0", "id": request["id"], "result": result }))
+            .await;
+    }
+
+    pub async fn notify(&mut self, method: &str, params: Value) {
+        self.send(json!({ "jsonrpc": "2.0", "method": method, "params": params }))
+            .await;
+    }
+
+    async fn send(&mut self, message: Value) {
+        let body = message.to_string();
+        let frame = format!("Content-Length: {}\r\n\r\n{body}", body.len());
+        self.writer.write_all(frame.as_bytes()).await.unwrap();
+    }
+
+    async fn recv(&mut self) -> Value {
+        let mut content_length = 0;
+        loop {
+            let mut header = String::new();
+            self.reader.read_line(&mut header).await.unwrap();
+            let header = header.trim_end();
+            if header.is_empty() {
+                break;
+            }
+            if let Some(length) = header.strip_prefix("Content-Length: ") {
+                content_length = length.parse().unwrap();
+            }
+        }
+
+        let mut body = vec![0; content_length];
+        self.reader.read_exact(&mut body).await.unwrap();
+        serde_json::from_slice(&body).unwrap()
+    }
+}
+
+/// Temporary directory standing in for a workspace folder, removed when
+/// dropped, even by a failing test.
+pub struct Workspace {
+    root: PathBuf,
+}
+
+impl Workspace {
+    /// Creates an empty directory unique to `name` and the test process.
+    pub fn new(name: &str) -> Self {
+        let root = std::env::temp_dir().join(format!("chroma-ls-{name}-{}", std::process::id()));
+        // Left over by a test process that was killed.
+        let _ = std::fs::remove_dir_all(&root);
+        std::fs::create_dir_all(&root).unwrap();
+        Self { root }
+    }
+
+    /// Returns the absolute path of `path`, relative to the root.
+    pub fn path(&self, path: &str) -> PathBuf {
+        self.root.join(path)
+    }
+
+    /// Writes `text` to `path`, relative to the root, creating its parent
+    /// directories, and returns the absolute path.
+    pub fn write(&self, path: &str, text: &str) -> PathBuf {
+        let path = self.path(path);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(&path, text).unwrap();
+        path
+    }
+
+    /// Returns the `file` URI of the root.
+    pub fn root_uri(&self) -> String {
+        file_uri(&self.root)
+    }
+
+    /// Returns the `file` URI of `path`, relative to the root.
+    pub fn uri(&self, path: &str) -> String {
+        file_uri(&self.path(path))
+    }
+}
+
+impl Drop for Workspace {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.root);
+    }
+}
+
+/// Returns the percent-encoded `file` URI of an absolute path.
+pub fn file_uri(path: &Path) -> String {
+    Uri::from_file_path(path).unwrap().as_str().to_string()
+}